@@ -0,0 +1,40 @@
+//! Hashes the files in `static/`, so that the game can tell cached assets of
+//! an older build from its own.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+fn main() -> io::Result<()> {
+    let root = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("static");
+    println!("cargo:rerun-if-changed={}", root.display());
+
+    let mut files = Vec::new();
+    list_files(&root, &mut files)?;
+    files.sort();
+    let mut hash = FNV_OFFSET_BASIS;
+    for file in &files {
+        let name = file.strip_prefix(&root).unwrap().to_string_lossy();
+        for byte in name.bytes().chain(fs::read(file)?) {
+            hash = (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+    println!("cargo:rustc-env=ASSETS_HASH={hash:016x}");
+    Ok(())
+}
+
+fn list_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -1 +1,5 @@
+if ("serviceWorker" in navigator) {
+  navigator.serviceWorker.register("sw.js").catch(console.error);
+}
+
 import("../pkg/index.js").catch(console.error);
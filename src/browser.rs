@@ -1,27 +1,26 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
 use anyhow::{anyhow, Result};
 use futures::Future;
 use js_sys::ArrayBuffer;
-use wasm_bindgen::{
-    closure::{WasmClosure, WasmClosureFnOnce},
-    prelude::*,
-};
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::{closure::WasmClosure, prelude::*, Clamped};
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{
-    CanvasRenderingContext2d, Document, Element, HtmlCanvasElement, HtmlElement, HtmlImageElement,
-    Response, Window,
+    Blob, CanvasRenderingContext2d, Document, EventTarget, HtmlCanvasElement, HtmlElement,
+    HtmlImageElement, ImageBitmap, ImageData, Performance, Response, WebGlRenderingContext, Window,
 };
 
-macro_rules! log {
-    ($($t:tt)*) => {
-        web_sys::console::log_1(&format!( $($t)*).into());
-    }
-}
+pub(crate) mod storage;
 
-macro_rules! error {
-    ($($t:tt)*) => {
-        web_sys::console::error_1(&format!( $($t)*).into());
-    }
-}
+mod cache;
+#[cfg(test)]
+mod fake_page;
+mod page;
+
+#[cfg(test)]
+pub(crate) use self::fake_page::FakePage;
+pub(crate) use self::page::{Listening, Page, WebPage};
 
 pub(crate) fn window() -> Result<Window> {
     web_sys::window().ok_or_else(|| anyhow!("no global `window` exists"))
@@ -41,8 +40,113 @@ pub(crate) fn canvas() -> Result<HtmlCanvasElement> {
         .map_err(|element| anyhow!("error converting {element:#?} to `HtmlCanvasElement`"))
 }
 
-pub(crate) fn context() -> Result<CanvasRenderingContext2d> {
-    canvas()?
+/// Saves what the canvas shows as a PNG, downloaded by the browser like any
+/// other file.
+pub(crate) fn capture_canvas() -> Result<()> {
+    let url = canvas()?
+        .to_data_url()
+        .map_err(|err| anyhow!("error encoding the canvas: {err:#?}"))?;
+    let link: HtmlElement = document()?
+        .create_element("a")
+        .map_err(|err| anyhow!("error creating a link: {err:#?}"))?
+        .dyn_into()
+        .map_err(|element| anyhow!("error converting {element:#?} to `HtmlElement`"))?;
+    let file_name = format!("walk-the-dog-{}.png", timestamp() as u64);
+    for (name, value) in [("href", url.as_str()), ("download", file_name.as_str())] {
+        link.set_attribute(name, value)
+            .map_err(|err| anyhow!("error setting `{name}` of the link: {err:#?}"))?;
+    }
+    link.click();
+    Ok(())
+}
+
+/// Covers the canvas with `title`, `details` and a `reload` button that
+/// reloads the page, for when the game cannot go on. Only the first call
+/// shows anything.
+pub(crate) fn show_crash_overlay(title: &str, details: &str, reload: &str) -> Result<()> {
+    if document()?.get_element_by_id("crash").is_some() {
+        return Ok(());
+    }
+    let (overlay, button) = show_overlay(title, details, reload)?;
+    overlay.set_id("crash");
+    let on_click = Closure::once_into_js(|| {
+        if let Err(err) = window().and_then(|window| {
+            window
+                .location()
+                .reload()
+                .map_err(|err| anyhow!("error reloading the page: {err:#?}"))
+        }) {
+            error!("{err:#}");
+        }
+    });
+    button.set_onclick(Some(on_click.unchecked_ref()));
+    Ok(())
+}
+
+/// Covers the canvas with `title`, `details` and a `retry` button, and
+/// waits until the button is clicked to take them away.
+pub(crate) async fn show_retry_overlay(title: &str, details: &str, retry: &str) -> Result<()> {
+    let (overlay, button) = show_overlay(title, details, retry)?;
+    let clicked = js_sys::Promise::new(&mut |resolve, _reject| {
+        button.set_onclick(Some(resolve.unchecked_ref()));
+    });
+    JsFuture::from(clicked)
+        .await
+        .map_err(|err| anyhow!("error waiting for a retry: {err:#?}"))?;
+    overlay.remove();
+    Ok(())
+}
+
+/// Adds an overlay with `title`, `details` and a button labelled `button`
+/// to the page, returning it and the button.
+fn show_overlay(title: &str, details: &str, button: &str) -> Result<(HtmlElement, HtmlElement)> {
+    let document = document()?;
+    let element = |tag: &str, text: &str| -> Result<HtmlElement> {
+        let element: HtmlElement = document
+            .create_element(tag)
+            .map_err(|err| anyhow!("error creating `{tag}`: {err:#?}"))?
+            .dyn_into()
+            .map_err(|element| anyhow!("error converting {element:#?} to `HtmlElement`"))?;
+        element.set_text_content(Some(text));
+        Ok(element)
+    };
+    let overlay = element("div", "")?;
+    overlay.set_class_name("overlay");
+    let (heading, details, button) = (
+        element("h1", title)?,
+        element("pre", details)?,
+        element("button", button)?,
+    );
+    overlay
+        .append_with_node_3(&heading, &details, &button)
+        .map_err(|err| anyhow!("error filling the overlay: {err:#?}"))?;
+    document
+        .body()
+        .ok_or_else(|| anyhow!("the page has no body"))?
+        .append_child(&overlay)
+        .map_err(|err| anyhow!("error showing the overlay: {err:#?}"))?;
+    Ok((overlay, button))
+}
+
+/// Resolves after `millis` milliseconds.
+pub(crate) async fn sleep(millis: i32) -> Result<()> {
+    let mut timeout = Ok(0);
+    let elapsed = js_sys::Promise::new(&mut |resolve, _reject| {
+        timeout = window().and_then(|window| {
+            window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis)
+                .map_err(|err| anyhow!("error setting a timeout: {err:#?}"))
+        });
+    });
+    timeout?;
+    JsFuture::from(elapsed)
+        .await
+        .map_err(|err| anyhow!("error waiting for a timeout: {err:#?}"))?;
+    Ok(())
+}
+
+pub(crate) fn context(canvas: &HtmlCanvasElement) -> Result<CanvasRenderingContext2d> {
+    canvas
         .get_context("2d")
         .map_err(|js_value| anyhow!("error getting 2d context {js_value:#?}"))?
         .ok_or_else(|| anyhow!("no 2d context found"))?
@@ -50,6 +154,111 @@ pub(crate) fn context() -> Result<CanvasRenderingContext2d> {
         .map_err(|element| anyhow!("error converting {element:#?} to `CanvasRenderingContext2d`"))
 }
 
+/// Gets a WebGL context that keeps its contents between frames, so that frames
+/// can be redrawn partially or skipped.
+pub(crate) fn webgl_context(canvas: &HtmlCanvasElement) -> Result<WebGlRenderingContext> {
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"preserveDrawingBuffer".into(), &true.into())
+        .map_err(|err| anyhow!("error creating webgl context options: {err:#?}"))?;
+    canvas
+        .get_context_with_context_options("webgl", &options)
+        .map_err(|js_value| anyhow!("error getting webgl context {js_value:#?}"))?
+        .ok_or_else(|| anyhow!("no webgl context found"))?
+        .dyn_into::<WebGlRenderingContext>()
+        .map_err(|element| anyhow!("error converting {element:#?} to `WebGlRenderingContext`"))
+}
+
+/// Creates a 2D context on a canvas that is not attached to the document.
+pub(crate) fn create_context() -> Result<CanvasRenderingContext2d> {
+    let canvas = document()?
+        .create_element("canvas")
+        .map_err(|err| anyhow!("error creating canvas: {err:#?}"))?
+        .dyn_into::<HtmlCanvasElement>()
+        .map_err(|element| anyhow!("error converting {element:#?} to `HtmlCanvasElement`"))?;
+    context(&canvas)
+}
+
+/// Returns the RGBA pixels of a `width` by `height` canvas after `draw` has
+/// drawn on it.
+pub(crate) fn canvas_pixels(
+    width: u32,
+    height: u32,
+    draw: impl FnOnce(&CanvasRenderingContext2d) -> Result<(), JsValue>,
+) -> Result<Vec<u8>> {
+    let context = create_context()?;
+    let canvas = context
+        .canvas()
+        .ok_or_else(|| anyhow!("the context has no canvas"))?;
+    canvas.set_width(width);
+    canvas.set_height(height);
+    draw(&context).map_err(|err| anyhow!("error drawing on canvas: {err:#?}"))?;
+    Ok(context
+        .get_image_data(0.0, 0.0, width.into(), height.into())
+        .map_err(|err| anyhow!("error reading canvas pixels: {err:#?}"))?
+        .data()
+        .0)
+}
+
+/// Returns a canvas that is not attached to the document, showing the RGBA
+/// `pixels` of a `width` by `height` image.
+pub(crate) fn canvas_with_pixels(
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Result<HtmlCanvasElement> {
+    let context = create_context()?;
+    let canvas = context
+        .canvas()
+        .ok_or_else(|| anyhow!("the context has no canvas"))?;
+    canvas.set_width(width);
+    canvas.set_height(height);
+    let image_data = ImageData::new_with_u8_clamped_array_and_sh(Clamped(pixels), width, height)
+        .map_err(|err| anyhow!("error creating image data: {err:#?}"))?;
+    context
+        .put_image_data(&image_data, 0.0, 0.0)
+        .map_err(|err| anyhow!("error writing canvas pixels: {err:#?}"))?;
+    Ok(canvas)
+}
+
+/// Returns the language the browser's user prefers, e.g. `en-US`.
+pub(crate) fn language() -> Result<String> {
+    window()?
+        .navigator()
+        .language()
+        .ok_or_else(|| anyhow!("the browser has no preferred language"))
+}
+
+/// Returns the query string of the page URL, including the leading `?`.
+pub(crate) fn query_string() -> Result<String> {
+    window()?
+        .location()
+        .search()
+        .map_err(|err| anyhow!("error reading the page URL: {err:#?}"))
+}
+
+/// Returns the page URL without its query string or fragment.
+pub(crate) fn page_url() -> Result<String> {
+    let location = window()?.location();
+    let read = |part: Result<String, JsValue>| {
+        part.map_err(|err| anyhow!("error reading the page URL: {err:#?}"))
+    };
+    Ok(format!(
+        "{}{}",
+        read(location.origin())?,
+        read(location.pathname())?
+    ))
+}
+
+/// Returns the value of `name` in the query string of the page URL.
+pub(crate) fn query_param(name: &str) -> Result<Option<String>> {
+    Ok(query_string()?
+        .trim_start_matches('?')
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string()))
+}
+
 pub(crate) fn spawn_local<F>(future: F)
 where
     F: Future<Output = ()> + 'static,
@@ -63,11 +272,50 @@ pub(crate) async fn fetch_with_str(resource: &str) -> Result<JsValue> {
         .map_err(|err| anyhow!("error fetching {err:#?}"))
 }
 
+/// The error status a server answered a fetch with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HttpError {
+    resource: String,
+    status: u16,
+    status_text: String,
+}
+
+impl HttpError {
+    /// Whether fetching again may succeed: the server failed, e.g. with
+    /// `503 Service Unavailable`, rather than refusing the request, e.g. with
+    /// `404 Not Found`.
+    pub(crate) fn is_transient(&self) -> bool {
+        self.status >= 500
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "error fetching {}: {} {}",
+            self.resource, self.status, self.status_text
+        )
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// Fetches `resource`, from the cache if it was fetched before but cannot be
+/// fetched now, so that the game can be played offline once it has been
+/// loaded.
+/// Fails with an [`HttpError`] if the server answers with an error.
 pub(crate) async fn fetch_response(resource: &str) -> Result<Response> {
-    fetch_with_str(resource)
-        .await?
-        .dyn_into()
-        .map_err(|element| anyhow!("error converting {element:#?} to `Response`"))
+    let response = cache::fetch(resource).await?;
+    if !response.ok() {
+        return Err(HttpError {
+            resource: resource.to_string(),
+            status: response.status(),
+            status_text: response.status_text(),
+        }
+        .into());
+    }
+    Ok(response)
 }
 
 pub(crate) async fn fetch_json(json_path: &str) -> Result<JsValue> {
@@ -80,6 +328,22 @@ pub(crate) async fn fetch_json(json_path: &str) -> Result<JsValue> {
     .map_err(|err| anyhow!("error fetching JSON: {err:#?}"))
 }
 
+/// Writes `value` as a JSON string using the browser's `JSON.stringify`.
+pub(crate) fn to_json_string(value: &impl Serialize) -> Result<String> {
+    let value = serde_wasm_bindgen::to_value(value)
+        .map_err(|err| anyhow!("could not convert value to JS: {err:#?}"))?;
+    js_sys::JSON::stringify(&value)
+        .map(String::from)
+        .map_err(|err| anyhow!("error writing JSON: {err:#?}"))
+}
+
+/// Reads a value from a JSON string using the browser's `JSON.parse`.
+pub(crate) fn from_json_string<T: DeserializeOwned>(json: &str) -> Result<T> {
+    let value = js_sys::JSON::parse(json).map_err(|err| anyhow!("error parsing JSON: {err:#?}"))?;
+    serde_wasm_bindgen::from_value(value)
+        .map_err(|err| anyhow!("could not convert value from JS: {err:#?}"))
+}
+
 pub(crate) async fn fetch_array_buffer(resource: &str) -> Result<ArrayBuffer> {
     let array_buffer = fetch_response(resource)
         .await?
@@ -92,22 +356,206 @@ pub(crate) async fn fetch_array_buffer(resource: &str) -> Result<ArrayBuffer> {
         .map_err(|err| anyhow!("error converting ras JSValue to ArrayBuffer: {err:#?}"))
 }
 
+/// Returns whether the browser can decode images with `createImageBitmap`.
+pub(crate) fn supports_image_bitmap() -> bool {
+    window().is_ok_and(|window| {
+        js_sys::Reflect::has(&window, &JsValue::from_str("createImageBitmap")).unwrap_or(false)
+    })
+}
+
+/// How [`share`] passed something on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ShareOutcome {
+    /// Handed to the device's share sheet.
+    Shared,
+    /// Copied to the clipboard, since there is no share sheet.
+    Copied,
+    /// The player closed the share sheet without sharing.
+    Cancelled,
+}
+
+/// Shares `text` and `url` with `navigator.share`, or copies them to the
+/// clipboard in browsers without it. Both are looked up at run time, as
+/// `web-sys` only has them behind its unstable APIs.
+pub(crate) async fn share(title: &str, text: &str, url: &str) -> Result<ShareOutcome> {
+    let navigator: JsValue = window()?.navigator().into();
+    let method = |name: &str| -> Result<Option<js_sys::Function>> {
+        let value = js_sys::Reflect::get(&navigator, &JsValue::from_str(name))
+            .map_err(|err| anyhow!("error looking up `{name}`: {err:#?}"))?;
+        Ok(value.dyn_into().ok())
+    };
+
+    if let Some(share) = method("share")? {
+        let data = js_sys::Object::new();
+        for (key, value) in [("title", title), ("text", text), ("url", url)] {
+            js_sys::Reflect::set(&data, &key.into(), &value.into())
+                .map_err(|err| anyhow!("error setting `{key}` to share: {err:#?}"))?;
+        }
+        let promise: js_sys::Promise = share
+            .call1(&navigator, &data)
+            .map_err(|err| anyhow!("error calling `share`: {err:#?}"))?
+            .dyn_into()
+            .map_err(|err| anyhow!("`share` did not return a promise: {err:#?}"))?;
+        return match JsFuture::from(promise).await {
+            Ok(_) => Ok(ShareOutcome::Shared),
+            Err(err)
+                if js_sys::Reflect::get(&err, &"name".into())
+                    .is_ok_and(|name| name.as_string().as_deref() == Some("AbortError")) =>
+            {
+                Ok(ShareOutcome::Cancelled)
+            }
+            Err(err) => Err(anyhow!("error sharing: {err:#?}")),
+        };
+    }
+
+    let clipboard = js_sys::Reflect::get(&navigator, &JsValue::from_str("clipboard"))
+        .map_err(|err| anyhow!("error looking up `clipboard`: {err:#?}"))?;
+    let write_text: js_sys::Function = js_sys::Reflect::get(&clipboard, &"writeText".into())
+        .ok()
+        .and_then(|value| value.dyn_into().ok())
+        .ok_or_else(|| anyhow!("the browser can neither share nor copy to the clipboard"))?;
+    let promise: js_sys::Promise = write_text
+        .call1(&clipboard, &format!("{text} {url}").into())
+        .map_err(|err| anyhow!("error calling `writeText`: {err:#?}"))?
+        .dyn_into()
+        .map_err(|err| anyhow!("`writeText` did not return a promise: {err:#?}"))?;
+    JsFuture::from(promise)
+        .await
+        .map_err(|err| anyhow!("error copying to the clipboard: {err:#?}"))?;
+    Ok(ShareOutcome::Copied)
+}
+
+/// Rumbles every connected gamepad that has vibration motors for `duration`
+/// milliseconds, with magnitudes between `0.0` and `1.0`. The Gamepad haptics
+/// API is looked up at run time, as `web-sys` only has it behind its unstable
+/// APIs, and gamepads without it are left alone.
+pub(crate) fn rumble(duration: f64, strong: f64, weak: f64) -> Result<()> {
+    let navigator: JsValue = window()?.navigator().into();
+    let Some(get_gamepads) = js_sys::Reflect::get(&navigator, &"getGamepads".into())
+        .ok()
+        .and_then(|value| value.dyn_into::<js_sys::Function>().ok())
+    else {
+        return Ok(());
+    };
+    let gamepads: js_sys::Array = get_gamepads
+        .call0(&navigator)
+        .map_err(|err| anyhow!("error calling `getGamepads`: {err:#?}"))?
+        .dyn_into()
+        .map_err(|err| anyhow!("`getGamepads` did not return an array: {err:#?}"))?;
+
+    let params = js_sys::Object::new();
+    for (key, value) in [
+        ("duration", duration),
+        ("strongMagnitude", strong),
+        ("weakMagnitude", weak),
+    ] {
+        js_sys::Reflect::set(&params, &key.into(), &value.into())
+            .map_err(|err| anyhow!("error setting `{key}` of the rumble: {err:#?}"))?;
+    }
+    // Disconnected gamepads are `null`.
+    for gamepad in gamepads.iter().filter(JsValue::is_object) {
+        let actuator = js_sys::Reflect::get(&gamepad, &"vibrationActuator".into())
+            .map_err(|err| anyhow!("error looking up `vibrationActuator`: {err:#?}"))?;
+        let Some(play_effect) = js_sys::Reflect::get(&actuator, &"playEffect".into())
+            .ok()
+            .and_then(|value| value.dyn_into::<js_sys::Function>().ok())
+        else {
+            continue;
+        };
+        let promise: js_sys::Promise = play_effect
+            .call2(&actuator, &"dual-rumble".into(), &params)
+            .map_err(|err| anyhow!("error calling `playEffect`: {err:#?}"))?
+            .dyn_into()
+            .map_err(|err| anyhow!("`playEffect` did not return a promise: {err:#?}"))?;
+        // A rumble cut short by the next one is not an error worth reporting.
+        spawn_local(async move {
+            let _ = JsFuture::from(promise).await;
+        });
+    }
+    Ok(())
+}
+
+pub(crate) async fn fetch_image_bitmap(resource: &str) -> Result<ImageBitmap> {
+    let blob: Blob = JsFuture::from(
+        fetch_response(resource)
+            .await?
+            .blob()
+            .map_err(|err| anyhow!("could not get blob from response: {err:#?}"))?,
+    )
+    .await
+    .map_err(|err| anyhow!("error fetching blob: {err:#?}"))?
+    .dyn_into()
+    .map_err(|err| anyhow!("error converting {err:#?} to `Blob`"))?;
+    let bitmap = window()?
+        .create_image_bitmap_with_blob(&blob)
+        .map_err(|err| anyhow!("could not decode {resource}: {err:#?}"))?;
+    JsFuture::from(bitmap)
+        .await
+        .map_err(|err| anyhow!("error decoding {resource}: {err:#?}"))?
+        .dyn_into()
+        .map_err(|err| anyhow!("error converting {err:#?} to `ImageBitmap`"))
+}
+
 pub(crate) fn new_image() -> Result<HtmlImageElement> {
     HtmlImageElement::new().map_err(|err| anyhow!("could not create `HtmlImageElement`: {err:#?}"))
 }
 
-pub(crate) fn closure_once<F, A, R>(fn_once: F) -> Closure<F::FnMut>
+pub(crate) fn closure_wrap<T>(data: Box<T>) -> Closure<T>
 where
-    F: 'static + WasmClosureFnOnce<A, R>,
+    T: WasmClosure + ?Sized,
 {
-    Closure::once(fn_once)
+    Closure::wrap(data)
 }
 
-pub(crate) fn closure_wrap<T>(data: Box<T>) -> Closure<T>
+/// A registered event listener that is removed from its target when dropped.
+///
+/// The closure backing the listener is owned by the handle, so it is freed
+/// together with the registration instead of being leaked with `forget()`.
+pub(crate) struct EventListenerHandle {
+    target: EventTarget,
+    event_type: &'static str,
+    listener: Box<dyn AsRef<JsValue>>,
+}
+
+impl std::fmt::Debug for EventListenerHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventListenerHandle")
+            .field("target", &self.target)
+            .field("event_type", &self.event_type)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for EventListenerHandle {
+    fn drop(&mut self) {
+        if let Err(err) = self.target.remove_event_listener_with_callback(
+            self.event_type,
+            (*self.listener).as_ref().unchecked_ref(),
+        ) {
+            error!(
+                "error removing `{}` event listener: {err:#?}",
+                self.event_type
+            );
+        }
+    }
+}
+
+pub(crate) fn add_event_listener<T>(
+    target: &EventTarget,
+    event_type: &'static str,
+    listener: Closure<T>,
+) -> Result<EventListenerHandle>
 where
-    T: WasmClosure + ?Sized,
+    T: WasmClosure + ?Sized + 'static,
 {
-    Closure::wrap(data)
+    target
+        .add_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref())
+        .map_err(|err| anyhow!("error adding `{event_type}` event listener: {err:#?}"))?;
+    Ok(EventListenerHandle {
+        target: target.clone(),
+        event_type,
+        listener: Box::new(listener),
+    })
 }
 
 pub(crate) type LoopClosure = Closure<dyn FnMut(f64)>;
@@ -117,53 +565,141 @@ pub(crate) fn request_animation_frame(callback: &LoopClosure) -> Result<i32> {
         .map_err(|err| anyhow!("cannot request animation frame: {err:#?}"))
 }
 
+pub(crate) fn cancel_animation_frame(id: i32) -> Result<()> {
+    window()?
+        .cancel_animation_frame(id)
+        .map_err(|err| anyhow!("cannot cancel animation frame: {err:#?}"))
+}
+
 pub(crate) fn create_raf_closure(f: impl FnMut(f64) + 'static) -> LoopClosure {
     closure_wrap(Box::new(f))
 }
 
-pub(crate) fn now() -> Result<f64> {
-    Ok(window()?
+fn performance() -> Result<Performance> {
+    window()?
         .performance()
-        .ok_or_else(|| anyhow!("performance object not found"))?
-        .now())
+        .ok_or_else(|| anyhow!("performance object not found"))
 }
 
-pub(crate) fn find_html_element_by_id(id: &str) -> Result<HtmlElement> {
-    let doc = document()?;
-    let element = doc
-        .get_element_by_id(id)
-        .ok_or_else(|| anyhow!("element with id {id} not found"))?;
-    element
-        .dyn_into()
-        .map_err(|err| anyhow!("error converting to `HtmlElement`: {err:#?}"))
+pub(crate) fn now() -> Result<f64> {
+    Ok(performance()?.now())
 }
 
-fn find_ui() -> Result<Element> {
-    let doc = document()?;
-    let ui = doc
-        .get_element_by_id("ui")
-        .ok_or_else(|| anyhow!("UI element not found"))?;
-    Ok(ui)
+/// Returns the milliseconds since the Unix epoch, which unlike [`now`] keep
+/// counting up across page loads.
+pub(crate) fn timestamp() -> f64 {
+    js_sys::Date::now()
 }
 
-pub(crate) fn draw_ui(html: &str) -> Result<()> {
-    let ui = find_ui()?;
-    ui.insert_adjacent_html("afterbegin", html)
-        .map_err(|err| anyhow!("error inserting HTML: {err:#?}"))
+/// Records a timestamp named `name` for the browser's profiler.
+pub(crate) fn mark(name: &str) -> Result<()> {
+    performance()?
+        .mark(name)
+        .map(drop)
+        .map_err(|err| anyhow!("error marking {name}: {err:#?}"))
 }
 
-pub(crate) fn hide_ui() -> Result<()> {
-    let ui = find_ui()?;
-    if let Some(child) = ui.first_child() {
-        ui.remove_child(&child)
-            .map_err(|err| anyhow!("error removing child: {err:#?}"))?;
-        canvas()?
-            .focus()
-            .map_err(|err| anyhow!("error focusing canvas: {err:#?}"))?;
-    }
+/// Records the span between the marks `start` and `end` as `name`, then
+/// clears the marks. Profilers pick up measures as they are made, so they are
+/// cleared as well rather than left to pile up.
+pub(crate) fn measure(name: &str, start: &str, end: &str) -> Result<()> {
+    let performance = performance()?;
+    performance
+        .measure_with_start_mark_and_end_mark(name, start, end)
+        .map(drop)
+        .map_err(|err| anyhow!("error measuring {name}: {err:#?}"))?;
+    performance.clear_marks_with_mark_name(start);
+    performance.clear_marks_with_mark_name(end);
+    performance.clear_measures_with_measure_name(name);
     Ok(())
 }
 
+pub(crate) fn window_size() -> Result<(f64, f64)> {
+    let window = window()?;
+    let width = window
+        .inner_width()
+        .map_err(|err| anyhow!("error getting window width: {err:#?}"))?
+        .as_f64()
+        .ok_or_else(|| anyhow!("window width is not a number"))?;
+    let height = window
+        .inner_height()
+        .map_err(|err| anyhow!("error getting window height: {err:#?}"))?
+        .as_f64()
+        .ok_or_else(|| anyhow!("window height is not a number"))?;
+    Ok((width, height))
+}
+
+pub(crate) fn device_pixel_ratio() -> Result<f64> {
+    Ok(window()?.device_pixel_ratio())
+}
+
+pub(crate) fn set_css_size(element: &HtmlElement, width: f64, height: f64) -> Result<()> {
+    let style = element.style();
+    style
+        .set_property("width", &format!("{width}px"))
+        .and_then(|()| style.set_property("height", &format!("{height}px")))
+        .map_err(|err| anyhow!("error setting element size: {err:#?}"))
+}
+
+pub(crate) fn add_resize_handler(handler: impl FnMut() + 'static) -> Result<EventListenerHandle> {
+    let on_resize = closure_wrap(Box::new(handler) as Box<dyn FnMut()>);
+    let window: EventTarget = window()?.into();
+    add_event_listener(&window, "resize", on_resize)
+}
+
+/// Calls `handler` whenever the user presses a key or a pointer button
+/// anywhere on the page.
+pub(crate) fn add_user_gesture_handler(
+    handler: impl FnMut() + 'static,
+) -> Result<Vec<EventListenerHandle>> {
+    let handler = Rc::new(RefCell::new(handler));
+    let window: EventTarget = window()?.into();
+    ["keydown", "pointerdown"]
+        .into_iter()
+        .map(|event_type| {
+            let handler = Rc::clone(&handler);
+            let on_gesture =
+                closure_wrap(Box::new(move || (handler.borrow_mut())()) as Box<dyn FnMut()>);
+            add_event_listener(&window, event_type, on_gesture)
+        })
+        .collect()
+}
+
+/// Calls `handler` whenever the window or the canvas loses the focus, after
+/// which key releases no longer reach the canvas.
+pub(crate) fn add_blur_handler(
+    handler: impl FnMut() + 'static,
+) -> Result<[EventListenerHandle; 2]> {
+    let handler = Rc::new(RefCell::new(handler));
+    let on_blur = || {
+        let handler = Rc::clone(&handler);
+        closure_wrap(Box::new(move || (handler.borrow_mut())()) as Box<dyn FnMut()>)
+    };
+    let window: EventTarget = window()?.into();
+    let canvas: EventTarget = canvas()?.into();
+    Ok([
+        add_event_listener(&window, "blur", on_blur())?,
+        add_event_listener(&canvas, "focusout", on_blur())?,
+    ])
+}
+
+pub(crate) fn is_document_hidden() -> Result<bool> {
+    Ok(document()?.hidden())
+}
+
+pub(crate) fn add_visibility_change_handler(
+    mut handler: impl FnMut(bool) + 'static,
+) -> Result<EventListenerHandle> {
+    let on_visibility_change = closure_wrap(Box::new(move || match is_document_hidden() {
+        Ok(hidden) => handler(hidden),
+        Err(err) => {
+            error!("error reading document visibility: {err:#?}");
+        }
+    }) as Box<dyn FnMut()>);
+    let document: EventTarget = document()?.into();
+    add_event_listener(&document, "visibilitychange", on_visibility_change)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -176,4 +712,15 @@ mod tests {
         let json = fetch_json("not_there.json").await;
         assert!(json.is_err());
     }
+
+    #[test]
+    fn only_server_errors_are_transient() {
+        let error = |status| HttpError {
+            resource: "rhb.json".to_string(),
+            status,
+            status_text: String::new(),
+        };
+        assert!(!error(404).is_transient());
+        assert!(error(503).is_transient());
+    }
 }
@@ -0,0 +1,129 @@
+//! Keeps a copy of every asset fetched in the browser's Cache API, so that
+//! sprites, sounds and JSON files load from there when they cannot be
+//! fetched, e.g. offline, after the first visit.
+
+use std::cell::Cell;
+
+use anyhow::{anyhow, Result};
+use js_sys::Array;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Cache, CacheStorage, Response};
+
+/// Named after a hash of the assets the game was built with, so that a new
+/// build fetches its assets afresh instead of playing with the old ones.
+const CACHE_NAME: &str = concat!("walk-the-dog-", env!("ASSETS_HASH"));
+const CACHE_PREFIX: &str = "walk-the-dog-";
+
+thread_local! {
+    static STALE_CACHES_DELETED: Cell<bool> = const { Cell::new(false) };
+}
+
+async fn open() -> Result<Cache> {
+    let caches = super::window()?
+        .caches()
+        .map_err(|err| anyhow!("error accessing the caches: {err:#?}"))?;
+    if !STALE_CACHES_DELETED.with(|deleted| deleted.replace(true)) {
+        if let Err(err) = delete_stale_caches(&caches).await {
+            error!("{err:#}");
+        }
+    }
+    JsFuture::from(caches.open(CACHE_NAME))
+        .await
+        .map_err(|err| anyhow!("error opening the cache: {err:#?}"))?
+        .dyn_into()
+        .map_err(|cache| anyhow!("error converting {cache:#?} to `Cache`"))
+}
+
+/// Deletes the caches left by earlier builds of the game.
+async fn delete_stale_caches(caches: &CacheStorage) -> Result<()> {
+    let names: Array = JsFuture::from(caches.keys())
+        .await
+        .map_err(|err| anyhow!("error listing the caches: {err:#?}"))?
+        .dyn_into()
+        .map_err(|names| anyhow!("error converting {names:#?} to `Array`"))?;
+    for name in names.iter().filter_map(|name| name.as_string()) {
+        if name.starts_with(CACHE_PREFIX) && name != CACHE_NAME {
+            JsFuture::from(caches.delete(&name))
+                .await
+                .map_err(|err| anyhow!("error deleting the cache {name}: {err:#?}"))?;
+        }
+    }
+    Ok(())
+}
+
+async fn lookup(cache: &Cache, resource: &str) -> Result<Option<Response>> {
+    let response = JsFuture::from(cache.match_with_str(resource))
+        .await
+        .map_err(|err| anyhow!("error looking up {resource} in the cache: {err:#?}"))?;
+    if response.is_undefined() {
+        return Ok(None);
+    }
+    response
+        .dyn_into()
+        .map(Some)
+        .map_err(|response| anyhow!("error converting {response:#?} to `Response`"))
+}
+
+async fn store(cache: &Cache, resource: &str, response: &Response) -> Result<()> {
+    let copy = response
+        .clone()
+        .map_err(|err| anyhow!("error copying the response for {resource}: {err:#?}"))?;
+    JsFuture::from(cache.put_with_str(resource, &copy))
+        .await
+        .map_err(|err| anyhow!("error caching {resource}: {err:#?}"))?;
+    Ok(())
+}
+
+/// Fetches `resource`, caching it for next time if it was fetched
+/// successfully, and falls back to the cached response if it cannot be
+/// fetched, e.g. offline. Resources with a query, such as the cache-busted
+/// ones [`crate::engine::AssetLoader::uncached`] asks for, are never cached.
+/// Where the cache cannot be used, e.g. on a page not served over HTTPS, this
+/// is a plain fetch.
+pub(crate) async fn fetch(resource: &str) -> Result<Response> {
+    if resource.contains('?') {
+        return fetch_from_network(resource).await;
+    }
+    let cache = match open().await {
+        Ok(cache) => Some(cache),
+        Err(err) => {
+            debug!("fetching {resource} without caching: {err:#}");
+            None
+        }
+    };
+
+    let err = match fetch_from_network(resource).await {
+        Ok(response) => {
+            if let Some(cache) = &cache {
+                if response.ok() {
+                    if let Err(err) = store(cache, resource, &response).await {
+                        error!("{err:#}");
+                    }
+                }
+            }
+            return Ok(response);
+        }
+        Err(err) => err,
+    };
+    if let Some(cache) = &cache {
+        match lookup(cache, resource).await {
+            Ok(Some(response)) => {
+                debug!("{resource} loaded from the cache: {err:#}");
+                return Ok(response);
+            }
+            Ok(None) => {}
+            Err(err) => {
+                error!("{err:#}");
+            }
+        }
+    }
+    Err(err)
+}
+
+async fn fetch_from_network(resource: &str) -> Result<Response> {
+    super::fetch_with_str(resource)
+        .await?
+        .dyn_into()
+        .map_err(|response| anyhow!("error converting {response:#?} to `Response`"))
+}
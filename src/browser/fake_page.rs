@@ -0,0 +1,73 @@
+//! A page for tests, which click on it themselves.
+
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+use anyhow::Result;
+
+use super::page::{Listening, Page};
+
+type ClickHandler = RefCell<Box<dyn FnMut(f32, f32)>>;
+
+/// Stands in for the page, passing clicks from [`FakePage::click`] on to the
+/// handlers that are still kept.
+#[derive(Default)]
+pub(crate) struct FakePage {
+    click_handlers: RefCell<Vec<Weak<ClickHandler>>>,
+}
+
+impl FakePage {
+    /// Clicks on the canvas at fractions `x` and `y` of its width and height.
+    pub(crate) fn click(&self, x: f32, y: f32) {
+        let mut handlers = self.click_handlers.borrow_mut();
+        handlers.retain(|handler| handler.strong_count() > 0);
+        for handler in handlers.iter().filter_map(Weak::upgrade) {
+            (handler.borrow_mut())(x, y);
+        }
+    }
+}
+
+impl std::fmt::Debug for FakePage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FakePage")
+            .field("click_handlers", &self.click_handlers.borrow().len())
+            .finish()
+    }
+}
+
+impl Page for FakePage {
+    fn on_canvas_click(&self, on_click: Box<dyn FnMut(f32, f32)>) -> Result<Listening> {
+        let handler = Rc::new(RefCell::new(on_click));
+        self.click_handlers
+            .borrow_mut()
+            .push(Rc::downgrade(&handler));
+        Ok(Listening::new(handler))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn clicks_stop_once_the_listening_is_dropped() {
+        let page = FakePage::default();
+        let clicks = Rc::new(Cell::new(0));
+        let listening = page
+            .on_canvas_click(Box::new({
+                let clicks = Rc::clone(&clicks);
+                move |_, _| clicks.set(clicks.get() + 1)
+            }))
+            .unwrap();
+
+        page.click(0.5, 0.5);
+        drop(listening);
+        page.click(0.5, 0.5);
+
+        assert_eq!(clicks.get(), 1);
+    }
+}
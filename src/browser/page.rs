@@ -0,0 +1,59 @@
+//! What the game's screens use of the page they are shown on, behind a trait
+//! so that tests can play them against a stand-in instead of a browser.
+
+use std::{any::Any, fmt};
+
+use anyhow::Result;
+use web_sys::MouseEvent;
+
+/// The page the game is shown on.
+pub(crate) trait Page: fmt::Debug {
+    /// Calls `on_click` with where every click on the canvas lands, as
+    /// fractions of the width and height the canvas is shown at, for as long
+    /// as the returned [`Listening`] is kept.
+    fn on_canvas_click(&self, on_click: Box<dyn FnMut(f32, f32)>) -> Result<Listening>;
+}
+
+/// Keeps a handler added to a [`Page`] until it is dropped.
+pub(crate) struct Listening {
+    _registration: Box<dyn Any>,
+}
+
+impl Listening {
+    pub(crate) fn new(registration: impl Any) -> Self {
+        Listening {
+            _registration: Box::new(registration),
+        }
+    }
+}
+
+impl fmt::Debug for Listening {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Listening").finish_non_exhaustive()
+    }
+}
+
+/// The page of the browser window the game runs in.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct WebPage;
+
+impl Page for WebPage {
+    fn on_canvas_click(&self, mut on_click: Box<dyn FnMut(f32, f32)>) -> Result<Listening> {
+        let canvas = super::canvas()?;
+        let listener = super::closure_wrap(Box::new({
+            let canvas = canvas.clone();
+            move |event: MouseEvent| {
+                let (shown_width, shown_height) = (canvas.client_width(), canvas.client_height());
+                if shown_width == 0 || shown_height == 0 {
+                    return;
+                }
+                on_click(
+                    event.offset_x() as f32 / shown_width as f32,
+                    event.offset_y() as f32 / shown_height as f32,
+                );
+            }
+        }) as Box<dyn FnMut(MouseEvent)>);
+        let handle = super::add_event_listener(&canvas, "click", listener)?;
+        Ok(Listening::new(handle))
+    }
+}
@@ -0,0 +1,14 @@
+//! Keeps small pieces of state, such as preferences and progress, in the
+//! browser's local storage so that they survive reloads.
+
+// The in-memory stand-in takes its place in tests, which run outside of a
+// browser.
+#[cfg_attr(test, allow(dead_code))]
+mod local;
+#[cfg(test)]
+mod memory;
+
+#[cfg(not(test))]
+pub(crate) use self::local::{load_item, load_json, save_item, save_json};
+#[cfg(test)]
+pub(crate) use self::memory::{load_item, load_json, save_item, save_json};
@@ -0,0 +1,36 @@
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use web_sys::Storage;
+
+use crate::browser;
+
+fn local_storage() -> Result<Storage> {
+    browser::window()?
+        .local_storage()
+        .map_err(|err| anyhow!("error accessing local storage: {err:#?}"))?
+        .ok_or_else(|| anyhow!("no local storage found"))
+}
+
+pub(crate) fn load_item(key: &str) -> Result<Option<String>> {
+    local_storage()?
+        .get_item(key)
+        .map_err(|err| anyhow!("error reading `{key}` from local storage: {err:#?}"))
+}
+
+pub(crate) fn save_item(key: &str, value: &str) -> Result<()> {
+    local_storage()?
+        .set_item(key, value)
+        .map_err(|err| anyhow!("error writing `{key}` to local storage: {err:#?}"))
+}
+
+/// Reads a value saved with [`save_json`], or `None` if nothing was saved
+/// under `key`.
+pub(crate) fn load_json<T: DeserializeOwned>(key: &str) -> Result<Option<T>> {
+    load_item(key)?
+        .map(|json| browser::from_json_string(&json))
+        .transpose()
+}
+
+pub(crate) fn save_json(key: &str, value: &impl Serialize) -> Result<()> {
+    save_item(key, &browser::to_json_string(value)?)
+}
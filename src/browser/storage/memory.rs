@@ -0,0 +1,39 @@
+//! Stands in for [`super::local`] in tests, which run outside of a browser.
+//! It has the same interface but keeps the items in memory, separately for
+//! every test thread.
+
+use std::{cell::RefCell, collections::HashMap};
+
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Serialize};
+
+thread_local! {
+    static ITEMS: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) fn load_item(key: &str) -> Result<Option<String>> {
+    Ok(ITEMS.with(|items| items.borrow().get(key).cloned()))
+}
+
+pub(crate) fn save_item(key: &str, value: &str) -> Result<()> {
+    ITEMS.with(|items| {
+        items
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string())
+    });
+    Ok(())
+}
+
+pub(crate) fn load_json<T: DeserializeOwned>(key: &str) -> Result<Option<T>> {
+    load_item(key)?
+        .map(|json| {
+            serde_json::from_str(&json).map_err(|err| anyhow!("error parsing `{key}`: {err}"))
+        })
+        .transpose()
+}
+
+pub(crate) fn save_json(key: &str, value: &impl Serialize) -> Result<()> {
+    let json =
+        serde_json::to_string(value).map_err(|err| anyhow!("error writing `{key}`: {err}"))?;
+    save_item(key, &json)
+}
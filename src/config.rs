@@ -0,0 +1,167 @@
+//! Options for a single session, read from the query string of the page URL
+//! so that testers can reproduce a scenario by sharing a link, e.g.
+//! `?seed=42&debug=1&mute=1&speed=6&log=debug`.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    browser, engine,
+    game::{GoldenMode, Weather},
+    logging,
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Config {
+    /// `seed`: generates the levels, so a run can be played again by passing
+    /// the seed it logged. A random one is used if it is missing.
+    pub(crate) seed: Option<u64>,
+    /// `server`: the URL of a relay server, e.g. `ws://localhost:9001`, to
+    /// race the other players connected to it.
+    pub(crate) server: Option<String>,
+    /// `rate`: how many times per second the game updates, which low-power
+    /// devices can lower to e.g. `30`. The game runs equally fast at any
+    /// rate within [`engine::UPDATES_PER_SECOND_RANGE`].
+    pub(crate) updates_per_second: Option<u16>,
+    /// `debug`: turns the debug mode on or off, whatever the settings say.
+    pub(crate) debug: Option<bool>,
+    /// `mute`: turns the sound off or on, whatever the settings say.
+    pub(crate) mute: Option<bool>,
+    /// `speed`: how many pixels the boy runs per update at the default rate.
+    pub(crate) speed: Option<f32>,
+    /// `weather`: fixes the weather to `clear`, `rain` or `snow` for the
+    /// whole session.
+    pub(crate) weather: Option<Weather>,
+    /// `level`: plays the level in `levels/<name>.json`, which ends at a goal,
+    /// instead of the endless run.
+    pub(crate) level: Option<String>,
+    /// `lang`: the language to show the game in, e.g. `de`, instead of the
+    /// browser's.
+    pub(crate) language: Option<String>,
+    /// `record`: records the keys pressed, for replaying the session in a
+    /// test.
+    pub(crate) record: bool,
+    /// `golden`: checks the rendering of a few scenes instead of playing, or
+    /// takes the hashes to check against with `golden=update`.
+    pub(crate) golden: Option<GoldenMode>,
+    /// `log`: what to log, e.g. `warn,engine=debug`; see [`logging::Filter`].
+    pub(crate) log: Option<logging::Filter>,
+}
+
+impl Config {
+    /// Reads the options in the page URL.
+    pub(crate) fn from_url() -> Result<Self> {
+        Ok(Self::parse(&browser::query_string()?))
+    }
+
+    /// Reads the options in `query`, a URL query string with or without the
+    /// leading `?`. Unknown options are ignored, and flags can be given
+    /// without a value, e.g. `?golden`. Invalid options are logged and
+    /// skipped, keeping the others.
+    pub(crate) fn parse(query: &str) -> Self {
+        let mut config = Config::default();
+        for pair in query.trim_start_matches('?').split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, "1"));
+            if let Err(err) = config.set(key, value) {
+                error!("ignoring the option {pair:?}: {err:#}");
+            }
+        }
+        config
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "seed" => self.seed = Some(parse_value(key, value)?),
+            "server" => self.server = Some(value.to_string()),
+            "rate" => {
+                let rate: u16 = parse_value(key, value)?;
+                if !engine::UPDATES_PER_SECOND_RANGE.contains(&rate) {
+                    return Err(anyhow!(
+                        "`rate` must be from {} to {}, not {value}",
+                        engine::UPDATES_PER_SECOND_RANGE.start(),
+                        engine::UPDATES_PER_SECOND_RANGE.end()
+                    ));
+                }
+                self.updates_per_second = Some(rate);
+            }
+            "debug" => self.debug = Some(parse_flag(key, value)?),
+            "mute" => self.mute = Some(parse_flag(key, value)?),
+            "speed" => {
+                let speed: f32 = parse_value(key, value)?;
+                if !speed.is_finite() || speed <= 0.0 {
+                    return Err(anyhow!("`speed` must be positive, not {value}"));
+                }
+                self.speed = Some(speed);
+            }
+            "weather" => self.weather = Some(parse_value(key, value)?),
+            "level" => self.level = Some(value.to_string()),
+            "lang" => self.language = Some(value.to_string()),
+            "record" => self.record = parse_flag(key, value)?,
+            "golden" => {
+                self.golden = match value {
+                    "0" | "false" => None,
+                    _ => Some(parse_value(key, value)?),
+                }
+            }
+            "log" => self.log = Some(parse_value(key, value)?),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("invalid value for `{key}`: {value:?}"))
+}
+
+fn parse_flag(key: &str, value: &str) -> Result<bool> {
+    match value {
+        "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        _ => Err(anyhow!("`{key}` must be 1 or 0, not {value:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_are_read_from_the_query_string() {
+        let config = Config::parse("?seed=42&debug=1&mute=0&speed=6&record&other=x");
+        assert_eq!(
+            config,
+            Config {
+                seed: Some(42),
+                debug: Some(true),
+                mute: Some(false),
+                speed: Some(6.0),
+                record: true,
+                ..Config::default()
+            }
+        );
+        assert_eq!(Config::parse(""), Config::default());
+        assert_eq!(
+            Config::parse("?golden=update").golden,
+            Some(GoldenMode::Update)
+        );
+        assert_eq!(Config::parse("?rate=120").updates_per_second, Some(120));
+    }
+
+    #[test]
+    fn invalid_options_are_skipped_and_the_others_kept() {
+        for query in ["?rate=fast", "?rate=0", "?rate=10000", "?speed=-1"] {
+            let config = Config::parse(&format!("{query}&seed=42"));
+            assert_eq!(
+                config,
+                Config {
+                    seed: Some(42),
+                    ..Config::default()
+                }
+            );
+        }
+    }
+}
@@ -1,132 +1,387 @@
 use std::{
     cell::{self, RefCell},
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    ops::RangeInclusive,
     rc::Rc,
-    sync::Mutex,
 };
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use futures::channel::{
-    mpsc::{unbounded, UnboundedReceiver},
-    oneshot::channel,
+use futures::channel::mpsc::{unbounded, UnboundedReceiver};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use web_sys::{HtmlCanvasElement, KeyboardEvent, MouseEvent};
+
+mod assets;
+// The silent stand-in takes its place in tests, which cannot reach the Web
+// Audio API.
+#[cfg_attr(test, allow(dead_code))]
+mod audio;
+mod canvas2d;
+mod capture;
+mod collision;
+mod draw_queue;
+mod ecs;
+mod events;
+mod frame_graph;
+mod image_source;
+mod input_recording;
+#[cfg(test)]
+mod recording;
+mod scene;
+#[cfg(test)]
+mod silent_audio;
+mod ui;
+mod webgl;
+
+#[cfg(not(test))]
+pub(crate) use self::audio::{Audio, Sound, SoundHandle};
+pub(crate) use self::{
+    assets::AssetLoader,
+    capture::{capture_frame, hash_pixels},
+    collision::{load_image_mask, CollisionMask, Shape},
+    ecs::{Body, Entity, Sprite, Velocity, World},
+    events::EventBus,
+    image_source::{load_image, ImageSource},
+    input_recording::InputRecording,
+    scene::{Scene, SceneChange, SceneStack, Transition},
+    ui::{Button, Label, Panel, Widget},
 };
-use serde::Deserialize;
-use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
-use web_sys::{
-    AudioBuffer, AudioContext, CanvasRenderingContext2d, HtmlElement, HtmlImageElement,
-    KeyboardEvent,
+use self::{
+    draw_queue::{DrawCommand, DrawQueue},
+    frame_graph::FrameGraph,
 };
-
-use crate::{
-    browser,
-    sound::{self, Looping},
+#[cfg(test)]
+pub(crate) use self::{
+    recording::Call,
+    silent_audio::{Audio, Sound, SoundHandle},
 };
+use crate::{browser, i18n};
 
-pub(crate) async fn load_image(source: &str) -> Result<HtmlImageElement> {
-    let image = browser::new_image()?;
+#[async_trait(?Send)]
+pub(crate) trait Game {
+    async fn initialize(&self, assets: &AssetLoader) -> Result<Box<dyn Game>>;
+    fn update(&mut self, keystate: &KeyState);
+    fn draw(&self, renderer: &Renderer);
 
-    let (complete_tx, complete_rx) = channel::<Result<()>>();
-    let success_tx = Rc::new(Mutex::new(Some(complete_tx)));
-    let error_tx = Rc::clone(&success_tx);
+    /// Releases what the game holds outside of the game loop, such as audio,
+    /// when the loop is stopped.
+    fn teardown(&mut self) {}
 
-    let success_callback = browser::closure_once(move || {
-        if let Some(success_tx) = success_tx.lock().ok().and_then(|mut opt| opt.take()) {
-            if let Err(err) = success_tx.send(Ok(())) {
-                error!("error sending success_tx: {err:#?}");
-            }
-        }
-    });
+    /// Tells the game loop how much of the screen changed since the last frame.
+    fn redraw(&self) -> Redraw {
+        Redraw::Everything
+    }
+}
 
-    let error_callback: Closure<dyn FnMut(JsValue)> = browser::closure_once(move |err| {
-        if let Some(error_tx) = error_tx.lock().ok().and_then(|mut opt| opt.take()) {
-            if let Err(err) = error_tx.send(Err(anyhow!("error loading image: {err:#?}"))) {
-                error!("error sending error_tx: {err:#?}");
-            }
+/// The part of the screen that has to be drawn again for the next frame.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Redraw {
+    Nothing,
+    Region(Rect),
+    Everything,
+}
+
+impl Redraw {
+    fn union(self, other: Redraw) -> Redraw {
+        match (self, other) {
+            (Redraw::Everything, _) | (_, Redraw::Everything) => Redraw::Everything,
+            (Redraw::Nothing, redraw) | (redraw, Redraw::Nothing) => redraw,
+            (Redraw::Region(a), Redraw::Region(b)) => Redraw::Region(a.union(&b)),
         }
-    });
+    }
+}
 
-    image.set_onload(Some(success_callback.as_ref().unchecked_ref()));
-    image.set_onerror(Some(error_callback.as_ref().unchecked_ref()));
-    image.set_src(source);
+pub(crate) const DEFAULT_UPDATES_PER_SECOND: u16 = 60;
+/// The rates the game loop accepts. Durations are counted in updates as
+/// `u16`, which would overflow at much higher rates.
+pub(crate) const UPDATES_PER_SECOND_RANGE: RangeInclusive<u16> = 10..=240;
 
-    complete_rx.await??;
+thread_local! {
+    static TIME_SCALE: cell::Cell<f32> = const { cell::Cell::new(1.0) };
+    static UPDATES_PER_SECOND: cell::Cell<u16> =
+        const { cell::Cell::new(DEFAULT_UPDATES_PER_SECOND) };
+    static PROFILING: cell::Cell<bool> = const { cell::Cell::new(false) };
+}
 
-    Ok(image)
+/// Runs `f` as a span named `name` in the browser's profiler, if profiling
+/// is on. The game loop turns profiling on along with the renderer's debug
+/// mode.
+pub(crate) fn profile<R>(name: &str, f: impl FnOnce() -> R) -> R {
+    if !PROFILING.with(cell::Cell::get) {
+        return f();
+    }
+    let (start, end) = (format!("{name}-start"), format!("{name}-end"));
+    if let Err(err) = browser::mark(&start) {
+        error!("{err:#?}");
+    }
+    let result = f();
+    if let Err(err) = browser::mark(&end).and_then(|()| browser::measure(name, &start, &end)) {
+        error!("{err:#?}");
+    }
+    result
 }
 
-#[async_trait(?Send)]
-pub(crate) trait Game {
-    async fn initialize(&self) -> Result<Box<dyn Game>>;
-    fn update(&mut self, keystate: &KeyState);
-    fn draw(&self, renderer: &Renderer);
+/// Returns how many times per second the running game loop updates the game.
+pub(crate) fn updates_per_second() -> u16 {
+    UPDATES_PER_SECOND.with(cell::Cell::get)
+}
+
+pub(crate) fn set_updates_per_second(updates_per_second: u16) {
+    UPDATES_PER_SECOND.with(|rate| rate.set(updates_per_second));
+}
+
+/// Returns how much of a default-rate update one update covers. Speeds and
+/// accelerations are tuned per default-rate update, so they are scaled by
+/// this to keep the game equally fast at any rate.
+pub(crate) fn update_scale() -> f32 {
+    f32::from(DEFAULT_UPDATES_PER_SECOND) / f32::from(updates_per_second())
+}
+
+/// Returns how many updates last `seconds` at the current rate, and at least
+/// one. Durations that are counted down in updates start from this.
+pub(crate) fn updates_in(seconds: f32) -> u16 {
+    ((seconds * f32::from(updates_per_second())).round() as u16).max(1)
 }
 
-const FRAME_SIZE: f32 = 1.0 / 60.0 * 1000.0;
+/// Returns how many seconds of game time one update covers.
+pub(crate) fn seconds_per_update() -> f32 {
+    1.0 / f32::from(updates_per_second())
+}
+
+/// Sets how fast game time passes compared to real time, e.g. `0.5` for slow
+/// motion. Updates keep their fixed step and simply run less often.
+pub(crate) fn set_time_scale(scale: f32) {
+    TIME_SCALE.with(|time_scale| time_scale.set(scale.max(0.0)));
+}
+
+fn time_scale() -> f32 {
+    TIME_SCALE.with(cell::Cell::get)
+}
 
 #[derive(Debug)]
 pub(crate) struct GameLoop {
     last_frame: f64,
     accumulated_delta: f32,
+    /// Milliseconds of game time covered by one update.
+    frame_size: f32,
+    suspended: Rc<cell::Cell<bool>>,
+    frame_graph: FrameGraph,
+    /// How many updates have run, which numbers recorded key events.
+    updates: u64,
+    recording: Option<Rc<RefCell<InputRecording>>>,
+    _input_listeners: KeyListeners,
+    _visibility_listener: browser::EventListenerHandle,
+    _resize_listener: browser::EventListenerHandle,
 }
 
 impl GameLoop {
-    pub async fn start(game: impl Game + 'static) -> Result<()> {
-        let mut keyevent_receiver = prepare_input()?;
-        let mut game = game.initialize().await?;
+    /// Runs `game`, updating it `updates_per_second` times per second of game
+    /// time regardless of how often the browser draws.
+    ///
+    /// Key events are added to `recording` if there is one, and it can be
+    /// taken back with [`GameLoopHandle::input_recording`].
+    pub async fn start(
+        game: impl Game + 'static,
+        updates_per_second: u16,
+        recording: Option<InputRecording>,
+    ) -> Result<GameLoopHandle> {
+        if !UPDATES_PER_SECOND_RANGE.contains(&updates_per_second) {
+            return Err(anyhow!(
+                "the game must update {} to {} times per second, not {updates_per_second}",
+                UPDATES_PER_SECOND_RANGE.start(),
+                UPDATES_PER_SECOND_RANGE.end()
+            ));
+        }
+        set_updates_per_second(updates_per_second);
+        let (mut keyevent_receiver, input_listeners) = prepare_input()?;
+        let (suspended, visibility_listener) = prepare_visibility()?;
+        let renderer = Rc::new(Renderer::new(browser::canvas()?)?);
+        let assets = AssetLoader::shared();
+        assets.on_progress({
+            let renderer = Rc::clone(&renderer);
+            move |progress| draw_loading_bar(&renderer, progress)
+        });
+        let game = loop {
+            draw_loading_bar(&renderer, 0.0);
+            match game.initialize(&assets).await {
+                Ok(game) => break game,
+                Err(err) => {
+                    error!("error initializing the game: {err:#}");
+                    offer_retry(&assets, &err).await?;
+                }
+            }
+        };
+        assets.clear_on_progress();
+        let game = Rc::new(RefCell::new(game));
+        let recording = recording.map(|recording| Rc::new(RefCell::new(recording)));
+
+        let resize_listener = browser::add_resize_handler({
+            let renderer = Rc::clone(&renderer);
+            move || {
+                if let Err(err) = renderer.fit_to_window() {
+                    error!("error resizing canvas: {err:#?}");
+                }
+            }
+        })?;
+
         let mut game_loop = GameLoop {
             last_frame: browser::now()?,
             accumulated_delta: 0.0,
+            frame_size: 1000.0 / f32::from(updates_per_second),
+            suspended,
+            frame_graph: FrameGraph::default(),
+            updates: 0,
+            recording: recording.clone(),
+            _input_listeners: input_listeners,
+            _visibility_listener: visibility_listener,
+            _resize_listener: resize_listener,
         };
 
-        let renderer = Renderer::new(browser::context()?);
-
         let f = Rc::new(RefCell::new(None));
         let g = Rc::clone(&f);
+        let frame_id = Rc::new(cell::Cell::new(0));
+        let handle = GameLoopHandle {
+            frame: Rc::clone(&f),
+            frame_id: Rc::clone(&frame_id),
+            game: Rc::clone(&game),
+            recording,
+        };
 
         let mut keystate = KeyState::new();
         *g.borrow_mut() = Some(browser::create_raf_closure(move |perf| {
-            process_input(&mut keystate, &mut keyevent_receiver);
+            let mut game = game.borrow_mut();
+            PROFILING.with(|profiling| profiling.set(renderer.debug_mode.get()));
+            process_input(
+                &mut keystate,
+                &mut keyevent_receiver,
+                game_loop.recording.as_deref(),
+                game_loop.updates,
+            );
 
             let frame_time = perf - game_loop.last_frame;
-            game_loop.accumulated_delta += frame_time as f32;
+            if game_loop.suspended.get() {
+                // Skip the time spent in the background so the game resumes
+                // where it was left instead of catching up all at once.
+                if !browser::is_document_hidden().unwrap_or(true) {
+                    game_loop.suspended.set(false);
+                }
+            } else {
+                game_loop.accumulated_delta += frame_time as f32 * time_scale();
+            }
 
-            while game_loop.accumulated_delta > FRAME_SIZE {
-                game.update(&keystate);
-                game_loop.accumulated_delta -= FRAME_SIZE;
+            while game_loop.accumulated_delta > game_loop.frame_size {
+                profile("update", || game.update(&keystate));
+                game_loop.updates += 1;
+                game_loop.accumulated_delta -= game_loop.frame_size;
+            }
+            if let Some(recording) = &game_loop.recording {
+                recording.borrow_mut().set_updates(game_loop.updates);
             }
             game_loop.last_frame = perf;
-            game.draw(&renderer);
+            game_loop.frame_graph.push(frame_time);
+
+            let redraw = renderer.redraw(game.redraw());
+            if !matches!(redraw, Redraw::Nothing) {
+                renderer.begin_frame(&redraw);
+                profile("draw", || game.draw(&renderer));
 
-            if renderer.debug_mode.get() {
-                unsafe {
-                    draw_frame_rate(&renderer, frame_time);
+                if renderer.debug_mode.get() {
+                    game_loop.frame_graph.draw(&renderer);
                 }
+                profile("render", || renderer.end_frame());
             }
 
-            if let Err(err) = browser::request_animation_frame(f.borrow().as_ref().unwrap()) {
-                error!("error requesting animation frame: {err:#?}");
+            match browser::request_animation_frame(f.borrow().as_ref().unwrap()) {
+                Ok(id) => frame_id.set(id),
+                Err(err) => {
+                    error!("error requesting animation frame: {err:#?}");
+                }
             }
         }));
 
-        browser::request_animation_frame(
+        handle.frame_id.set(browser::request_animation_frame(
             g.borrow()
                 .as_ref()
                 .ok_or_else(|| anyhow!("GameLoop: loop is `None`"))?,
-        )?;
+        )?);
+        Ok(handle)
+    }
+}
+
+/// Controls a running [`GameLoop`]. Dropping the handle leaves the loop
+/// running.
+pub(crate) struct GameLoopHandle {
+    frame: Rc<RefCell<Option<browser::LoopClosure>>>,
+    frame_id: Rc<cell::Cell<i32>>,
+    game: Rc<RefCell<Box<dyn Game>>>,
+    recording: Option<Rc<RefCell<InputRecording>>>,
+}
+
+impl GameLoopHandle {
+    /// Returns the key events recorded so far, if the loop was started with
+    /// a recording.
+    pub(crate) fn input_recording(&self) -> Option<InputRecording> {
+        self.recording
+            .as_ref()
+            .map(|recording| recording.borrow().clone())
+    }
+
+    /// Cancels the next frame, tears the game down and drops the loop along
+    /// with its event listeners, so that the canvas can be removed.
+    pub(crate) fn stop(self) -> Result<()> {
+        browser::cancel_animation_frame(self.frame_id.get())?;
+        // The loop closure holds the only other reference to itself, so
+        // dropping it here also drops everything it captured.
+        self.frame.borrow_mut().take();
+        self.game.borrow_mut().teardown();
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+const LOADING_BAR: Rect = Rect::from_xy(150.0, 290.0, 300.0, 20.0);
+
+/// Lists the assets that could not be loaded, or `err` if they all were, and
+/// waits for the player to try loading them again.
+async fn offer_retry(assets: &AssetLoader, err: &anyhow::Error) -> Result<()> {
+    let mut failures = assets
+        .failures()
+        .into_iter()
+        .map(|(url, reason)| format!("{url}: {reason}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if failures.is_empty() {
+        failures = format!("{err:#}");
+    }
+    browser::show_retry_overlay(
+        &i18n::t("loading_failed.title"),
+        &failures,
+        &i18n::t("loading_failed.retry"),
+    )
+    .await
+}
+
+fn draw_loading_bar(renderer: &Renderer, progress: f32) {
+    let filled = LOADING_BAR.width * progress.clamp(0.0, 1.0);
+    renderer.begin_frame(&Redraw::Everything);
+    renderer.clear(&LOADING_BAR);
+    renderer.fill_rect(
+        &Rect::new(LOADING_BAR.position, filled, LOADING_BAR.height),
+        "black",
+    );
+    renderer.draw_rect(&LOADING_BAR);
+    renderer.end_frame();
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub(crate) struct Rect {
     pub(crate) position: Point,
-    pub(crate) width: i16,
-    pub(crate) height: i16,
+    pub(crate) width: f32,
+    pub(crate) height: f32,
 }
 
 impl Rect {
-    pub(crate) const fn new(position: Point, width: i16, height: i16) -> Self {
+    pub(crate) const fn new(position: Point, width: f32, height: f32) -> Self {
         Self {
             position,
             width,
@@ -134,7 +389,7 @@ impl Rect {
         }
     }
 
-    pub(crate) const fn from_xy(x: i16, y: i16, width: i16, height: i16) -> Self {
+    pub(crate) const fn from_xy(x: f32, y: f32, width: f32, height: f32) -> Self {
         Rect::new(Point { x, y }, width, height)
     }
 
@@ -143,114 +398,729 @@ impl Rect {
             && (self.top() < rect.bottom() && self.bottom() > rect.top())
     }
 
-    pub(crate) const fn x(&self) -> i16 {
+    pub(crate) fn contains(&self, point: Point) -> bool {
+        (self.left()..self.right()).contains(&point.x)
+            && (self.top()..self.bottom()).contains(&point.y)
+    }
+
+    /// Returns the smallest rectangle that contains both rectangles.
+    pub(crate) fn union(&self, rect: &Rect) -> Rect {
+        let left = self.left().min(rect.left());
+        let top = self.top().min(rect.top());
+        Rect::from_xy(
+            left,
+            top,
+            self.right().max(rect.right()) - left,
+            self.bottom().max(rect.bottom()) - top,
+        )
+    }
+
+    /// Returns the area covered by both rectangles, if they overlap.
+    pub(crate) fn intersection(&self, rect: &Rect) -> Option<Rect> {
+        let left = self.left().max(rect.left());
+        let top = self.top().max(rect.top());
+        let right = self.right().min(rect.right());
+        let bottom = self.bottom().min(rect.bottom());
+        (left < right && top < bottom).then(|| Rect::from_xy(left, top, right - left, bottom - top))
+    }
+
+    pub(crate) const fn x(&self) -> f32 {
         self.position.x
     }
 
-    pub(crate) fn set_x(&mut self, x: i16) {
+    pub(crate) fn set_x(&mut self, x: f32) {
         self.position.x = x;
     }
 
-    pub(crate) const fn y(&self) -> i16 {
+    pub(crate) const fn y(&self) -> f32 {
         self.position.y
     }
 
-    pub(crate) fn set_y(&mut self, y: i16) {
+    pub(crate) fn set_y(&mut self, y: f32) {
         self.position.y = y;
     }
 
-    pub(crate) const fn left(&self) -> i16 {
+    pub(crate) fn move_by(&mut self, x: f32, y: f32) {
+        self.position.x += x;
+        self.position.y += y;
+    }
+
+    pub(crate) const fn left(&self) -> f32 {
         self.x()
     }
 
-    pub(crate) const fn right(&self) -> i16 {
+    pub(crate) const fn right(&self) -> f32 {
         self.x() + self.width
     }
 
-    pub(crate) const fn top(&self) -> i16 {
+    pub(crate) const fn top(&self) -> f32 {
         self.y()
     }
 
-    pub(crate) const fn bottom(&self) -> i16 {
+    pub(crate) const fn bottom(&self) -> f32 {
         self.y() + self.height
     }
+
+    pub(crate) const fn center(&self) -> Point {
+        Point {
+            x: self.x() + self.width / 2.0,
+            y: self.y() + self.height / 2.0,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub(crate) struct Point {
-    pub x: i16,
-    pub y: i16,
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    /// Snaps the point to whole pixels, so that sprites at fractional world
+    /// positions are still drawn crisply.
+    pub(crate) fn round(self) -> Point {
+        Point {
+            x: self.x.round(),
+            y: self.y.round(),
+        }
+    }
+}
+
+/// The part of the world that is visible on screen.
+///
+/// World coordinates are translated into screen coordinates by subtracting
+/// the camera position, so scrolling only has to move the camera.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Camera {
+    position: Point,
+}
+
+impl Camera {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn x(&self) -> f32 {
+        self.position.x
+    }
+
+    pub(crate) fn set_x(&mut self, x: f32) {
+        self.position.x = x;
+    }
+
+    pub(crate) fn to_screen(self, point: Point) -> Point {
+        Point {
+            x: point.x - self.position.x,
+            y: point.y - self.position.y,
+        }
+    }
+}
+
+/// Flipping and rotation applied around the center of a drawing.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Transform {
+    pub(crate) flip_horizontal: bool,
+    pub(crate) flip_vertical: bool,
+    /// Clockwise rotation in radians.
+    pub(crate) rotation: f64,
+}
+
+impl Transform {
+    pub(crate) const fn flipped_horizontally() -> Self {
+        Self {
+            flip_horizontal: true,
+            flip_vertical: false,
+            rotation: 0.0,
+        }
+    }
+}
+
+/// How a tint is combined with what has already been drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BlendMode {
+    Normal,
+    Multiply,
+}
+
+impl BlendMode {
+    const fn composite_operation(self) -> &'static str {
+        match self {
+            Self::Normal => "source-over",
+            Self::Multiply => "multiply",
+        }
+    }
+}
+
+/// A screen shake whose magnitude (in pixels) is multiplied by `decay` on every
+/// drawn frame until it fades out.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ScreenShake {
+    magnitude: f64,
+    decay: f64,
+}
+
+impl ScreenShake {
+    const MINIMUM_MAGNITUDE: f64 = 0.5;
+
+    pub(crate) const fn new(magnitude: f64, decay: f64) -> Self {
+        Self { magnitude, decay }
+    }
+
+    fn is_active(&self) -> bool {
+        self.magnitude >= Self::MINIMUM_MAGNITUDE
+    }
+
+    fn next_offset(&mut self) -> (f64, f64) {
+        if !self.is_active() {
+            return (0.0, 0.0);
+        }
+        let mut rng = rand::thread_rng();
+        let offset = (
+            rng.gen_range(-self.magnitude..=self.magnitude),
+            rng.gen_range(-self.magnitude..=self.magnitude),
+        );
+        self.magnitude *= self.decay;
+        offset
+    }
+}
+
+/// A rumble of the connected gamepads, felt through their strong
+/// (low-frequency) and weak (high-frequency) motors.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Rumble {
+    /// In milliseconds.
+    duration: f64,
+    strong: f64,
+    weak: f64,
+}
+
+impl Rumble {
+    pub(crate) const fn new(duration: f64, strong: f64, weak: f64) -> Self {
+        Self {
+            duration,
+            strong,
+            weak,
+        }
+    }
+
+    /// Rumbles the gamepads with the motors scaled by `intensity`, from `0.0`
+    /// (not at all) to `1.0`. Tests run without a browser, and so without
+    /// gamepads.
+    pub(crate) fn play(&self, intensity: f32) {
+        if cfg!(test) || intensity <= 0.0 {
+            return;
+        }
+        let intensity = f64::from(intensity.min(1.0));
+        if let Err(err) = browser::rumble(
+            self.duration,
+            self.strong * intensity,
+            self.weak * intensity,
+        ) {
+            error!("error rumbling the gamepads: {err:#?}");
+        }
+    }
+}
+
+/// The drawing primitives a [`Renderer`] is built on.
+///
+/// Transforms, alpha and blend mode behave like the 2D canvas state: they
+/// apply to every later draw call until the matching [`RenderBackend::restore`].
+trait RenderBackend: std::fmt::Debug {
+    fn begin_frame(&self, scale: f64);
+    fn end_frame(&self);
+    /// Limits every later draw call to `rect` until the end of the frame.
+    fn clip(&self, rect: &Rect);
+    fn save(&self);
+    fn restore(&self);
+    fn translate(&self, x: f64, y: f64);
+    fn rotate(&self, angle: f64);
+    fn scale(&self, x: f64, y: f64);
+    fn multiply_alpha(&self, alpha: f64);
+    fn set_blend_mode(&self, mode: BlendMode);
+    fn clear(&self, rect: &Rect);
+    fn draw_image(&self, image: &ImageSource, frame: &Rect, destination: &Rect);
+    fn stroke_rect(&self, rect: &Rect, style: &StrokeStyle);
+    fn fill_rect(&self, rect: &Rect, color: &str);
+    fn fill_polygon(&self, points: &[(f64, f64)], color: &str);
+    fn fill_text(&self, text: &str, location: (f64, f64), style: &TextStyle) -> Result<()>;
+    fn measure_text(&self, text: &str, style: &TextStyle) -> Result<f64>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl TextAlign {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Left => "left",
+            Self::Center => "center",
+            Self::Right => "right",
+        }
+    }
+}
+
+/// How a piece of text looks. Styles are meant to be built as constants:
+///
+/// ```ignore
+/// const TITLE: TextStyle = TextStyle::new("'Ken Future', sans-serif", 48)
+///     .bold()
+///     .with_color("white")
+///     .aligned(TextAlign::Center);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct TextStyle {
+    family: &'static str,
+    size: u16,
+    bold: bool,
+    color: &'static str,
+    align: TextAlign,
+    stroke: Option<(&'static str, u8)>,
+}
+
+impl TextStyle {
+    pub(crate) const fn new(family: &'static str, size: u16) -> Self {
+        Self {
+            family,
+            size,
+            bold: false,
+            color: "black",
+            align: TextAlign::Left,
+            stroke: None,
+        }
+    }
+
+    pub(crate) const fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+
+    pub(crate) const fn with_color(self, color: &'static str) -> Self {
+        Self { color, ..self }
+    }
+
+    pub(crate) const fn aligned(self, align: TextAlign) -> Self {
+        Self { align, ..self }
+    }
+
+    /// Outlines the text with a `width` pixel line of `color`.
+    pub(crate) const fn with_stroke(self, color: &'static str, width: u8) -> Self {
+        Self {
+            stroke: Some((color, width)),
+            ..self
+        }
+    }
+
+    fn font(&self) -> String {
+        let weight = if self.bold { "bold " } else { "" };
+        format!("{weight}{}px {}", self.size, self.family)
+    }
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self::new("serif", 21)
+    }
+}
+
+/// How an outline is broken up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub(crate) enum LineDash {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl LineDash {
+    /// Returns the lengths in pixels of the drawn and skipped parts of the
+    /// line, or `None` if it is not broken up.
+    const fn pattern(self) -> Option<(f64, f64)> {
+        match self {
+            LineDash::Solid => None,
+            LineDash::Dashed => Some((6.0, 4.0)),
+            LineDash::Dotted => Some((2.0, 3.0)),
+        }
+    }
+
+    /// Returns where the drawn parts of a line from `start` to `end` start
+    /// and end, for backends that draw dashes one by one.
+    fn segments(self, start: f64, end: f64) -> Vec<(f64, f64)> {
+        let Some((on, off)) = self.pattern() else {
+            return vec![(start, end)];
+        };
+        (0..)
+            .map(|i| start + f64::from(i) * (on + off))
+            .take_while(|&from| from < end)
+            .map(|from| (from, (from + on).min(end)))
+            .collect()
+    }
+}
+
+/// The color and dash of an outline, built like a [`TextStyle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct StrokeStyle {
+    color: &'static str,
+    dash: LineDash,
+}
+
+impl StrokeStyle {
+    pub(crate) const fn new(color: &'static str) -> Self {
+        Self {
+            color,
+            dash: LineDash::Solid,
+        }
+    }
+
+    pub(crate) const fn with_dash(self, dash: LineDash) -> Self {
+        Self { dash, ..self }
+    }
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self::new("black")
+    }
+}
+
+/// What a bounding box drawn in debug mode belongs to. Each category can be
+/// given its own [`StrokeStyle`] with [`Renderer::set_debug_styles`], so that
+/// overlapping boxes can be told apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum DebugCategory {
+    Player,
+    Platform,
+    /// The tops of platforms, where the player lands.
+    LandingSurface,
+    /// Anything that knocks the player out.
+    Barrier,
+    /// Entities that are picked up.
+    Item,
+    Other,
+}
+
+/// Picks the WebGL backend unless `?renderer=canvas` is given or WebGL is not
+/// available, in which case the 2D canvas is used.
+fn create_backend(canvas: &HtmlCanvasElement) -> Result<Box<dyn RenderBackend>> {
+    if browser::query_param("renderer")?.as_deref() != Some("canvas") {
+        match browser::webgl_context(canvas).and_then(webgl::WebGl::new) {
+            Ok(backend) => return Ok(Box::new(backend)),
+            Err(err) => {
+                warn!("WebGL is not available, falling back to the 2D canvas: {err:#?}");
+            }
+        }
+    }
+    Ok(Box::new(canvas2d::Canvas2d::new(browser::context(canvas)?)))
+}
+
+const CIRCLE_SEGMENTS: usize = 32;
+const HEART_SEGMENTS: usize = 16;
+
+fn cubic_bezier(
+    (x0, y0): (f64, f64),
+    (x1, y1): (f64, f64),
+    (x2, y2): (f64, f64),
+    (x3, y3): (f64, f64),
+    t: f64,
+) -> (f64, f64) {
+    let u = 1.0 - t;
+    let [w0, w1, w2, w3] = [u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t];
+    (
+        w0 * x0 + w1 * x1 + w2 * x2 + w3 * x3,
+        w0 * y0 + w1 * y1 + w2 * y2 + w3 * y3,
+    )
 }
 
 #[derive(Debug)]
 pub(crate) struct Renderer {
-    context: CanvasRenderingContext2d,
+    backend: Box<dyn RenderBackend>,
+    queue: RefCell<DrawQueue>,
+    /// Tests draw without a canvas, through a backend that records calls.
+    canvas: Option<HtmlCanvasElement>,
+    logical_width: f64,
+    logical_height: f64,
+    scale: cell::Cell<f64>,
+    invalidated: cell::Cell<bool>,
     debug_mode: cell::Cell<bool>,
+    debug_styles: cell::Cell<&'static [(DebugCategory, StrokeStyle)]>,
+    shake: cell::Cell<ScreenShake>,
 }
 
 impl Renderer {
-    fn new(context: CanvasRenderingContext2d) -> Self {
-        Self {
-            context,
+    /// Creates a renderer whose logical size is the initial size of the canvas.
+    fn new(canvas: HtmlCanvasElement) -> Result<Self> {
+        let renderer = Self::unscaled(canvas)?;
+        renderer.fit_to_window()?;
+        Ok(renderer)
+    }
+
+    /// Creates a renderer that draws to the canvas at its logical size,
+    /// whatever the size of the window.
+    fn unscaled(canvas: HtmlCanvasElement) -> Result<Self> {
+        Ok(Self {
+            backend: create_backend(&canvas)?,
+            queue: RefCell::new(DrawQueue::default()),
+            logical_width: canvas.width().into(),
+            logical_height: canvas.height().into(),
+            canvas: Some(canvas),
+            scale: cell::Cell::new(1.0),
+            invalidated: cell::Cell::new(true),
             debug_mode: cell::Cell::new(false),
+            debug_styles: cell::Cell::new(&[]),
+            shake: cell::Cell::new(ScreenShake::default()),
+        })
+    }
+
+    /// Resizes the canvas to the largest size that fits in the window without
+    /// changing its aspect ratio. Drawing keeps using logical coordinates.
+    ///
+    /// The backing store is additionally multiplied by `devicePixelRatio` so
+    /// that the canvas stays sharp on high density displays.
+    fn fit_to_window(&self) -> Result<()> {
+        let Some(canvas) = &self.canvas else {
+            return Ok(());
+        };
+        let (window_width, window_height) = browser::window_size()?;
+        let fit = (window_width / self.logical_width).min(window_height / self.logical_height);
+        let pixel_ratio = browser::device_pixel_ratio()?;
+        let (css_width, css_height) = (self.logical_width * fit, self.logical_height * fit);
+
+        browser::set_css_size(canvas, css_width, css_height)?;
+        canvas.set_width((css_width * pixel_ratio).round() as u32);
+        canvas.set_height((css_height * pixel_ratio).round() as u32);
+        self.scale.set(fit * pixel_ratio);
+        // Resizing the canvas clears it, so the next frame has to be complete.
+        self.invalidated.set(true);
+        Ok(())
+    }
+
+    /// Combines the redraw the game asked for with what the renderer itself
+    /// needs, like a full frame after a resize or while the screen shakes.
+    fn redraw(&self, requested: Redraw) -> Redraw {
+        if self.invalidated.replace(false) || self.shake.get().is_active() {
+            return Redraw::Everything;
+        }
+        if self.debug_mode.get() {
+            return requested.union(Redraw::Region(FrameGraph::area(self)));
+        }
+        requested
+    }
+
+    /// Maps logical coordinates onto the current canvas size and limits
+    /// drawing to the part of the screen that is redrawn.
+    fn begin_frame(&self, redraw: &Redraw) {
+        self.backend.begin_frame(self.scale.get());
+        if let Redraw::Region(rect) = redraw {
+            self.backend.clip(rect);
         }
     }
 
+    /// Submits everything drawn since [`Renderer::begin_frame`].
+    fn end_frame(&self) {
+        self.queue.borrow_mut().flush(&*self.backend);
+        self.backend.end_frame();
+    }
+
+    fn push(&self, command: DrawCommand) {
+        self.queue.borrow_mut().push(command);
+    }
+
     pub(crate) fn set_debug_mode(&self, debug_mode: bool) {
         self.debug_mode.set(debug_mode);
     }
 
+    /// Sets how the bounding boxes of each category are outlined. Those of
+    /// categories missing from `styles` are drawn in the default style.
+    pub(crate) fn set_debug_styles(&self, styles: &'static [(DebugCategory, StrokeStyle)]) {
+        self.debug_styles.set(styles);
+    }
+
+    /// Starts shaking everything drawn through [`Renderer::with_camera`], unless
+    /// a stronger shake is already running.
+    pub(crate) fn shake(&self, shake: ScreenShake) {
+        if shake.magnitude >= self.shake.get().magnitude {
+            self.shake.set(shake);
+        }
+    }
+
+    /// Runs `draw` with every draw call translated from world coordinates into
+    /// the screen coordinates of `camera`, offset by the current screen shake.
+    pub(crate) fn with_camera(&self, camera: &Camera, draw: impl FnOnce(&Renderer)) {
+        let origin = camera.to_screen(Point::default()).round();
+        let mut shake = self.shake.get();
+        let (shake_x, shake_y) = shake.next_offset();
+        self.shake.set(shake);
+
+        self.push(DrawCommand::Save);
+        self.push(DrawCommand::Translate(
+            f64::from(origin.x) + shake_x,
+            f64::from(origin.y) + shake_y,
+        ));
+        draw(self);
+        self.push(DrawCommand::Restore);
+    }
+
     pub(crate) fn clear(&self, rect: &Rect) {
-        self.context.clear_rect(
-            rect.x().into(),
-            rect.y().into(),
-            rect.width.into(),
-            rect.height.into(),
-        )
+        self.push(DrawCommand::Clear(*rect));
     }
 
-    pub(crate) fn draw_image(&self, image: &HtmlImageElement, frame: &Rect, destination: &Rect) {
-        self.context
-            .draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
-                image,
-                frame.x().into(),
-                frame.y().into(),
-                frame.width.into(),
-                frame.height.into(),
-                destination.x().into(),
-                destination.y().into(),
-                destination.width.into(),
-                destination.height.into(),
-            )
-            .expect("error drawing image");
+    /// Runs `draw` with every draw call made translucent by `alpha`, on top of
+    /// any alpha that is already applied.
+    pub(crate) fn with_alpha(&self, alpha: f64, draw: impl FnOnce(&Renderer)) {
+        self.push(DrawCommand::Save);
+        self.push(DrawCommand::MultiplyAlpha(alpha.clamp(0.0, 1.0)));
+        draw(self);
+        self.push(DrawCommand::Restore);
     }
 
-    pub(crate) fn draw_entire_image(&self, image: &HtmlImageElement, position: Point) {
-        self.context
-            .draw_image_with_html_image_element(image, position.x.into(), position.y.into())
-            .expect("error drawing image");
+    /// Fills `rect` with `color`, blended with what has already been drawn.
+    pub(crate) fn tint(&self, rect: &Rect, color: &str, mode: BlendMode) {
+        self.push(DrawCommand::Save);
+        self.push(DrawCommand::SetBlendMode(mode));
+        self.push(DrawCommand::FillRect(*rect, color.to_string()));
+        self.push(DrawCommand::Restore);
     }
 
-    pub(crate) fn draw_rect(&self, rect: &Rect) {
-        self.context.stroke_rect(
-            rect.x().into(),
-            rect.y().into(),
-            rect.width.into(),
-            rect.height.into(),
+    /// Runs `draw` with every draw call flipped and rotated by `transform`
+    /// around `center`.
+    pub(crate) fn with_transform(
+        &self,
+        center: &Point,
+        transform: &Transform,
+        draw: impl FnOnce(&Renderer),
+    ) {
+        let (x, y) = (f64::from(center.x), f64::from(center.y));
+        let scale_x = if transform.flip_horizontal { -1.0 } else { 1.0 };
+        let scale_y = if transform.flip_vertical { -1.0 } else { 1.0 };
+
+        self.push(DrawCommand::Save);
+        self.push(DrawCommand::Translate(x, y));
+        self.push(DrawCommand::Rotate(transform.rotation));
+        self.push(DrawCommand::Scale(scale_x, scale_y));
+        self.push(DrawCommand::Translate(-x, -y));
+        draw(self);
+        self.push(DrawCommand::Restore);
+    }
+
+    pub(crate) fn draw_image(
+        &self,
+        image: &ImageSource,
+        frame: &Rect,
+        destination: &Rect,
+        transform: &Transform,
+    ) {
+        let destination = Rect::new(
+            destination.position.round(),
+            destination.width,
+            destination.height,
         );
+        self.with_transform(&destination.center(), transform, |renderer| {
+            renderer.push(DrawCommand::Image {
+                image: image.clone(),
+                frame: *frame,
+                destination,
+            });
+        });
     }
 
-    pub(crate) fn draw_text(&self, test: &str, location: &Point) -> Result<()> {
-        self.context.set_font("16pt serif");
-        self.context
-            .fill_text(test, location.x.into(), location.y.into())
-            .map_err(|err| anyhow!("error drawing text: {err:#?}"))?;
-        Ok(())
+    pub(crate) fn draw_entire_image(&self, image: &ImageSource, position: Point) {
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        self.push(DrawCommand::Image {
+            image: image.clone(),
+            frame: Rect::from_xy(0.0, 0.0, width, height),
+            destination: Rect::new(position.round(), width, height),
+        });
+    }
+
+    pub(crate) fn draw_rect(&self, rect: &Rect) {
+        self.push(DrawCommand::StrokeRect(*rect, StrokeStyle::default()));
+    }
+
+    pub(crate) fn fill_rect(&self, rect: &Rect, color: &str) {
+        self.push(DrawCommand::FillRect(*rect, color.to_string()));
+    }
+
+    pub(crate) fn fill_circle(&self, center: &Point, radius: f32, color: &str) {
+        let (x, y, radius) = (f64::from(center.x), f64::from(center.y), f64::from(radius));
+        let points: Vec<_> = (0..CIRCLE_SEGMENTS)
+            .map(|i| {
+                let angle = std::f64::consts::TAU * i as f64 / CIRCLE_SEGMENTS as f64;
+                (x + radius * angle.cos(), y + radius * angle.sin())
+            })
+            .collect();
+        self.push(DrawCommand::FillPolygon(points, color.to_string()));
     }
 
-    pub(crate) fn draw_bounding_box(&self, rect: &Rect) {
+    /// Fills the polygon with corners at `points`, in order.
+    pub(crate) fn fill_polygon(&self, points: &[Point], color: &str) {
+        let points = points
+            .iter()
+            .map(|point| (f64::from(point.x), f64::from(point.y)))
+            .collect();
+        self.push(DrawCommand::FillPolygon(points, color.to_string()));
+    }
+
+    pub(crate) fn fill_heart(&self, center: &Point, size: f32, color: &str) {
+        let (x, y, size) = (f64::from(center.x), f64::from(center.y), f64::from(size));
+        let tip = (x, y + size * 0.35);
+        let notch = (x, y - size * 0.2);
+        let left = [
+            (x - size * 0.6, y - size * 0.1),
+            (x - size * 0.25, y - size * 0.6),
+        ];
+        let right = [
+            (x + size * 0.25, y - size * 0.6),
+            (x + size * 0.6, y - size * 0.1),
+        ];
+        let points: Vec<_> = (0..HEART_SEGMENTS)
+            .map(|i| {
+                cubic_bezier(
+                    tip,
+                    left[0],
+                    left[1],
+                    notch,
+                    i as f64 / HEART_SEGMENTS as f64,
+                )
+            })
+            .chain((0..HEART_SEGMENTS).map(|i| {
+                cubic_bezier(
+                    notch,
+                    right[0],
+                    right[1],
+                    tip,
+                    i as f64 / HEART_SEGMENTS as f64,
+                )
+            }))
+            .collect();
+        self.push(DrawCommand::FillPolygon(points, color.to_string()));
+    }
+
+    /// Draws `text` with its baseline at `location`, aligned around it as
+    /// `style` says.
+    pub(crate) fn draw_text(&self, text: &str, location: &Point, style: &TextStyle) {
+        self.push(DrawCommand::Text {
+            text: text.to_string(),
+            location: (location.x.into(), location.y.into()),
+            style: *style,
+        });
+    }
+
+    /// Returns how wide `text` is when drawn with `style`.
+    pub(crate) fn measure_text(&self, text: &str, style: &TextStyle) -> Result<f64> {
+        self.backend.measure_text(text, style)
+    }
+
+    pub(crate) fn draw_bounding_box(&self, rect: &Rect, category: DebugCategory) {
         if self.debug_mode.get() {
-            self.draw_rect(rect);
+            let style = self
+                .debug_styles
+                .get()
+                .iter()
+                .find(|(styled, _)| *styled == category)
+                .map_or_else(StrokeStyle::default, |(_, style)| *style);
+            self.push(DrawCommand::StrokeRect(*rect, style));
         }
     }
 }
@@ -259,12 +1129,21 @@ impl Renderer {
 enum KeyPress {
     KeyUp(KeyboardEvent),
     KeyDown(KeyboardEvent),
+    PointerUp,
+    PointerDown,
+    /// The canvas lost the focus, so the keys held down will be released
+    /// without it hearing about it.
+    Blur,
 }
 
-fn prepare_input() -> Result<UnboundedReceiver<KeyPress>> {
+type KeyListeners = [browser::EventListenerHandle; 7];
+
+fn prepare_input() -> Result<(UnboundedReceiver<KeyPress>, KeyListeners)> {
     let (keydown_sender, keyevent_receiver) = unbounded();
     let keydown_sender = Rc::new(RefCell::new(keydown_sender));
     let keyup_sender = Rc::clone(&keydown_sender);
+    let pointer_sender = Rc::clone(&keydown_sender);
+    let blur_sender = Rc::clone(&keydown_sender);
 
     let onkeydown = browser::closure_wrap(Box::new(move |keycode| {
         if let Err(err) = keydown_sender
@@ -284,73 +1163,170 @@ fn prepare_input() -> Result<UnboundedReceiver<KeyPress>> {
         }
     }) as Box<dyn FnMut(KeyboardEvent)>);
 
-    browser::canvas()?.set_onkeydown(Some(onkeydown.as_ref().unchecked_ref()));
-    browser::canvas()?.set_onkeyup(Some(onkeyup.as_ref().unchecked_ref()));
-    onkeydown.forget();
-    onkeyup.forget();
-    Ok(keyevent_receiver)
+    let pointer_handler = |press: KeyPress| {
+        let sender = Rc::clone(&pointer_sender);
+        browser::closure_wrap(Box::new(move |_event| {
+            if let Err(err) = sender.borrow_mut().start_send(press.clone()) {
+                error!("error sending pointer event: {err:#?}");
+            }
+        }) as Box<dyn FnMut(MouseEvent)>)
+    };
+
+    let [blur_listener, focusout_listener] = browser::add_blur_handler(move || {
+        if let Err(err) = blur_sender.borrow_mut().start_send(KeyPress::Blur) {
+            error!("error sending blur event: {err:#?}");
+        }
+    })?;
+
+    let canvas = browser::canvas()?;
+    // A pointer pressed on the canvas can be let go of anywhere, or taken
+    // over by the browser, e.g. to scroll, and has to be released either way.
+    let window = browser::window()?;
+    let listeners = [
+        browser::add_event_listener(&canvas, "keydown", onkeydown)?,
+        browser::add_event_listener(&canvas, "keyup", onkeyup)?,
+        browser::add_event_listener(
+            &canvas,
+            "pointerdown",
+            pointer_handler(KeyPress::PointerDown),
+        )?,
+        browser::add_event_listener(&window, "pointerup", pointer_handler(KeyPress::PointerUp))?,
+        browser::add_event_listener(
+            &window,
+            "pointercancel",
+            pointer_handler(KeyPress::PointerUp),
+        )?,
+        blur_listener,
+        focusout_listener,
+    ];
+    Ok((keyevent_receiver, listeners))
+}
+
+fn prepare_visibility() -> Result<(Rc<cell::Cell<bool>>, browser::EventListenerHandle)> {
+    let suspended = Rc::new(cell::Cell::new(browser::is_document_hidden()?));
+    let listener = browser::add_visibility_change_handler({
+        let suspended = Rc::clone(&suspended);
+        move |hidden| {
+            if hidden {
+                suspended.set(true);
+            }
+        }
+    })?;
+    Ok((suspended, listener))
 }
 
-fn process_input(state: &mut KeyState, keyevent_receiver: &mut UnboundedReceiver<KeyPress>) {
+fn process_input(
+    state: &mut KeyState,
+    keyevent_receiver: &mut UnboundedReceiver<KeyPress>,
+    recording: Option<&RefCell<InputRecording>>,
+    update: u64,
+) {
     loop {
-        match keyevent_receiver.try_next() {
+        let (code, pressed) = match keyevent_receiver.try_next() {
             Ok(None) => break,
             Err(_err) => break,
-            Ok(Some(evt)) => match evt {
-                KeyPress::KeyUp(evt) => state.set_released(&evt.code()),
-                KeyPress::KeyDown(evt) => state.set_pressed(&evt.code(), evt),
-            },
+            Ok(Some(KeyPress::Blur)) => {
+                for code in state.release_all() {
+                    debug!("released on blur: {:?}", code);
+                    if let Some(recording) = recording {
+                        recording.borrow_mut().record(update, &code, false);
+                    }
+                }
+                continue;
+            }
+            Ok(Some(KeyPress::KeyUp(evt))) => (evt.code(), false),
+            Ok(Some(KeyPress::KeyDown(evt))) => (evt.code(), true),
+            Ok(Some(KeyPress::PointerUp)) => (KeyState::POINTER.to_string(), false),
+            Ok(Some(KeyPress::PointerDown)) => (KeyState::POINTER.to_string(), true),
+        };
+        let changed = if pressed {
+            trace!("pressed: {:?}", code);
+            state.set_pressed(&code)
+        } else {
+            trace!("released: {:?}", code);
+            state.set_released(&code)
+        };
+        // Held keys repeat their keydown events, which change nothing.
+        if let (true, Some(recording)) = (changed, recording) {
+            recording.borrow_mut().record(update, &code, pressed);
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct KeyState {
-    pressed_keys: HashMap<String, KeyboardEvent>,
+    pressed_keys: HashSet<String>,
 }
 
 impl KeyState {
-    fn new() -> Self {
+    /// The code the mouse button or a finger on the canvas is pressed as,
+    /// like a key.
+    pub(crate) const POINTER: &'static str = "Pointer";
+
+    pub(crate) fn new() -> Self {
+        KeyState {
+            pressed_keys: HashSet::new(),
+        }
+    }
+
+    /// Returns the state in which exactly the keys `codes` are held down, for
+    /// scripting input in tests.
+    #[cfg(test)]
+    pub(crate) fn pressing(codes: &[&str]) -> Self {
         KeyState {
-            pressed_keys: HashMap::new(),
+            pressed_keys: codes.iter().map(|code| code.to_string()).collect(),
         }
     }
 
     pub(crate) fn is_pressed(&self, code: &str) -> bool {
-        self.pressed_keys.contains_key(code)
+        self.pressed_keys.contains(code)
+    }
+
+    pub(crate) fn pressed_keys(&self) -> impl Iterator<Item = &str> {
+        self.pressed_keys.iter().map(String::as_str)
+    }
+
+    /// Returns whether the key was up before.
+    fn set_pressed(&mut self, code: &str) -> bool {
+        self.pressed_keys.insert(code.into())
     }
 
-    fn set_pressed(&mut self, code: &str, event: KeyboardEvent) {
-        log!("pressed: {:?}", code);
-        self.pressed_keys.insert(code.into(), event);
+    /// Returns whether the key was down before.
+    fn set_released(&mut self, code: &str) -> bool {
+        self.pressed_keys.remove(code)
     }
 
-    fn set_released(&mut self, code: &str) {
-        log!("released: {:?}", code);
-        self.pressed_keys.remove(code);
+    /// Releases every key held down, returning their codes.
+    fn release_all(&mut self) -> Vec<String> {
+        self.pressed_keys.drain().collect()
     }
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct Image {
-    element: HtmlImageElement,
+    element: ImageSource,
     bounding_box: Rect,
+    mask: Option<Rc<CollisionMask>>,
 }
 
 impl Image {
-    pub(crate) fn new(element: HtmlImageElement, position: Point) -> Self {
-        let bounding_box = Rect::new(
-            position,
-            element.width().try_into().unwrap(),
-            element.height().try_into().unwrap(),
-        );
+    /// Creates an image drawn at `position`. It has a collision mask if one
+    /// was loaded for `element` with [`load_image_mask`].
+    pub(crate) fn new(element: ImageSource, position: Point) -> Self {
+        let bounding_box = Rect::new(position, element.width() as f32, element.height() as f32);
+        let mask = collision::image_mask(&element);
         Self {
             element,
             bounding_box,
+            mask,
         }
     }
 
-    pub(crate) fn right(&self) -> i16 {
+    pub(crate) fn mask(&self) -> Option<&CollisionMask> {
+        self.mask.as_deref()
+    }
+
+    pub(crate) fn right(&self) -> f32 {
         self.bounding_box.right()
     }
 
@@ -358,14 +1334,18 @@ impl Image {
         &self.bounding_box
     }
 
-    pub(crate) fn set_x(&mut self, x: i16) {
+    pub(crate) fn set_x(&mut self, x: f32) {
         self.bounding_box.set_x(x);
     }
 
-    pub(crate) fn move_horizontally(&mut self, distance: i16) {
+    pub(crate) fn move_horizontally(&mut self, distance: f32) {
         self.bounding_box.set_x(self.bounding_box.x() + distance);
     }
 
+    pub(crate) fn move_by(&mut self, x: f32, y: f32) {
+        self.bounding_box.move_by(x, y);
+    }
+
     pub(crate) fn draw(&self, renderer: &Renderer) {
         renderer.draw_entire_image(&self.element, self.bounding_box.position);
     }
@@ -376,29 +1356,46 @@ pub(crate) struct Sheet {
     pub(crate) frames: HashMap<String, Cell>,
 }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+impl Sheet {
+    /// Sets the hitboxes of the frames named in `hitboxes`, which usually
+    /// come from a file next to the sheet.
+    pub(crate) fn with_hitboxes(mut self, hitboxes: HashMap<String, SheetRect>) -> Self {
+        for (name, hitbox) in hitboxes {
+            if let Some(cell) = self.frames.get_mut(&name) {
+                cell.hitbox = Some(hitbox);
+            }
+        }
+        self
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
 pub(crate) struct SheetRect {
-    pub(crate) x: i16,
-    pub(crate) y: i16,
-    pub(crate) w: i16,
-    pub(crate) h: i16,
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) w: f32,
+    pub(crate) h: f32,
 }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct Cell {
     pub(crate) frame: SheetRect,
     pub(crate) sprite_source_size: SheetRect,
+    /// The part of the frame that collides with things, relative to its top
+    /// left corner.
+    #[serde(default)]
+    pub(crate) hitbox: Option<SheetRect>,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct SpriteSheet {
     sheet: Sheet,
-    image: HtmlImageElement,
+    image: ImageSource,
 }
 
 impl SpriteSheet {
-    pub(crate) fn new(sheet: Sheet, image: HtmlImageElement) -> Self {
+    pub(crate) fn new(sheet: Sheet, image: ImageSource) -> Self {
         Self { sheet, image }
     }
 
@@ -407,76 +1404,27 @@ impl SpriteSheet {
     }
 
     pub(crate) fn draw(&self, renderer: &Renderer, source: &Rect, destination: &Rect) {
-        renderer.draw_image(&self.image, source, destination);
+        renderer.draw_image(&self.image, source, destination, &Transform::default());
     }
 }
 
-#[derive(Debug, Clone)]
-pub(crate) struct Audio {
-    context: AudioContext,
-}
-
-#[derive(Debug, Clone)]
-pub(crate) struct Sound {
-    buffer: AudioBuffer,
-}
-
-impl Audio {
-    pub(crate) fn new() -> Result<Self> {
-        Ok(Audio {
-            context: sound::create_audio_context()?,
-        })
-    }
-
-    pub(crate) async fn load_sound(&self, filename: &str) -> Result<Sound> {
-        let array_buffer = browser::fetch_array_buffer(filename).await?;
-        let audio_buffer = sound::decode_audio_data(&self.context, &array_buffer).await?;
-        Ok(Sound {
-            buffer: audio_buffer,
-        })
-    }
-
-    pub(crate) fn play_sound(&self, sound: &Sound) -> Result<()> {
-        sound::play_sound(&self.context, &sound.buffer, Looping::No)
-    }
-
-    pub(crate) fn play_looping_sound(&self, sound: &Sound) -> Result<()> {
-        sound::play_sound(&self.context, &sound.buffer, Looping::Yes)
-    }
-}
-
-pub(crate) fn add_click_handler(elem: HtmlElement) -> UnboundedReceiver<()> {
+/// Sends the location of every click on the canvas of `page`, in the
+/// logical coordinates of `viewport`, however large the canvas is shown.
+pub(crate) fn add_pointer_handler(
+    page: &dyn browser::Page,
+    viewport: Rect,
+) -> Result<(UnboundedReceiver<Point>, browser::Listening)> {
     let (mut click_sender, click_receiver) = unbounded();
-    let on_click = browser::closure_wrap(Box::new(move || {
-        if let Err(err) = click_sender.start_send(()) {
+    let listening = page.on_canvas_click(Box::new(move |x, y| {
+        let point = Point {
+            x: viewport.x() + x * viewport.width,
+            y: viewport.y() + y * viewport.height,
+        };
+        if let Err(err) = click_sender.start_send(point) {
             error!("error sending click event: {err:#?}");
         }
-    }) as Box<dyn FnMut()>);
-    elem.set_onclick(Some(on_click.as_ref().unchecked_ref()));
-    on_click.forget();
-    click_receiver
-}
-
-unsafe fn draw_frame_rate(renderer: &Renderer, frame_time: f64) {
-    static mut FRAMES_COUNTED: i32 = 0;
-    static mut TOTAL_FRAME_TIME: f64 = 0.0;
-    static mut FRAME_RATE: i32 = 0;
-
-    FRAMES_COUNTED += 1;
-    TOTAL_FRAME_TIME += frame_time;
-
-    if TOTAL_FRAME_TIME > 1000.0 {
-        FRAME_RATE = FRAMES_COUNTED;
-        TOTAL_FRAME_TIME = 0.0;
-        FRAMES_COUNTED = 0;
-    }
-
-    if let Err(err) = renderer.draw_text(
-        &format!("Frame Rate {FRAME_RATE}"),
-        &Point { x: 400, y: 100 },
-    ) {
-        error!("error drawing frame rate: {err:#?}");
-    }
+    }))?;
+    Ok((click_receiver, listening))
 }
 
 #[cfg(test)]
@@ -486,14 +1434,14 @@ mod tests {
     #[test]
     fn two_rects_that_intersect_on_the_left() {
         let rect1 = Rect {
-            position: Point { x: 10, y: 10 },
-            height: 100,
-            width: 100,
+            position: Point { x: 10.0, y: 10.0 },
+            height: 100.0,
+            width: 100.0,
         };
         let rect2 = Rect {
-            position: Point { x: 0, y: 10 },
-            height: 100,
-            width: 100,
+            position: Point { x: 0.0, y: 10.0 },
+            height: 100.0,
+            width: 100.0,
         };
         assert!(rect2.intersects(&rect1))
     }
@@ -501,14 +1449,14 @@ mod tests {
     #[test]
     fn two_rects_that_intersect_on_the_right() {
         let rect1 = Rect {
-            position: Point { x: 10, y: 10 },
-            height: 100,
-            width: 100,
+            position: Point { x: 10.0, y: 10.0 },
+            height: 100.0,
+            width: 100.0,
         };
         let rect2 = Rect {
-            position: Point { x: 90, y: 10 },
-            height: 100,
-            width: 100,
+            position: Point { x: 90.0, y: 10.0 },
+            height: 100.0,
+            width: 100.0,
         };
         assert!(rect2.intersects(&rect1))
     }
@@ -516,14 +1464,14 @@ mod tests {
     #[test]
     fn two_rects_that_intersect_on_the_top() {
         let rect1 = Rect {
-            position: Point { x: 10, y: 10 },
-            height: 100,
-            width: 100,
+            position: Point { x: 10.0, y: 10.0 },
+            height: 100.0,
+            width: 100.0,
         };
         let rect2 = Rect {
-            position: Point { x: 10, y: 0 },
-            height: 100,
-            width: 100,
+            position: Point { x: 10.0, y: 0.0 },
+            height: 100.0,
+            width: 100.0,
         };
         assert!(rect2.intersects(&rect1))
     }
@@ -531,30 +1479,108 @@ mod tests {
     #[test]
     fn two_rects_that_intersect_on_the_bottom() {
         let rect1 = Rect {
-            position: Point { x: 10, y: 10 },
-            height: 100,
-            width: 100,
+            position: Point { x: 10.0, y: 10.0 },
+            height: 100.0,
+            width: 100.0,
         };
         let rect2 = Rect {
-            position: Point { x: 10, y: 90 },
-            height: 100,
-            width: 100,
+            position: Point { x: 10.0, y: 90.0 },
+            height: 100.0,
+            width: 100.0,
         };
         assert!(rect2.intersects(&rect1))
     }
 
+    #[test]
+    fn camera_translates_world_to_screen_coordinates() {
+        let mut camera = Camera::new();
+        camera.set_x(100.0);
+        let screen = camera.to_screen(Point { x: 150.0, y: 20.0 });
+        assert_eq!((screen.x, screen.y), (50.0, 20.0));
+    }
+
+    #[test]
+    fn screen_shake_decays_until_it_stops() {
+        let mut shake = ScreenShake::new(4.0, 0.5);
+        for _ in 0..4 {
+            let (x, y) = shake.next_offset();
+            assert!(x.abs() <= 4.0 && y.abs() <= 4.0);
+        }
+        assert_eq!(shake.next_offset(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn text_style_builds_a_css_font() {
+        const STYLE: TextStyle = TextStyle::new("'Ken Future', sans-serif", 48).bold();
+        assert_eq!(STYLE.font(), "bold 48px 'Ken Future', sans-serif");
+        assert_eq!(TextStyle::default().font(), "21px serif");
+    }
+
+    #[test]
+    fn redraw_regions_merge_into_their_bounding_box() {
+        let redraw = Redraw::Region(Rect::from_xy(10.0, 10.0, 20.0, 20.0))
+            .union(Redraw::Nothing)
+            .union(Redraw::Region(Rect::from_xy(50.0, 0.0, 10.0, 10.0)));
+        let Redraw::Region(rect) = redraw else {
+            panic!("expected a region, got {redraw:?}");
+        };
+        assert_eq!(
+            (rect.x(), rect.y(), rect.width, rect.height),
+            (10.0, 0.0, 50.0, 30.0)
+        );
+        assert!(matches!(
+            redraw.union(Redraw::Everything),
+            Redraw::Everything
+        ));
+    }
+
     #[test]
     fn two_rects_that_does_not_intersect() {
         let rect1 = Rect {
-            position: Point { x: 10, y: 10 },
-            height: 100,
-            width: 100,
+            position: Point { x: 10.0, y: 10.0 },
+            height: 100.0,
+            width: 100.0,
         };
         let rect2 = Rect {
-            position: Point { x: 110, y: 110 },
-            height: 100,
-            width: 100,
+            position: Point { x: 110.0, y: 110.0 },
+            height: 100.0,
+            width: 100.0,
         };
         assert!(!rect2.intersects(&rect1))
     }
+
+    #[test]
+    fn bounding_boxes_are_outlined_in_the_style_of_their_category() {
+        const STYLES: [(DebugCategory, StrokeStyle); 1] = [(
+            DebugCategory::Player,
+            StrokeStyle::new("lime").with_dash(LineDash::Dashed),
+        )];
+        let rect = Rect::from_xy(10.0, 20.0, 30.0, 40.0);
+        let calls = Renderer::record(100.0, 100.0, |renderer| {
+            renderer.set_debug_mode(true);
+            renderer.set_debug_styles(&STYLES);
+            renderer.draw_bounding_box(&rect, DebugCategory::Player);
+            renderer.draw_bounding_box(&rect, DebugCategory::Barrier);
+        });
+        assert_eq!(
+            calls,
+            [
+                recording::Call::StrokeRect(rect, STYLES[0].1),
+                recording::Call::StrokeRect(rect, StrokeStyle::default()),
+            ]
+        );
+        assert_eq!(
+            LineDash::Dashed.segments(0.0, 15.0),
+            [(0.0, 6.0), (10.0, 15.0)]
+        );
+    }
+
+    #[test]
+    fn every_held_key_is_released_at_once() {
+        let mut keystate = KeyState::pressing(&["ArrowDown", "Space"]);
+        let mut released = keystate.release_all();
+        released.sort();
+        assert_eq!(released, ["ArrowDown", "Space"]);
+        assert_eq!(keystate.pressed_keys().count(), 0);
+    }
 }
@@ -0,0 +1,296 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+    fmt,
+    future::Future,
+    rc::Rc,
+};
+
+use anyhow::{anyhow, Result};
+use futures::future::{self, FutureExt, LocalBoxFuture, Shared};
+use serde::de::DeserializeOwned;
+use wasm_bindgen::JsValue;
+
+use super::{Audio, ImageSource, Sheet, Sound, SpriteSheet};
+use crate::browser;
+
+#[derive(Debug, Clone)]
+enum Asset {
+    Image(ImageSource),
+    Json(JsValue),
+    Sound(Sound),
+    SpriteSheet(Rc<SpriteSheet>),
+}
+
+/// How many times a failed load is tried again, each after waiting twice as
+/// long as the one before, starting from [`RETRY_DELAY`] milliseconds. Only
+/// failures that may go away are retried, see [`is_transient`].
+const RETRIES: u32 = 3;
+const RETRY_DELAY: i32 = 250;
+
+type PendingAsset = Shared<LocalBoxFuture<'static, Result<Asset, Rc<anyhow::Error>>>>;
+type ProgressCallback = Box<dyn Fn(f32)>;
+
+/// Loads images, JSON files and sounds concurrently. Each URL is fetched only
+/// once, and every request made through the loader counts towards its
+/// progress.
+///
+/// Clones share the same registry, so assets loaded through
+/// [`AssetLoader::shared`] stay available when the game is started again.
+#[derive(Clone, Default)]
+pub(crate) struct AssetLoader(Rc<Registry>);
+
+#[derive(Default)]
+struct Registry {
+    assets: RefCell<HashMap<String, PendingAsset>>,
+    requested: Cell<usize>,
+    loaded: Cell<usize>,
+    on_progress: RefCell<Option<ProgressCallback>>,
+    /// Why each asset that could not be loaded failed, by URL.
+    failures: RefCell<BTreeMap<String, String>>,
+    /// Added to every URL fetched, so that the browser's cache is bypassed.
+    cache_buster: Option<String>,
+}
+
+impl fmt::Debug for AssetLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AssetLoader")
+            .field("requested", &self.0.requested.get())
+            .field("loaded", &self.0.loaded.get())
+            .finish()
+    }
+}
+
+thread_local! {
+    static SHARED: AssetLoader = AssetLoader::default();
+}
+
+impl AssetLoader {
+    /// Returns the loader whose assets are kept for as long as the page is
+    /// open.
+    pub(crate) fn shared() -> Self {
+        SHARED.with(Clone::clone)
+    }
+
+    /// Returns a loader that fetches every asset again, even ones the browser
+    /// has cached, for picking up files that changed while the game runs.
+    pub(crate) fn uncached() -> Self {
+        Self(Rc::new(Registry {
+            cache_buster: Some(format!("reload={}", browser::timestamp())),
+            ..Registry::default()
+        }))
+    }
+
+    /// Calls `callback` with the current progress every time an asset
+    /// finishes loading.
+    pub(crate) fn on_progress(&self, callback: impl Fn(f32) + 'static) {
+        self.0.on_progress.replace(Some(Box::new(callback)));
+    }
+
+    /// Removes the callback set by [`AssetLoader::on_progress`].
+    pub(crate) fn clear_on_progress(&self) {
+        self.0.on_progress.take();
+    }
+
+    /// Returns the URL of every asset that failed to load, even after
+    /// retrying, with the reason, and that was not loaded since.
+    pub(crate) fn failures(&self) -> Vec<(String, String)> {
+        self.0
+            .failures
+            .borrow()
+            .iter()
+            .map(|(url, reason)| (url.clone(), reason.clone()))
+            .collect()
+    }
+
+    pub(crate) fn image(&self, url: &str) -> impl Future<Output = Result<ImageSource>> {
+        let asset = self.request(url, |url| {
+            with_retries(url, |url| async move {
+                super::load_image(&url).await.map(Asset::Image)
+            })
+        });
+        let url = url.to_string();
+        async move {
+            match asset
+                .await
+                .map_err(|err| anyhow!("error loading {url}: {err:#}"))?
+            {
+                Asset::Image(image) => Ok(image),
+                asset => Err(anyhow!("{url} was loaded as {asset:?}, not an image")),
+            }
+        }
+    }
+
+    /// Loads the images at `urls` concurrently, in the same order.
+    pub(crate) fn images<const N: usize>(
+        &self,
+        urls: [&str; N],
+    ) -> impl Future<Output = Result<[ImageSource; N]>> {
+        let images = future::try_join_all(urls.map(|url| self.image(url)));
+        async move {
+            images.await?.try_into().map_err(|images: Vec<_>| {
+                anyhow!("expected {N} images but loaded {}", images.len())
+            })
+        }
+    }
+
+    /// Fetches the JSON file at `url` and deserializes it into `T`.
+    pub(crate) fn json<T: DeserializeOwned>(&self, url: &str) -> impl Future<Output = Result<T>> {
+        let asset = self.request(url, |url| {
+            with_retries(url, |url| async move {
+                browser::fetch_json(&url).await.map(Asset::Json)
+            })
+        });
+        let url = url.to_string();
+        async move {
+            match asset
+                .await
+                .map_err(|err| anyhow!("error loading {url}: {err:#}"))?
+            {
+                Asset::Json(json) => serde_wasm_bindgen::from_value(json)
+                    .map_err(|err| anyhow!("could not convert `{url}`: {err:#?}")),
+                asset => Err(anyhow!("{url} was loaded as {asset:?}, not JSON")),
+            }
+        }
+    }
+
+    pub(crate) fn sound(&self, audio: &Audio, url: &str) -> impl Future<Output = Result<Sound>> {
+        let audio = audio.clone();
+        let asset = self.request(url, |url| {
+            with_retries(url, move |url| {
+                let audio = audio.clone();
+                async move { audio.load_sound(&url).await.map(Asset::Sound) }
+            })
+        });
+        let url = url.to_string();
+        async move {
+            match asset
+                .await
+                .map_err(|err| anyhow!("error loading {url}: {err:#}"))?
+            {
+                Asset::Sound(sound) => Ok(sound),
+                asset => Err(anyhow!("{url} was loaded as {asset:?}, not a sound")),
+            }
+        }
+    }
+
+    /// Loads the sheet description at `sheet_url` along with the image at
+    /// `image_url`, sharing the result with every other request for the same
+    /// pair.
+    pub(crate) fn sprite_sheet(
+        &self,
+        sheet_url: &str,
+        image_url: &str,
+    ) -> impl Future<Output = Result<Rc<SpriteSheet>>> {
+        let sheet = self.json::<Sheet>(sheet_url);
+        let image = self.image(image_url);
+        let key = format!("{sheet_url}+{image_url}");
+        let asset = self.request(&key, |_| async move {
+            let (sheet, image) = futures::try_join!(sheet, image)?;
+            Ok(Asset::SpriteSheet(Rc::new(SpriteSheet::new(sheet, image))))
+        });
+        async move {
+            match asset
+                .await
+                .map_err(|err| anyhow!("error loading {key}: {err:#}"))?
+            {
+                Asset::SpriteSheet(sheet) => Ok(sheet),
+                asset => Err(anyhow!("{key} was loaded as {asset:?}, not a sprite sheet")),
+            }
+        }
+    }
+
+    /// Returns the asset at `url`, starting to load it with `load` unless it
+    /// was already requested. Failed loads are forgotten so that they can be
+    /// retried.
+    fn request<F>(&self, url: &str, load: impl FnOnce(String) -> F) -> PendingAsset
+    where
+        F: Future<Output = Result<Asset>> + 'static,
+    {
+        if let Some(asset) = self.0.assets.borrow().get(url) {
+            return asset.clone();
+        }
+
+        self.0.requested.set(self.0.requested.get() + 1);
+        let registry = Rc::downgrade(&self.0);
+        let key = url.to_string();
+        let load = load(match &self.0.cache_buster {
+            Some(cache_buster) => format!("{url}?{cache_buster}"),
+            None => url.to_string(),
+        });
+        let asset = async move {
+            let asset = load.await.map_err(Rc::new);
+            if let Some(registry) = registry.upgrade() {
+                match &asset {
+                    Ok(_) => {
+                        registry.failures.borrow_mut().remove(&key);
+                        registry.loaded.set(registry.loaded.get() + 1);
+                    }
+                    Err(err) => {
+                        registry
+                            .failures
+                            .borrow_mut()
+                            .insert(key.clone(), format!("{err:#}"));
+                        registry.assets.borrow_mut().remove(&key);
+                        registry.requested.set(registry.requested.get() - 1);
+                    }
+                }
+                registry.report_progress();
+            }
+            asset
+        }
+        .boxed_local()
+        .shared();
+        self.0
+            .assets
+            .borrow_mut()
+            .insert(url.to_string(), asset.clone());
+        asset
+    }
+}
+
+/// Loads the asset at `url` with `load`, trying again a few times if it
+/// fails, e.g. because of a flaky connection.
+async fn with_retries<F>(url: String, load: impl Fn(String) -> F) -> Result<Asset>
+where
+    F: Future<Output = Result<Asset>>,
+{
+    let mut delay = RETRY_DELAY;
+    for _ in 0..RETRIES {
+        match load(url.clone()).await {
+            Ok(asset) => return Ok(asset),
+            Err(err) if !is_transient(&err) => return Err(err),
+            Err(err) => {
+                warn!("error loading {url}, retrying in {delay}ms: {err:#}");
+                browser::sleep(delay).await?;
+                delay *= 2;
+            }
+        }
+    }
+    load(url).await
+}
+
+/// Whether loading again may succeed. Network errors and server errors may
+/// go away, but an error status like `404 Not Found` will not.
+fn is_transient(err: &anyhow::Error) -> bool {
+    err.chain()
+        .find_map(|err| err.downcast_ref::<browser::HttpError>())
+        .is_none_or(browser::HttpError::is_transient)
+}
+
+impl Registry {
+    /// Returns the fraction of requested assets that have finished loading,
+    /// from `0.0` to `1.0`.
+    fn progress(&self) -> f32 {
+        match self.requested.get() {
+            0 => 1.0,
+            requested => self.loaded.get() as f32 / requested as f32,
+        }
+    }
+
+    fn report_progress(&self) {
+        if let Some(callback) = &*self.on_progress.borrow() {
+            callback(self.progress());
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+};
+
+use anyhow::Result;
+use web_sys::{AudioBuffer, AudioBufferSourceNode, AudioContext, AudioNode, GainNode};
+
+use crate::{
+    browser,
+    sound::{self, Looping},
+};
+
+/// Plays sounds through two channels, music and sound effects, whose volume
+/// can be changed separately. Both channels go through a master gain that
+/// mutes everything at once.
+#[derive(Debug, Clone)]
+pub(crate) struct Audio {
+    context: AudioContext,
+    master: GainNode,
+    music: GainNode,
+    sfx: GainNode,
+    sounds: Rc<RefCell<Vec<Weak<RefCell<Playback>>>>>,
+    current_music: Rc<RefCell<Option<SoundHandle>>>,
+    unlock_listeners: Rc<RefCell<Vec<browser::EventListenerHandle>>>,
+    paused_music: Rc<RefCell<Option<SoundHandle>>>,
+    /// Whether the page is hidden, which pauses the music.
+    hidden: Rc<Cell<bool>>,
+    /// Whether the game is paused, which pauses the music too.
+    music_paused: Rc<Cell<bool>>,
+    visibility_listener: Rc<RefCell<Option<browser::EventListenerHandle>>>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Sound {
+    buffer: AudioBuffer,
+}
+
+impl Audio {
+    pub(crate) fn new() -> Result<Self> {
+        let context = sound::create_audio_context()?;
+        let master = sound::create_gain(&context, &context.destination())?;
+        let audio = Audio {
+            music: sound::create_gain(&context, &master)?,
+            sfx: sound::create_gain(&context, &master)?,
+            master,
+            context,
+            sounds: Rc::new(RefCell::new(vec![])),
+            current_music: Rc::new(RefCell::new(None)),
+            unlock_listeners: Rc::new(RefCell::new(vec![])),
+            paused_music: Rc::new(RefCell::new(None)),
+            hidden: Rc::new(Cell::new(false)),
+            music_paused: Rc::new(Cell::new(false)),
+            visibility_listener: Rc::new(RefCell::new(None)),
+        };
+        if !audio.is_unlocked() {
+            audio.unlock_on_gesture()?;
+        }
+        audio.pause_music_while_hidden()?;
+        Ok(audio)
+    }
+
+    /// Returns whether the browser lets the game play sound yet. Until it
+    /// does, sounds that are started wait for the first key press or click.
+    pub(crate) fn is_unlocked(&self) -> bool {
+        sound::is_running(&self.context)
+    }
+
+    fn unlock_on_gesture(&self) -> Result<()> {
+        let audio = self.clone();
+        let listeners = browser::add_user_gesture_handler(move || {
+            let audio = audio.clone();
+            browser::spawn_local(async move {
+                if let Err(err) = audio.unlock().await {
+                    error!("error unlocking audio: {err:#?}");
+                }
+            });
+        })?;
+        self.unlock_listeners.replace(listeners);
+        Ok(())
+    }
+
+    /// Pauses the music while the page is hidden, as the game loop stops
+    /// updating then, and resumes it when the page is shown again unless the
+    /// game is paused.
+    fn pause_music_while_hidden(&self) -> Result<()> {
+        let audio = self.clone();
+        let listener = browser::add_visibility_change_handler(move |hidden| {
+            audio.hidden.set(hidden);
+            if let Err(err) = audio.update_music_pause() {
+                error!("error pausing or resuming music: {err:#?}");
+            }
+        })?;
+        self.visibility_listener.replace(Some(listener));
+        Ok(())
+    }
+
+    /// Pauses the music while the game is paused, and resumes it once it is
+    /// not, unless the page is hidden.
+    pub(crate) fn set_music_paused(&self, paused: bool) -> Result<()> {
+        self.music_paused.set(paused);
+        self.update_music_pause()
+    }
+
+    fn update_music_pause(&self) -> Result<()> {
+        if self.hidden.get() || self.music_paused.get() {
+            self.pause_music()
+        } else {
+            self.resume_music()
+        }
+    }
+
+    fn pause_music(&self) -> Result<()> {
+        let Some(music) = self.current_music.borrow().clone() else {
+            return Ok(());
+        };
+        if music.is_playing() || music.is_waiting() {
+            music.pause()?;
+            self.paused_music.replace(Some(music));
+        }
+        Ok(())
+    }
+
+    fn resume_music(&self) -> Result<()> {
+        match self.paused_music.take() {
+            Some(music) => music.play(),
+            None => Ok(()),
+        }
+    }
+
+    /// Resumes the audio context and plays the sounds that were waiting for
+    /// it.
+    async fn unlock(&self) -> Result<()> {
+        sound::resume_audio_context(&self.context).await?;
+        self.unlock_listeners.take();
+        let waiting: Vec<_> = self
+            .sounds
+            .borrow()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|playback| playback.borrow().waiting)
+            .collect();
+        for playback in waiting {
+            SoundHandle(playback).play()?;
+        }
+        Ok(())
+    }
+
+    /// Silences every sound for good and removes the event listeners audio
+    /// depends on.
+    pub(crate) fn close(&self) -> Result<()> {
+        self.unlock_listeners.take();
+        self.visibility_listener.take();
+        self.current_music.take();
+        self.paused_music.take();
+        sound::close_audio_context(&self.context)
+    }
+
+    pub(crate) fn set_muted(&self, muted: bool) {
+        sound::set_gain(&self.master, if muted { 0.0 } else { 1.0 });
+    }
+
+    /// Sets the volume of looping sounds, from `0.0` (silent) to `1.0`.
+    pub(crate) fn set_music_volume(&self, volume: f32) {
+        sound::set_gain(&self.music, volume.clamp(0.0, 1.0));
+    }
+
+    /// Sets the volume of one-shot sounds, from `0.0` (silent) to `1.0`.
+    pub(crate) fn set_sfx_volume(&self, volume: f32) {
+        sound::set_gain(&self.sfx, volume.clamp(0.0, 1.0));
+    }
+
+    pub(crate) async fn load_sound(&self, filename: &str) -> Result<Sound> {
+        let array_buffer = browser::fetch_array_buffer(filename).await?;
+        let audio_buffer = sound::decode_audio_data(&self.context, &array_buffer).await?;
+        Ok(Sound {
+            buffer: audio_buffer,
+        })
+    }
+
+    pub(crate) fn play_sound(&self, sound: &Sound) -> Result<SoundHandle> {
+        self.start(sound, &self.sfx, Looping::No)
+    }
+
+    /// Plays `sound` once, panned between the left (`-1.0`) and right (`1.0`)
+    /// speakers.
+    pub(crate) fn play_sound_at(&self, sound: &Sound, pan: f32) -> Result<SoundHandle> {
+        let panner = sound::create_stereo_panner(&self.context, &self.sfx, pan)?;
+        self.start(sound, &panner, Looping::No)
+    }
+
+    pub(crate) fn play_looping_sound(&self, sound: &Sound) -> Result<SoundHandle> {
+        let handle = self.start(sound, &self.music, Looping::Yes)?;
+        self.paused_music.take();
+        self.current_music.replace(Some(handle.clone()));
+        Ok(handle)
+    }
+
+    /// Crossfades from the music that is currently playing to `sound` over
+    /// `duration` seconds.
+    pub(crate) fn fade_to(
+        &self,
+        sound: &Sound,
+        looping: Looping,
+        duration: f64,
+    ) -> Result<SoundHandle> {
+        self.paused_music.take();
+        if let Some(previous) = self.current_music.take() {
+            previous.fade_out(duration)?;
+        }
+        let handle = self.start(sound, &self.music, looping)?;
+        handle.fade_in(duration)?;
+        self.current_music.replace(Some(handle.clone()));
+        Ok(handle)
+    }
+
+    fn start(&self, sound: &Sound, channel: &AudioNode, looping: Looping) -> Result<SoundHandle> {
+        let handle = SoundHandle(Rc::new(RefCell::new(Playback {
+            context: self.context.clone(),
+            buffer: sound.buffer.clone(),
+            gain: sound::create_gain(&self.context, channel)?,
+            looping,
+            source: None,
+            started_at: 0.0,
+            offset: 0.0,
+            waiting: false,
+        })));
+        handle.play()?;
+
+        let mut sounds = self.sounds.borrow_mut();
+        sounds.retain(|sound| sound.strong_count() > 0);
+        sounds.push(Rc::downgrade(&handle.0));
+        Ok(handle)
+    }
+}
+
+#[derive(Debug)]
+struct Playback {
+    context: AudioContext,
+    buffer: AudioBuffer,
+    /// Controls the volume of this sound alone, for fading it in and out.
+    gain: GainNode,
+    looping: Looping,
+    source: Option<AudioBufferSourceNode>,
+    /// The audio context time at which the current source started.
+    started_at: f64,
+    /// The position in the sound the current source started from.
+    offset: f64,
+    /// Set when the sound was played before audio was unlocked, so that it
+    /// starts once it is.
+    waiting: bool,
+}
+
+impl Playback {
+    fn position(&self) -> f64 {
+        let position = self.context.current_time() - self.started_at + self.offset;
+        match self.looping {
+            Looping::Yes => position % self.buffer.duration(),
+            Looping::No => position,
+        }
+    }
+
+    fn is_playing(&self) -> bool {
+        self.source.is_some()
+            && (matches!(self.looping, Looping::Yes) || self.position() < self.buffer.duration())
+    }
+}
+
+/// Controls a sound started by [`Audio`]. Dropping the handle lets the sound
+/// play on until it ends.
+#[derive(Debug, Clone)]
+pub(crate) struct SoundHandle(Rc<RefCell<Playback>>);
+
+impl SoundHandle {
+    pub(crate) fn is_playing(&self) -> bool {
+        self.0.borrow().is_playing()
+    }
+
+    fn is_waiting(&self) -> bool {
+        self.0.borrow().waiting
+    }
+
+    /// Plays the sound from where it was paused, or from the beginning if it
+    /// was stopped or has ended.
+    pub(crate) fn play(&self) -> Result<()> {
+        if self.is_playing() {
+            return Ok(());
+        }
+        let mut playback = self.0.borrow_mut();
+        playback.waiting = !sound::is_running(&playback.context);
+        if playback.waiting {
+            return Ok(());
+        }
+        if playback.source.take().is_some() {
+            playback.offset = 0.0;
+        }
+        let source = sound::play_sound(
+            &playback.context,
+            &playback.buffer,
+            &playback.gain,
+            playback.looping,
+            playback.offset,
+        )?;
+        playback.started_at = playback.context.current_time();
+        playback.source = Some(source);
+        Ok(())
+    }
+
+    pub(crate) fn pause(&self) -> Result<()> {
+        self.0.borrow_mut().waiting = false;
+        if !self.is_playing() {
+            return Ok(());
+        }
+        let mut playback = self.0.borrow_mut();
+        playback.offset = playback.position();
+        match playback.source.take() {
+            Some(source) => sound::stop_sound(&source),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn stop(&self) -> Result<()> {
+        let mut playback = self.0.borrow_mut();
+        playback.offset = 0.0;
+        playback.waiting = false;
+        match playback.source.take() {
+            Some(source) => sound::stop_sound(&source),
+            None => Ok(()),
+        }
+    }
+
+    fn fade_in(&self, duration: f64) -> Result<()> {
+        let playback = self.0.borrow();
+        sound::fade_gain(&playback.context, &playback.gain, 0.0, 1.0, duration)
+    }
+
+    /// Fades the sound out over `duration` seconds and then stops it.
+    fn fade_out(&self, duration: f64) -> Result<()> {
+        if !self.is_playing() || duration <= 0.0 {
+            return self.stop();
+        }
+        let mut playback = self.0.borrow_mut();
+        playback.waiting = false;
+        let volume = playback.gain.gain().value();
+        sound::fade_gain(&playback.context, &playback.gain, volume, 0.0, duration)?;
+        playback.offset = 0.0;
+        match playback.source.take() {
+            Some(source) => {
+                sound::stop_sound_at(&source, playback.context.current_time() + duration)
+            }
+            None => Ok(()),
+        }
+    }
+}
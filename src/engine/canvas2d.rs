@@ -0,0 +1,172 @@
+use anyhow::{anyhow, Result};
+use wasm_bindgen::JsValue;
+use web_sys::CanvasRenderingContext2d;
+
+use super::{BlendMode, ImageSource, Rect, RenderBackend, StrokeStyle, TextStyle};
+
+/// Draws through the 2D canvas API.
+#[derive(Debug)]
+pub(super) struct Canvas2d {
+    context: CanvasRenderingContext2d,
+}
+
+impl Canvas2d {
+    pub(super) fn new(context: CanvasRenderingContext2d) -> Self {
+        Self { context }
+    }
+}
+
+impl RenderBackend for Canvas2d {
+    fn begin_frame(&self, scale: f64) {
+        self.context.save();
+        if let Err(err) = self.context.set_transform(scale, 0.0, 0.0, scale, 0.0, 0.0) {
+            error!("error scaling canvas: {err:#?}");
+        }
+    }
+
+    fn end_frame(&self) {
+        self.context.restore();
+    }
+
+    fn clip(&self, rect: &Rect) {
+        self.context.begin_path();
+        self.context.rect(
+            rect.x().into(),
+            rect.y().into(),
+            rect.width.into(),
+            rect.height.into(),
+        );
+        self.context.clip();
+    }
+
+    fn save(&self) {
+        self.context.save();
+    }
+
+    fn restore(&self) {
+        self.context.restore();
+    }
+
+    fn translate(&self, x: f64, y: f64) {
+        if let Err(err) = self.context.translate(x, y) {
+            error!("error translating canvas: {err:#?}");
+        }
+    }
+
+    fn rotate(&self, angle: f64) {
+        if let Err(err) = self.context.rotate(angle) {
+            error!("error rotating canvas: {err:#?}");
+        }
+    }
+
+    fn scale(&self, x: f64, y: f64) {
+        if let Err(err) = self.context.scale(x, y) {
+            error!("error scaling canvas: {err:#?}");
+        }
+    }
+
+    fn multiply_alpha(&self, alpha: f64) {
+        self.context
+            .set_global_alpha(self.context.global_alpha() * alpha);
+    }
+
+    fn set_blend_mode(&self, mode: BlendMode) {
+        if let Err(err) = self
+            .context
+            .set_global_composite_operation(mode.composite_operation())
+        {
+            error!("error setting composite operation: {err:#?}");
+        }
+    }
+
+    fn clear(&self, rect: &Rect) {
+        self.context.clear_rect(
+            rect.x().into(),
+            rect.y().into(),
+            rect.width.into(),
+            rect.height.into(),
+        )
+    }
+
+    fn draw_image(&self, image: &ImageSource, frame: &Rect, destination: &Rect) {
+        image
+            .draw_on(&self.context, frame, destination)
+            .expect("error drawing image");
+    }
+
+    fn stroke_rect(&self, rect: &Rect, style: &StrokeStyle) {
+        let dash = match style.dash.pattern() {
+            Some((on, off)) => js_sys::Array::of2(&on.into(), &off.into()),
+            None => js_sys::Array::new(),
+        };
+        self.context.save();
+        self.context
+            .set_stroke_style(&JsValue::from_str(style.color));
+        if let Err(err) = self.context.set_line_dash(&dash) {
+            error!("error setting the line dash: {err:#?}");
+        }
+        self.context.stroke_rect(
+            rect.x().into(),
+            rect.y().into(),
+            rect.width.into(),
+            rect.height.into(),
+        );
+        self.context.restore();
+    }
+
+    fn fill_rect(&self, rect: &Rect, color: &str) {
+        self.context.save();
+        self.context.set_fill_style(&JsValue::from_str(color));
+        self.context.fill_rect(
+            rect.x().into(),
+            rect.y().into(),
+            rect.width.into(),
+            rect.height.into(),
+        );
+        self.context.restore();
+    }
+
+    fn fill_polygon(&self, points: &[(f64, f64)], color: &str) {
+        let Some(((first_x, first_y), rest)) = points.split_first() else {
+            return;
+        };
+        self.context.save();
+        self.context.set_fill_style(&JsValue::from_str(color));
+        self.context.begin_path();
+        self.context.move_to(*first_x, *first_y);
+        for (x, y) in rest {
+            self.context.line_to(*x, *y);
+        }
+        self.context.close_path();
+        self.context.fill();
+        self.context.restore();
+    }
+
+    fn fill_text(&self, text: &str, (x, y): (f64, f64), style: &TextStyle) -> Result<()> {
+        self.context.save();
+        self.context.set_font(&style.font());
+        self.context.set_text_align(style.align.as_str());
+        let mut result = Ok(());
+        if let Some((color, width)) = style.stroke {
+            self.context.set_stroke_style(&JsValue::from_str(color));
+            self.context.set_line_width(width.into());
+            result = self.context.stroke_text(text, x, y);
+        }
+        self.context.set_fill_style(&JsValue::from_str(style.color));
+        let result = result
+            .and_then(|()| self.context.fill_text(text, x, y))
+            .map_err(|err| anyhow!("error drawing text: {err:#?}"));
+        self.context.restore();
+        result
+    }
+
+    fn measure_text(&self, text: &str, style: &TextStyle) -> Result<f64> {
+        self.context.save();
+        self.context.set_font(&style.font());
+        let metrics = self.context.measure_text(text);
+        self.context.restore();
+        metrics
+            .map(|metrics| metrics.width())
+            .map_err(|err| anyhow!("error measuring text: {err:#?}"))
+    }
+}
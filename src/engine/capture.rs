@@ -0,0 +1,45 @@
+use anyhow::{anyhow, Result};
+
+use super::{Redraw, Renderer};
+use crate::browser;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Draws a single frame with `draw` on the page's canvas, at its logical size
+/// so that the result does not depend on the window, and returns its RGBA
+/// pixels.
+pub(crate) fn capture_frame(draw: impl FnOnce(&Renderer)) -> Result<Vec<u8>> {
+    let canvas = browser::canvas()?;
+    let renderer = Renderer::unscaled(canvas.clone())?;
+    renderer.begin_frame(&Redraw::Everything);
+    draw(&renderer);
+    renderer.end_frame();
+    // The canvas is read in the same task it was drawn in, before WebGL is
+    // allowed to discard its drawing buffer.
+    browser::canvas_pixels(canvas.width(), canvas.height(), |context| {
+        context.draw_image_with_html_canvas_element(&canvas, 0.0, 0.0)
+    })
+    .map_err(|err| anyhow!("error capturing frame: {err:#}"))
+}
+
+/// Returns the 64-bit FNV-1a hash of `pixels` in hexadecimal, which is short
+/// enough to store for every frame that is compared.
+pub(crate) fn hash_pixels(pixels: &[u8]) -> String {
+    let hash = pixels.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_match_the_fnv_1a_reference() {
+        assert_eq!(hash_pixels(b""), "cbf29ce484222325");
+        assert_eq!(hash_pixels(b"a"), "af63dc4c8601ec8c");
+        assert_eq!(hash_pixels(b"foobar"), "85944171f73967e8");
+    }
+}
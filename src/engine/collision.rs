@@ -0,0 +1,223 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::{ImageSource, Point, Rect};
+
+/// Pixels at least this opaque count as solid.
+const ALPHA_THRESHOLD: u8 = 128;
+
+thread_local! {
+    static IMAGE_MASKS: RefCell<HashMap<String, Rc<CollisionMask>>> = RefCell::new(HashMap::new());
+}
+
+/// Which pixels of a sprite are solid, so that collisions can ignore its
+/// transparent parts.
+///
+/// Building a mask reads the pixels back from a canvas, so masks are meant
+/// to be built once when the images are loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CollisionMask {
+    width: i32,
+    height: i32,
+    solid: Vec<bool>,
+}
+
+impl CollisionMask {
+    /// Builds the mask of `width` by `height` RGBA pixels, row by row.
+    pub(crate) fn from_rgba(width: u32, height: u32, pixels: &[u8]) -> Self {
+        Self {
+            width: width as i32,
+            height: height as i32,
+            solid: pixels
+                .iter()
+                .skip(3)
+                .step_by(4)
+                .map(|&alpha| alpha >= ALPHA_THRESHOLD)
+                .collect(),
+        }
+    }
+
+    /// Builds the mask of the part of `image` inside `area`.
+    pub(crate) fn from_image(image: &ImageSource, area: &Rect) -> Result<Self> {
+        let (width, height) = (area.width as u32, area.height as u32);
+        Ok(Self::from_rgba(width, height, &image.pixels(area)?))
+    }
+
+    fn is_solid(&self, x: i32, y: i32) -> bool {
+        self.solid[(y * self.width + x) as usize]
+    }
+
+    /// Returns whether a solid pixel of this mask drawn at `position`
+    /// overlaps a solid pixel of `other` drawn at `other_position`.
+    pub(crate) fn overlaps(&self, position: Point, other: &Self, other_position: Point) -> bool {
+        let dx = (other_position.x - position.x).round() as i32;
+        let dy = (other_position.y - position.y).round() as i32;
+        let (left, right) = (dx.max(0), (dx + other.width).min(self.width));
+        let (top, bottom) = (dy.max(0), (dy + other.height).min(self.height));
+        (top..bottom)
+            .any(|y| (left..right).any(|x| self.is_solid(x, y) && other.is_solid(x - dx, y - dy)))
+    }
+}
+
+/// The outline of something that can be collided with.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) enum Shape {
+    Rect(Rect),
+    Circle {
+        center: Point,
+        radius: f32,
+    },
+    /// The right triangle filling the bottom half of `area`, whose surface
+    /// runs between two opposite corners. A `rising` slope is lowest on the
+    /// left.
+    Slope {
+        area: Rect,
+        rising: bool,
+    },
+}
+
+impl From<Rect> for Shape {
+    fn from(rect: Rect) -> Self {
+        Shape::Rect(rect)
+    }
+}
+
+impl Shape {
+    /// Returns the circle whose arc spans the top and bottom corners of
+    /// `area`, like a mound as wide and tall as the area.
+    pub(crate) fn mound(area: &Rect) -> Self {
+        let (half_width, height) = (area.width / 2.0, area.height);
+        let radius = (height * height + half_width * half_width) / (2.0 * height);
+        Shape::Circle {
+            center: Point {
+                x: area.center().x,
+                y: area.top() + radius,
+            },
+            radius,
+        }
+    }
+
+    pub(crate) fn bounding_box(&self) -> Rect {
+        match *self {
+            Shape::Rect(rect) | Shape::Slope { area: rect, .. } => rect,
+            Shape::Circle { center, radius } => Rect::from_xy(
+                center.x - radius,
+                center.y - radius,
+                radius * 2.0,
+                radius * 2.0,
+            ),
+        }
+    }
+
+    /// Returns the height of the top of the shape at `x`, which is clamped
+    /// to the shape's extent.
+    pub(crate) fn top_at(&self, x: f32) -> f32 {
+        match *self {
+            Shape::Rect(rect) => rect.top(),
+            Shape::Circle { center, radius } => {
+                let dx = (x - center.x).clamp(-radius, radius);
+                center.y - (radius * radius - dx * dx).sqrt()
+            }
+            Shape::Slope { area, rising } => {
+                let along = ((x - area.left()) / area.width).clamp(0.0, 1.0);
+                let rise = if rising { along } else { 1.0 - along };
+                area.bottom() - rise * area.height
+            }
+        }
+    }
+
+    pub(crate) fn intersects(&self, rect: &Rect) -> bool {
+        let bounding_box = self.bounding_box();
+        if !bounding_box.intersects(rect) {
+            return false;
+        }
+        match *self {
+            Shape::Rect(_) => true,
+            Shape::Circle { center, radius } => {
+                let dx = center.x - center.x.clamp(rect.left(), rect.right());
+                let dy = center.y - center.y.clamp(rect.top(), rect.bottom());
+                dx * dx + dy * dy < radius * radius
+            }
+            Shape::Slope { rising, .. } => {
+                // The surface is highest at the end of the overlap it rises
+                // towards.
+                let x = if rising {
+                    rect.right().min(bounding_box.right())
+                } else {
+                    rect.left().max(bounding_box.left())
+                };
+                rect.bottom() > self.top_at(x)
+            }
+        }
+    }
+
+    pub(crate) fn move_by(&mut self, x: f32, y: f32) {
+        match self {
+            Shape::Rect(rect) | Shape::Slope { area: rect, .. } => {
+                rect.set_x(rect.x() + x);
+                rect.set_y(rect.y() + y);
+            }
+            Shape::Circle { center, .. } => {
+                center.x += x;
+                center.y += y;
+            }
+        }
+    }
+}
+
+/// Builds the mask of the whole of `image` and keeps it, so that every
+/// [`super::Image`] made from the same source gets it.
+pub(crate) fn load_image_mask(image: &ImageSource) -> Result<()> {
+    let area = Rect::from_xy(0.0, 0.0, image.width() as f32, image.height() as f32);
+    let mask = Rc::new(CollisionMask::from_image(image, &area)?);
+    IMAGE_MASKS.with(|masks| masks.borrow_mut().insert(image.url(), mask));
+    Ok(())
+}
+
+pub(super) fn image_mask(image: &ImageSource) -> Option<Rc<CollisionMask>> {
+    IMAGE_MASKS.with(|masks| masks.borrow().get(&image.url()).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_solid_pixels_overlap() {
+        // A 2x2 mask whose top left pixel is transparent.
+        let corner = CollisionMask::from_rgba(
+            2,
+            2,
+            &[0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255],
+        );
+        let dot = CollisionMask::from_rgba(1, 1, &[0, 0, 0, 255]);
+        let origin = Point { x: 10.0, y: 10.0 };
+
+        assert!(!corner.overlaps(origin, &dot, Point { x: 10.0, y: 10.0 }));
+        assert!(corner.overlaps(origin, &dot, Point { x: 11.0, y: 10.0 }));
+        assert!(dot.overlaps(Point { x: 11.0, y: 11.0 }, &corner, origin));
+        assert!(!corner.overlaps(origin, &dot, Point { x: 12.0, y: 10.0 }));
+    }
+
+    #[test]
+    fn rounded_and_sloped_shapes_leave_their_corners_free() {
+        let corner = Rect::from_xy(0.0, 0.0, 10.0, 10.0);
+        let circle = Shape::Circle {
+            center: Point { x: 20.0, y: 20.0 },
+            radius: 12.0,
+        };
+        assert!(circle.bounding_box().intersects(&corner));
+        assert!(!circle.intersects(&corner));
+        assert!(circle.intersects(&Rect::from_xy(0.0, 15.0, 10.0, 10.0)));
+
+        let ramp = Shape::Slope {
+            area: Rect::from_xy(0.0, 0.0, 100.0, 50.0),
+            rising: true,
+        };
+        assert_eq!(ramp.top_at(50.0), 25.0);
+        assert!(!ramp.intersects(&Rect::from_xy(0.0, 0.0, 20.0, 30.0)));
+        assert!(ramp.intersects(&Rect::from_xy(80.0, 0.0, 20.0, 30.0)));
+    }
+}
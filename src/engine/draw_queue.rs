@@ -0,0 +1,215 @@
+use super::{BlendMode, ImageSource, Rect, RenderBackend, StrokeStyle, TextStyle};
+
+#[derive(Debug, Clone)]
+pub(super) enum DrawCommand {
+    Save,
+    Restore,
+    Translate(f64, f64),
+    Rotate(f64),
+    Scale(f64, f64),
+    MultiplyAlpha(f64),
+    SetBlendMode(BlendMode),
+    Clear(Rect),
+    Image {
+        image: ImageSource,
+        frame: Rect,
+        destination: Rect,
+    },
+    StrokeRect(Rect, StrokeStyle),
+    FillRect(Rect, String),
+    FillPolygon(Vec<(f64, f64)>, String),
+    Text {
+        text: String,
+        location: (f64, f64),
+        style: TextStyle,
+    },
+}
+
+impl DrawCommand {
+    const fn changes_state(&self) -> bool {
+        matches!(
+            self,
+            Self::Translate(..)
+                | Self::Rotate(_)
+                | Self::Scale(..)
+                | Self::MultiplyAlpha(_)
+                | Self::SetBlendMode(_)
+        )
+    }
+
+    fn is_noop(&self) -> bool {
+        match *self {
+            Self::Translate(x, y) => x == 0.0 && y == 0.0,
+            Self::Rotate(angle) => angle == 0.0,
+            Self::Scale(x, y) => x == 1.0 && y == 1.0,
+            Self::MultiplyAlpha(alpha) => alpha == 1.0,
+            _ => false,
+        }
+    }
+
+    fn execute(self, backend: &dyn RenderBackend) {
+        match self {
+            Self::Save => backend.save(),
+            Self::Restore => backend.restore(),
+            Self::Translate(x, y) => backend.translate(x, y),
+            Self::Rotate(angle) => backend.rotate(angle),
+            Self::Scale(x, y) => backend.scale(x, y),
+            Self::MultiplyAlpha(alpha) => backend.multiply_alpha(alpha),
+            Self::SetBlendMode(mode) => backend.set_blend_mode(mode),
+            Self::Clear(rect) => backend.clear(&rect),
+            Self::Image {
+                image,
+                frame,
+                destination,
+            } => backend.draw_image(&image, &frame, &destination),
+            Self::StrokeRect(rect, style) => backend.stroke_rect(&rect, &style),
+            Self::FillRect(rect, color) => backend.fill_rect(&rect, &color),
+            Self::FillPolygon(points, color) => backend.fill_polygon(&points, &color),
+            Self::Text {
+                text,
+                location,
+                style,
+            } => {
+                if let Err(err) = backend.fill_text(&text, location, &style) {
+                    error!("error drawing text: {err:#?}");
+                }
+            }
+        }
+    }
+}
+
+/// Collects the draw calls of a frame so that they can be cleaned up and
+/// reordered before they reach the backend.
+#[derive(Debug, Default)]
+pub(super) struct DrawQueue {
+    commands: Vec<DrawCommand>,
+}
+
+impl DrawQueue {
+    pub(super) fn push(&mut self, command: DrawCommand) {
+        self.commands.push(command);
+    }
+
+    /// Sends every queued command to `backend` and empties the queue.
+    pub(super) fn flush(&mut self, backend: &dyn RenderBackend) {
+        let commands = group_images(simplify(std::mem::take(&mut self.commands)));
+        for command in commands {
+            command.execute(backend);
+        }
+    }
+}
+
+/// Removes state changes that have no effect: identity transforms, adjacent
+/// translations that cancel out, and `Save`/`Restore` pairs with no state
+/// change in between.
+fn simplify(commands: Vec<DrawCommand>) -> Vec<DrawCommand> {
+    let mut merged: Vec<DrawCommand> = Vec::with_capacity(commands.len());
+    for command in commands {
+        match (merged.last_mut(), &command) {
+            (Some(DrawCommand::Translate(x, y)), DrawCommand::Translate(dx, dy)) => {
+                *x += dx;
+                *y += dy;
+            }
+            _ => merged.push(command),
+        }
+        if merged.last().is_some_and(DrawCommand::is_noop) {
+            merged.pop();
+        }
+    }
+
+    let mut keep = vec![true; merged.len()];
+    let mut saves: Vec<(usize, bool)> = vec![];
+    for (index, command) in merged.iter().enumerate() {
+        match command {
+            DrawCommand::Save => saves.push((index, false)),
+            DrawCommand::Restore => {
+                if let Some((save, changed)) = saves.pop() {
+                    if !changed {
+                        keep[save] = false;
+                        keep[index] = false;
+                    }
+                }
+            }
+            command if command.changes_state() => {
+                if let Some((_, changed)) = saves.last_mut() {
+                    *changed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    merged
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(command, keep)| keep.then_some(command))
+        .collect()
+}
+
+/// Moves image draws next to earlier draws of the same image, as long as they
+/// do not pass over anything they overlap, so that backends can batch them.
+fn group_images(commands: Vec<DrawCommand>) -> Vec<DrawCommand> {
+    let mut grouped: Vec<DrawCommand> = Vec::with_capacity(commands.len());
+    let mut run_start = 0;
+    for command in commands {
+        let DrawCommand::Image {
+            image, destination, ..
+        } = &command
+        else {
+            grouped.push(command);
+            run_start = grouped.len();
+            continue;
+        };
+
+        let source = image.url();
+        let mut insert_at = grouped.len();
+        for index in (run_start..grouped.len()).rev() {
+            let DrawCommand::Image {
+                image: other,
+                destination: other_destination,
+                ..
+            } = &grouped[index]
+            else {
+                break;
+            };
+            if other.url() == source {
+                insert_at = index + 1;
+                break;
+            }
+            if other_destination.intersects(destination) {
+                break;
+            }
+        }
+        grouped.insert(insert_at, command);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_transforms_inside_save_and_restore_are_dropped() {
+        let commands = simplify(vec![
+            DrawCommand::Save,
+            DrawCommand::Translate(10.0, 20.0),
+            DrawCommand::Rotate(0.0),
+            DrawCommand::Scale(1.0, 1.0),
+            DrawCommand::Translate(-10.0, -20.0),
+            DrawCommand::StrokeRect(Rect::default(), StrokeStyle::default()),
+            DrawCommand::Restore,
+            DrawCommand::Save,
+            DrawCommand::MultiplyAlpha(0.5),
+            DrawCommand::Restore,
+        ]);
+        assert!(matches!(
+            commands.as_slice(),
+            [
+                DrawCommand::StrokeRect(..),
+                DrawCommand::Save,
+                DrawCommand::MultiplyAlpha(_),
+                DrawCommand::Restore,
+            ]
+        ));
+    }
+}
@@ -0,0 +1,396 @@
+use std::{collections::HashMap, fmt};
+
+use super::{DebugCategory, Point, Rect, Renderer};
+
+/// An identifier for something in a [`World`], which is nothing more than
+/// the components attached to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Entity(u32);
+
+type Components<T> = HashMap<Entity, T>;
+type DrawFn = Box<dyn Fn(&Renderer, &Rect)>;
+
+/// How far an entity moves on every update.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Velocity {
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+}
+
+/// The size of the area, starting at the entity's position, that other
+/// things collide with.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Collider {
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+}
+
+/// Draws an entity into its bounding box.
+pub(crate) struct Sprite(DrawFn);
+
+impl Sprite {
+    pub(crate) fn new(draw: impl Fn(&Renderer, &Rect) + 'static) -> Self {
+        Self(Box::new(draw))
+    }
+}
+
+impl fmt::Debug for Sprite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Sprite")
+    }
+}
+
+/// Game specific data with a shape of its own, such as an obstacle built
+/// from several parts. An entity spawned for a body collides over its
+/// bounding box, and the body moves along with the entity.
+pub(crate) trait Body {
+    fn bounding_box(&self) -> Rect;
+    fn move_by(&mut self, x: f32, y: f32);
+}
+
+impl<B: Body + ?Sized> Body for Box<B> {
+    fn bounding_box(&self) -> Rect {
+        (**self).bounding_box()
+    }
+
+    fn move_by(&mut self, x: f32, y: f32) {
+        (**self).move_by(x, y);
+    }
+}
+
+/// A set of entities along with their components. Besides the components
+/// every game needs, each entity can carry game specific data `D`.
+///
+/// Systems such as movement, collision and drawing run over every entity
+/// with the components they need, so adding a new kind of entity only
+/// means spawning it with the right components.
+#[derive(Debug)]
+pub(crate) struct World<D> {
+    next_entity: u32,
+    entities: Vec<Entity>,
+    positions: Components<Point>,
+    velocities: Components<Velocity>,
+    colliders: Components<Collider>,
+    sprites: Components<Sprite>,
+    data: Components<D>,
+}
+
+impl<D> Default for World<D> {
+    fn default() -> Self {
+        Self {
+            next_entity: 0,
+            entities: vec![],
+            positions: HashMap::new(),
+            velocities: HashMap::new(),
+            colliders: HashMap::new(),
+            sprites: HashMap::new(),
+            data: HashMap::new(),
+        }
+    }
+}
+
+impl<D> World<D> {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a new entity at `position`, whose other components are added
+    /// through the returned builder.
+    pub(crate) fn spawn(&mut self, position: Point) -> EntityBuilder<'_, D> {
+        let entity = Entity(self.next_entity);
+        self.next_entity += 1;
+        self.entities.push(entity);
+        self.positions.insert(entity, position);
+        EntityBuilder {
+            world: self,
+            entity,
+        }
+    }
+
+    pub(crate) fn despawn(&mut self, entity: Entity) -> Option<D> {
+        self.entities.retain(|&other| other != entity);
+        self.positions.remove(&entity);
+        self.velocities.remove(&entity);
+        self.colliders.remove(&entity);
+        self.sprites.remove(&entity);
+        self.data.remove(&entity)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = Self {
+            next_entity: self.next_entity,
+            ..Self::default()
+        };
+    }
+
+    /// Returns the position and data of every entity that has data, in the
+    /// order they were spawned.
+    pub(crate) fn data(&self) -> impl Iterator<Item = (Point, &D)> + '_ {
+        self.entities.iter().filter_map(|entity| {
+            let position = *self.positions.get(entity)?;
+            Some((position, self.data.get(entity)?))
+        })
+    }
+
+    pub(crate) fn get(&self, entity: Entity) -> Option<&D> {
+        self.data.get(&entity)
+    }
+
+    /// Returns the data of every entity that has data, in the order they were
+    /// spawned.
+    pub(crate) fn values(&self) -> impl Iterator<Item = &D> + '_ {
+        self.entities
+            .iter()
+            .filter_map(|entity| self.data.get(entity))
+    }
+
+    /// Returns every entity that has data along with the area it occupies,
+    /// for systems that only act on some kinds of entities.
+    pub(crate) fn entities_with_data(&self) -> impl Iterator<Item = (Entity, Rect, &D)> + '_ {
+        self.entities.iter().filter_map(|&entity| {
+            Some((entity, self.bounding_box(entity)?, self.data.get(&entity)?))
+        })
+    }
+
+    /// Returns how far the entity moves on every update, which is nothing
+    /// unless it has a velocity.
+    pub(crate) fn velocity(&self, entity: Entity) -> Velocity {
+        self.velocities.get(&entity).copied().unwrap_or_default()
+    }
+
+    pub(crate) fn set_velocity(&mut self, entity: Entity, velocity: Velocity) {
+        if self.positions.contains_key(&entity) {
+            self.velocities.insert(entity, velocity);
+        }
+    }
+
+    /// Returns the area the entity occupies, which is empty unless it has a
+    /// collider.
+    pub(crate) fn bounding_box(&self, entity: Entity) -> Option<Rect> {
+        let position = *self.positions.get(&entity)?;
+        let (width, height) = self
+            .colliders
+            .get(&entity)
+            .map_or((0.0, 0.0), |collider| (collider.width, collider.height));
+        Some(Rect::new(position, width, height))
+    }
+
+    /// The movement system: moves every entity by its velocity.
+    pub(crate) fn move_entities(&mut self) {
+        let scale = super::update_scale();
+        for (entity, velocity) in &self.velocities {
+            if let Some(position) = self.positions.get_mut(entity) {
+                position.x += velocity.x * scale;
+                position.y += velocity.y * scale;
+            }
+        }
+    }
+
+    /// Moves every entity horizontally by `x`, keeping the relative
+    /// positions intact.
+    pub(crate) fn move_horizontally(&mut self, x: f32) {
+        for position in self.positions.values_mut() {
+            position.x += x;
+        }
+    }
+
+    /// The collision system: returns the entities whose collider intersects
+    /// `rect`, oldest first.
+    pub(crate) fn colliding_with(&self, rect: &Rect) -> Vec<Entity> {
+        self.entities
+            .iter()
+            .copied()
+            .filter(|entity| self.colliders.contains_key(entity))
+            .filter(|&entity| {
+                self.bounding_box(entity)
+                    .is_some_and(|bounding_box| rect.intersects(&bounding_box))
+            })
+            .collect()
+    }
+
+    /// Removes every entity that lies entirely to the left of `x`.
+    pub(crate) fn despawn_left_of(&mut self, x: f32) {
+        let gone: Vec<_> = self
+            .entities
+            .iter()
+            .copied()
+            .filter(|&entity| {
+                self.bounding_box(entity)
+                    .is_some_and(|bounding_box| bounding_box.right() < x)
+            })
+            .collect();
+        for entity in gone {
+            self.despawn(entity);
+        }
+    }
+
+    /// The rendering system: draws every entity with a sprite, in the order
+    /// they were spawned.
+    pub(crate) fn draw(&self, renderer: &Renderer) {
+        for &entity in &self.entities {
+            let Some(bounding_box) = self.bounding_box(entity) else {
+                continue;
+            };
+            if let Some(Sprite(draw)) = self.sprites.get(&entity) {
+                draw(renderer, &bounding_box);
+            }
+            if self.colliders.contains_key(&entity) {
+                renderer.draw_bounding_box(&bounding_box, DebugCategory::Item);
+            }
+        }
+    }
+}
+
+impl<D: Body> World<D> {
+    /// Starts a new entity for `body`, which collides over its bounding box.
+    pub(crate) fn spawn_body(&mut self, body: D) -> EntityBuilder<'_, D> {
+        let bounding_box = body.bounding_box();
+        self.spawn(bounding_box.position)
+            .with_collider(bounding_box.width, bounding_box.height)
+            .with_data(body)
+    }
+
+    /// The movement system for bodies: moves every entity by its velocity,
+    /// and its body along with it.
+    pub(crate) fn move_bodies(&mut self) {
+        let scale = super::update_scale();
+        for (entity, velocity) in &self.velocities {
+            let (x, y) = (velocity.x * scale, velocity.y * scale);
+            if let Some(position) = self.positions.get_mut(entity) {
+                position.x += x;
+                position.y += y;
+            }
+            if let Some(body) = self.data.get_mut(entity) {
+                body.move_by(x, y);
+            }
+        }
+    }
+
+    /// Moves every entity and body horizontally by `x`, keeping the relative
+    /// positions intact.
+    pub(crate) fn move_bodies_horizontally(&mut self, x: f32) {
+        self.move_horizontally(x);
+        for body in self.data.values_mut() {
+            body.move_by(x, 0.0);
+        }
+    }
+
+    /// Lets every body change its shape on its own, oldest first, and fits
+    /// the entities to the new shapes.
+    pub(crate) fn update_bodies(&mut self, mut update: impl FnMut(&mut D)) {
+        for entity in &self.entities {
+            let Some(body) = self.data.get_mut(entity) else {
+                continue;
+            };
+            update(body);
+            let bounding_box = body.bounding_box();
+            self.positions.insert(*entity, bounding_box.position);
+            self.colliders.insert(
+                *entity,
+                Collider {
+                    width: bounding_box.width,
+                    height: bounding_box.height,
+                },
+            );
+        }
+    }
+}
+
+/// Adds components to an entity that has just been spawned.
+pub(crate) struct EntityBuilder<'a, D> {
+    world: &'a mut World<D>,
+    entity: Entity,
+}
+
+impl<D> EntityBuilder<'_, D> {
+    pub(crate) fn with_velocity(self, velocity: Velocity) -> Self {
+        self.world.velocities.insert(self.entity, velocity);
+        self
+    }
+
+    pub(crate) fn with_collider(self, width: f32, height: f32) -> Self {
+        self.world
+            .colliders
+            .insert(self.entity, Collider { width, height });
+        self
+    }
+
+    pub(crate) fn with_sprite(self, sprite: Sprite) -> Self {
+        self.world.sprites.insert(self.entity, sprite);
+        self
+    }
+
+    pub(crate) fn with_data(self, data: D) -> Self {
+        self.world.data.insert(self.entity, data);
+        self
+    }
+
+    pub(crate) fn build(self) -> Entity {
+        self.entity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moving_entities_collide_and_leave_the_screen() {
+        let mut world = World::new();
+        let still = world
+            .spawn(Point { x: 0.0, y: 0.0 })
+            .with_collider(10.0, 10.0)
+            .with_data("still")
+            .build();
+        let moving = world
+            .spawn(Point { x: 20.0, y: 0.0 })
+            .with_collider(10.0, 10.0)
+            .with_data("moving")
+            .build();
+        world.set_velocity(moving, Velocity { x: -5.0, y: 0.0 });
+
+        let target = Rect::from_xy(12.0, 0.0, 5.0, 5.0);
+        assert!(world.colliding_with(&target).is_empty());
+        world.move_entities();
+        assert_eq!(world.colliding_with(&target), [moving]);
+
+        world.move_horizontally(-15.0);
+        world.despawn_left_of(0.0);
+        assert_eq!(world.entities, [moving]);
+        assert_eq!(world.despawn(still), None);
+        assert_eq!(world.despawn(moving), Some("moving"));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Block(Rect);
+
+    impl Body for Block {
+        fn bounding_box(&self) -> Rect {
+            self.0
+        }
+
+        fn move_by(&mut self, x: f32, y: f32) {
+            self.0.move_by(x, y);
+        }
+    }
+
+    #[test]
+    fn bodies_move_and_collide_along_with_their_entities() {
+        let mut world = World::new();
+        let block = world
+            .spawn_body(Block(Rect::from_xy(0.0, 0.0, 10.0, 10.0)))
+            .with_velocity(Velocity { x: 2.0, y: 0.0 })
+            .build();
+        world.move_bodies();
+        world.move_bodies_horizontally(-5.0);
+        world.update_bodies(|Block(area)| area.height = 20.0);
+
+        let area = Rect::from_xy(-3.0, 0.0, 10.0, 20.0);
+        assert_eq!(world.get(block), Some(&Block(area)));
+        assert_eq!(world.bounding_box(block), Some(area));
+        assert_eq!(
+            world.colliding_with(&Rect::from_xy(0.0, 15.0, 1.0, 1.0)),
+            [block]
+        );
+    }
+}
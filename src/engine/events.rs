@@ -0,0 +1,90 @@
+use std::{cell::RefCell, fmt};
+
+type Subscriber<E> = Box<dyn FnMut(&E)>;
+
+/// Lets systems such as audio or scoring react to gameplay events without the
+/// code that raises them knowing about them.
+///
+/// Published events are queued and only delivered by [`EventBus::dispatch`],
+/// so subscribers may publish further events without re-entering the bus.
+pub(crate) struct EventBus<E> {
+    queue: RefCell<Vec<E>>,
+    subscribers: RefCell<Vec<Subscriber<E>>>,
+}
+
+impl<E> Default for EventBus<E> {
+    fn default() -> Self {
+        Self {
+            queue: RefCell::new(vec![]),
+            subscribers: RefCell::new(vec![]),
+        }
+    }
+}
+
+impl<E: fmt::Debug> fmt::Debug for EventBus<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventBus")
+            .field("queue", &self.queue.borrow())
+            .field("subscribers", &self.subscribers.borrow().len())
+            .finish()
+    }
+}
+
+impl<E> EventBus<E> {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn subscribe(&self, subscriber: impl FnMut(&E) + 'static) {
+        self.subscribers.borrow_mut().push(Box::new(subscriber));
+    }
+
+    pub(crate) fn publish(&self, event: E) {
+        self.queue.borrow_mut().push(event);
+    }
+
+    /// Delivers the queued events, in the order they were published, to every
+    /// subscriber. Events published meanwhile wait for the next dispatch.
+    pub(crate) fn dispatch(&self) {
+        let events = self.queue.take();
+        let mut subscribers = self.subscribers.take();
+        for event in &events {
+            for subscriber in &mut subscribers {
+                subscriber(event);
+            }
+        }
+        let mut added = self.subscribers.replace(subscribers);
+        self.subscribers.borrow_mut().append(&mut added);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn events_are_delivered_in_order_on_dispatch() {
+        let bus = Rc::new(EventBus::new());
+        let received = Rc::new(RefCell::new(vec![]));
+        bus.subscribe({
+            let bus = Rc::downgrade(&bus);
+            let received = Rc::clone(&received);
+            move |event: &u8| {
+                received.borrow_mut().push(*event);
+                if *event == 1 {
+                    bus.upgrade().unwrap().publish(3);
+                }
+            }
+        });
+
+        bus.publish(1);
+        bus.publish(2);
+        assert!(received.borrow().is_empty());
+        bus.dispatch();
+        assert_eq!(*received.borrow(), [1, 2]);
+        bus.dispatch();
+        assert_eq!(*received.borrow(), [1, 2, 3]);
+    }
+}
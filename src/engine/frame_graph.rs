@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+
+use super::{Point, Rect, Renderer, TextAlign, TextStyle};
+use crate::i18n::t_with;
+
+/// How many of the latest frames are plotted.
+const SAMPLES: usize = 120;
+/// Frames taking longer than this miss a 60 Hz display's refresh.
+const FRAME_BUDGET: f64 = 1000.0 / 60.0;
+/// The frame time drawn as a full height bar. Longer frames are clipped.
+const GRAPH_MAX: f64 = FRAME_BUDGET * 3.0;
+const BAR_WIDTH: f32 = 2.0;
+const GRAPH_HEIGHT: f32 = 60.0;
+const MARGIN: f32 = 10.0;
+/// Room above the graph for the summary.
+const LABEL_HEIGHT: f32 = 20.0;
+
+/// Plots the times of the latest frames as a bar graph, with frames over
+/// budget in red, so that hitches can be seen as they happen.
+#[derive(Debug, Default)]
+pub(super) struct FrameGraph {
+    frame_times: VecDeque<f64>,
+}
+
+impl FrameGraph {
+    pub(super) fn push(&mut self, frame_time: f64) {
+        if self.frame_times.len() == SAMPLES {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    fn average(&self) -> f64 {
+        match self.frame_times.len() {
+            0 => 0.0,
+            len => self.frame_times.iter().sum::<f64>() / len as f64,
+        }
+    }
+
+    fn slow_frames(&self) -> usize {
+        self.frame_times
+            .iter()
+            .filter(|&&frame_time| frame_time > FRAME_BUDGET)
+            .count()
+    }
+
+    /// Returns the part of the screen the graph and its summary cover, in
+    /// the bottom right corner.
+    pub(super) fn area(renderer: &Renderer) -> Rect {
+        let width = SAMPLES as f32 * BAR_WIDTH;
+        let height = GRAPH_HEIGHT + LABEL_HEIGHT;
+        Rect::from_xy(
+            renderer.logical_width as f32 - MARGIN - width,
+            renderer.logical_height as f32 - MARGIN - height,
+            width,
+            height,
+        )
+    }
+
+    pub(super) fn draw(&self, renderer: &Renderer) {
+        const STYLE: TextStyle = TextStyle::new("monospace", 12)
+            .with_color("white")
+            .aligned(TextAlign::Right);
+
+        let area = Self::area(renderer);
+        let (left, right, bottom) = (area.left(), area.right(), area.bottom());
+        let width = area.width;
+        let top = bottom - GRAPH_HEIGHT;
+        renderer.fill_rect(
+            &Rect::from_xy(left, top, width, GRAPH_HEIGHT),
+            "rgba(0, 0, 0, 0.6)",
+        );
+        let scale = f64::from(GRAPH_HEIGHT) / GRAPH_MAX;
+        let budget_y = bottom - (FRAME_BUDGET * scale) as f32;
+        renderer.fill_rect(&Rect::from_xy(left, budget_y, width, 1.0), "yellow");
+        for (index, &frame_time) in self.frame_times.iter().enumerate() {
+            let height = (frame_time.min(GRAPH_MAX) * scale) as f32;
+            let color = if frame_time > FRAME_BUDGET {
+                "red"
+            } else {
+                "limegreen"
+            };
+            renderer.fill_rect(
+                &Rect::from_xy(
+                    left + index as f32 * BAR_WIDTH,
+                    bottom - height,
+                    BAR_WIDTH,
+                    height,
+                ),
+                color,
+            );
+        }
+
+        let average = self.average();
+        let frame_rate = if average > 0.0 { 1000.0 / average } else { 0.0 };
+        renderer.draw_text(
+            &t_with(
+                "frame_graph.summary",
+                &[
+                    ("fps", &format!("{frame_rate:.0}")),
+                    ("average", &format!("{average:.1}")),
+                    ("slow", &self.slow_frames().to_string()),
+                ],
+            ),
+            &Point {
+                x: right,
+                y: top - 4.0,
+            },
+            &STYLE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_the_latest_frames() {
+        let mut graph = FrameGraph::default();
+        for _ in 0..SAMPLES {
+            graph.push(40.0);
+        }
+        for _ in 0..SAMPLES {
+            graph.push(10.0);
+        }
+        assert_eq!(graph.frame_times.len(), SAMPLES);
+        assert_eq!(graph.average(), 10.0);
+        assert_eq!(graph.slow_frames(), 0);
+    }
+}
@@ -0,0 +1,168 @@
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement, ImageBitmap};
+
+use super::Rect;
+use crate::browser;
+
+/// An image that can be drawn by any backend.
+///
+/// Drawing an `ImageBitmap` is much faster than drawing an `HtmlImageElement`
+/// in some browsers, so [`load_image`] decodes images into bitmaps wherever
+/// `createImageBitmap` is available.
+#[derive(Debug, Clone)]
+pub(crate) enum ImageSource {
+    Element(HtmlImageElement),
+    /// Bitmaps do not remember where they came from, so their URL is kept to
+    /// tell images apart.
+    Bitmap {
+        bitmap: ImageBitmap,
+        url: Rc<str>,
+    },
+    /// An image made in the game, such as a recolored sprite sheet, named by
+    /// a made up URL.
+    Canvas {
+        canvas: HtmlCanvasElement,
+        url: Rc<str>,
+    },
+    /// An opaque image that draws nothing, standing in for real images in
+    /// tests.
+    #[cfg(test)]
+    Blank {
+        url: Rc<str>,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl ImageSource {
+    /// Returns the URL the image was loaded from, which identifies it.
+    pub(crate) fn url(&self) -> String {
+        match self {
+            ImageSource::Element(element) => element.src(),
+            ImageSource::Bitmap { url, .. } | ImageSource::Canvas { url, .. } => url.to_string(),
+            #[cfg(test)]
+            ImageSource::Blank { url, .. } => url.to_string(),
+        }
+    }
+
+    pub(crate) fn width(&self) -> u32 {
+        match self {
+            ImageSource::Element(element) => element.natural_width(),
+            ImageSource::Bitmap { bitmap, .. } => bitmap.width(),
+            ImageSource::Canvas { canvas, .. } => canvas.width(),
+            #[cfg(test)]
+            ImageSource::Blank { width, .. } => *width,
+        }
+    }
+
+    pub(crate) fn height(&self) -> u32 {
+        match self {
+            ImageSource::Element(element) => element.natural_height(),
+            ImageSource::Bitmap { bitmap, .. } => bitmap.height(),
+            ImageSource::Canvas { canvas, .. } => canvas.height(),
+            #[cfg(test)]
+            ImageSource::Blank { height, .. } => *height,
+        }
+    }
+
+    /// Draws the part of the image inside `frame` to `destination`.
+    pub(crate) fn draw_on(
+        &self,
+        context: &CanvasRenderingContext2d,
+        frame: &Rect,
+        destination: &Rect,
+    ) -> Result<(), JsValue> {
+        let (sx, sy, sw, sh) = (
+            frame.x().into(),
+            frame.y().into(),
+            frame.width.into(),
+            frame.height.into(),
+        );
+        let (dx, dy, dw, dh) = (
+            destination.x().into(),
+            destination.y().into(),
+            destination.width.into(),
+            destination.height.into(),
+        );
+        match self {
+            ImageSource::Element(element) => context
+                .draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                    element, sx, sy, sw, sh, dx, dy, dw, dh,
+                ),
+            ImageSource::Bitmap { bitmap, .. } => context
+                .draw_image_with_image_bitmap_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                    bitmap, sx, sy, sw, sh, dx, dy, dw, dh,
+                ),
+            ImageSource::Canvas { canvas, .. } => context
+                .draw_image_with_html_canvas_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                    canvas, sx, sy, sw, sh, dx, dy, dw, dh,
+                ),
+            #[cfg(test)]
+            ImageSource::Blank { .. } => Ok(()),
+        }
+    }
+
+    /// Reads back the RGBA pixels of the part of the image inside `area`,
+    /// row by row.
+    pub(crate) fn pixels(&self, area: &Rect) -> Result<Vec<u8>> {
+        let (width, height) = (area.width as u32, area.height as u32);
+        #[cfg(test)]
+        if let ImageSource::Blank { .. } = self {
+            return Ok(vec![u8::MAX; (width * height * 4) as usize]);
+        }
+        let destination = Rect::from_xy(0.0, 0.0, area.width, area.height);
+        browser::canvas_pixels(width, height, |context| {
+            self.draw_on(context, area, &destination)
+        })
+    }
+
+    /// Returns a copy of the image named `url` whose pixels `edit` has
+    /// changed. `edit` is given the RGBA pixels of the whole image, row by
+    /// row.
+    pub(crate) fn edited(&self, url: &str, edit: impl FnOnce(&mut [u8])) -> Result<ImageSource> {
+        let (width, height) = (self.width(), self.height());
+        #[cfg(test)]
+        if let ImageSource::Blank { .. } = self {
+            return Ok(ImageSource::Blank {
+                url: url.into(),
+                width,
+                height,
+            });
+        }
+        let mut pixels = self.pixels(&Rect::from_xy(0.0, 0.0, width as f32, height as f32))?;
+        edit(&mut pixels);
+        Ok(ImageSource::Canvas {
+            canvas: browser::canvas_with_pixels(width, height, &pixels)?,
+            url: url.into(),
+        })
+    }
+}
+
+/// Loads and decodes the image at `source`, as a bitmap if the browser
+/// supports it.
+pub(crate) async fn load_image(source: &str) -> Result<ImageSource> {
+    if browser::supports_image_bitmap() {
+        let bitmap = browser::fetch_image_bitmap(source).await?;
+        Ok(ImageSource::Bitmap {
+            bitmap,
+            url: source.into(),
+        })
+    } else {
+        load_image_element(source).await.map(ImageSource::Element)
+    }
+}
+
+/// Loads the image at `source` into an element and waits for it to be
+/// decoded, so that drawing it the first time does not stall.
+async fn load_image_element(source: &str) -> Result<HtmlImageElement> {
+    let image = browser::new_image()?;
+    image.set_src(source);
+    JsFuture::from(image.decode())
+        .await
+        .map_err(|err| anyhow!("error loading image: {err:#?}"))?;
+    Ok(image)
+}
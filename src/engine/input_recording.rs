@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+use super::KeyState;
+use crate::game::Settings;
+
+/// A key going down or coming up, numbered by how many updates had run when
+/// it arrived.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct KeyEvent {
+    update: u64,
+    code: String,
+    pressed: bool,
+}
+
+/// The raw key events of a session, kept so that it can be played again
+/// without a browser.
+///
+/// Updates have a fixed length, so replaying the same events against a game
+/// started from the same seed, rate, speed and settings repeats the session
+/// exactly, whatever the frame rate was while it was recorded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct InputRecording {
+    seed: u64,
+    updates_per_second: u16,
+    /// How far the boy runs per update at the default rate.
+    running_speed: f32,
+    settings: Settings,
+    /// How many updates the session has lasted.
+    updates: u64,
+    events: Vec<KeyEvent>,
+}
+
+impl InputRecording {
+    /// Starts an empty recording of a game started from `seed`, and played
+    /// with the rest.
+    pub(crate) fn new(
+        seed: u64,
+        updates_per_second: u16,
+        running_speed: f32,
+        settings: Settings,
+    ) -> Self {
+        Self {
+            seed,
+            updates_per_second,
+            running_speed,
+            settings,
+            updates: 0,
+            events: vec![],
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[cfg(test)]
+    pub(crate) fn updates_per_second(&self) -> u16 {
+        self.updates_per_second
+    }
+
+    #[cfg(test)]
+    pub(crate) fn running_speed(&self) -> f32 {
+        self.running_speed
+    }
+
+    #[cfg(test)]
+    pub(crate) fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub(crate) fn set_updates(&mut self, updates: u64) {
+        self.updates = updates;
+    }
+
+    pub(crate) fn record(&mut self, update: u64, code: &str, pressed: bool) {
+        self.events.push(KeyEvent {
+            update,
+            code: code.to_string(),
+            pressed,
+        });
+    }
+
+    /// Returns the keys held down during every update of the session, in
+    /// order.
+    #[cfg(test)]
+    pub(crate) fn replay(&self) -> impl Iterator<Item = KeyState> + '_ {
+        let mut keystate = KeyState::new();
+        let mut events = self.events.iter().peekable();
+        (0..self.updates).map(move |update| {
+            while let Some(event) = events.next_if(|event| event.update <= update) {
+                if event.pressed {
+                    keystate.set_pressed(&event.code);
+                } else {
+                    keystate.set_released(&event.code);
+                }
+            }
+            keystate.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_hold_keys_between_their_events() {
+        let mut recording = InputRecording::new(1, 60, 4.0, Settings::default());
+        recording.record(1, "Space", true);
+        recording.record(3, "Space", false);
+        recording.set_updates(4);
+
+        let held: Vec<_> = recording
+            .replay()
+            .map(|keystate| keystate.is_pressed("Space"))
+            .collect();
+        assert_eq!(held, [false, true, true, false]);
+    }
+}
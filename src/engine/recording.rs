@@ -0,0 +1,140 @@
+use std::{
+    cell::{self, RefCell},
+    rc::Rc,
+};
+
+use anyhow::Result;
+
+use super::{
+    draw_queue::DrawQueue, BlendMode, ImageSource, Rect, Redraw, RenderBackend, Renderer,
+    ScreenShake, StrokeStyle, TextStyle,
+};
+
+/// A call made to a [`Recording`] backend.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Call {
+    Save,
+    Restore,
+    Translate(f64, f64),
+    Rotate(f64),
+    Scale(f64, f64),
+    MultiplyAlpha(f64),
+    SetBlendMode(BlendMode),
+    Clear(Rect),
+    Image { frame: Rect, destination: Rect },
+    StrokeRect(Rect, StrokeStyle),
+    FillRect(Rect, String),
+    FillPolygon(Vec<(f64, f64)>, String),
+    Text { text: String, location: (f64, f64) },
+}
+
+/// A backend that keeps the calls it receives instead of drawing, so that
+/// drawing code can be tested without a canvas.
+#[derive(Debug, Default)]
+struct Recording {
+    calls: Rc<RefCell<Vec<Call>>>,
+}
+
+impl Recording {
+    fn record(&self, call: Call) {
+        self.calls.borrow_mut().push(call);
+    }
+}
+
+impl RenderBackend for Recording {
+    fn begin_frame(&self, _scale: f64) {}
+
+    fn end_frame(&self) {}
+
+    fn clip(&self, _rect: &Rect) {}
+
+    fn save(&self) {
+        self.record(Call::Save);
+    }
+
+    fn restore(&self) {
+        self.record(Call::Restore);
+    }
+
+    fn translate(&self, x: f64, y: f64) {
+        self.record(Call::Translate(x, y));
+    }
+
+    fn rotate(&self, angle: f64) {
+        self.record(Call::Rotate(angle));
+    }
+
+    fn scale(&self, x: f64, y: f64) {
+        self.record(Call::Scale(x, y));
+    }
+
+    fn multiply_alpha(&self, alpha: f64) {
+        self.record(Call::MultiplyAlpha(alpha));
+    }
+
+    fn set_blend_mode(&self, mode: BlendMode) {
+        self.record(Call::SetBlendMode(mode));
+    }
+
+    fn clear(&self, rect: &Rect) {
+        self.record(Call::Clear(*rect));
+    }
+
+    fn draw_image(&self, _image: &ImageSource, frame: &Rect, destination: &Rect) {
+        self.record(Call::Image {
+            frame: *frame,
+            destination: *destination,
+        });
+    }
+
+    fn stroke_rect(&self, rect: &Rect, style: &StrokeStyle) {
+        self.record(Call::StrokeRect(*rect, *style));
+    }
+
+    fn fill_rect(&self, rect: &Rect, color: &str) {
+        self.record(Call::FillRect(*rect, color.to_string()));
+    }
+
+    fn fill_polygon(&self, points: &[(f64, f64)], color: &str) {
+        self.record(Call::FillPolygon(points.to_vec(), color.to_string()));
+    }
+
+    fn fill_text(&self, text: &str, location: (f64, f64), _style: &TextStyle) -> Result<()> {
+        self.record(Call::Text {
+            text: text.to_string(),
+            location,
+        });
+        Ok(())
+    }
+
+    /// Pretends every character is half as wide as the font is tall.
+    fn measure_text(&self, text: &str, style: &TextStyle) -> Result<f64> {
+        Ok(text.chars().count() as f64 * f64::from(style.size) / 2.0)
+    }
+}
+
+impl Renderer {
+    /// Runs `draw` on a `width` by `height` renderer without a canvas and
+    /// returns the calls that reached the backend, after the draw queue has
+    /// cleaned them up.
+    pub(crate) fn record(width: f32, height: f32, draw: impl FnOnce(&Renderer)) -> Vec<Call> {
+        let backend = Recording::default();
+        let calls = Rc::clone(&backend.calls);
+        let renderer = Renderer {
+            backend: Box::new(backend),
+            queue: RefCell::new(DrawQueue::default()),
+            canvas: None,
+            logical_width: width.into(),
+            logical_height: height.into(),
+            scale: cell::Cell::new(1.0),
+            invalidated: cell::Cell::new(false),
+            debug_mode: cell::Cell::new(false),
+            debug_styles: cell::Cell::new(&[]),
+            shake: cell::Cell::new(ScreenShake::default()),
+        };
+        renderer.begin_frame(&Redraw::Everything);
+        draw(&renderer);
+        renderer.end_frame();
+        calls.take()
+    }
+}
@@ -0,0 +1,246 @@
+use std::fmt;
+
+use super::{KeyState, Rect, Redraw, Renderer};
+
+/// One screen of the game, such as the title, the gameplay or a pause menu.
+///
+/// Scenes share a context `C` owned by their [`SceneStack`], and ask the
+/// stack to change the current scene by returning a [`SceneChange`].
+pub(crate) trait Scene<C>: fmt::Debug {
+    fn update(&mut self, context: &mut C, keystate: &KeyState) -> SceneChange<C>;
+    fn draw(&self, context: &C, renderer: &Renderer);
+
+    fn redraw(&self) -> Redraw {
+        Redraw::Everything
+    }
+
+    /// Whether the scene below should be drawn first, as for a pause menu
+    /// shown on top of the gameplay.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+pub(crate) enum SceneChange<C> {
+    None,
+    Push(Box<dyn Scene<C>>, Transition),
+    Pop(Transition),
+    Replace(Box<dyn Scene<C>>, Transition),
+}
+
+impl<C> fmt::Debug for SceneChange<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneChange::None => f.write_str("None"),
+            SceneChange::Push(scene, transition) => f
+                .debug_tuple("Push")
+                .field(scene)
+                .field(transition)
+                .finish(),
+            SceneChange::Pop(transition) => f.debug_tuple("Pop").field(transition).finish(),
+            SceneChange::Replace(scene, transition) => f
+                .debug_tuple("Replace")
+                .field(scene)
+                .field(transition)
+                .finish(),
+        }
+    }
+}
+
+impl<C> SceneChange<C> {
+    fn transition(&self) -> Transition {
+        match self {
+            SceneChange::None => Transition::Cut,
+            SceneChange::Push(_, transition)
+            | SceneChange::Pop(transition)
+            | SceneChange::Replace(_, transition) => *transition,
+        }
+    }
+}
+
+/// How the screen changes from one scene to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transition {
+    Cut,
+    /// Fades to black over the given number of updates, changes the scene,
+    /// then fades back in over as many updates.
+    Fade(u16),
+}
+
+#[derive(Debug)]
+struct Fade<C> {
+    frames: u16,
+    frame: u16,
+    change: Option<SceneChange<C>>,
+}
+
+impl<C> Fade<C> {
+    /// Returns how dark the screen is, from `0.0` to `1.0`.
+    fn darkness(&self) -> f32 {
+        let frames = f32::from(self.frames);
+        let frame = f32::from(self.frame);
+        if self.change.is_some() {
+            frame / frames
+        } else {
+            1.0 - frame / frames
+        }
+    }
+}
+
+/// Keeps the scenes of a game, of which only the topmost one is updated.
+#[derive(Debug)]
+pub(crate) struct SceneStack<C> {
+    context: C,
+    scenes: Vec<Box<dyn Scene<C>>>,
+    fade: Option<Fade<C>>,
+}
+
+impl<C> SceneStack<C> {
+    pub(crate) fn new(context: C, scene: Box<dyn Scene<C>>) -> Self {
+        Self {
+            context,
+            scenes: vec![scene],
+            fade: None,
+        }
+    }
+
+    pub(crate) fn context_mut(&mut self) -> &mut C {
+        &mut self.context
+    }
+
+    /// Updates the topmost scene, unless a transition is running, and applies
+    /// the change it asks for.
+    pub(crate) fn update(&mut self, keystate: &KeyState) {
+        if self.update_fade() {
+            return;
+        }
+        let Some(scene) = self.scenes.last_mut() else {
+            return;
+        };
+        let change = scene.update(&mut self.context, keystate);
+        match change.transition() {
+            Transition::Fade(frames) if frames > 0 => {
+                self.fade = Some(Fade {
+                    frames,
+                    frame: 0,
+                    change: Some(change),
+                });
+            }
+            _ => self.apply(change),
+        }
+    }
+
+    /// Advances the running transition, if any, and returns whether it is
+    /// still running.
+    fn update_fade(&mut self) -> bool {
+        let Some(fade) = &mut self.fade else {
+            return false;
+        };
+        fade.frame += 1;
+        if fade.frame < fade.frames {
+            return true;
+        }
+        match fade.change.take() {
+            Some(change) => {
+                fade.frame = 0;
+                self.apply(change);
+            }
+            None => self.fade = None,
+        }
+        true
+    }
+
+    fn apply(&mut self, change: SceneChange<C>) {
+        match change {
+            SceneChange::None => {}
+            SceneChange::Push(scene, _) => self.scenes.push(scene),
+            SceneChange::Pop(_) => {
+                if self.scenes.len() > 1 {
+                    self.scenes.pop();
+                } else {
+                    error!("cannot pop the last scene");
+                }
+            }
+            SceneChange::Replace(scene, _) => {
+                self.scenes.pop();
+                self.scenes.push(scene);
+            }
+        }
+    }
+
+    /// Draws the topmost scene along with every overlay scene below it, then
+    /// darkens the screen while a fade is running.
+    pub(crate) fn draw(&self, renderer: &Renderer) {
+        let first_visible = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+        for scene in &self.scenes[first_visible..] {
+            scene.draw(&self.context, renderer);
+        }
+
+        if let Some(fade) = &self.fade {
+            let screen = Rect::from_xy(
+                0.0,
+                0.0,
+                renderer.logical_width as f32,
+                renderer.logical_height as f32,
+            );
+            renderer.with_alpha(fade.darkness().into(), |renderer| {
+                renderer.fill_rect(&screen, "black");
+            });
+        }
+    }
+
+    pub(crate) fn redraw(&self) -> Redraw {
+        if self.fade.is_some() {
+            return Redraw::Everything;
+        }
+        self.scenes.last().map_or(Redraw::Everything, |scene| {
+            if scene.is_overlay() {
+                Redraw::Everything
+            } else {
+                scene.redraw()
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Counter(u8);
+
+    impl Scene<Vec<u8>> for Counter {
+        fn update(&mut self, context: &mut Vec<u8>, _keystate: &KeyState) -> SceneChange<Vec<u8>> {
+            context.push(self.0);
+            match self.0 {
+                1 => SceneChange::Push(Box::new(Counter(2)), Transition::Fade(2)),
+                _ => SceneChange::Pop(Transition::Cut),
+            }
+        }
+
+        fn draw(&self, _context: &Vec<u8>, _renderer: &Renderer) {}
+    }
+
+    #[test]
+    fn fades_hold_updates_until_the_scene_has_changed() {
+        let keystate = KeyState::new();
+        let mut stack = SceneStack::new(vec![], Box::new(Counter(1)));
+
+        stack.update(&keystate);
+        assert_eq!(stack.scenes.len(), 1);
+        stack.update(&keystate);
+        stack.update(&keystate);
+        assert_eq!(stack.scenes.len(), 2);
+        stack.update(&keystate);
+        stack.update(&keystate);
+        assert!(stack.fade.is_none());
+        stack.update(&keystate);
+        assert_eq!(stack.scenes.len(), 1);
+        assert_eq!(stack.context, [1, 2]);
+    }
+}
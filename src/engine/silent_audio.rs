@@ -0,0 +1,64 @@
+//! Stands in for [`super::audio`] in tests, which run outside of a browser.
+//! It has the same interface but plays nothing.
+
+use anyhow::Result;
+
+use crate::sound::Looping;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Audio;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Sound;
+
+impl Audio {
+    pub(crate) fn new() -> Result<Self> {
+        Ok(Audio)
+    }
+
+    pub(crate) fn is_unlocked(&self) -> bool {
+        true
+    }
+
+    pub(crate) fn close(&self) -> Result<()> {
+        Ok(())
+    }
+
+    pub(crate) fn set_muted(&self, _muted: bool) {}
+
+    pub(crate) fn set_music_paused(&self, _paused: bool) -> Result<()> {
+        Ok(())
+    }
+
+    pub(crate) fn set_music_volume(&self, _volume: f32) {}
+
+    pub(crate) fn set_sfx_volume(&self, _volume: f32) {}
+
+    pub(crate) async fn load_sound(&self, _filename: &str) -> Result<Sound> {
+        Ok(Sound)
+    }
+
+    pub(crate) fn play_sound(&self, _sound: &Sound) -> Result<SoundHandle> {
+        Ok(SoundHandle)
+    }
+
+    pub(crate) fn play_sound_at(&self, _sound: &Sound, _pan: f32) -> Result<SoundHandle> {
+        Ok(SoundHandle)
+    }
+
+    pub(crate) fn play_looping_sound(&self, _sound: &Sound) -> Result<SoundHandle> {
+        Ok(SoundHandle)
+    }
+
+    pub(crate) fn fade_to(
+        &self,
+        _sound: &Sound,
+        _looping: Looping,
+        _duration: f64,
+    ) -> Result<SoundHandle> {
+        Ok(SoundHandle)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SoundHandle;
@@ -0,0 +1,177 @@
+//! Widgets drawn on the canvas, so that menus match the rest of the game and
+//! keep working in fullscreen, where DOM elements around the canvas are
+//! hidden.
+//!
+//! A screen is a tree of widgets in logical coordinates. Drawing it and
+//! finding the button under the pointer both walk the same tree, so what is
+//! clicked is always what is shown.
+
+use super::{Point, Rect, Renderer, TextAlign, TextStyle};
+
+const PANEL_COLOR: &str = "rgba(0, 0, 0, 0.5)";
+const PANEL_BORDER_COLOR: &str = "rgba(255, 255, 255, 0.8)";
+const BUTTON_COLOR: &str = "rgb(218, 96, 52)";
+const BUTTON_SHADOW_COLOR: &str = "rgb(120, 44, 24)";
+/// Borders and shadows are whole pixel steps, like the sprites.
+const BORDER: f32 = 4.0;
+
+#[derive(Debug, Clone)]
+pub(crate) enum Widget<A> {
+    Label(Label),
+    Button(Button<A>),
+    Panel(Panel<A>),
+}
+
+impl<A: Copy> Widget<A> {
+    pub(crate) fn draw(&self, renderer: &Renderer) {
+        match self {
+            Self::Label(label) => label.draw(renderer),
+            Self::Button(button) => button.draw(renderer),
+            Self::Panel(panel) => panel.draw(renderer),
+        }
+    }
+
+    /// Returns the action of the button at `point`, if there is one.
+    pub(crate) fn hit(&self, point: Point) -> Option<A> {
+        match self {
+            Self::Label(_) => None,
+            Self::Button(button) => button.bounds.contains(point).then_some(button.action),
+            Self::Panel(panel) => panel.hit(point),
+        }
+    }
+}
+
+/// Text that does nothing when clicked.
+#[derive(Debug, Clone)]
+pub(crate) struct Label {
+    text: String,
+    position: Point,
+    style: TextStyle,
+}
+
+impl Label {
+    pub(crate) fn new(text: String, position: Point, style: TextStyle) -> Self {
+        Self {
+            text,
+            position,
+            style,
+        }
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        renderer.draw_text(&self.text, &self.position, &self.style);
+    }
+}
+
+/// A box with centered text that reports `action` when clicked.
+#[derive(Debug, Clone)]
+pub(crate) struct Button<A> {
+    text: String,
+    bounds: Rect,
+    style: TextStyle,
+    action: A,
+}
+
+impl<A> Button<A> {
+    pub(crate) fn new(text: String, bounds: Rect, style: TextStyle, action: A) -> Self {
+        Self {
+            text,
+            bounds,
+            style: style.aligned(TextAlign::Center),
+            action,
+        }
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        let Rect {
+            position: Point { x, y },
+            width,
+            height,
+        } = self.bounds;
+        renderer.fill_rect(&self.bounds, BUTTON_SHADOW_COLOR);
+        renderer.fill_rect(&Rect::from_xy(x, y, width, height - BORDER), BUTTON_COLOR);
+        let baseline = Point {
+            x: x + width / 2.0,
+            y: y + (height - BORDER) / 2.0 + f32::from(self.style.size) / 3.0,
+        };
+        renderer.draw_text(&self.text, &baseline, &self.style);
+    }
+}
+
+/// A framed box drawn behind the widgets it holds.
+#[derive(Debug, Clone)]
+pub(crate) struct Panel<A> {
+    bounds: Rect,
+    children: Vec<Widget<A>>,
+}
+
+impl<A: Copy> Panel<A> {
+    pub(crate) fn new(bounds: Rect, children: Vec<Widget<A>>) -> Self {
+        Self { bounds, children }
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        let Rect {
+            position: Point { x, y },
+            width,
+            height,
+        } = self.bounds;
+        renderer.fill_rect(&self.bounds, PANEL_BORDER_COLOR);
+        renderer.fill_rect(
+            &Rect::from_xy(
+                x + BORDER,
+                y + BORDER,
+                width - BORDER * 2.0,
+                height - BORDER * 2.0,
+            ),
+            PANEL_COLOR,
+        );
+        for child in &self.children {
+            child.draw(renderer);
+        }
+    }
+
+    fn hit(&self, point: Point) -> Option<A> {
+        if !self.bounds.contains(point) {
+            return None;
+        }
+        self.children.iter().find_map(|child| child.hit(point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clicks_reach_only_the_button_under_the_pointer() {
+        const STYLE: TextStyle = TextStyle::new("serif", 20);
+        let screen = Widget::Panel(Panel::new(
+            Rect::from_xy(100.0, 100.0, 400.0, 300.0),
+            vec![
+                Widget::Label(Label::new(
+                    "Title".to_string(),
+                    Point { x: 300.0, y: 150.0 },
+                    STYLE,
+                )),
+                Widget::Button(Button::new(
+                    "Yes".to_string(),
+                    Rect::from_xy(150.0, 300.0, 100.0, 40.0),
+                    STYLE,
+                    true,
+                )),
+                Widget::Button(Button::new(
+                    "No".to_string(),
+                    Rect::from_xy(350.0, 300.0, 100.0, 40.0),
+                    STYLE,
+                    false,
+                )),
+            ],
+        ));
+
+        assert_eq!(screen.hit(Point { x: 200.0, y: 320.0 }), Some(true));
+        assert_eq!(screen.hit(Point { x: 400.0, y: 339.0 }), Some(false));
+        assert_eq!(screen.hit(Point { x: 300.0, y: 150.0 }), None);
+        assert_eq!(screen.hit(Point { x: 10.0, y: 10.0 }), None);
+    }
+}
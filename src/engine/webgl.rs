@@ -0,0 +1,733 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+};
+
+use anyhow::{anyhow, Result};
+use js_sys::Float32Array;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    CanvasRenderingContext2d, HtmlCanvasElement, WebGlBuffer, WebGlProgram,
+    WebGlRenderingContext as Gl, WebGlShader, WebGlTexture, WebGlUniformLocation,
+};
+
+use crate::browser;
+
+use super::{BlendMode, ImageSource, Rect, RenderBackend, StrokeStyle, TextAlign, TextStyle};
+
+const VERTEX_SHADER: &str = r#"
+    attribute vec2 a_position;
+    attribute vec2 a_tex_coord;
+    attribute vec4 a_color;
+    uniform vec2 u_resolution;
+    varying vec2 v_tex_coord;
+    varying vec4 v_color;
+
+    void main() {
+        vec2 clip = a_position / u_resolution * vec2(2.0, -2.0) + vec2(-1.0, 1.0);
+        gl_Position = vec4(clip, 0.0, 1.0);
+        v_tex_coord = a_tex_coord;
+        v_color = a_color;
+    }
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+    precision mediump float;
+    uniform sampler2D u_texture;
+    varying vec2 v_tex_coord;
+    varying vec4 v_color;
+
+    void main() {
+        gl_FragColor = texture2D(u_texture, v_tex_coord) * v_color;
+    }
+"#;
+
+/// Position, texture coordinate and premultiplied color.
+const FLOATS_PER_VERTEX: usize = 8;
+const MAX_BATCH_VERTICES: usize = 6 * 1024;
+const MAX_CACHED_TEXTS: usize = 64;
+const TEXT_PADDING: f64 = 2.0;
+
+/// A 2D affine transform laid out like the canvas `setTransform` arguments.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Affine([f64; 6]);
+
+impl Affine {
+    const fn scale(x: f64, y: f64) -> Self {
+        Self([x, 0.0, 0.0, y, 0.0, 0.0])
+    }
+
+    fn apply(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let [a, b, c, d, e, f] = self.0;
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    fn then_translate(self, x: f64, y: f64) -> Self {
+        let [a, b, c, d, e, f] = self.0;
+        Self([a, b, c, d, a * x + c * y + e, b * x + d * y + f])
+    }
+
+    fn then_scale(self, x: f64, y: f64) -> Self {
+        let [a, b, c, d, e, f] = self.0;
+        Self([a * x, b * x, c * y, d * y, e, f])
+    }
+
+    fn then_rotate(self, angle: f64) -> Self {
+        let [a, b, c, d, e, f] = self.0;
+        let (sin, cos) = angle.sin_cos();
+        Self([
+            a * cos + c * sin,
+            b * cos + d * sin,
+            c * cos - a * sin,
+            d * cos - b * sin,
+            e,
+            f,
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DeviceRect {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl DeviceRect {
+    fn intersection(&self, other: &DeviceRect) -> DeviceRect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        DeviceRect {
+            x,
+            y,
+            width: ((self.x + self.width).min(other.x + other.width) - x).max(0),
+            height: ((self.y + self.height).min(other.y + other.height) - y).max(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DrawState {
+    transform: Affine,
+    alpha: f64,
+    blend_mode: BlendMode,
+}
+
+#[derive(Debug)]
+struct Texture {
+    texture: WebGlTexture,
+    width: f64,
+    height: f64,
+}
+
+#[derive(Debug)]
+struct TextTexture {
+    texture: Texture,
+    ascent: f64,
+    padding: f64,
+}
+
+/// Draws textured quads through WebGL, batching consecutive draws that share a
+/// texture and blend mode into a single draw call.
+///
+/// Solid shapes are drawn with a white texture, and text is rasterized once
+/// with a hidden 2D canvas and then drawn like any other image.
+#[derive(Debug)]
+pub(super) struct WebGl {
+    gl: Gl,
+    program: WebGlProgram,
+    buffer: WebGlBuffer,
+    resolution: WebGlUniformLocation,
+    white: WebGlTexture,
+    scratch: CanvasRenderingContext2d,
+    state: Cell<DrawState>,
+    stack: RefCell<Vec<DrawState>>,
+    clip: Cell<Option<DeviceRect>>,
+    vertices: RefCell<Vec<f32>>,
+    batch_texture: RefCell<Option<WebGlTexture>>,
+    batch_blend_mode: Cell<BlendMode>,
+    images: RefCell<HashMap<String, Texture>>,
+    texts: RefCell<HashMap<(String, TextStyle), TextTexture>>,
+    colors: RefCell<HashMap<String, [f32; 4]>>,
+}
+
+impl WebGl {
+    pub(super) fn new(gl: Gl) -> Result<Self> {
+        let vertex_shader = compile_shader(&gl, Gl::VERTEX_SHADER, VERTEX_SHADER)?;
+        let fragment_shader = compile_shader(&gl, Gl::FRAGMENT_SHADER, FRAGMENT_SHADER)?;
+        let program = link_program(&gl, &vertex_shader, &fragment_shader)?;
+        gl.use_program(Some(&program));
+
+        let buffer = gl
+            .create_buffer()
+            .ok_or_else(|| anyhow!("error creating vertex buffer"))?;
+        gl.bind_buffer(Gl::ARRAY_BUFFER, Some(&buffer));
+        let stride = (FLOATS_PER_VERTEX * std::mem::size_of::<f32>()) as i32;
+        for (name, size, offset) in [
+            ("a_position", 2, 0),
+            ("a_tex_coord", 2, 2),
+            ("a_color", 4, 4),
+        ] {
+            let location = gl.get_attrib_location(&program, name);
+            let location = u32::try_from(location)
+                .map_err(|_| anyhow!("attribute `{name}` not found in shader"))?;
+            gl.enable_vertex_attrib_array(location);
+            gl.vertex_attrib_pointer_with_i32(
+                location,
+                size,
+                Gl::FLOAT,
+                false,
+                stride,
+                offset * std::mem::size_of::<f32>() as i32,
+            );
+        }
+        let resolution = gl
+            .get_uniform_location(&program, "u_resolution")
+            .ok_or_else(|| anyhow!("uniform `u_resolution` not found in shader"))?;
+
+        gl.enable(Gl::BLEND);
+        gl.pixel_storei(Gl::UNPACK_PREMULTIPLY_ALPHA_WEBGL, 1);
+        let white = create_texture(&gl)?;
+        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            Gl::TEXTURE_2D,
+            0,
+            Gl::RGBA as i32,
+            1,
+            1,
+            0,
+            Gl::RGBA,
+            Gl::UNSIGNED_BYTE,
+            Some(&[255, 255, 255, 255]),
+        )
+        .map_err(|err| anyhow!("error creating white texture: {err:#?}"))?;
+
+        let backend = Self {
+            gl,
+            program,
+            buffer,
+            resolution,
+            white,
+            scratch: browser::create_context()?,
+            state: Cell::new(DrawState {
+                transform: Affine::scale(1.0, 1.0),
+                alpha: 1.0,
+                blend_mode: BlendMode::Normal,
+            }),
+            stack: RefCell::new(vec![]),
+            clip: Cell::new(None),
+            vertices: RefCell::new(Vec::with_capacity(MAX_BATCH_VERTICES * FLOATS_PER_VERTEX)),
+            batch_texture: RefCell::new(None),
+            batch_blend_mode: Cell::new(BlendMode::Normal),
+            images: RefCell::new(HashMap::new()),
+            texts: RefCell::new(HashMap::new()),
+            colors: RefCell::new(HashMap::new()),
+        };
+        backend.apply_blend_mode(BlendMode::Normal);
+        Ok(backend)
+    }
+
+    /// Converts `rect` into the bottom-up pixel coordinates of the drawing
+    /// buffer, ignoring any rotation.
+    fn device_rect(&self, rect: &Rect) -> DeviceRect {
+        let transform = self.state.get().transform;
+        let (x0, y0) = transform.apply((rect.x().into(), rect.y().into()));
+        let (x1, y1) = transform.apply((rect.right().into(), rect.bottom().into()));
+        let height = f64::from(self.gl.drawing_buffer_height());
+        DeviceRect {
+            x: x0.min(x1).floor() as i32,
+            y: (height - y0.max(y1)).floor() as i32,
+            width: (x1 - x0).abs().ceil() as i32,
+            height: (y1 - y0).abs().ceil() as i32,
+        }
+    }
+
+    fn set_scissor(&self, area: Option<DeviceRect>) {
+        match area {
+            Some(area) => {
+                self.gl.enable(Gl::SCISSOR_TEST);
+                self.gl.scissor(area.x, area.y, area.width, area.height);
+            }
+            None => self.gl.disable(Gl::SCISSOR_TEST),
+        }
+    }
+
+    fn apply_blend_mode(&self, mode: BlendMode) {
+        match mode {
+            BlendMode::Normal => self.gl.blend_func(Gl::ONE, Gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Multiply => self.gl.blend_func(Gl::DST_COLOR, Gl::ONE_MINUS_SRC_ALPHA),
+        }
+    }
+
+    fn flush(&self) {
+        let mut vertices = self.vertices.borrow_mut();
+        if vertices.is_empty() {
+            return;
+        }
+        let texture = self.batch_texture.borrow();
+        self.gl.use_program(Some(&self.program));
+        self.gl.bind_buffer(Gl::ARRAY_BUFFER, Some(&self.buffer));
+        self.gl.bind_texture(Gl::TEXTURE_2D, texture.as_ref());
+        self.apply_blend_mode(self.batch_blend_mode.get());
+        self.gl.buffer_data_with_array_buffer_view(
+            Gl::ARRAY_BUFFER,
+            &Float32Array::from(vertices.as_slice()),
+            Gl::STREAM_DRAW,
+        );
+        self.gl.draw_arrays(
+            Gl::TRIANGLES,
+            0,
+            (vertices.len() / FLOATS_PER_VERTEX) as i32,
+        );
+        vertices.clear();
+    }
+
+    /// Queues triangles given as `(position, texture coordinate)` pairs.
+    fn push_triangles(
+        &self,
+        texture: &WebGlTexture,
+        color: [f32; 4],
+        triangles: impl IntoIterator<Item = ((f64, f64), (f64, f64))>,
+    ) {
+        let state = self.state.get();
+        let same_batch = self.batch_texture.borrow().as_ref() == Some(texture)
+            && self.batch_blend_mode.get() == state.blend_mode;
+        if !same_batch || self.vertices.borrow().len() >= MAX_BATCH_VERTICES * FLOATS_PER_VERTEX {
+            self.flush();
+            *self.batch_texture.borrow_mut() = Some(texture.clone());
+            self.batch_blend_mode.set(state.blend_mode);
+        }
+
+        let alpha = color[3] * state.alpha as f32;
+        let [r, g, b] = [color[0] * alpha, color[1] * alpha, color[2] * alpha];
+        let mut vertices = self.vertices.borrow_mut();
+        for (position, (u, v)) in triangles {
+            let (x, y) = state.transform.apply(position);
+            vertices.extend([x as f32, y as f32, u as f32, v as f32, r, g, b, alpha]);
+        }
+    }
+
+    fn push_quad(&self, texture: &WebGlTexture, color: [f32; 4], source: [f64; 4], rect: [f64; 4]) {
+        let [u0, v0, u1, v1] = source;
+        let [x0, y0, x1, y1] = rect;
+        self.push_triangles(
+            texture,
+            color,
+            [
+                ((x0, y0), (u0, v0)),
+                ((x1, y0), (u1, v0)),
+                ((x0, y1), (u0, v1)),
+                ((x0, y1), (u0, v1)),
+                ((x1, y0), (u1, v0)),
+                ((x1, y1), (u1, v1)),
+            ],
+        );
+    }
+
+    fn fill(&self, rect: [f64; 4], color: [f32; 4]) {
+        self.push_quad(&self.white, color, [0.0, 0.0, 1.0, 1.0], rect);
+    }
+
+    /// Resolves any CSS color through the hidden 2D canvas.
+    fn parse_color(&self, color: &str) -> [f32; 4] {
+        if let Some(rgba) = self.colors.borrow().get(color) {
+            return *rgba;
+        }
+        self.scratch.clear_rect(0.0, 0.0, 1.0, 1.0);
+        self.scratch.set_fill_style(&JsValue::from_str(color));
+        self.scratch.fill_rect(0.0, 0.0, 1.0, 1.0);
+        let rgba = match self.scratch.get_image_data(0.0, 0.0, 1.0, 1.0) {
+            Ok(data) => {
+                let data = data.data();
+                [0, 1, 2, 3].map(|i| f32::from(data[i]) / 255.0)
+            }
+            Err(err) => {
+                error!("error reading color `{color}`: {err:#?}");
+                [0.0, 0.0, 0.0, 1.0]
+            }
+        };
+        self.colors.borrow_mut().insert(color.to_string(), rgba);
+        rgba
+    }
+
+    fn with_image_texture<R>(
+        &self,
+        image: &ImageSource,
+        f: impl FnOnce(&Texture) -> R,
+    ) -> Result<R> {
+        let source = image.url();
+        if !self.images.borrow().contains_key(&source) {
+            let texture = create_texture(&self.gl)?;
+            let (target, format, kind) = (Gl::TEXTURE_2D, Gl::RGBA, Gl::UNSIGNED_BYTE);
+            match image {
+                ImageSource::Element(element) => self.gl.tex_image_2d_with_u32_and_u32_and_image(
+                    target,
+                    0,
+                    format as i32,
+                    format,
+                    kind,
+                    element,
+                ),
+                ImageSource::Bitmap { bitmap, .. } => {
+                    self.gl.tex_image_2d_with_u32_and_u32_and_image_bitmap(
+                        target,
+                        0,
+                        format as i32,
+                        format,
+                        kind,
+                        bitmap,
+                    )
+                }
+                ImageSource::Canvas { canvas, .. } => {
+                    self.gl.tex_image_2d_with_u32_and_u32_and_canvas(
+                        target,
+                        0,
+                        format as i32,
+                        format,
+                        kind,
+                        canvas,
+                    )
+                }
+                #[cfg(test)]
+                ImageSource::Blank { .. } => Ok(()),
+            }
+            .map_err(|err| anyhow!("error uploading `{source}`: {err:#?}"))?;
+            let texture = Texture {
+                texture,
+                width: image.width().into(),
+                height: image.height().into(),
+            };
+            self.images.borrow_mut().insert(source.clone(), texture);
+        }
+        Ok(f(&self.images.borrow()[&source]))
+    }
+
+    fn create_text_texture(&self, text: &str, style: &TextStyle) -> Result<TextTexture> {
+        let font = style.font();
+        self.scratch.set_font(&font);
+        let metrics = self
+            .scratch
+            .measure_text(text)
+            .map_err(|err| anyhow!("error measuring text: {err:#?}"))?;
+        let padding = TEXT_PADDING + style.stroke.map_or(0.0, |(_, width)| f64::from(width));
+        let ascent = metrics.actual_bounding_box_ascent().ceil() + padding;
+        let width = metrics.width().ceil() + padding * 2.0;
+        let height = ascent + metrics.actual_bounding_box_descent().ceil() + padding;
+
+        let canvas = self
+            .scratch
+            .canvas()
+            .ok_or_else(|| anyhow!("no canvas found for the scratch context"))?;
+        canvas.set_width(width as u32);
+        canvas.set_height(height as u32);
+        self.scratch.set_font(&font);
+        let mut result = Ok(());
+        if let Some((color, width)) = style.stroke {
+            self.scratch.set_stroke_style(&JsValue::from_str(color));
+            self.scratch.set_line_width(width.into());
+            result = self.scratch.stroke_text(text, padding, ascent);
+        }
+        self.scratch.set_fill_style(&JsValue::from_str(style.color));
+        result
+            .and_then(|()| self.scratch.fill_text(text, padding, ascent))
+            .map_err(|err| anyhow!("error drawing text: {err:#?}"))?;
+
+        let texture = create_texture(&self.gl)?;
+        self.gl
+            .tex_image_2d_with_u32_and_u32_and_canvas(
+                Gl::TEXTURE_2D,
+                0,
+                Gl::RGBA as i32,
+                Gl::RGBA,
+                Gl::UNSIGNED_BYTE,
+                &canvas,
+            )
+            .map_err(|err| anyhow!("error uploading text: {err:#?}"))?;
+
+        canvas.set_width(1);
+        canvas.set_height(1);
+        Ok(TextTexture {
+            texture: Texture {
+                texture,
+                width,
+                height,
+            },
+            ascent,
+            padding,
+        })
+    }
+}
+
+impl RenderBackend for WebGl {
+    fn begin_frame(&self, scale: f64) {
+        if let Some(canvas) = self
+            .gl
+            .canvas()
+            .and_then(|canvas| canvas.dyn_into::<HtmlCanvasElement>().ok())
+        {
+            let (width, height) = (canvas.width() as i32, canvas.height() as i32);
+            self.gl.viewport(0, 0, width, height);
+            self.gl.use_program(Some(&self.program));
+            self.gl
+                .uniform2f(Some(&self.resolution), width as f32, height as f32);
+        }
+        self.stack.borrow_mut().clear();
+        self.state.set(DrawState {
+            transform: Affine::scale(scale, scale),
+            alpha: 1.0,
+            blend_mode: BlendMode::Normal,
+        });
+    }
+
+    fn end_frame(&self) {
+        self.flush();
+        self.clip.set(None);
+        self.set_scissor(None);
+    }
+
+    fn clip(&self, rect: &Rect) {
+        self.flush();
+        let area = self.device_rect(rect);
+        self.clip.set(Some(area));
+        self.set_scissor(Some(area));
+    }
+
+    fn save(&self) {
+        self.stack.borrow_mut().push(self.state.get());
+    }
+
+    fn restore(&self) {
+        if let Some(state) = self.stack.borrow_mut().pop() {
+            self.state.set(state);
+        }
+    }
+
+    fn translate(&self, x: f64, y: f64) {
+        let mut state = self.state.get();
+        state.transform = state.transform.then_translate(x, y);
+        self.state.set(state);
+    }
+
+    fn rotate(&self, angle: f64) {
+        let mut state = self.state.get();
+        state.transform = state.transform.then_rotate(angle);
+        self.state.set(state);
+    }
+
+    fn scale(&self, x: f64, y: f64) {
+        let mut state = self.state.get();
+        state.transform = state.transform.then_scale(x, y);
+        self.state.set(state);
+    }
+
+    fn multiply_alpha(&self, alpha: f64) {
+        let mut state = self.state.get();
+        state.alpha *= alpha;
+        self.state.set(state);
+    }
+
+    fn set_blend_mode(&self, mode: BlendMode) {
+        let mut state = self.state.get();
+        state.blend_mode = mode;
+        self.state.set(state);
+    }
+
+    fn clear(&self, rect: &Rect) {
+        self.flush();
+        let mut area = self.device_rect(rect);
+        if let Some(clip) = self.clip.get() {
+            area = area.intersection(&clip);
+        }
+        self.set_scissor(Some(area));
+        self.gl.clear_color(0.0, 0.0, 0.0, 0.0);
+        self.gl.clear(Gl::COLOR_BUFFER_BIT);
+        self.set_scissor(self.clip.get());
+    }
+
+    fn draw_image(&self, image: &ImageSource, frame: &Rect, destination: &Rect) {
+        let result = self.with_image_texture(image, |texture| {
+            let source = [
+                f64::from(frame.x()) / texture.width,
+                f64::from(frame.y()) / texture.height,
+                f64::from(frame.right()) / texture.width,
+                f64::from(frame.bottom()) / texture.height,
+            ];
+            let rect = [
+                destination.x().into(),
+                destination.y().into(),
+                destination.right().into(),
+                destination.bottom().into(),
+            ];
+            self.push_quad(&texture.texture, [1.0; 4], source, rect);
+        });
+        if let Err(err) = result {
+            error!("error drawing image: {err:#?}");
+        }
+    }
+
+    fn stroke_rect(&self, rect: &Rect, style: &StrokeStyle) {
+        let color = self.parse_color(style.color);
+        let [x0, y0, x1, y1] = [
+            f64::from(rect.x()) - 0.5,
+            f64::from(rect.y()) - 0.5,
+            f64::from(rect.right()) + 0.5,
+            f64::from(rect.bottom()) + 0.5,
+        ];
+        for (from, to) in style.dash.segments(x0, x1) {
+            self.fill([from, y0, to, y0 + 1.0], color);
+            self.fill([from, y1 - 1.0, to, y1], color);
+        }
+        for (from, to) in style.dash.segments(y0, y1) {
+            self.fill([x0, from, x0 + 1.0, to], color);
+            self.fill([x1 - 1.0, from, x1, to], color);
+        }
+    }
+
+    fn fill_rect(&self, rect: &Rect, color: &str) {
+        let rect = [
+            rect.x().into(),
+            rect.y().into(),
+            rect.right().into(),
+            rect.bottom().into(),
+        ];
+        self.fill(rect, self.parse_color(color));
+    }
+
+    /// Fills `points` as a triangle fan around their centroid, which covers
+    /// the convex and star-shaped outlines the renderer produces.
+    fn fill_polygon(&self, points: &[(f64, f64)], color: &str) {
+        if points.len() < 3 {
+            return;
+        }
+        let count = points.len() as f64;
+        let (sum_x, sum_y) = points
+            .iter()
+            .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+        let center = (sum_x / count, sum_y / count);
+        let edges = points.iter().zip(points.iter().cycle().skip(1));
+        let triangles =
+            edges.flat_map(|(&a, &b)| [center, a, b].map(|position| (position, (0.5, 0.5))));
+        self.push_triangles(&self.white, self.parse_color(color), triangles);
+    }
+
+    fn fill_text(&self, text: &str, (x, y): (f64, f64), style: &TextStyle) -> Result<()> {
+        let key = (text.to_string(), *style);
+        if !self.texts.borrow().contains_key(&key) {
+            let text_texture = self.create_text_texture(text, style)?;
+            let mut texts = self.texts.borrow_mut();
+            if texts.len() >= MAX_CACHED_TEXTS {
+                self.flush();
+                for (_, old) in texts.drain() {
+                    self.gl.delete_texture(Some(&old.texture.texture));
+                }
+            }
+            texts.insert(key.clone(), text_texture);
+        }
+
+        let texts = self.texts.borrow();
+        let TextTexture {
+            texture,
+            ascent,
+            padding,
+        } = &texts[&key];
+        let left = match style.align {
+            TextAlign::Left => x - padding,
+            TextAlign::Center => x - texture.width / 2.0,
+            TextAlign::Right => x - texture.width + padding,
+        };
+        let top = y - ascent;
+        self.push_quad(
+            &texture.texture,
+            [1.0; 4],
+            [0.0, 0.0, 1.0, 1.0],
+            [left, top, left + texture.width, top + texture.height],
+        );
+        Ok(())
+    }
+
+    fn measure_text(&self, text: &str, style: &TextStyle) -> Result<f64> {
+        self.scratch.set_font(&style.font());
+        self.scratch
+            .measure_text(text)
+            .map(|metrics| metrics.width())
+            .map_err(|err| anyhow!("error measuring text: {err:#?}"))
+    }
+}
+
+fn compile_shader(gl: &Gl, shader_type: u32, source: &str) -> Result<WebGlShader> {
+    let shader = gl
+        .create_shader(shader_type)
+        .ok_or_else(|| anyhow!("error creating shader"))?;
+    gl.shader_source(&shader, source);
+    gl.compile_shader(&shader);
+    if gl
+        .get_shader_parameter(&shader, Gl::COMPILE_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(shader)
+    } else {
+        Err(anyhow!(
+            "error compiling shader: {}",
+            gl.get_shader_info_log(&shader).unwrap_or_default()
+        ))
+    }
+}
+
+fn link_program(
+    gl: &Gl,
+    vertex_shader: &WebGlShader,
+    fragment_shader: &WebGlShader,
+) -> Result<WebGlProgram> {
+    let program = gl
+        .create_program()
+        .ok_or_else(|| anyhow!("error creating shader program"))?;
+    gl.attach_shader(&program, vertex_shader);
+    gl.attach_shader(&program, fragment_shader);
+    gl.link_program(&program);
+    if gl
+        .get_program_parameter(&program, Gl::LINK_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(program)
+    } else {
+        Err(anyhow!(
+            "error linking shader program: {}",
+            gl.get_program_info_log(&program).unwrap_or_default()
+        ))
+    }
+}
+
+/// Creates and binds a texture that is clamped and linearly filtered, which
+/// lets images of any size be uploaded under WebGL 1.
+fn create_texture(gl: &Gl) -> Result<WebGlTexture> {
+    let texture = gl
+        .create_texture()
+        .ok_or_else(|| anyhow!("error creating texture"))?;
+    gl.bind_texture(Gl::TEXTURE_2D, Some(&texture));
+    for (parameter, value) in [
+        (Gl::TEXTURE_WRAP_S, Gl::CLAMP_TO_EDGE),
+        (Gl::TEXTURE_WRAP_T, Gl::CLAMP_TO_EDGE),
+        (Gl::TEXTURE_MIN_FILTER, Gl::LINEAR),
+        (Gl::TEXTURE_MAG_FILTER, Gl::LINEAR),
+    ] {
+        gl.tex_parameteri(Gl::TEXTURE_2D, parameter, value as i32);
+    }
+    Ok(texture)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn affine_transforms_compose_like_the_canvas() {
+        let transform = Affine::scale(2.0, 2.0)
+            .then_translate(10.0, 0.0)
+            .then_rotate(std::f64::consts::FRAC_PI_2);
+        let (x, y) = transform.apply((1.0, 0.0));
+        assert!((x - 20.0).abs() < 1e-9);
+        assert!((y - 2.0).abs() < 1e-9);
+    }
+}
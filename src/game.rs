@@ -1,548 +1,3094 @@
-use std::{fmt::Debug, rc::Rc};
+use std::{
+    cell,
+    collections::{HashMap, HashSet},
+    f32::consts::TAU,
+    fmt::Debug,
+    rc::Rc,
+};
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use futures::channel::mpsc::UnboundedReceiver;
-use rand::{seq::SliceRandom, thread_rng};
-use web_sys::HtmlImageElement;
+use futures::channel::{mpsc::UnboundedReceiver, oneshot};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     browser,
-    engine::{self, Audio, Cell, Game, Image, KeyState, Point, Rect, Renderer, Sheet, SpriteSheet},
-    segments::SEGMENT_GENERATORS,
+    config::Config,
+    engine::{
+        self, AssetLoader, Audio, BlendMode, Body, Button, Camera, Cell, DebugCategory, Entity,
+        EventBus, Game, Image, ImageSource, InputRecording, KeyState, Label, LineDash, Panel,
+        Point, Rect, Redraw, Renderer, Rumble, Scene, SceneChange, SceneStack, ScreenShake, Shape,
+        Sheet, SheetRect, Sound, SoundHandle, Sprite, SpriteSheet, StrokeStyle, TextAlign,
+        TextStyle, Transform, Transition, Velocity, Widget, World,
+    },
+    i18n::{self, t, t_with},
+    net::{Message, NetClient, PlayerId},
+    segments::{Difficulty, Segment, SegmentPicker, SEGMENT_GENERATORS},
+    sound::Looping,
+    tiled::TiledMap,
 };
 
-use self::red_hat_boy::RedHatBoy;
+use self::{
+    boss::{Boss, BossAction},
+    cheats::{Cheat, Cheats},
+    combo::{Combo, Trick},
+    contacts::{ContactLog, ContactOutcome},
+    editor::Editor,
+    level::{Level, LevelComplete, LevelManifest},
+    red_hat_boy::{Animations, BoySounds, RedHatBoy},
+    settings::{Action, SettingsMenu},
+    shatter::Shatter,
+    skins::Skin,
+    stats::{RunStats, Stats, StatsScreen},
+    theme::{ThemeAssets, Themes},
+    toast::Toasts,
+    tutorial::Tutorial,
+    weather::WeatherEffects,
+};
 
-mod red_hat_boy;
+pub(crate) use self::{
+    boost_pad::BoostPad,
+    golden::{run_golden_tests, GoldenMode},
+    overhead_bar::OverheadBar,
+    pool::Pool,
+    power_up::{PowerUp, PowerUpKind},
+    saw::Saw,
+    settings::Settings,
+    snapshot::ObstacleSnapshot,
+    spring::Spring,
+    weather::Weather,
+};
 
-pub(crate) const WIDTH: i16 = 600;
-pub(crate) const HEIGHT: i16 = 600;
-const TIMELINE_MINIMUM: i16 = 1000;
-const OBSTACLE_BUFFER: i16 = 20;
+mod boost_pad;
+mod boss;
+mod cheats;
+mod combo;
+mod contacts;
+mod daylight;
+mod editor;
+mod golden;
+mod hud;
+mod inspector;
+mod level;
+mod magnet;
+mod overhead_bar;
+mod pool;
+mod power_up;
+mod radar;
+mod red_hat_boy;
+mod saw;
+mod settings;
+mod shatter;
+mod skins;
+mod snapshot;
+mod spring;
+mod stats;
+mod theme;
+mod toast;
+mod tutorial;
+mod weather;
+
+pub(crate) const WIDTH: f32 = 600.0;
+pub(crate) const HEIGHT: f32 = 600.0;
+const TIMELINE_MINIMUM: f32 = 1000.0;
+const OBSTACLE_BUFFER: f32 = 20.0;
+const MAP_WEIGHT: u32 = 2;
+const MAP_DIFFICULTY: Difficulty = Difficulty::Medium;
+/// Once the camera has moved this far, the whole world is shifted back to the
+/// origin so that world coordinates keep their sub-pixel precision.
+const REBASE_THRESHOLD: f32 = 16384.0;
+/// How far beyond the boy's bounding box obstacles are still tested for
+/// collisions, to cover his whole sprite.
+const BROADPHASE_MARGIN: f32 = 50.0;
+/// A checkpoint is announced every time the boy has run this far.
+const CHECKPOINT_DISTANCE: f32 = 10000.0;
+/// How far ahead of the boy an overhead obstacle makes the one-button mode
+/// slide rather than jump.
+const SLIDE_LOOKAHEAD: f32 = 200.0;
+const HARD_LANDING_SPEED: f32 = 20.0;
+const HIT_TIME_SCALE: f32 = 0.5;
+const HIT_SLOW_MOTION_SECONDS: f32 = 0.5;
+const HIT_SHAKE: ScreenShake = ScreenShake::new(10.0, 0.9);
+const LANDING_SHAKE: ScreenShake = ScreenShake::new(3.0, 0.8);
+/// How the bounding boxes of the debug mode are outlined, so that a boy
+/// standing on a platform next to a stone can still be made out.
+const DEBUG_STYLES: [(DebugCategory, StrokeStyle); 6] = [
+    (DebugCategory::Player, StrokeStyle::new("lime")),
+    (DebugCategory::Platform, StrokeStyle::new("deepskyblue")),
+    (
+        DebugCategory::LandingSurface,
+        StrokeStyle::new("yellow").with_dash(LineDash::Dashed),
+    ),
+    (DebugCategory::Barrier, StrokeStyle::new("red")),
+    (
+        DebugCategory::Item,
+        StrokeStyle::new("gold").with_dash(LineDash::Dotted),
+    ),
+    (
+        DebugCategory::Other,
+        StrokeStyle::new("magenta").with_dash(LineDash::Dashed),
+    ),
+];
+const HIT_RUMBLE: Rumble = Rumble::new(400.0, 1.0, 0.8);
+const LANDING_RUMBLE: Rumble = Rumble::new(120.0, 0.4, 0.2);
+const COIN_SIZE: f32 = 24.0;
+const COIN_POINTS: u32 = 1;
+/// The bonus for kicking a breakable stone apart.
+const BREAK_POINTS: u32 = 3;
+const DEBRIS_COLOR: &str = "slategray";
+const STARTING_LIVES: u8 = 3;
+const MUSIC_VOLUME: f32 = 0.4;
+const MUSIC_FADE_SECONDS: f64 = 1.0;
+const SFX_VOLUME: f32 = 0.8;
+const KEN_FUTURE: &str = "'Ken Future', sans-serif";
+const NEW_GAME_FADE_SECONDS: f32 = 0.25;
+/// Ghosts of players who have not been heard from for this many seconds are
+/// removed.
+const GHOST_TIMEOUT_SECONDS: f32 = 3.0;
+const SOUND_PROMPT_STYLE: TextStyle = TextStyle::new("serif", 18).aligned(TextAlign::Center);
 
 #[derive(Debug)]
 pub(crate) struct WalkTheDog {
-    machine: Option<WalkTheDogStateMachine>,
+    seed: u64,
+    config: Config,
+    scenes: Option<SceneStack<Walk>>,
 }
 
-#[derive(Debug, derive_more::From)]
-enum WalkTheDogStateMachine {
-    Ready(WalkTheDogState<Ready>),
-    Walking(WalkTheDogState<Walking>),
-    GameOver(WalkTheDogState<GameOver>),
+/// Shows the logo, fading it in, before the title screen.
+#[derive(Debug, Default)]
+struct Splash {
+    frame: u16,
 }
-impl WalkTheDogStateMachine {
-    fn new(walk: Walk) -> Self {
-        WalkTheDogState::new(walk).into()
-    }
 
-    fn update(self, keystate: &KeyState) -> Self {
-        log!("Keystate is {keystate:#?}");
-        match self {
-            WalkTheDogStateMachine::Ready(state) => state.update(keystate),
-            WalkTheDogStateMachine::Walking(state) => state.update(keystate),
-            WalkTheDogStateMachine::GameOver(state) => state.update(),
-        }
+impl Splash {
+    /// How many seconds the logo takes to fade in, and to fade out with the
+    /// change to the title screen.
+    const FADE_SECONDS: f32 = 0.5;
+    const HOLD_SECONDS: f32 = 1.0;
+
+    /// Returns how visible the logo is, from `0.0` to `1.0`.
+    fn alpha(&self) -> f32 {
+        (f32::from(self.frame) / f32::from(engine::updates_in(Self::FADE_SECONDS))).min(1.0)
     }
+}
 
-    fn draw(&self, renderer: &Renderer) {
-        match self {
-            WalkTheDogStateMachine::Ready(state) => state.draw(renderer),
-            WalkTheDogStateMachine::Walking(state) => state.draw(renderer),
-            WalkTheDogStateMachine::GameOver(state) => state.draw(renderer),
+impl Scene<Walk> for Splash {
+    fn update(&mut self, _walk: &mut Walk, keystate: &KeyState) -> SceneChange<Walk> {
+        self.frame = self.frame.saturating_add(1);
+        if keystate.pressed_keys().next().is_some() {
+            SceneChange::Replace(Box::new(Ready), Transition::Cut)
+        } else if self.frame >= engine::updates_in(Self::FADE_SECONDS + Self::HOLD_SECONDS) {
+            let fade = engine::updates_in(Self::FADE_SECONDS);
+            SceneChange::Replace(Box::new(Ready), Transition::Fade(fade))
+        } else {
+            SceneChange::None
         }
     }
-}
 
-#[derive(Debug)]
-struct WalkTheDogState<T> {
-    walk: Walk,
-    _state: T,
-}
+    fn draw(&self, walk: &Walk, renderer: &Renderer) {
+        const TITLE_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 36)
+            .with_color("white")
+            .aligned(TextAlign::Center);
 
-impl<T> WalkTheDogState<T> {
-    fn draw(&self, renderer: &Renderer) {
-        self.walk.draw(renderer);
+        renderer.fill_rect(&Rect::from_xy(0.0, 0.0, WIDTH, HEIGHT), "black");
+        let (width, height) = (walk.logo.width() as f32, walk.logo.height() as f32);
+        renderer.with_alpha(self.alpha().into(), |renderer| {
+            renderer.draw_entire_image(
+                &walk.logo,
+                Point {
+                    x: (WIDTH - width) / 2.0,
+                    y: (HEIGHT - height) / 2.0 - 30.0,
+                },
+            );
+            renderer.draw_text(
+                &t("splash.title"),
+                &Point {
+                    x: WIDTH / 2.0,
+                    y: (HEIGHT + height) / 2.0 + 20.0,
+                },
+                &TITLE_STYLE,
+            );
+        });
     }
 }
 
 #[derive(Debug)]
 struct Ready;
 
-impl WalkTheDogState<Ready> {
-    fn new(walk: Walk) -> WalkTheDogState<Ready> {
-        Self {
-            _state: Ready,
-            walk,
-        }
-    }
-
-    fn update(mut self, keystate: &KeyState) -> WalkTheDogStateMachine {
-        self.walk.boy.update();
+impl Scene<Walk> for Ready {
+    fn update(&mut self, walk: &mut Walk, keystate: &KeyState) -> SceneChange<Walk> {
+        walk.boy.update();
 
-        if keystate.is_pressed("ArrowRight") {
-            self.start_running()
+        if walk.is_action_pressed(keystate, Action::Run)
+            || walk.one_button_action(keystate).is_some()
+        {
+            walk.boy.run_right();
+            SceneChange::Replace(Box::new(Walking), Transition::Cut)
         } else {
-            self.into()
+            SceneChange::None
         }
     }
 
-    fn start_running(mut self) -> WalkTheDogStateMachine {
-        self.run_right();
-        WalkTheDogStateMachine::Walking(WalkTheDogState {
-            walk: self.walk,
-            _state: Walking,
-        })
-    }
-
-    fn run_right(&mut self) {
-        self.walk.boy.run_right();
+    fn draw(&self, walk: &Walk, renderer: &Renderer) {
+        walk.draw(renderer);
     }
 }
 
 #[derive(Debug)]
 struct Walking;
 
-impl WalkTheDogState<Walking> {
-    fn update(mut self, keystate: &KeyState) -> WalkTheDogStateMachine {
-        if keystate.is_pressed("ArrowDown") {
-            self.walk.boy.slide();
+impl Scene<Walk> for Walking {
+    fn update(&mut self, walk: &mut Walk, keystate: &KeyState) -> SceneChange<Walk> {
+        if walk.action_just_pressed(keystate, Action::Pause) {
+            walk.pause_music(true);
+            return SceneChange::Push(Box::new(Paused), Transition::Cut);
+        }
+        if walk.action_just_pressed(keystate, Action::Restart) {
+            walk.record_run(false);
+            return walk.restart();
+        }
+        if walk.settings.debug_mode && walk.just_pressed(keystate, "F2") {
+            return SceneChange::Push(Box::new(Editor::new(walk)), Transition::Cut);
+        }
+        if walk.settings.debug_mode && walk.just_pressed(keystate, "F8") {
+            if let Err(err) = walk.save_snapshot() {
+                error!("error saving the run: {err:#?}");
+            }
+        }
+        if walk.settings.debug_mode && walk.just_pressed(keystate, "F7") {
+            walk.reload_sprites();
+        }
+        if walk.settings.debug_mode && walk.just_pressed(keystate, "F9") {
+            if let Err(err) = walk.load_snapshot() {
+                error!("error loading the run: {err:#?}");
+            }
+        }
+        if walk.settings.debug_mode && walk.just_pressed(keystate, "F6") {
+            walk.frame_step = !walk.frame_step;
+        }
+        // Frame by frame, the world only moves on when the step key is
+        // pressed, with whatever other keys are held down at the time.
+        if walk.frame_step && walk.settings.debug_mode && !walk.just_pressed(keystate, "Period") {
+            return SceneChange::None;
+        }
+        // The lessons are about the keys that the one-button mode does without.
+        if let (false, Some(tutorial)) = (walk.settings.one_button, &mut walk.tutorial) {
+            let boy_right = walk.boy.bounding_box().right();
+            if tutorial.update(
+                keystate,
+                &walk.settings.bindings,
+                &walk.obstacles,
+                boy_right,
+            ) {
+                return SceneChange::None;
+            }
+            if tutorial.is_finished() {
+                walk.tutorial = None;
+            }
+        }
+
+        let one_button_action = walk.one_button_action(keystate);
+        let pressed =
+            |action| one_button_action == Some(action) || walk.is_action_pressed(keystate, action);
+        let (slide, attack, jump) = (
+            pressed(Action::Slide),
+            pressed(Action::Attack),
+            pressed(Action::Jump),
+        );
+        if slide {
+            let was_sliding = walk.boy.sliding();
+            walk.boy.slide();
+            if !was_sliding && walk.boy.sliding() {
+                walk.events.publish(GameEvent::BoySlid);
+            }
+        }
+        if attack {
+            walk.boy.attack();
         }
-        if keystate.is_pressed("Space") {
-            self.walk.boy.jump();
+        if jump {
+            let was_jumping = walk.boy.jumping();
+            walk.boy.jump();
+            if !was_jumping && walk.boy.jumping() {
+                walk.events.publish(GameEvent::BoyJumped);
+            }
+        } else {
+            walk.boy.release_jump();
         }
         if keystate.is_pressed("KeyD") {
-            self.walk.debug_mode = !self.walk.debug_mode;
+            walk.settings.debug_mode = !walk.settings.debug_mode;
         }
 
-        self.walk.boy.update();
+        let was_jumping = walk.boy.jumping();
+        let falling_speed = walk.boy.velocity_y();
+        let lives = walk.boy.lives();
+        let distance = walk.distance();
+        let boy_left = walk.boy.bounding_box().left();
+        let shielded = walk.boy.shielded();
+
+        walk.boy.update();
+        walk.boy.update_power_ups();
+        if let Some(level) = &mut walk.level {
+            level.tick();
+        }
+        walk.run_stats.borrow_mut().frames += 1;
+        let slip = walk.weather.update();
+        if slip > 0.0 {
+            walk.boy.carry(slip);
+        }
 
-        let walking_speed = self.walk.velocity();
-        for background in &mut self.walk.backgrounds {
-            background.move_horizontally(walking_speed);
+        walk.follow_boy();
+        walk.update_theme();
+        let left_edge = walk.camera.x();
+        if (walk.distance() / CHECKPOINT_DISTANCE).floor()
+            > (distance / CHECKPOINT_DISTANCE).floor()
+        {
+            walk.events.publish(GameEvent::CheckpointReached);
+        }
+        if !walk.sound_unlocked && walk.audio.is_unlocked() {
+            walk.sound_unlocked = true;
+            walk.events.publish(GameEvent::SoundEnabled);
         }
-        let [first_background, second_background] = &mut self.walk.backgrounds;
-        if first_background.right() < 0 {
+
+        let [first_background, second_background] = &mut walk.backgrounds;
+        if first_background.right() < left_edge {
             first_background.set_x(second_background.right());
         }
-        if second_background.right() < 0 {
+        if second_background.right() < left_edge {
             second_background.set_x(first_background.right());
         }
 
-        self.walk.obstacles.retain(|obstacle| obstacle.right() > 0);
+        walk.obstacles.despawn_left_of(left_edge);
+        walk.weather_zones.retain(|zone| zone.right > left_edge);
+        walk.update_weather();
 
-        for obstacle in &mut self.walk.obstacles {
-            obstacle.move_horizontally(walking_speed);
-            obstacle.check_intersection(&mut self.walk.boy);
-        }
+        let dt = engine::seconds_per_update();
+        walk.obstacles.update_bodies(|obstacle| obstacle.update(dt));
+        walk.obstacles.move_bodies();
+        walk.update_boss();
+        engine::profile("collisions", || walk.check_obstacle_collisions());
+        walk.play_obstacle_sounds(left_edge);
 
-        if self.walk.timeline < TIMELINE_MINIMUM {
-            self.walk.generate_next_segment();
+        if walk.boy.magnetic() {
+            magnet::attract_coins(&mut walk.items, walk.boy.bounding_box().center());
+        }
+        walk.items.move_entities();
+        walk.items.despawn_left_of(left_edge);
+        walk.collect_items();
+        walk.update_slow_motion();
+
+        walk.shatter.update();
+        walk.contacts.update();
+        walk.combo.update();
+        if shielded && !walk.boy.shielded() {
+            walk.events.publish(GameEvent::ShieldShattered);
+            walk.shatter.burst(
+                walk.boy.bounding_box().center(),
+                PowerUpKind::Shield.color(),
+            );
+            walk.combo.reset();
+        } else if walk.boy.lives() < lives {
+            walk.events.publish(GameEvent::BoyHit);
+            walk.shake.set(Some(HIT_SHAKE));
+            HIT_RUMBLE.play(walk.settings.rumble);
+            walk.start_slow_motion();
+            walk.combo.reset();
         } else {
-            self.walk.timeline += walking_speed;
+            let landed = was_jumping && !walk.boy.jumping();
+            if landed {
+                walk.weather.land();
+                if falling_speed >= HARD_LANDING_SPEED {
+                    walk.shake.set(Some(LANDING_SHAKE));
+                    LANDING_RUMBLE.play(walk.settings.rumble);
+                }
+            }
+            walk.score_tricks(boy_left, landed);
+        }
+
+        if walk.timeline - left_edge < TIMELINE_MINIMUM {
+            walk.generate_next_segment();
+        }
+        if left_edge > REBASE_THRESHOLD {
+            walk.rebase(left_edge);
         }
+        walk.events.dispatch();
+        walk.toasts.borrow_mut().update();
 
-        if self.walk.knocked_out() {
-            self.end_game()
+        if walk.knocked_out() {
+            SceneChange::Replace(Box::new(GameOver::new(walk)), Transition::Cut)
+        } else if walk.reached_goal() {
+            SceneChange::Replace(Box::new(LevelComplete::new(walk)), Transition::Cut)
         } else {
-            self.into()
+            SceneChange::None
         }
     }
 
-    fn end_game(self) -> WalkTheDogStateMachine {
-        browser::draw_ui("<button id='new_game'>New Game</button>").unwrap();
-        let element = browser::find_html_element_by_id("new_game").unwrap();
-        let receiver = engine::add_click_handler(element);
+    fn draw(&self, walk: &Walk, renderer: &Renderer) {
+        walk.draw(renderer);
+    }
+}
 
-        WalkTheDogState {
-            walk: self.walk,
-            _state: GameOver {
-                new_game_event: receiver,
-            },
+#[derive(Debug)]
+struct Paused;
+
+impl Scene<Walk> for Paused {
+    fn update(&mut self, walk: &mut Walk, keystate: &KeyState) -> SceneChange<Walk> {
+        if walk.action_just_pressed(keystate, Action::Pause) {
+            walk.pause_music(false);
+            SceneChange::Pop(Transition::Cut)
+        } else if walk.just_pressed(keystate, "KeyS") {
+            SceneChange::Push(Box::new(SettingsMenu::new()), Transition::Cut)
+        } else if walk.just_pressed(keystate, "KeyT") {
+            SceneChange::Push(Box::new(StatsScreen), Transition::Cut)
+        } else {
+            SceneChange::None
         }
-        .into()
+    }
+
+    fn draw(&self, _walk: &Walk, renderer: &Renderer) {
+        const PAUSED_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 48)
+            .bold()
+            .with_color("white")
+            .with_stroke("black", 4)
+            .aligned(TextAlign::Center);
+        const HINT_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 20)
+            .with_color("white")
+            .aligned(TextAlign::Center);
+
+        renderer.tint(
+            &Rect::from_xy(0.0, 0.0, WIDTH, HEIGHT),
+            "rgb(100, 100, 100)",
+            BlendMode::Multiply,
+        );
+        renderer.draw_text(
+            &t("paused.title"),
+            &Point {
+                x: WIDTH / 2.0,
+                y: HEIGHT / 2.0,
+            },
+            &PAUSED_STYLE,
+        );
+        renderer.draw_text(
+            &t("paused.settings_hint"),
+            &Point {
+                x: WIDTH / 2.0,
+                y: HEIGHT / 2.0 + 50.0,
+            },
+            &HINT_STYLE,
+        );
+        renderer.draw_text(
+            &t("paused.stats_hint"),
+            &Point {
+                x: WIDTH / 2.0,
+                y: HEIGHT / 2.0 + 80.0,
+            },
+            &HINT_STYLE,
+        );
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+enum GameOverButton {
+    PlayAgain,
+    Share,
+}
+
 #[derive(Debug)]
 struct GameOver {
-    new_game_event: UnboundedReceiver<()>,
+    clicks: UnboundedReceiver<Point>,
+    _click_listener: browser::Listening,
+    screen: Widget<GameOverButton>,
+    overlay_drawn: cell::Cell<bool>,
+    /// The key of the message telling how sharing went, set once it has.
+    share_status: Rc<cell::Cell<Option<&'static str>>>,
 }
 
 impl GameOver {
-    fn new_game_pressed(&mut self) -> bool {
-        matches!(self.new_game_event.try_next(), Ok(Some(())))
+    fn new(walk: &mut Walk) -> Self {
+        if walk.score > walk.best_score {
+            walk.best_score = walk.score;
+            if let Err(err) = skins::save_best_score(walk.best_score) {
+                error!("error saving the best score: {err:#?}");
+            }
+        }
+        walk.record_run(true);
+        walk.stop_slow_motion();
+        let defeat_jingle = walk.defeat_jingle.clone();
+        walk.fade_music_to(&defeat_jingle, Looping::No);
+        let viewport = Rect::from_xy(0.0, 0.0, WIDTH, HEIGHT);
+        let (clicks, listener) = engine::add_pointer_handler(&*walk.page, viewport).unwrap();
+
+        GameOver {
+            clicks,
+            _click_listener: listener,
+            screen: Self::screen(walk, None),
+            overlay_drawn: cell::Cell::new(false),
+            share_status: Rc::new(cell::Cell::new(None)),
+        }
+    }
+
+    fn screen(walk: &Walk, share_status: Option<&str>) -> Widget<GameOverButton> {
+        const TITLE_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 48)
+            .bold()
+            .with_color("white")
+            .with_stroke("black", 4)
+            .aligned(TextAlign::Center);
+        const TEXT_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 24)
+            .with_color("white")
+            .aligned(TextAlign::Center);
+        const HINT_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 16)
+            .with_color("white")
+            .aligned(TextAlign::Center);
+
+        let center_x = WIDTH / 2.0;
+        let score = walk.score.to_string();
+        let best_score = walk.best_score.to_string();
+        let label =
+            |text, style, y| Widget::Label(Label::new(text, Point { x: center_x, y }, style));
+        let mut widgets = vec![
+            label(t("game_over.title"), TITLE_STYLE, 220.0),
+            label(
+                t_with("game_over.score", &[("score", &score)]),
+                TEXT_STYLE,
+                280.0,
+            ),
+            label(
+                t_with("game_over.best", &[("score", &best_score)]),
+                TEXT_STYLE,
+                315.0,
+            ),
+            Widget::Button(Button::new(
+                t("game_over.play_again"),
+                Rect::from_xy(center_x - 190.0, 350.0, 180.0, 56.0),
+                TEXT_STYLE,
+                GameOverButton::PlayAgain,
+            )),
+            Widget::Button(Button::new(
+                t("game_over.share"),
+                Rect::from_xy(center_x + 10.0, 350.0, 180.0, 56.0),
+                TEXT_STYLE,
+                GameOverButton::Share,
+            )),
+            label(
+                t_with(
+                    "game_over.stats_hint",
+                    &[("key", walk.settings.bindings.key(Action::Restart))],
+                ),
+                HINT_STYLE,
+                460.0,
+            ),
+        ];
+        if let Some(key) = share_status {
+            widgets.push(label(t(key), HINT_STYLE, 430.0));
+        }
+        Widget::Panel(Panel::new(
+            Rect::from_xy(100.0, 160.0, WIDTH - 200.0, 330.0),
+            widgets,
+        ))
+    }
+
+    /// Returns the last button clicked since the previous update, if any.
+    fn pressed_button(&mut self) -> Option<GameOverButton> {
+        let mut pressed = None;
+        while let Ok(Some(point)) = self.clicks.try_next() {
+            pressed = self.screen.hit(point).or(pressed);
+        }
+        pressed
+    }
+
+    /// Shares the score with a link that plays the same run, telling how it
+    /// went on the screen once it has.
+    fn share(&self, walk: &Walk) {
+        let text = t_with("share.text", &[("score", &walk.score.to_string())]);
+        let seed = walk.seed;
+        let share_status = Rc::clone(&self.share_status);
+        browser::spawn_local(async move {
+            let shared = async {
+                let url = format!("{}?seed={seed}", browser::page_url()?);
+                browser::share(&t("share.title"), &text, &url).await
+            };
+            match shared.await {
+                Ok(browser::ShareOutcome::Copied) => share_status.set(Some("share.copied")),
+                Ok(browser::ShareOutcome::Shared | browser::ShareOutcome::Cancelled) => {}
+                Err(err) => {
+                    error!("error sharing the score: {err:#?}");
+                    share_status.set(Some("share.failed"));
+                }
+            }
+        });
+    }
+
+    fn draw_overlay(&self, renderer: &Renderer) {
+        renderer.tint(
+            &Rect::from_xy(0.0, 0.0, WIDTH, HEIGHT),
+            "rgb(100, 100, 100)",
+            BlendMode::Multiply,
+        );
+        self.screen.draw(renderer);
+        self.overlay_drawn.set(true);
     }
 }
 
-impl WalkTheDogState<GameOver> {
-    fn update(mut self) -> WalkTheDogStateMachine {
-        if self._state.new_game_pressed() {
-            self.new_game()
+impl Scene<Walk> for GameOver {
+    fn update(&mut self, walk: &mut Walk, keystate: &KeyState) -> SceneChange<Walk> {
+        if let Some(key) = self.share_status.take() {
+            self.screen = Self::screen(walk, Some(key));
+            self.overlay_drawn.set(false);
+        }
+        let pressed = self.pressed_button();
+        if matches!(pressed, Some(GameOverButton::Share)) {
+            self.share(walk);
+        }
+        if walk.action_just_pressed(keystate, Action::Restart) {
+            walk.restart()
+        } else if matches!(pressed, Some(GameOverButton::PlayAgain)) || keystate.is_pressed("Enter")
+        {
+            walk.reset();
+            let background_music = walk.background_music.clone();
+            walk.fade_music_to(&background_music, Looping::Yes);
+            let fade = engine::updates_in(NEW_GAME_FADE_SECONDS);
+            SceneChange::Replace(Box::new(Ready), Transition::Fade(fade))
+        } else if walk.just_pressed(keystate, "KeyT") {
+            // The stats screen covers this one, which has to be drawn again
+            // once it is back.
+            self.overlay_drawn.set(false);
+            SceneChange::Push(Box::new(StatsScreen), Transition::Cut)
         } else {
-            self.into()
+            SceneChange::None
         }
     }
 
-    fn new_game(self) -> WalkTheDogStateMachine {
-        if let Err(err) = browser::hide_ui() {
-            error!("error hiding UI: {err:#?}");
-        }
-        WalkTheDogState {
-            _state: Ready,
-            walk: Walk::reset(self.walk),
+    fn draw(&self, walk: &Walk, renderer: &Renderer) {
+        walk.draw(renderer);
+        self.draw_overlay(renderer);
+    }
+
+    /// The game over screen never changes once it has been drawn.
+    fn redraw(&self) -> Redraw {
+        if self.overlay_drawn.get() {
+            Redraw::Nothing
+        } else {
+            Redraw::Everything
         }
-        .into()
     }
 }
 
+/// A stretch of the world with the weather of the segment placed there.
+#[derive(Debug)]
+struct WeatherZone {
+    left: f32,
+    right: f32,
+    weather: Weather,
+}
+
+/// Another player's runner, as last reported by them.
+#[derive(Debug)]
+struct Ghost {
+    /// Measured from the start of the run, like the positions players send.
+    position: Point,
+    frame: String,
+    /// Updates since the player was last heard from.
+    age: u16,
+}
+
 #[derive(Debug)]
 pub(crate) struct Walk {
-    debug_mode: bool,
+    settings: Settings,
     boy: RedHatBoy,
+    /// The boy's sprite sheet image before a skin recolors it.
+    boy_image: ImageSource,
+    /// The skin the boy is drawn in, which is the one in the settings once
+    /// it is unlocked.
+    skin: Skin,
     backgrounds: [Image; 2],
     obstacle_sheet: Rc<SpriteSheet>,
-    obstacles: Vec<Box<dyn Obstacle>>,
-    stone: HtmlImageElement,
-    timeline: i16,
+    /// Segments authored in the Tiled editor.
+    maps: Vec<TiledMap>,
+    segment_picker: SegmentPicker,
+    /// Every game played starts from this seed, so it repeats the same
+    /// segments as long as the boy takes the same path.
+    seed: u64,
+    rng: StdRng,
+    obstacles: World<Box<dyn Obstacle>>,
+    items: World<Reward>,
+    tutorial: Option<Tutorial>,
+    camera: Camera,
+    shake: cell::Cell<Option<ScreenShake>>,
+    stone: ImageSource,
+    /// Shown on the splash screen.
+    logo: ImageSource,
+    night_background: ImageSource,
+    timeline: f32,
+    /// How far the world has been shifted back by rebasing.
+    rebased_distance: f32,
+    score: u32,
+    best_score: u32,
+    audio: Audio,
+    enemy_sound: Sound,
+    music: SoundHandle,
+    background_music: Sound,
+    defeat_jingle: Sound,
+    held_keys: HashSet<String>,
+    /// Stops the simulation but for one update per press of the step key, in
+    /// debug mode.
+    frame_step: bool,
+    cheats: Cheats,
+    net: Option<NetClient>,
+    ghosts: HashMap<PlayerId, Ghost>,
+    slow_motion_frames: u16,
+    events: EventBus<GameEvent>,
+    /// Shared with the event subscriber that announces events.
+    toasts: Rc<cell::RefCell<Toasts>>,
+    /// Whether audio was unlocked by the previous update, to announce it
+    /// once it is.
+    sound_unlocked: bool,
+    /// Where the screens listen for clicks.
+    page: Rc<dyn browser::Page>,
+    weather: WeatherEffects,
+    /// Fixes the weather for the whole session, whatever the distance and
+    /// the segments say.
+    weather_override: Option<Weather>,
+    /// Stretches of the world whose segments set their own weather, in
+    /// world coordinates.
+    weather_zones: Vec<WeatherZone>,
+    combo: Combo,
+    shatter: Shatter,
+    contacts: ContactLog,
+    /// The boss fight ahead or under way, if there is one.
+    boss: Option<Boss>,
+    /// How many boss fights have been started, one per [`boss::INTERVAL`].
+    bosses: u32,
+    /// Receives the sprites being fetched again, if they are.
+    sprite_reload: Option<oneshot::Receiver<Result<Sprites>>>,
+    /// The level being played, or `None` for the endless run.
+    level: Option<Level>,
+    themes: Themes,
+    stats: Stats,
+    /// Shared with the event subscriber that counts what the boy does.
+    run_stats: Rc<cell::RefCell<RunStats>>,
 }
 
-impl Walk {
-    async fn new() -> Result<Self> {
+/// What a run is built from, fetched before the run starts so that tests can
+/// build one from stand-ins instead.
+#[derive(Debug)]
+struct WalkAssets {
+    page: Rc<dyn browser::Page>,
+    audio: Audio,
+    background_music: Sound,
+    defeat_jingle: Sound,
+    jump_sound: Sound,
+    bounce_sound: Sound,
+    splash_sound: Sound,
+    coin_sound: Sound,
+    enemy_sound: Sound,
+    shatter_sound: Sound,
+    background: ImageSource,
+    night_background: ImageSource,
+    stone: ImageSource,
+    logo: ImageSource,
+    sprites: Sprites,
+}
+
+impl WalkAssets {
+    async fn load(assets: &AssetLoader) -> Result<Self> {
         let audio = Audio::new()?;
-        let background_music = audio.load_sound("sounds/background_song.mp3").await?;
-        audio.play_looping_sound(&background_music)?;
+        let (
+            background_music,
+            defeat_jingle,
+            jump_sound,
+            bounce_sound,
+            splash_sound,
+            coin_sound,
+            enemy_sound,
+            shatter_sound,
+            [background, night_background, stone, logo],
+            sprites,
+        ) = futures::try_join!(
+            assets.sound(&audio, "sounds/background_song.mp3"),
+            assets.sound(&audio, "sounds/SFX_Defeat.wav"),
+            assets.sound(&audio, "sounds/SFX_Jump_23.mp3"),
+            assets.sound(&audio, "sounds/SFX_Boing.wav"),
+            assets.sound(&audio, "sounds/SFX_Splash.wav"),
+            assets.sound(&audio, "sounds/SFX_Coin.wav"),
+            assets.sound(&audio, "sounds/SFX_Enemy.wav"),
+            assets.sound(&audio, "sounds/SFX_Shatter.wav"),
+            assets.images([
+                "images/BG.png",
+                "images/BG_night.png",
+                "images/Stone.png",
+                "images/icon-192.png",
+            ]),
+            Sprites::load(assets),
+        )?;
+        Ok(WalkAssets {
+            page: Rc::new(browser::WebPage),
+            audio,
+            background_music,
+            defeat_jingle,
+            jump_sound,
+            bounce_sound,
+            splash_sound,
+            coin_sound,
+            enemy_sound,
+            shatter_sound,
+            background,
+            night_background,
+            stone,
+            logo,
+            sprites,
+        })
+    }
+}
 
-        let rhb_json = browser::fetch_json("sprites_sheets/rhb.json").await?;
-        let rhb_sheet: Sheet = serde_wasm_bindgen::from_value(rhb_json).map_err(|err| {
-            anyhow!("could not convert `rhb.json` into a `Sheet` structure: {err:#?}")
-        })?;
-        let image = engine::load_image("sprites_sheets/rhb.png").await?;
-        let sound = audio.load_sound("sounds/SFX_Jump_23.mp3").await?;
-        let rhb = RedHatBoy::new(rhb_sheet, image, audio, sound);
-
-        let background = engine::load_image("images/BG.png").await?;
-        let stone = engine::load_image("images/Stone.png").await?;
-
-        let obstacle_json = browser::fetch_json("sprites_sheets/tiles.json").await?;
-        let obstacle_sheet = Rc::new(SpriteSheet::new(
-            serde_wasm_bindgen::from_value(obstacle_json).map_err(|err| {
-                anyhow!("could not convert `tiles.json` into a `Sheet` structure: {err:#?}")
-            })?,
-            engine::load_image("sprites_sheets/tiles.png").await?,
-        ));
-
-        let background_width = background.width() as i16;
+/// The sprite sheets and segment maps, which artists change the most. In debug
+/// mode they can be fetched again while the game runs, to see the changes
+/// without losing the run.
+#[derive(Debug)]
+struct Sprites {
+    /// The boy's sheet, with the hitboxes of his frames.
+    rhb_sheet: Sheet,
+    rhb_image: ImageSource,
+    obstacle_sheet: Rc<SpriteSheet>,
+    maps: Vec<TiledMap>,
+}
+
+impl Sprites {
+    async fn load(assets: &AssetLoader) -> Result<Self> {
+        let (rhb_sheet, rhb_hitboxes, rhb_image, obstacle_sheet, steps_map) = futures::try_join!(
+            assets.json::<Sheet>("sprites_sheets/rhb.json"),
+            assets.json::<HashMap<String, SheetRect>>("sprites_sheets/rhb_hitboxes.json"),
+            assets.image("sprites_sheets/rhb.png"),
+            assets.sprite_sheet("sprites_sheets/tiles.json", "sprites_sheets/tiles.png"),
+            assets.json::<TiledMap>("maps/steps.json"),
+        )?;
+        Ok(Sprites {
+            rhb_sheet: rhb_sheet.with_hitboxes(rhb_hitboxes),
+            rhb_image,
+            obstacle_sheet,
+            maps: vec![steps_map],
+        })
+    }
+}
+
+impl Walk {
+    async fn new(
+        assets: &AssetLoader,
+        seed: u64,
+        settings: Settings,
+        running_speed: f32,
+        net: Option<NetClient>,
+    ) -> Result<Self> {
+        let assets = WalkAssets::load(assets).await?;
+        let mut walk = Walk::from_assets(assets, seed, settings, running_speed, net)?;
+        walk.tutorial = Tutorial::load();
+        walk.best_score = skins::load_best_score();
+        walk.stats = Stats::load();
+        // Skins unlocked by the saved best score can only be applied now.
+        walk.apply_settings();
+        Ok(walk)
+    }
+
+    /// Starts a run with `assets`, without the tutorial or the saved best
+    /// score. The boy runs
+    /// `running_speed` pixels per update.
+    fn from_assets(
+        assets: WalkAssets,
+        seed: u64,
+        settings: Settings,
+        running_speed: f32,
+        net: Option<NetClient>,
+    ) -> Result<Self> {
+        let WalkAssets {
+            page,
+            audio,
+            background_music,
+            defeat_jingle,
+            jump_sound,
+            bounce_sound,
+            splash_sound,
+            coin_sound,
+            enemy_sound,
+            shatter_sound,
+            background,
+            night_background,
+            stone,
+            logo,
+            sprites:
+                Sprites {
+                    rhb_sheet,
+                    rhb_image,
+                    obstacle_sheet,
+                    maps,
+                },
+        } = assets;
+        let music = audio.play_looping_sound(&background_music)?;
+        let sound_unlocked = audio.is_unlocked();
+        // Frames and their masks are built up front, since reading pixels
+        // back is slow.
+        let rhb_animations = Animations::load(&rhb_sheet, &rhb_image)?;
+        engine::load_image_mask(&stone)?;
+        let rhb = RedHatBoy::new(
+            rhb_sheet,
+            rhb_image.clone(),
+            Rc::new(rhb_animations),
+            audio.clone(),
+            BoySounds {
+                jump: jump_sound,
+                bounce: bounce_sound,
+                splash: splash_sound,
+            },
+            STARTING_LIVES,
+            running_speed,
+        );
+
+        let themes = Themes::new(ThemeAssets {
+            background: background.clone(),
+            night_background: night_background.clone(),
+            obstacle_sheet: Rc::clone(&obstacle_sheet),
+            music: background_music.clone(),
+        });
+        let background_width = background.width() as f32;
         let backgrounds = [
-            Image::new(background.clone(), Point { x: 0, y: 0 }),
+            Image::new(background.clone(), Point { x: 0.0, y: 0.0 }),
             Image::new(
                 background,
                 Point {
                     x: background_width,
-                    y: 0,
+                    y: 0.0,
                 },
             ),
         ];
 
         let mut walk = Walk {
-            debug_mode: cfg!(debug_assertions),
+            settings,
             boy: rhb,
+            boy_image: rhb_image,
+            skin: Skin::Classic,
             backgrounds,
-            obstacles: vec![],
+            obstacles: World::new(),
+            items: World::new(),
+            tutorial: None,
+            camera: Camera::new(),
+            shake: cell::Cell::new(None),
             obstacle_sheet,
+            maps,
+            segment_picker: SegmentPicker::new(),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
             stone,
-            timeline: 0,
+            logo,
+            night_background,
+            timeline: 0.0,
+            rebased_distance: 0.0,
+            score: 0,
+            best_score: 0,
+            audio,
+            enemy_sound,
+            music,
+            background_music,
+            defeat_jingle,
+            held_keys: HashSet::new(),
+            frame_step: false,
+            cheats: Cheats::default(),
+            net,
+            ghosts: HashMap::new(),
+            slow_motion_frames: 0,
+            events: EventBus::new(),
+            toasts: Rc::new(cell::RefCell::new(Toasts::default())),
+            sound_unlocked,
+            page,
+            weather: WeatherEffects::new(seed),
+            weather_override: None,
+            weather_zones: vec![],
+            combo: Combo::default(),
+            shatter: Shatter::default(),
+            contacts: ContactLog::default(),
+            boss: None,
+            bosses: 0,
+            sprite_reload: None,
+            level: None,
+            themes,
+            stats: Stats::default(),
+            run_stats: Rc::new(cell::RefCell::new(RunStats::default())),
         };
+        walk.apply_settings();
+        walk.events.subscribe({
+            let audio = walk.audio.clone();
+            move |event| match event {
+                GameEvent::CoinCollected => {
+                    if let Err(err) = audio.play_sound(&coin_sound) {
+                        error!("error playing coin sound: {err:#?}");
+                    }
+                }
+                GameEvent::ShieldShattered | GameEvent::ObstacleBroken => {
+                    if let Err(err) = audio.play_sound(&shatter_sound) {
+                        error!("error playing shatter sound: {err:#?}");
+                    }
+                }
+                _ => {}
+            }
+        });
+        walk.events.subscribe({
+            let run_stats = Rc::clone(&walk.run_stats);
+            move |event| match event {
+                GameEvent::BoyJumped => run_stats.borrow_mut().jumps += 1,
+                GameEvent::BoySlid => run_stats.borrow_mut().slides += 1,
+                _ => {}
+            }
+        });
+        walk.events.subscribe({
+            let toasts = Rc::clone(&walk.toasts);
+            move |event| {
+                let key = match event {
+                    GameEvent::NewHighScore => "toast.new_high_score",
+                    GameEvent::CheckpointReached => "toast.checkpoint_reached",
+                    GameEvent::SoundEnabled => "toast.sound_enabled",
+                    GameEvent::BossDefeated => "toast.boss_defeated",
+                    _ => return,
+                };
+                toasts.borrow_mut().push(t(key));
+            }
+        });
         walk.generate_next_segment();
         Ok(walk)
     }
 
-    fn reset(mut walk: Self) -> Self {
-        walk.obstacles = vec![];
-        walk.timeline = 0;
-        walk.generate_next_segment();
-        walk.boy = RedHatBoy::reset(walk.boy);
-        walk
+    fn reset(&mut self) {
+        self.obstacles.clear();
+        self.items.clear();
+        self.camera = Camera::new();
+        let [first_background, second_background] = &mut self.backgrounds;
+        first_background.set_x(0.0);
+        second_background.set_x(first_background.right());
+        self.timeline = 0.0;
+        self.rebased_distance = 0.0;
+        self.score = 0;
+        self.toasts.borrow_mut().clear();
+        *self.run_stats.borrow_mut() = RunStats::default();
+        self.weather = WeatherEffects::new(self.seed);
+        self.weather_zones.clear();
+        self.combo.reset();
+        self.shatter.clear();
+        self.boss = None;
+        self.bosses = 0;
+        self.segment_picker = SegmentPicker::new();
+        self.rng = StdRng::seed_from_u64(self.seed);
+        if let Some(theme) = self.themes.reset() {
+            self.switch_theme(theme);
+        }
+        if let Some(level) = &mut self.level {
+            level.restart();
+        }
+        self.generate_next_segment();
+        self.boy = self.boy.reset();
     }
 
-    fn velocity(&self) -> i16 {
-        -self.boy.walking_speed()
+    /// Moves on to the next theme once it is due and has arrived.
+    fn update_theme(&mut self) {
+        if let Some(theme) = self.themes.update(self.distance(), &self.audio) {
+            self.switch_theme(theme);
+            let music = self.background_music.clone();
+            self.fade_music_to(&music, Looping::Yes);
+        }
     }
 
-    fn knocked_out(&self) -> bool {
-        self.boy.knocked_out()
+    /// Changes the backgrounds, the tiles of the segments still to come and
+    /// the music to play next to those of `theme`.
+    fn switch_theme(&mut self, theme: ThemeAssets) {
+        for background in &mut self.backgrounds {
+            *background = Image::new(theme.background.clone(), background.bounding_box().position);
+        }
+        self.night_background = theme.night_background;
+        self.obstacle_sheet = theme.obstacle_sheet;
+        self.background_music = theme.music;
     }
 
-    fn generate_next_segment(&mut self) {
-        let mut rng = thread_rng();
-
-        let generator = SEGMENT_GENERATORS.choose(&mut rng).unwrap();
-
-        let mut next_obstacles = generator(
-            self.stone.clone(),
-            Rc::clone(&self.obstacle_sheet),
-            self.timeline + OBSTACLE_BUFFER,
-        );
-
-        self.timeline = rightmost(&next_obstacles);
-        self.obstacles.append(&mut next_obstacles);
+    /// Starts a new run straight away, with the boy already running, for
+    /// practicing a stretch again and again.
+    fn restart(&mut self) -> SceneChange<Walk> {
+        self.reset();
+        let background_music = self.background_music.clone();
+        self.fade_music_to(&background_music, Looping::Yes);
+        self.boy.run_right();
+        SceneChange::Replace(Box::new(Walking), Transition::Cut)
     }
 
-    fn draw(&self, renderer: &Renderer) {
-        renderer.set_debug_mode(self.debug_mode);
-
-        for background in &self.backgrounds {
-            background.draw(renderer);
-        }
-        self.boy.draw(renderer);
-        for obstacle in &self.obstacles {
-            obstacle.draw(renderer);
+    /// Adds the run that just ended to the statistics and saves them.
+    fn record_run(&mut self, died: bool) {
+        self.stats
+            .add_run(&self.run_stats.borrow(), self.distance(), died);
+        if let Err(err) = self.stats.save() {
+            error!("error saving the statistics: {err:#?}");
         }
     }
-}
 
-impl WalkTheDog {
-    pub(crate) fn new() -> Self {
-        WalkTheDog { machine: None }
+    /// Plays `level` from its start instead of the endless run.
+    fn start_level(&mut self, level: Level) {
+        self.level = Some(level);
+        self.reset();
     }
-}
 
-#[async_trait(?Send)]
-impl Game for WalkTheDog {
-    async fn initialize(&self) -> Result<Box<dyn Game>> {
-        match self.machine {
-            None => {
-                let walk = Walk::new().await?;
-                let machine = WalkTheDogStateMachine::new(walk);
-                Ok(Box::new(Self {
-                    machine: Some(machine),
-                }))
-            }
-            Some(_) => Err(anyhow!("game already initialized")),
-        }
+    /// Returns whether the boy has run past the goal flag of the level.
+    fn reached_goal(&self) -> bool {
+        let boy_x = self.boy.bounding_box().center().x;
+        self.obstacles
+            .values()
+            .any(|obstacle| obstacle.kind() == ObstacleKind::Goal && obstacle.left() <= boy_x)
     }
 
-    fn update(&mut self, keystate: &KeyState) {
-        if let Some(machine) = self.machine.take() {
-            self.machine.replace(machine.update(keystate));
+    /// Pauses or resumes the music along with the game.
+    fn pause_music(&self, paused: bool) {
+        if let Err(err) = self.audio.set_music_paused(paused) {
+            error!("error pausing or resuming music: {err:#?}");
         }
-        assert!(self.machine.is_some());
     }
 
-    fn draw(&self, renderer: &Renderer) {
-        renderer.clear(&Rect::from_xy(0, 0, WIDTH, HEIGHT));
-
-        if let Some(machine) = &self.machine {
-            machine.draw(renderer);
+    fn fade_music_to(&mut self, sound: &Sound, looping: Looping) {
+        match self.audio.fade_to(sound, looping, MUSIC_FADE_SECONDS) {
+            Ok(music) => self.music = music,
+            Err(err) => {
+                error!("error changing music: {err:#?}");
+            }
         }
     }
-}
 
-pub(crate) trait Obstacle: Debug {
-    fn right(&self) -> i16;
-    fn check_intersection(&self, boy: &mut RedHatBoy);
-    fn draw(&self, renderer: &Renderer);
-    fn move_horizontally(&mut self, x: i16);
-}
+    /// Returns whether the key `code` has been pressed since the last time
+    /// this was asked, so that holding it down only counts once.
+    fn just_pressed(&mut self, keystate: &KeyState, code: &str) -> bool {
+        if keystate.is_pressed(code) {
+            self.held_keys.insert(code.to_string())
+        } else {
+            self.held_keys.remove(code);
+            false
+        }
+    }
 
-#[derive(Debug, Clone)]
-pub(crate) struct Platform {
-    sheet: Rc<SpriteSheet>,
-    bounding_boxes: Vec<Rect>,
-    sprites: Vec<Cell>,
-    position: Point,
-}
+    fn action_just_pressed(&mut self, keystate: &KeyState, action: Action) -> bool {
+        let code = self.settings.bindings.key(action).to_string();
+        self.just_pressed(keystate, &code)
+    }
 
-impl Platform {
-    pub(crate) fn new<'a>(
-        sheet: Rc<SpriteSheet>,
-        position: Point,
-        sprite_names: impl IntoIterator<Item = &'a str> + 'a,
-        bounding_boxes: impl IntoIterator<Item = Rect>,
-    ) -> Self {
-        let sprites = sprite_names
-            .into_iter()
-            .map(|sprite_name| sheet.cell(sprite_name).cloned())
-            .collect::<Option<Vec<_>>>()
-            .unwrap();
-        let bounding_boxes = bounding_boxes
-            .into_iter()
-            .map(|mut bounding_box| {
-                bounding_box.set_x(bounding_box.x() + position.x);
-                bounding_box.set_y(bounding_box.y() + position.y);
-                bounding_box
-            })
-            .collect();
-        Self {
-            sheet,
-            position,
-            sprites,
-            bounding_boxes,
-        }
+    fn is_action_pressed(&self, keystate: &KeyState, action: Action) -> bool {
+        keystate.is_pressed(self.settings.bindings.key(action))
     }
-}
 
-impl Obstacle for Platform {
-    fn right(&self) -> i16 {
-        self.bounding_boxes
-            .last()
-            .unwrap_or(&Rect::default())
-            .right()
+    /// Returns what the single input of the one-button mode does while any
+    /// key but those that pause, mute and restart the game is held down, or
+    /// the canvas is pressed: see [`contextual_action`].
+    fn one_button_action(&self, keystate: &KeyState) -> Option<Action> {
+        let reserved = [Action::Pause, Action::Mute, Action::Restart]
+            .map(|action| self.settings.bindings.key(action));
+        let pressed = keystate
+            .pressed_keys()
+            .any(|code| !reserved.contains(&code));
+        (self.settings.one_button && pressed)
+            .then(|| contextual_action(&self.obstacles, &self.boy.bounding_box()))
     }
 
-    fn check_intersection(&self, boy: &mut RedHatBoy) {
-        let boy_bounding_box = boy.bounding_box();
+    /// Returns a key that has been pressed since the last time keys were
+    /// checked, if there is one.
+    fn newly_pressed(&mut self, keystate: &KeyState) -> Option<String> {
+        self.held_keys.retain(|code| keystate.is_pressed(code));
+        let code = keystate
+            .pressed_keys()
+            .find(|code| !self.held_keys.contains(*code))?
+            .to_string();
+        self.held_keys.insert(code.clone());
+        Some(code)
+    }
 
-        if let Some(box_to_land_on) = self
-            .bounding_boxes
-            .iter()
-            .find(|bounding_box| boy_bounding_box.intersects(bounding_box))
-        {
-            if boy.velocity_y() > 0 && boy_bounding_box.top() < box_to_land_on.top() {
-                boy.land_on(box_to_land_on.top());
-            } else {
-                boy.knock_out();
+    /// Toggles the sound once per press of the mute key and remembers the
+    /// choice.
+    fn toggle_mute_on_key(&mut self, keystate: &KeyState) {
+        if self.action_just_pressed(keystate, Action::Mute) {
+            self.settings.muted = !self.settings.muted;
+            self.apply_settings();
+            if let Err(err) = self.settings.save() {
+                error!("error saving settings: {err:#?}");
             }
         }
     }
 
-    fn draw(&self, renderer: &Renderer) {
-        let mut x = 0;
-        for sprite in &self.sprites {
-            self.sheet.draw(
-                renderer,
-                &Rect::from_xy(
-                    sprite.frame.x,
-                    sprite.frame.y,
-                    sprite.frame.w,
-                    sprite.frame.h,
-                ),
-                &Rect::from_xy(
-                    self.position.x + x,
-                    self.position.y,
-                    sprite.frame.w,
-                    sprite.frame.h,
-                ),
-            );
-            x += sprite.frame.w;
+    /// Toggles the cheats whose codes have just been typed, in any scene.
+    fn update_cheats(&mut self, keystate: &KeyState) {
+        let toggled = self.cheats.update(keystate);
+        for cheat in &toggled {
+            let key = if self.cheats.is_enabled(*cheat) {
+                "toast.cheat_on"
+            } else {
+                "toast.cheat_off"
+            };
+            self.toasts
+                .borrow_mut()
+                .push(t_with(key, &[("cheat", &t(cheat.label_key()))]));
         }
-        for bounding_box in &self.bounding_boxes {
-            renderer.draw_bounding_box(bounding_box);
+        if !toggled.is_empty() {
+            self.apply_settings();
         }
     }
 
-    fn move_horizontally(&mut self, x: i16) {
-        self.position.x += x;
-        for bounding_box in &mut self.bounding_boxes {
-            bounding_box.set_x(bounding_box.x() + x);
+    /// Returns the score that skins are unlocked by, which is beaten by any
+    /// score while every skin is unlocked by a cheat.
+    fn skin_unlock_score(&self) -> u32 {
+        if self.cheats.is_enabled(Cheat::AllSkins) {
+            u32::MAX
+        } else {
+            self.best_score
         }
     }
-}
-
-#[derive(Debug, Clone)]
-pub(crate) struct Barrier {
-    image: Image,
-}
 
-impl Barrier {
-    pub(crate) fn new(image: Image) -> Self {
-        Self { image }
+    /// Downloads a screenshot of the game when F10 is pressed, in any scene.
+    fn capture_on_key(&mut self, keystate: &KeyState) {
+        if self.just_pressed(keystate, "F10") {
+            match browser::capture_canvas() {
+                Ok(()) => self.toasts.borrow_mut().push(t("toast.screenshot_saved")),
+                Err(err) => {
+                    error!("error taking a screenshot: {err:#?}");
+                }
+            }
+        }
     }
-}
 
-impl Obstacle for Barrier {
-    fn right(&self) -> i16 {
-        self.image.right()
-    }
+    fn apply_settings(&mut self) {
+        self.boy
+            .set_pixel_perfect_collisions(self.settings.pixel_perfect_collisions);
+        self.audio.set_music_volume(self.settings.music_volume);
+        self.audio.set_sfx_volume(self.settings.sfx_volume);
+        self.audio.set_muted(self.settings.muted);
 
-    fn check_intersection(&self, boy: &mut RedHatBoy) {
-        if boy.bounding_box().intersects(self.image.bounding_box()) {
-            boy.knock_out();
-        }
-    }
+        self.boy
+            .set_infinite_lives(self.cheats.is_enabled(Cheat::InfiniteLives));
+        self.boy
+            .set_big_head(self.cheats.is_enabled(Cheat::BigHead));
 
-    fn draw(&self, renderer: &Renderer) {
+        let skin = if self.settings.skin.is_unlocked(self.skin_unlock_score()) {
+            self.settings.skin
+        } else {
+            Skin::Classic
+        };
+        if skin != self.skin {
+            match skin.apply(&self.boy_image) {
+                Ok(image) => {
+                    self.boy.set_image(image);
+                    self.skin = skin;
+                }
+                Err(err) => {
+                    error!("error applying skin {skin:?}: {err:#?}");
+                }
+            }
+        }
+    }
+
+    /// Tells other players where the boy is and moves their ghosts to where
+    /// they said they are.
+    fn update_ghosts(&mut self) -> Result<()> {
+        let Some(net) = &mut self.net else {
+            return Ok(());
+        };
+        net.update()?;
+        let (frame, position) = self.boy.ghost_frame();
+        net.send(&Message::Position {
+            player: net.player(),
+            x: position.x + self.rebased_distance,
+            y: position.y,
+            frame,
+        })?;
+
+        for ghost in self.ghosts.values_mut() {
+            ghost.age += 1;
+        }
+        for message in net.receive() {
+            match message {
+                Message::Position {
+                    player,
+                    x,
+                    y,
+                    frame,
+                } => {
+                    let ghost = Ghost {
+                        position: Point { x, y },
+                        frame,
+                        age: 0,
+                    };
+                    self.ghosts.insert(player, ghost);
+                }
+                Message::Leave { player } => {
+                    self.ghosts.remove(&player);
+                }
+            }
+        }
+        let timeout = engine::updates_in(GHOST_TIMEOUT_SECONDS);
+        self.ghosts.retain(|_, ghost| ghost.age < timeout);
+        Ok(())
+    }
+
+    /// Starts fetching the sprite sheets and maps again, bypassing every
+    /// cache. They are swapped in by [`Walk::swap_reloaded_sprites`].
+    fn reload_sprites(&mut self) {
+        let (sender, receiver) = oneshot::channel();
+        browser::spawn_local(async move {
+            let sprites = Sprites::load(&AssetLoader::uncached()).await;
+            // The receiver is gone if the game was stopped meanwhile.
+            let _ = sender.send(sprites);
+        });
+        self.sprite_reload = Some(receiver);
+    }
+
+    /// Swaps in the sprites fetched by [`Walk::reload_sprites`] once they
+    /// have arrived. Obstacles already in the world keep the tiles they were
+    /// built with, so changed tiles show up from the next segment on.
+    fn swap_reloaded_sprites(&mut self) -> Result<()> {
+        let Some(receiver) = &mut self.sprite_reload else {
+            return Ok(());
+        };
+        let sprites = match receiver.try_recv() {
+            Ok(None) => return Ok(()),
+            Ok(Some(sprites)) => sprites,
+            Err(oneshot::Canceled) => Err(anyhow!("the sprites were never sent")),
+        };
+        self.sprite_reload = None;
+        let Sprites {
+            rhb_sheet,
+            rhb_image,
+            obstacle_sheet,
+            maps,
+        } = sprites?;
+        let image = self.skin.apply(&rhb_image)?;
+        self.boy.set_sprites(rhb_sheet, image)?;
+        self.boy_image = rhb_image;
+        self.obstacle_sheet = obstacle_sheet;
+        self.maps = maps;
+        info!("reloaded sprites");
+        Ok(())
+    }
+
+    fn follow_boy(&mut self) {
+        self.camera
+            .set_x(self.boy.x() - red_hat_boy::STARTING_POINT);
+    }
+
+    /// Shifts everything in the world left by `distance`, keeping the relative
+    /// positions intact.
+    fn rebase(&mut self, distance: f32) {
+        self.boy.carry(-distance);
+        for background in &mut self.backgrounds {
+            background.move_horizontally(-distance);
+        }
+        self.obstacles.move_bodies_horizontally(-distance);
+        self.items.move_horizontally(-distance);
+        self.shatter.move_horizontally(-distance);
+        self.contacts.move_horizontally(-distance);
+        if let Some(boss) = &mut self.boss {
+            boss.move_horizontally(-distance);
+        }
+        for zone in &mut self.weather_zones {
+            zone.left -= distance;
+            zone.right -= distance;
+        }
+        self.timeline -= distance;
+        self.rebased_distance += distance;
+        self.follow_boy();
+    }
+
+    /// Picks the weather for where the boy is: the one set for the session,
+    /// or else the one of the segment he is in, or else the one of the
+    /// distance run.
+    fn update_weather(&mut self) {
+        let boy_x = self.boy.x();
+        let weather = self.weather_override.unwrap_or_else(|| {
+            self.weather_zones
+                .iter()
+                .find(|zone| (zone.left..zone.right).contains(&boy_x))
+                .map_or_else(
+                    || Weather::for_distance(self.distance()),
+                    |zone| zone.weather,
+                )
+        });
+        self.weather.set_weather(weather);
+    }
+
+    /// Returns how far the camera has travelled since the game started.
+    fn distance(&self) -> f32 {
+        self.rebased_distance + self.camera.x()
+    }
+
+    fn knocked_out(&self) -> bool {
+        self.boy.knocked_out()
+    }
+
+    fn generate_next_segment(&mut self) {
+        let offset_x = self.timeline + OBSTACLE_BUFFER;
+        let segment = match &mut self.level {
+            Some(level) => {
+                level.next_segment(&mut self.rng, &self.stone, &self.obstacle_sheet, offset_x)
+            }
+            None => self.pick_segment(offset_x),
+        };
+        if let Some(segment) = segment {
+            self.add_segment(offset_x, segment);
+        }
+    }
+
+    /// Adds `segment`, placed at `offset_x`, to the run. The next segment goes
+    /// after its rightmost obstacle, or after `offset_x` if it has none.
+    fn add_segment(&mut self, offset_x: f32, segment: Segment) {
+        let Segment {
+            obstacles,
+            collectibles,
+            weather,
+        } = segment;
+        let timeline = rightmost(&obstacles).unwrap_or(offset_x);
+        if let Some(weather) = weather {
+            self.weather_zones.push(WeatherZone {
+                left: offset_x,
+                right: timeline,
+                weather,
+            });
+        }
+        self.timeline = timeline;
+        for obstacle in obstacles {
+            spawn_obstacle(&mut self.obstacles, obstacle);
+        }
+        for collectible in collectibles {
+            collectible.spawn(&mut self.items);
+        }
+    }
+
+    /// Picks the next segment of the endless run, placed at `offset_x`, or
+    /// starts a boss fight there instead when one is due.
+    fn pick_segment(&mut self, offset_x: f32) -> Option<Segment> {
+        let bosses_due = (self.distance() / boss::INTERVAL) as u32;
+        if self.boss.is_none() && bosses_due > self.bosses {
+            self.bosses = bosses_due;
+            self.boss = Some(Boss::new(offset_x));
+            self.timeline = offset_x + boss::ARENA_LENGTH;
+            return None;
+        }
+        let candidates: Vec<_> = self
+            .maps
+            .iter()
+            .map(|_| (MAP_WEIGHT, MAP_DIFFICULTY))
+            .chain(
+                SEGMENT_GENERATORS
+                    .iter()
+                    .map(|generator| (generator.weight, generator.difficulty)),
+            )
+            .collect();
+        let difficulty = Difficulty::for_distance(self.distance());
+        let choice = self
+            .segment_picker
+            .pick(&mut self.rng, &candidates, difficulty);
+        let segment = match self.maps.get(choice) {
+            Some(map) => map
+                .segment(&self.stone, &self.obstacle_sheet, offset_x)
+                .unwrap_or_else(|err| {
+                    error!("error building segment from map: {err:#?}");
+                    Segment::default()
+                }),
+            None => (SEGMENT_GENERATORS[choice - self.maps.len()].generate)(
+                &mut self.rng,
+                self.stone.clone(),
+                Rc::clone(&self.obstacle_sheet),
+                offset_x,
+            ),
+        };
+        Some(segment)
+    }
+
+    /// Tests the boy against the obstacles around him, which the world's
+    /// collision system picks out, from left to right.
+    fn check_obstacle_collisions(&mut self) {
+        let boy_box = self.boy.bounding_box();
+        let around_boy = Rect::from_xy(
+            boy_box.x() - BROADPHASE_MARGIN,
+            boy_box.y() - BROADPHASE_MARGIN,
+            boy_box.width + BROADPHASE_MARGIN * 2.0,
+            boy_box.height + BROADPHASE_MARGIN * 2.0,
+        );
+        let mut nearby: Vec<_> = self
+            .obstacles
+            .colliding_with(&around_boy)
+            .into_iter()
+            .filter_map(|entity| Some((entity, self.obstacles.bounding_box(entity)?)))
+            .collect();
+        nearby.sort_by(|(_, a), (_, b)| a.left().total_cmp(&b.left()));
+        let attacking = self.boy.attacking();
+        let mut broken = vec![];
+        for (entity, _) in nearby {
+            let Some(obstacle) = self.obstacles.get(entity) else {
+                continue;
+            };
+            match obstacle.breakable_area() {
+                Some(area) if attacking && boy_box.intersects(&area) => broken.push((entity, area)),
+                _ => {
+                    let before = self.boy_contact_state();
+                    obstacle.check_intersection(&mut self.boy);
+                    let outcome = ContactOutcome::between(before, self.boy_contact_state());
+                    if let (true, Some(outcome)) = (self.settings.debug_mode, outcome) {
+                        self.contacts
+                            .record(boy_box, &obstacle.collision_boxes(), outcome);
+                    }
+                }
+            }
+        }
+        for (entity, area) in broken {
+            self.obstacles.despawn(entity);
+            self.shatter.burst(area.center(), DEBRIS_COLOR);
+            self.score += BREAK_POINTS;
+            self.events.publish(GameEvent::ObstacleBroken);
+        }
+    }
+
+    /// Returns what tells whether a collision landed the boy or knocked him
+    /// out: see [`ContactOutcome::between`].
+    fn boy_contact_state(&self) -> (u8, bool, bool) {
+        (self.boy.lives(), self.boy.hurt(), self.boy.airborne())
+    }
+
+    /// Scores the tricks done in this update: jumping over stones and sliding
+    /// under platforms or bars overhead that the boy's left edge has passed
+    /// since it was at `previous_left`, and landing on a platform if he has
+    /// just `landed`.
+    fn score_tricks(&mut self, previous_left: f32, landed: bool) {
+        let boy_box = self.boy.bounding_box();
+        let mut tricks = vec![];
+        if landed && boy_box.bottom() < HEIGHT - 1.0 {
+            tricks.push(Trick::LandOnPlatform);
+        }
+        for obstacle in self.obstacles.values() {
+            if !(previous_left..boy_box.left()).contains(&obstacle.right()) {
+                continue;
+            }
+            match obstacle.kind() {
+                ObstacleKind::Stone if self.boy.jumping() => tricks.push(Trick::JumpOverStone),
+                ObstacleKind::Platform | ObstacleKind::Ceiling
+                    if self.boy.sliding()
+                        && obstacle
+                            .collision_boxes()
+                            .iter()
+                            .all(|collision_box| collision_box.bottom() <= boy_box.top()) =>
+                {
+                    tricks.push(Trick::SlideUnderCeiling);
+                }
+                _ => {}
+            }
+        }
+        for trick in tricks {
+            self.score += self.combo.trick(trick);
+        }
+    }
+
+    fn update_boss(&mut self) {
+        let Some(boss) = &mut self.boss else {
+            return;
+        };
+        match boss.update(self.boy.x(), self.camera.x()) {
+            Some(BossAction::Throw(projectile)) => {
+                spawn_obstacle(&mut self.obstacles, Box::new(projectile));
+            }
+            Some(BossAction::Defeated) => {
+                self.boss = None;
+                self.score += boss::REWARD;
+                self.events.publish(GameEvent::BossDefeated);
+            }
+            None => {}
+        }
+    }
+
+    /// Plays the sounds obstacles make this frame, panned toward the side of
+    /// the boy they are on.
+    fn play_obstacle_sounds(&self, left_edge: f32) {
+        let boy_x = self.boy.bounding_box().center().x;
+        for x in self
+            .obstacles
+            .values()
+            .filter_map(|obstacle| obstacle.emitted_sound(left_edge))
+        {
+            let pan = (x - boy_x) / (WIDTH / 2.0);
+            if let Err(err) = self.audio.play_sound_at(&self.enemy_sound, pan) {
+                error!("error playing enemy sound: {err:#?}");
+            }
+        }
+    }
+
+    /// Slows the game down for a moment so that a hit can sink in.
+    fn start_slow_motion(&mut self) {
+        self.slow_motion_frames = engine::updates_in(HIT_SLOW_MOTION_SECONDS);
+        engine::set_time_scale(HIT_TIME_SCALE);
+    }
+
+    fn update_slow_motion(&mut self) {
+        match self.slow_motion_frames {
+            0 => {}
+            1 => self.stop_slow_motion(),
+            _ => self.slow_motion_frames -= 1,
+        }
+    }
+
+    fn stop_slow_motion(&mut self) {
+        self.slow_motion_frames = 0;
+        engine::set_time_scale(1.0);
+    }
+
+    fn collect_items(&mut self) {
+        let boy_bounding_box = self.boy.bounding_box();
+        let mut points = 0;
+        let mut coins = 0;
+        let mut power_ups = vec![];
+        for item in self.items.colliding_with(&boy_bounding_box) {
+            match self.items.despawn(item) {
+                Some(Reward::Points(reward)) => {
+                    points += reward;
+                    coins += 1;
+                }
+                Some(Reward::PowerUp(kind)) => power_ups.push(kind),
+                None => {}
+            }
+        }
+
+        for kind in power_ups {
+            self.boy.activate_power_up(kind);
+            self.events.publish(GameEvent::PowerUpCollected);
+        }
+        if let Some(level) = &mut self.level {
+            level.collect_coins(coins);
+        }
+        if points > 0 {
+            let previous_score = self.score;
+            self.score += points;
+            if self.best_score > 0
+                && previous_score <= self.best_score
+                && self.score > self.best_score
+            {
+                self.events.publish(GameEvent::NewHighScore);
+            }
+            self.events.publish(GameEvent::CoinCollected);
+        }
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        renderer.set_debug_mode(self.settings.debug_mode);
+        renderer.set_debug_styles(&DEBUG_STYLES);
+        if let Some(shake) = self.shake.take() {
+            if !self.settings.reduced_motion {
+                renderer.shake(shake);
+            }
+        }
+
+        renderer.with_camera(&self.camera, |renderer| {
+            for background in &self.backgrounds {
+                background.draw(renderer);
+            }
+            self.themes.draw_fade(
+                renderer,
+                self.backgrounds
+                    .iter()
+                    .map(|background| background.bounding_box().position),
+            );
+            daylight::draw_sky(self, renderer);
+            for ghost in self.ghosts.values() {
+                let position = Point {
+                    x: ghost.position.x - self.rebased_distance,
+                    y: ghost.position.y,
+                };
+                self.boy.draw_ghost(renderer, &ghost.frame, position);
+            }
+            self.boy.draw(renderer);
+            self.shatter.draw(renderer);
+            for obstacle in self.obstacles.values() {
+                obstacle.draw(renderer);
+            }
+            if self.settings.debug_mode {
+                self.contacts.draw(renderer);
+            }
+            self.items.draw(renderer);
+            if let Some(boss) = &self.boss {
+                boss.draw(renderer, self.camera.x());
+            }
+        });
+        daylight::light(self, renderer);
+        if !self.settings.reduced_motion {
+            self.weather.draw(renderer);
+        }
+        if self.boy.hurt() {
+            renderer.tint(
+                &Rect::from_xy(0.0, 0.0, WIDTH, HEIGHT),
+                "rgba(255, 0, 0, 0.2)",
+                BlendMode::Normal,
+            );
+        }
+        if let Some(tutorial) = &self.tutorial {
+            tutorial.draw(renderer, &self.settings.bindings);
+        }
+        if self.settings.debug_mode {
+            inspector::draw(self, renderer);
+        }
+
+        hud::draw(self, renderer);
+        radar::draw(self, renderer);
+        self.toasts.borrow().draw(renderer);
+        if !self.audio.is_unlocked() {
+            renderer.draw_text(
+                &t("hud.enable_sound"),
+                &Point {
+                    x: WIDTH / 2.0,
+                    y: HEIGHT - 20.0,
+                },
+                &SOUND_PROMPT_STYLE,
+            );
+        }
+    }
+}
+
+impl WalkTheDog {
+    /// Creates a game whose levels are generated from `seed`, so that a run
+    /// can be played again by using the same seed.
+    ///
+    /// The options in `config` take precedence over the saved settings, and
+    /// if it names a relay server, the game races against the other players
+    /// connected to it.
+    pub(crate) fn new(seed: u64, config: Config) -> Self {
+        WalkTheDog {
+            seed,
+            config,
+            scenes: None,
+        }
+    }
+
+    /// Starts an empty recording of the game about to be played, at
+    /// `updates_per_second`.
+    pub(crate) fn new_recording(&self, updates_per_second: u16) -> InputRecording {
+        InputRecording::new(
+            self.seed,
+            updates_per_second,
+            self.running_speed(),
+            self.settings(),
+        )
+    }
+
+    /// Returns the saved settings, overridden by the options in the URL.
+    fn settings(&self) -> Settings {
+        let mut settings = Settings::load();
+        if let Some(debug) = self.config.debug {
+            settings.debug_mode = debug;
+        }
+        if let Some(mute) = self.config.mute {
+            settings.muted = mute;
+        }
+        settings
+    }
+
+    fn running_speed(&self) -> f32 {
+        self.config.speed.unwrap_or(red_hat_boy::RUNNING_SPEED)
+    }
+}
+
+#[async_trait(?Send)]
+impl Game for WalkTheDog {
+    async fn initialize(&self, assets: &AssetLoader) -> Result<Box<dyn Game>> {
+        match self.scenes {
+            None => {
+                let net = match &self.config.server {
+                    Some(server) => match NetClient::connect(server, rand::random()) {
+                        Ok(net) => Some(net),
+                        Err(err) => {
+                            error!("error connecting to {server}: {err:#?}");
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                let language =
+                    i18n::pick_language(self.config.language.as_deref()).unwrap_or_else(|err| {
+                        error!("error picking a language: {err:#}");
+                        "en".to_string()
+                    });
+                if let Err(err) = i18n::load(assets, &language).await {
+                    error!("error loading the {language} translation: {err:#}");
+                }
+                let (settings, running_speed) = (self.settings(), self.running_speed());
+                let mut walk = Walk::new(assets, self.seed, settings, running_speed, net).await?;
+                walk.weather_override = self.config.weather;
+                if let Some(name) = &self.config.level {
+                    let manifest = assets
+                        .json::<LevelManifest>(&format!("levels/{name}.json"))
+                        .await?;
+                    walk.start_level(Level::from_manifest(manifest)?);
+                }
+                Ok(Box::new(Self {
+                    seed: self.seed,
+                    config: self.config.clone(),
+                    scenes: Some(SceneStack::new(walk, Box::<Splash>::default())),
+                }))
+            }
+            Some(_) => Err(anyhow!("game already initialized")),
+        }
+    }
+
+    fn update(&mut self, keystate: &KeyState) {
+        if let Some(scenes) = &mut self.scenes {
+            if scenes.context_mut().settings.log_input {
+                info!("Keystate is {keystate:#?}");
+            }
+            scenes.update(keystate);
+            let walk = scenes.context_mut();
+            walk.toggle_mute_on_key(keystate);
+            walk.capture_on_key(keystate);
+            walk.update_cheats(keystate);
+            if let Err(err) = walk.update_ghosts() {
+                error!("error updating other players: {err:#?}");
+            }
+            if let Err(err) = walk.swap_reloaded_sprites() {
+                error!("error reloading sprites: {err:#?}");
+            }
+        }
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        renderer.clear(&Rect::from_xy(0.0, 0.0, WIDTH, HEIGHT));
+
+        if let Some(scenes) = &self.scenes {
+            scenes.draw(renderer);
+        }
+    }
+
+    fn teardown(&mut self) {
+        if let Some(scenes) = &mut self.scenes {
+            let walk = scenes.context_mut();
+            if let Err(err) = walk.audio.close() {
+                error!("error closing audio: {err:#?}");
+            }
+            // Dropping the client tells the other players that we left.
+            walk.net = None;
+        }
+    }
+
+    fn redraw(&self) -> Redraw {
+        self.scenes
+            .as_ref()
+            .map_or(Redraw::Everything, SceneStack::redraw)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ObstacleKind {
+    Platform,
+    Stone,
+    Enemy,
+    BoostPad,
+    Spring,
+    Pool,
+    Saw,
+    Ceiling,
+    Goal,
+}
+
+/// Something in the boy's way. Obstacles are spawned into the walk's world,
+/// whose systems scroll and move them and pick out the ones near the boy.
+pub(crate) trait Obstacle: Body + Debug {
+    fn kind(&self) -> ObstacleKind;
+    fn left(&self) -> f32;
+    fn right(&self) -> f32;
+    fn check_intersection(&self, boy: &mut RedHatBoy);
+    fn draw(&self, renderer: &Renderer);
+
+    /// Captures the obstacle's current state so that it can be restored.
+    fn snapshot(&self) -> ObstacleSnapshot;
+
+    /// Advances animated obstacles by `dt` seconds.
+    fn update(&mut self, _dt: f32) {}
+
+    /// Returns how far the obstacle moves on its own on every update at the
+    /// default rate, which it is spawned with.
+    fn velocity(&self) -> Velocity {
+        Velocity::default()
+    }
+
+    /// Returns the boxes that the boy lands on or is knocked out by, to show
+    /// in debug mode which one he touched.
+    fn collision_boxes(&self) -> Vec<Rect> {
+        vec![]
+    }
+
+    /// Returns the area that an attacking boy breaks the obstacle apart in,
+    /// if it can be broken.
+    fn breakable_area(&self) -> Option<Rect> {
+        None
+    }
+
+    /// Returns the x position of a sound the obstacle makes this frame, if it
+    /// makes one while on the screen that starts at `left_edge`.
+    fn emitted_sound(&self, _left_edge: f32) -> Option<f32> {
+        None
+    }
+}
+
+/// Where the boy touches a part of a platform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Contact {
+    /// Falling onto the top, which is at the given height.
+    Top(f32),
+    /// Running into the left side, the given number of pixels deep.
+    Side(f32),
+    Other,
+}
+
+impl Contact {
+    /// The deepest the boy can be inside a platform and still be touching
+    /// its side, a little more than he runs in one update.
+    const SIDE_DEPTH: f32 = 16.0;
+
+    fn classify(collider: &Shape, boy_bounding_box: &Rect, velocity_y: f32) -> Self {
+        let top = collider.top_at(boy_bounding_box.center().x);
+        let depth = boy_bounding_box.right() - collider.bounding_box().left();
+        if velocity_y > 0.0 && boy_bounding_box.top() < top {
+            Self::Top(top)
+        } else if depth <= Self::SIDE_DEPTH {
+            Self::Side(depth)
+        } else {
+            Self::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Platform {
+    sheet: Rc<SpriteSheet>,
+    colliders: Vec<Shape>,
+    sprites: Vec<Cell>,
+    position: Point,
+}
+
+impl Platform {
+    pub(crate) fn new<'a>(
+        sheet: Rc<SpriteSheet>,
+        position: Point,
+        sprite_names: impl IntoIterator<Item = &'a str> + 'a,
+        colliders: impl IntoIterator<Item = impl Into<Shape>>,
+    ) -> Self {
+        let sprites = sprite_names
+            .into_iter()
+            .map(|sprite_name| sheet.cell(sprite_name).cloned())
+            .collect::<Option<Vec<_>>>()
+            .unwrap();
+        let colliders = colliders
+            .into_iter()
+            .map(|collider| {
+                let mut collider = collider.into();
+                collider.move_by(position.x, position.y);
+                collider
+            })
+            .collect();
+        Self {
+            sheet,
+            position,
+            sprites,
+            colliders,
+        }
+    }
+}
+
+impl Platform {
+    /// Lands the boy on top of the platform or knocks him out if he hits it
+    /// from any other side. Returns `true` if the boy is standing on it.
+    fn land_or_knock_out(&self, boy: &mut RedHatBoy) -> bool {
+        let boy_bounding_box = boy.bounding_box();
+
+        if let Some(collider) = self
+            .colliders
+            .iter()
+            .find(|collider| collider.intersects(&boy_bounding_box))
+        {
+            match Contact::classify(collider, &boy_bounding_box, boy.velocity_y()) {
+                Contact::Top(top) => {
+                    boy.land_on(top);
+                    return true;
+                }
+                Contact::Side(depth) if boy.airborne() => boy.grab_wall(depth),
+                Contact::Side(_) | Contact::Other => boy.knock_out(),
+            }
+        }
+        false
+    }
+}
+
+impl Body for Platform {
+    fn bounding_box(&self) -> Rect {
+        self.colliders
+            .iter()
+            .map(Shape::bounding_box)
+            .reduce(|area, bounding_box| area.union(&bounding_box))
+            .unwrap_or(Rect::new(self.position, 0.0, 0.0))
+    }
+
+    fn move_by(&mut self, x: f32, y: f32) {
+        self.position.x += x;
+        self.position.y += y;
+        for collider in &mut self.colliders {
+            collider.move_by(x, y);
+        }
+    }
+}
+
+impl Obstacle for Platform {
+    fn kind(&self) -> ObstacleKind {
+        ObstacleKind::Platform
+    }
+
+    fn left(&self) -> f32 {
+        self.colliders
+            .first()
+            .map_or(0.0, |collider| collider.bounding_box().left())
+    }
+
+    fn right(&self) -> f32 {
+        self.colliders
+            .last()
+            .map_or(0.0, |collider| collider.bounding_box().right())
+    }
+
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        self.land_or_knock_out(boy);
+    }
+
+    fn collision_boxes(&self) -> Vec<Rect> {
+        self.colliders
+            .iter()
+            .map(|collider| collider.bounding_box())
+            .collect()
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        let mut x = 0.0;
+        for sprite in &self.sprites {
+            self.sheet.draw(
+                renderer,
+                &Rect::from_xy(
+                    sprite.frame.x,
+                    sprite.frame.y,
+                    sprite.frame.w,
+                    sprite.frame.h,
+                ),
+                &Rect::from_xy(
+                    self.position.x + x,
+                    self.position.y,
+                    sprite.frame.w,
+                    sprite.frame.h,
+                ),
+            );
+            x += sprite.frame.w;
+        }
+        for collider in &self.colliders {
+            let bounding_box = collider.bounding_box();
+            renderer.draw_bounding_box(&bounding_box, DebugCategory::Platform);
+            renderer.draw_bounding_box(
+                &Rect::from_xy(bounding_box.x(), bounding_box.y(), bounding_box.width, 0.0),
+                DebugCategory::LandingSurface,
+            );
+        }
+    }
+
+    fn snapshot(&self) -> ObstacleSnapshot {
+        ObstacleSnapshot::Platform(self.platform_snapshot())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct MovingPlatform {
+    platform: Platform,
+    axis: Axis,
+    amplitude: f32,
+    /// How far through its swing the platform is, in radians.
+    phase: f32,
+    offset: f32,
+    delta: f32,
+}
+
+impl MovingPlatform {
+    /// How fast the platform swings, in radians per second.
+    const SPEED: f32 = 1.8;
+
+    /// Creates a platform that oscillates along `axis` by up to `amplitude`
+    /// pixels around its initial position.
+    pub(crate) fn new(platform: Platform, axis: Axis, amplitude: f32) -> Self {
+        Self {
+            platform,
+            axis,
+            amplitude,
+            phase: 0.0,
+            offset: 0.0,
+            delta: 0.0,
+        }
+    }
+}
+
+impl Body for MovingPlatform {
+    fn bounding_box(&self) -> Rect {
+        self.platform.bounding_box()
+    }
+
+    fn move_by(&mut self, x: f32, y: f32) {
+        self.platform.move_by(x, y);
+    }
+}
+
+impl Obstacle for MovingPlatform {
+    fn kind(&self) -> ObstacleKind {
+        self.platform.kind()
+    }
+
+    fn left(&self) -> f32 {
+        self.platform.left()
+    }
+
+    fn right(&self) -> f32 {
+        self.platform.right()
+    }
+
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        if self.platform.land_or_knock_out(boy) && matches!(self.axis, Axis::Horizontal) {
+            // Vertical movement is followed by landing on the new top every
+            // frame, but horizontal movement has to be passed on explicitly.
+            boy.carry(self.delta);
+        }
+    }
+
+    fn collision_boxes(&self) -> Vec<Rect> {
+        self.platform.collision_boxes()
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        self.platform.draw(renderer);
+    }
+
+    fn snapshot(&self) -> ObstacleSnapshot {
+        ObstacleSnapshot::MovingPlatform {
+            platform: self.platform.platform_snapshot(),
+            axis: self.axis,
+            amplitude: self.amplitude,
+            phase: self.phase,
+            offset: self.offset,
+            delta: self.delta,
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.phase = (self.phase + Self::SPEED * dt) % TAU;
+        let offset = self.phase.sin() * self.amplitude;
+        self.delta = offset - self.offset;
+        self.offset = offset;
+        match self.axis {
+            Axis::Horizontal => self.platform.move_by(self.delta, 0.0),
+            Axis::Vertical => self.platform.move_by(0.0, self.delta),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Barrier {
+    image: Image,
+    breakable: bool,
+}
+
+impl Barrier {
+    pub(crate) fn new(image: Image) -> Self {
+        Self {
+            image,
+            breakable: false,
+        }
+    }
+
+    /// Returns a cracked barrier, which the boy can kick apart.
+    pub(crate) fn breakable(image: Image) -> Self {
+        Self {
+            image,
+            breakable: true,
+        }
+    }
+
+    fn draw_cracks(&self, renderer: &Renderer) {
+        let area = self.image.bounding_box();
+        let at = |x: f32, y: f32| Point {
+            x: area.x() + area.width * x,
+            y: area.y() + area.height * y,
+        };
+        let crack = [
+            at(0.45, 0.1),
+            at(0.55, 0.4),
+            at(0.4, 0.6),
+            at(0.55, 0.95),
+            at(0.5, 0.6),
+            at(0.62, 0.4),
+            at(0.5, 0.1),
+        ];
+        renderer.fill_polygon(&crack, "rgba(0, 0, 0, 0.6)");
+    }
+}
+
+impl Body for Barrier {
+    fn bounding_box(&self) -> Rect {
+        *self.image.bounding_box()
+    }
+
+    fn move_by(&mut self, x: f32, y: f32) {
+        self.image.move_by(x, y);
+    }
+}
+
+impl Obstacle for Barrier {
+    fn kind(&self) -> ObstacleKind {
+        ObstacleKind::Stone
+    }
+
+    fn left(&self) -> f32 {
+        self.image.bounding_box().left()
+    }
+
+    fn right(&self) -> f32 {
+        self.image.right()
+    }
+
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        let area = self.image.bounding_box();
+        let mask = self.image.mask().map(|mask| (mask, area));
+        if boy.collides_with(&Shape::mound(area), mask) {
+            boy.knock_out();
+        }
+    }
+
+    fn collision_boxes(&self) -> Vec<Rect> {
+        vec![*self.image.bounding_box()]
+    }
+
+    fn draw(&self, renderer: &Renderer) {
         self.image.draw(renderer);
-        renderer.draw_bounding_box(self.image.bounding_box());
+        if self.breakable {
+            self.draw_cracks(renderer);
+        }
+        renderer.draw_bounding_box(self.image.bounding_box(), DebugCategory::Barrier);
+    }
+
+    fn snapshot(&self) -> ObstacleSnapshot {
+        ObstacleSnapshot::Barrier {
+            position: self.image.bounding_box().position,
+            breakable: self.breakable,
+        }
+    }
+
+    fn breakable_area(&self) -> Option<Rect> {
+        self.breakable.then(|| *self.image.bounding_box())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Enemy {
+    bounding_box: Rect,
+    base_y: f32,
+    velocity_x: f32,
+    amplitude: f32,
+    /// How far through its bob the enemy is, in radians.
+    phase: f32,
+    /// Seconds until the enemy calls out again.
+    call_timer: f32,
+    /// Whether the enemy called out in the last update.
+    calling: bool,
+}
+
+impl Enemy {
+    const WIDTH: f32 = 40.0;
+    const HEIGHT: f32 = 30.0;
+    /// How fast the enemy bobs, in radians per second.
+    const BOB_SPEED: f32 = 6.0;
+    const CALL_SECONDS: f32 = 0.75;
+
+    /// Creates an enemy that moves `velocity_x` pixels per default-rate update
+    /// on its own and bobs up and down by `amplitude` pixels around
+    /// `position`.
+    pub(crate) fn new(position: Point, velocity_x: f32, amplitude: f32) -> Self {
+        Self {
+            bounding_box: Rect::new(position, Self::WIDTH, Self::HEIGHT),
+            base_y: position.y,
+            velocity_x,
+            amplitude,
+            phase: 0.0,
+            call_timer: Self::CALL_SECONDS,
+            calling: false,
+        }
+    }
+}
+
+impl Body for Enemy {
+    fn bounding_box(&self) -> Rect {
+        self.bounding_box
+    }
+
+    fn move_by(&mut self, x: f32, y: f32) {
+        self.bounding_box.move_by(x, y);
+        self.base_y += y;
+    }
+}
+
+impl Obstacle for Enemy {
+    fn kind(&self) -> ObstacleKind {
+        ObstacleKind::Enemy
+    }
+
+    fn left(&self) -> f32 {
+        self.bounding_box.left()
+    }
+
+    fn right(&self) -> f32 {
+        self.bounding_box.right()
+    }
+
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        if boy.bounding_box().intersects(&self.bounding_box) {
+            boy.knock_out();
+        }
+    }
+
+    fn collision_boxes(&self) -> Vec<Rect> {
+        vec![self.bounding_box]
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        // The enemy is drawn facing left and flipped when it moves right.
+        let transform = if self.velocity_x > 0.0 {
+            Transform::flipped_horizontally()
+        } else {
+            Transform::default()
+        };
+        renderer.with_transform(&self.bounding_box.center(), &transform, |renderer| {
+            let radius = Self::HEIGHT / 2.0;
+            let body = Point {
+                x: self.bounding_box.x() + Self::WIDTH - radius,
+                y: self.bounding_box.y() + radius,
+            };
+            let head = Point {
+                x: self.bounding_box.x() + radius / 2.0,
+                y: body.y - radius / 3.0,
+            };
+            renderer.fill_circle(&body, radius, "saddlebrown");
+            renderer.fill_circle(&head, radius * 2.0 / 3.0, "sienna");
+            renderer.fill_circle(&head, 3.0, "black");
+        });
+        renderer.draw_bounding_box(&self.bounding_box, DebugCategory::Barrier);
+    }
+
+    fn snapshot(&self) -> ObstacleSnapshot {
+        ObstacleSnapshot::Enemy {
+            bounding_box: self.bounding_box,
+            base_y: self.base_y,
+            velocity_x: self.velocity_x,
+            amplitude: self.amplitude,
+            phase: self.phase,
+            call_timer: self.call_timer,
+        }
+    }
+
+    fn velocity(&self) -> Velocity {
+        Velocity {
+            x: self.velocity_x,
+            y: 0.0,
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.phase = (self.phase + Self::BOB_SPEED * dt) % TAU;
+        self.bounding_box
+            .set_y(self.base_y + self.phase.sin() * self.amplitude);
+        self.call_timer -= dt;
+        self.calling = self.call_timer <= 0.0;
+        if self.calling {
+            self.call_timer += Self::CALL_SECONDS;
+        }
+    }
+
+    fn emitted_sound(&self, left_edge: f32) -> Option<f32> {
+        let on_screen = self.right() > left_edge && self.left() < left_edge + WIDTH;
+        (on_screen && self.calling).then(|| self.bounding_box.center().x)
+    }
+}
+
+/// Something that happened during play which other systems may react to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GameEvent {
+    BoyJumped,
+    BoySlid,
+    BoyHit,
+    CoinCollected,
+    PowerUpCollected,
+    NewHighScore,
+    CheckpointReached,
+    SoundEnabled,
+    BossDefeated,
+    ShieldShattered,
+    ObstacleBroken,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) enum Reward {
+    Points(u32),
+    PowerUp(PowerUpKind),
+}
+
+/// Something a segment places in the world for the boy to pick up.
+pub(crate) trait Collectible: Debug {
+    fn spawn(&self, world: &mut World<Reward>) -> Entity;
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Coin {
+    position: Point,
+}
+
+impl Coin {
+    pub(crate) fn new(position: Point) -> Self {
+        Self { position }
+    }
+}
+
+impl Collectible for Coin {
+    fn spawn(&self, world: &mut World<Reward>) -> Entity {
+        world
+            .spawn(self.position)
+            .with_collider(COIN_SIZE, COIN_SIZE)
+            .with_sprite(Sprite::new(|renderer, bounding_box| {
+                let radius = COIN_SIZE / 2.0;
+                let center = bounding_box.center();
+                renderer.fill_circle(&center, radius, "goldenrod");
+                renderer.fill_circle(&center, radius - 4.0, "gold");
+            }))
+            .with_data(Reward::Points(COIN_POINTS))
+            .build()
     }
+}
 
-    fn move_horizontally(&mut self, x: i16) {
-        self.image.move_horizontally(x);
+/// Returns what the one-button mode does for a boy at `boy_box`: slide if an
+/// obstacle he cannot jump over hangs above him or just ahead, and jump
+/// otherwise.
+fn contextual_action(obstacles: &World<Box<dyn Obstacle>>, boy_box: &Rect) -> Action {
+    let overhead_ahead = obstacles.values().any(|obstacle| {
+        obstacle.kind() == ObstacleKind::Ceiling
+            && obstacle.right() > boy_box.left()
+            && obstacle.left() - boy_box.right() < SLIDE_LOOKAHEAD
+    });
+    if overhead_ahead {
+        Action::Slide
+    } else {
+        Action::Jump
     }
 }
 
-fn rightmost(obstacle_list: &[Box<dyn Obstacle>]) -> i16 {
+/// Spawns `obstacle` into `world`, moving on its own at its velocity.
+fn spawn_obstacle(world: &mut World<Box<dyn Obstacle>>, obstacle: Box<dyn Obstacle>) -> Entity {
+    let velocity = obstacle.velocity();
+    world.spawn_body(obstacle).with_velocity(velocity).build()
+}
+
+fn rightmost(obstacle_list: &[Box<dyn Obstacle>]) -> Option<f32> {
     obstacle_list
         .iter()
         .map(|obstacle| obstacle.right())
-        .max()
-        .unwrap_or(0)
-}
-
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use futures::channel::mpsc::unbounded;
-//     use std::collections::HashMap;
-//     use web_sys::AudioBufferOptions;
-
-//     use wasm_bindgen_test::wasm_bindgen_test;
-
-//     wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
-
-//     #[wasm_bindgen_test]
-//     async fn test_transition_from_game_over_to_new_game() {
-//         let (_, receiver) = unbounded();
-
-//         let image = HtmlImageElement::new().unwrap();
-//         let audio = Audio::new().unwrap();
-//         let options = AudioBufferOptions::new(1, 8000.0);
-//         let sound = audio.load_sound_from_options(&options).unwrap();
-//         let rhb = RedHatBoy::new(
-//             Sheet {
-//                 frames: HashMap::new(),
-//             },
-//             image.clone(),
-//             audio,
-//             sound,
-//         );
-//         let sprite_sheet = SpriteSheet::new(
-//             Sheet {
-//                 frames: HashMap::new(),
-//             },
-//             image.clone(),
-//         );
-//         let walk = Walk {
-//             boy: rhb,
-//             backgrounds: [
-//                 Image::new(image.clone(), Point { x: 0, y: 0 }),
-//                 Image::new(image.clone(), Point { x: 0, y: 0 }),
-//             ],
-//             obstacles: vec![],
-//             obstacle_sheet: Rc::new(sprite_sheet),
-//             stone: image.clone(),
-//             timeline: 0,
-//             debug_mode: false,
-//         };
-
-//         let document = browser::document().unwrap();
-//         document
-//             .body()
-//             .unwrap()
-//             .insert_adjacent_html("afterbegin", "<div id='ui'></div>")
-//             .unwrap();
-//         browser::draw_ui("<p>This is the UI</p>").unwrap();
-//         let state = WalkTheDogState {
-//             _state: GameOver {
-//                 new_game_event: receiver,
-//             },
-//             walk,
-//         };
-
-//         state.new_game();
-
-//         let ui = browser::find_html_element_by_id("ui").unwrap();
-//         assert_eq!(ui.child_element_count(), 0);
-//     }
-// }
+        .reduce(f32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{browser::FakePage, engine::Call};
+
+    const SEED: u64 = 7;
+
+    fn json<T: serde::de::DeserializeOwned>(json: &str) -> T {
+        serde_json::from_str(json).expect("the asset is valid JSON")
+    }
+
+    fn blank(url: &str, width: u32, height: u32) -> ImageSource {
+        ImageSource::Blank {
+            url: url.into(),
+            width,
+            height,
+        }
+    }
+
+    /// Builds a run from the game's own sheets and map, with silent sounds and
+    /// blank images the size of the real ones. Blank images are solid all
+    /// over, so pixel perfect collisions behave like bounding boxes.
+    fn new_walk(seed: u64) -> Walk {
+        new_walk_with(seed, Settings::default(), red_hat_boy::RUNNING_SPEED)
+    }
+
+    fn new_walk_with(seed: u64, settings: Settings, running_speed: f32) -> Walk {
+        let rhb_sheet: Sheet = json(include_str!("../static/sprites_sheets/rhb.json"));
+        let rhb_hitboxes = json(include_str!("../static/sprites_sheets/rhb_hitboxes.json"));
+        let obstacle_sheet = SpriteSheet::new(
+            json(include_str!("../static/sprites_sheets/tiles.json")),
+            blank("tiles.png", 650, 485),
+        );
+        let assets = WalkAssets {
+            page: Rc::new(FakePage::default()),
+            audio: Audio::new().unwrap(),
+            background_music: Sound,
+            defeat_jingle: Sound,
+            jump_sound: Sound,
+            bounce_sound: Sound,
+            splash_sound: Sound,
+            coin_sound: Sound,
+            enemy_sound: Sound,
+            shatter_sound: Sound,
+            background: blank("BG.png", 1000, 750),
+            night_background: blank("BG_night.png", 1000, 750),
+            stone: blank("Stone.png", 90, 54),
+            logo: blank("icon-192.png", 192, 192),
+            sprites: Sprites {
+                rhb_sheet: rhb_sheet.with_hitboxes(rhb_hitboxes),
+                rhb_image: blank("rhb.png", 1989, 485),
+                obstacle_sheet: Rc::new(obstacle_sheet),
+                maps: vec![json(include_str!("../static/maps/steps.json"))],
+            },
+        };
+        Walk::from_assets(assets, seed, settings, running_speed, None).unwrap()
+    }
+
+    /// Plays a run from the ready screen, holding down each set of keys in
+    /// `script` for the number of updates next to it.
+    fn play(seed: u64, script: &[(&[&str], usize)]) -> SceneStack<Walk> {
+        play_walk(new_walk(seed), script)
+    }
+
+    fn play_walk(walk: Walk, script: &[(&[&str], usize)]) -> SceneStack<Walk> {
+        let mut scenes = SceneStack::new(walk, Box::new(Ready));
+        for (codes, updates) in script {
+            let keystate = KeyState::pressing(codes);
+            for _ in 0..*updates {
+                scenes.update(&keystate);
+            }
+        }
+        scenes
+    }
+
+    /// Plays a recorded session again from the splash screen, where the game
+    /// starts, with the rate, speed and settings it was recorded with.
+    fn replay(recording: &InputRecording) -> SceneStack<Walk> {
+        engine::set_updates_per_second(recording.updates_per_second());
+        let walk = new_walk_with(
+            recording.seed(),
+            recording.settings().clone(),
+            recording.running_speed(),
+        );
+        let mut scenes = SceneStack::new(walk, Box::<Splash>::default());
+        for keystate in recording.replay() {
+            scenes.update(&keystate);
+        }
+        scenes
+    }
+
+    /// Plays `script` from the splash screen, recording the keys that go down
+    /// and come up the way the game loop does with `?record`.
+    fn record(seed: u64, script: &[(&[&str], usize)]) -> InputRecording {
+        let settings = Settings::default();
+        let running_speed = red_hat_boy::RUNNING_SPEED;
+        let mut recording = InputRecording::new(
+            seed,
+            engine::DEFAULT_UPDATES_PER_SECOND,
+            running_speed,
+            settings.clone(),
+        );
+        let walk = new_walk_with(seed, settings, running_speed);
+        let mut scenes = SceneStack::new(walk, Box::<Splash>::default());
+        let mut held: &[&str] = &[];
+        let mut update = 0;
+        for (codes, updates) in script {
+            for code in held.iter().filter(|code| !codes.contains(code)) {
+                recording.record(update, code, false);
+            }
+            for code in codes.iter().filter(|code| !held.contains(code)) {
+                recording.record(update, code, true);
+            }
+            held = codes;
+            let keystate = KeyState::pressing(codes);
+            for _ in 0..*updates {
+                scenes.update(&keystate);
+                update += 1;
+            }
+        }
+        recording.set_updates(update);
+        recording
+    }
+
+    /// Runs the boy at a cracked stone just ahead of him, pressing `keys` as
+    /// he gets close to it.
+    fn run_at_a_cracked_stone(keys: &[&str]) -> SceneStack<Walk> {
+        let mut walk = new_walk(SEED);
+        // Nothing else is in the way, nor placed there during the run.
+        walk.obstacles.clear();
+        walk.items.clear();
+        walk.timeline += TIMELINE_MINIMUM;
+        let position = Point {
+            x: walk.boy.bounding_box().right() + 40.0,
+            y: HEIGHT - walk.stone.height() as f32,
+        };
+        let stone = Image::new(walk.stone.clone(), position);
+        spawn_obstacle(&mut walk.obstacles, Box::new(Barrier::breakable(stone)));
+        play_walk(
+            walk,
+            &[(&["ArrowRight"], 1), (&[], 4), (keys, 1), (&[], 30)],
+        )
+    }
+
+    /// Returns how many pieces of broken stone are flying about.
+    fn debris(walk: &Walk) -> usize {
+        let calls = Renderer::record(WIDTH, HEIGHT, |renderer| walk.shatter.draw(renderer));
+        calls
+            .iter()
+            .filter(|call| matches!(call, Call::FillRect(_, color) if color == DEBRIS_COLOR))
+            .count()
+    }
+
+    /// Where everything ended up, to compare runs by.
+    fn outcome(walk: &Walk) -> (Point, u8, u32, Vec<f32>) {
+        let obstacles = walk.obstacles.values().map(|obstacle| obstacle.left());
+        (
+            walk.boy.position(),
+            walk.boy.lives(),
+            walk.score,
+            obstacles.collect(),
+        )
+    }
+
+    fn polygon_colors(calls: &[Call]) -> Vec<&str> {
+        calls
+            .iter()
+            .filter_map(|call| match call {
+                Call::FillPolygon(_, color) => Some(color.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Builds a tall wall whose left side is `depth` pixels inside the boy.
+    fn wall_in(walk: &Walk, depth: f32) -> Platform {
+        let position = Point {
+            x: walk.boy.bounding_box().right() - depth,
+            y: 0.0,
+        };
+        let wall = Rect::from_xy(0.0, 0.0, 100.0, HEIGHT);
+        Platform::new(Rc::clone(&walk.obstacle_sheet), position, ["1.png"], [wall])
+    }
+
+    #[test]
+    fn platforms_are_landed_on_from_above_and_grabbed_from_the_side() {
+        let platform = Shape::Rect(Rect::from_xy(200.0, 300.0, 100.0, 50.0));
+        let above = Rect::from_xy(150.0, 220.0, 60.0, 90.0);
+        assert_eq!(
+            Contact::classify(&platform, &above, 5.0),
+            Contact::Top(300.0)
+        );
+        assert_eq!(
+            Contact::classify(&platform, &above, -5.0),
+            Contact::Side(10.0)
+        );
+
+        let beside = Rect::from_xy(150.0, 310.0, 60.0, 90.0);
+        assert_eq!(
+            Contact::classify(&platform, &beside, 5.0),
+            Contact::Side(10.0)
+        );
+
+        let deep_inside = Rect::from_xy(200.0, 310.0, 60.0, 90.0);
+        assert_eq!(
+            Contact::classify(&platform, &deep_inside, 5.0),
+            Contact::Other
+        );
+    }
+
+    #[test]
+    fn jumping_into_a_platform_grabs_its_wall() {
+        let mut walk = new_walk(SEED);
+        walk.boy.run_right();
+        walk.boy.jump();
+        walk.boy.update();
+        let wall = wall_in(&walk, 5.0);
+        let right = walk.boy.bounding_box().right();
+
+        assert!(!wall.land_or_knock_out(&mut walk.boy));
+        assert_eq!(walk.boy.state_name(), "WallSliding");
+        assert_eq!(walk.boy.lives(), STARTING_LIVES);
+        assert_eq!(walk.boy.bounding_box().right(), right - 5.0);
+    }
+
+    #[test]
+    fn running_into_a_platform_knocks_the_boy_out() {
+        let mut walk = new_walk(SEED);
+        walk.boy.run_right();
+        walk.boy.update();
+        let wall = wall_in(&walk, 5.0);
+
+        assert!(!wall.land_or_knock_out(&mut walk.boy));
+        assert_ne!(walk.boy.state_name(), "WallSliding");
+        assert!(walk.boy.lives() < STARTING_LIVES);
+    }
+
+    #[test]
+    fn enemies_face_the_way_they_move() {
+        let position = Point { x: 100.0, y: 300.0 };
+        let left = Enemy::new(position, -2.0, 0.0);
+        let calls = Renderer::record(WIDTH, HEIGHT, |renderer| left.draw(renderer));
+        assert_eq!(polygon_colors(&calls), ["saddlebrown", "sienna", "black"]);
+        assert!(!calls.contains(&Call::Scale(-1.0, 1.0)));
+
+        let right = Enemy::new(position, 2.0, 0.0);
+        let calls = Renderer::record(WIDTH, HEIGHT, |renderer| right.draw(renderer));
+        assert_eq!(polygon_colors(&calls), ["saddlebrown", "sienna", "black"]);
+        assert!(calls.contains(&Call::Scale(-1.0, 1.0)));
+    }
+
+    #[test]
+    fn enemies_move_and_bob_as_far_at_any_update_rate() {
+        let position = Point { x: 500.0, y: 300.0 };
+        let after_updates = |updates_per_second: u16, updates| {
+            engine::set_updates_per_second(updates_per_second);
+            let mut obstacles = World::new();
+            let enemy = spawn_obstacle(&mut obstacles, Box::new(Enemy::new(position, -2.0, 20.0)));
+            let dt = engine::seconds_per_update();
+            for _ in 0..updates {
+                obstacles.update_bodies(|obstacle| obstacle.update(dt));
+                obstacles.move_bodies();
+            }
+            engine::set_updates_per_second(engine::DEFAULT_UPDATES_PER_SECOND);
+            let bounding_box = obstacles.get(enemy).unwrap().bounding_box();
+            assert_eq!(obstacles.bounding_box(enemy), Some(bounding_box));
+            bounding_box
+        };
+        let (moved, half_moved) = (after_updates(60, 50), after_updates(30, 25));
+        assert!((moved.x() - half_moved.x()).abs() < 0.01);
+        assert!((moved.y() - half_moved.y()).abs() < 0.01);
+        assert!(moved.x() < position.x);
+        assert!(moved.y() != position.y);
+    }
+
+    #[test]
+    fn segments_without_obstacles_still_move_the_timeline_on() {
+        let mut walk = new_walk(SEED);
+        let offset_x = walk.timeline + OBSTACLE_BUFFER;
+        walk.add_segment(offset_x, Segment::default());
+        assert_eq!(walk.timeline, offset_x);
+    }
+
+    #[test]
+    fn restored_runs_play_on_like_the_original() {
+        // Hits cannot end either run, whatever it runs into.
+        let immortal_walk = || {
+            let mut walk = new_walk(SEED);
+            walk.boy.set_infinite_lives(true);
+            walk
+        };
+        let mut original = SceneStack::new(immortal_walk(), Box::new(Ready));
+        original.update(&KeyState::pressing(&["ArrowRight"]));
+        let keystate = KeyState::new();
+        for _ in 0..200 {
+            original.update(&keystate);
+        }
+        let snapshot = original.context_mut().snapshot();
+        let snapshot = json(&serde_json::to_string(&snapshot).unwrap());
+        let mut restored = SceneStack::new(immortal_walk(), Box::new(Walking));
+        restored.context_mut().restore(snapshot).unwrap();
+
+        for _ in 0..1200 {
+            original.update(&keystate);
+            restored.update(&keystate);
+        }
+        assert_eq!(
+            outcome(original.context_mut()),
+            outcome(restored.context_mut())
+        );
+    }
+
+    #[test]
+    fn the_boy_waits_until_run_is_pressed() {
+        let mut scenes = play(SEED, &[(&[], 60)]);
+        let walk = scenes.context_mut();
+        assert_eq!(walk.boy.state_name(), "Idle");
+        assert_eq!(walk.distance(), 0.0);
+    }
+
+    #[test]
+    fn any_key_skips_the_splash_screen() {
+        let mut scenes = SceneStack::new(new_walk(SEED), Box::<Splash>::default());
+        for _ in 0..10 {
+            scenes.update(&KeyState::new());
+        }
+        // The first press only skips the logo, and the next one runs.
+        scenes.update(&KeyState::pressing(&["ArrowRight"]));
+        assert_eq!(scenes.context_mut().boy.state_name(), "Idle");
+        scenes.update(&KeyState::pressing(&["ArrowRight"]));
+        assert_eq!(scenes.context_mut().boy.state_name(), "Running");
+    }
+
+    #[test]
+    fn r_restarts_the_run_at_once() {
+        let mut scenes = play(SEED, &[(&["ArrowRight"], 1), (&[], 120), (&["KeyR"], 1)]);
+        let walk = scenes.context_mut();
+        assert_eq!(walk.boy.state_name(), "Running");
+        assert_eq!(walk.score, 0);
+        assert!(walk.distance() < 120.0 * red_hat_boy::RUNNING_SPEED);
+    }
+
+    #[test]
+    fn playing_again_after_a_game_over_starts_a_new_game() {
+        let page = Rc::new(FakePage::default());
+        let mut walk = new_walk(SEED);
+        walk.page = Rc::clone(&page) as Rc<dyn browser::Page>;
+        let mut scenes = SceneStack::new(walk, Box::new(Ready));
+        scenes.update(&KeyState::pressing(&["ArrowRight"]));
+        for _ in 0..10_000 {
+            if scenes.context_mut().knocked_out() {
+                break;
+            }
+            scenes.update(&KeyState::new());
+        }
+        assert!(scenes.context_mut().knocked_out());
+        assert!(scenes.context_mut().distance() > 0.0);
+
+        // Clicks the Play Again button of the game over screen.
+        page.click((WIDTH / 2.0 - 100.0) / WIDTH, 378.0 / HEIGHT);
+        for _ in 0..=engine::updates_in(NEW_GAME_FADE_SECONDS) * 2 {
+            scenes.update(&KeyState::new());
+        }
+        let walk = scenes.context_mut();
+        assert_eq!(walk.boy.state_name(), "Idle");
+        assert_eq!(walk.boy.lives(), STARTING_LIVES);
+        assert_eq!(walk.score, 0);
+        assert_eq!(walk.distance(), 0.0);
+    }
+
+    #[test]
+    fn attacks_break_cracked_stones() {
+        let mut scenes = run_at_a_cracked_stone(&["KeyX"]);
+        let walk = scenes.context_mut();
+        assert_eq!(walk.obstacles.values().count(), 0);
+        assert_eq!(walk.score, BREAK_POINTS);
+        assert!(debris(walk) > 0);
+        assert_eq!(walk.boy.lives(), STARTING_LIVES);
+    }
+
+    #[test]
+    fn cracked_stones_still_hurt_without_an_attack() {
+        let mut scenes = run_at_a_cracked_stone(&[]);
+        let walk = scenes.context_mut();
+        assert_eq!(walk.obstacles.values().count(), 1);
+        assert_eq!(walk.score, 0);
+        assert_eq!(debris(walk), 0);
+        assert!(walk.boy.lives() < STARTING_LIVES);
+    }
+
+    #[test]
+    fn the_restart_key_can_be_rebound() {
+        let mut walk = new_walk(SEED);
+        walk.settings.bindings = json(r#"{"jump": "KeyR", "restart": "KeyQ"}"#);
+        let mut scenes = SceneStack::new(walk, Box::new(Ready));
+        scenes.update(&KeyState::pressing(&["ArrowRight"]));
+        for _ in 0..120 {
+            scenes.update(&KeyState::new());
+        }
+        let distance = scenes.context_mut().distance();
+
+        scenes.update(&KeyState::pressing(&["KeyR"]));
+        let walk = scenes.context_mut();
+        assert!(walk.boy.jumping());
+        assert!(walk.distance() >= distance);
+
+        scenes.update(&KeyState::pressing(&["KeyQ"]));
+        assert!(scenes.context_mut().distance() < distance);
+    }
+
+    #[test]
+    fn a_jump_lifts_the_boy_off_the_floor_and_lands_him_again() {
+        let mut scenes = play(SEED, &[(&["ArrowRight"], 1), (&[], 10)]);
+        let floor = scenes.context_mut().boy.position().y;
+
+        for _ in 0..10 {
+            scenes.update(&KeyState::pressing(&["Space"]));
+        }
+        let walk = scenes.context_mut();
+        assert!(walk.boy.jumping());
+        assert!(walk.boy.position().y < floor);
+
+        for _ in 0..60 {
+            scenes.update(&KeyState::new());
+        }
+        let walk = scenes.context_mut();
+        assert_eq!(walk.boy.state_name(), "Running");
+        assert_eq!(walk.boy.position().y, floor);
+        assert!(walk.distance() > 0.0);
+    }
+
+    #[test]
+    fn runs_with_the_same_seed_and_input_play_out_the_same() {
+        let script: &[(&[&str], usize)] = &[
+            (&["ArrowRight"], 1),
+            (&[], 90),
+            (&["Space"], 15),
+            (&[], 90),
+            (&["ArrowDown"], 20),
+            (&[], 60),
+        ];
+        let mut first = play(SEED, script);
+        let mut second = play(SEED, script);
+        assert_eq!(outcome(first.context_mut()), outcome(second.context_mut()));
+        assert!(!first.context_mut().knocked_out());
+    }
+
+    /// Writes the session that the recording tests replay, to be run again
+    /// with `--ignored` whenever the recording format changes.
+    #[test]
+    #[ignore = "rewrites the recordings in tests/recordings"]
+    fn record_the_high_platform_session() {
+        let recording = record(
+            SEED,
+            &[
+                // The first press skips the splash screen, and the boy runs
+                // on the next update it is held for.
+                (&[], 20),
+                (&["ArrowRight"], 4),
+                (&[], 5),
+                (&["Space"], 12),
+                (&[], 40),
+                (&["Space"], 12),
+                (&[], 78),
+            ],
+        );
+        let json = serde_json::to_string_pretty(&recording).unwrap();
+        std::fs::write("tests/recordings/high_platform.json", json + "\n").unwrap();
+    }
+
+    #[test]
+    fn a_recorded_session_still_lands_on_the_high_platform() {
+        let recording = json(include_str!("../tests/recordings/high_platform.json"));
+        let floor = new_walk(SEED).boy.position().y;
+        let mut scenes = replay(&recording);
+        let walk = scenes.context_mut();
+        assert_eq!(walk.boy.state_name(), "Running");
+        assert!(walk.boy.position().y < floor - 100.0);
+        assert_eq!(walk.boy.lives(), STARTING_LIVES);
+        assert!(walk.score > 0);
+    }
+
+    #[test]
+    fn the_one_button_slides_only_near_overhead_bars() {
+        let boy_box = Rect::from_xy(100.0, 400.0, 60.0, 100.0);
+        let bar_at = |x| {
+            let mut obstacles = World::new();
+            spawn_obstacle(&mut obstacles, Box::new(OverheadBar::new(x)));
+            obstacles
+        };
+        assert_eq!(contextual_action(&World::new(), &boy_box), Action::Jump);
+        assert_eq!(contextual_action(&bar_at(250.0), &boy_box), Action::Slide);
+        assert_eq!(contextual_action(&bar_at(50.0), &boy_box), Action::Slide);
+        assert_eq!(contextual_action(&bar_at(600.0), &boy_box), Action::Jump);
+        assert_eq!(contextual_action(&bar_at(-200.0), &boy_box), Action::Jump);
+    }
+
+    #[test]
+    fn overhead_bars_knock_out_running_boys_but_not_sliding_ones() {
+        let mut running = new_walk(SEED);
+        running.boy.run_right();
+        running.boy.update();
+        let bar = OverheadBar::new(running.boy.bounding_box().x());
+        bar.check_intersection(&mut running.boy);
+        assert!(running.boy.lives() < STARTING_LIVES);
+
+        let mut sliding = new_walk(SEED);
+        sliding.boy.run_right();
+        sliding.boy.slide();
+        sliding.boy.update();
+        let bar = OverheadBar::new(sliding.boy.bounding_box().x());
+        assert!(sliding.boy.bounding_box().top() > bar.collision_boxes()[0].bottom());
+        bar.check_intersection(&mut sliding.boy);
+        assert_eq!(sliding.boy.state_name(), "Sliding");
+        assert_eq!(sliding.boy.lives(), STARTING_LIVES);
+    }
+
+    #[test]
+    fn sliding_under_a_ceiling_scores_a_trick_but_not_under_an_enemy() {
+        let mut walk = new_walk(SEED);
+        walk.obstacles.clear();
+        walk.boy.run_right();
+        walk.boy.slide();
+        walk.boy.update();
+        let boy_box = walk.boy.bounding_box();
+        let passed = boy_box.left() - 5.0;
+
+        let mut enemy = Enemy::new(
+            Point {
+                x: 0.0,
+                y: boy_box.y(),
+            },
+            0.0,
+            0.0,
+        );
+        enemy.move_by(passed - enemy.right(), 0.0);
+        spawn_obstacle(&mut walk.obstacles, Box::new(enemy));
+        walk.score_tricks(passed - 5.0, false);
+        assert_eq!(walk.score, 0);
+
+        let overhead = Rect::from_xy(0.0, 0.0, 100.0, 20.0);
+        let position = Point {
+            x: passed - overhead.width,
+            y: boy_box.top() - overhead.height,
+        };
+        walk.obstacles.clear();
+        let platform = Platform::new(
+            Rc::clone(&walk.obstacle_sheet),
+            position,
+            ["1.png"],
+            [overhead],
+        );
+        spawn_obstacle(&mut walk.obstacles, Box::new(platform));
+        walk.score_tricks(passed - 5.0, false);
+        assert_eq!(walk.score, 2);
+
+        walk.obstacles.clear();
+        let bar = OverheadBar::new(passed - OverheadBar::WIDTH);
+        spawn_obstacle(&mut walk.obstacles, Box::new(bar));
+        walk.score_tricks(passed - 5.0, false);
+        assert_eq!(walk.score, 2 + 2 * 2);
+    }
+
+    #[test]
+    fn frame_step_mode_advances_one_update_per_press() {
+        // The debug keys only work in debug mode, which is off by default in
+        // release builds.
+        let mut walk = new_walk(SEED);
+        walk.settings.debug_mode = true;
+        let mut stepped = play_walk(
+            walk,
+            &[(&["ArrowRight"], 1), (&[], 30), (&["F6"], 1), (&[], 10)],
+        );
+        let walk = stepped.context_mut();
+        assert_eq!(
+            outcome(walk),
+            outcome(play(SEED, &[(&["ArrowRight"], 1), (&[], 30)]).context_mut())
+        );
+
+        for _ in 0..3 {
+            stepped.update(&KeyState::pressing(&["Period"]));
+        }
+        let mut expected = play(SEED, &[(&["ArrowRight"], 1), (&[], 31)]);
+        assert_eq!(
+            outcome(stepped.context_mut()),
+            outcome(expected.context_mut())
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Pads on the ground that make the boy run faster for a while when he runs
+//! over them.
+
+use crate::engine::{self, Body, DebugCategory, Point, Rect, Renderer};
+
+use super::{
+    red_hat_boy::{RedHatBoy, SpeedModifier},
+    Obstacle, ObstacleKind, ObstacleSnapshot, HEIGHT,
+};
+
+#[derive(Debug, Clone)]
+pub(crate) struct BoostPad {
+    bounding_box: Rect,
+}
+
+impl BoostPad {
+    pub(crate) const WIDTH: f32 = 120.0;
+    const HEIGHT: f32 = 12.0;
+    const FACTOR: f32 = 1.8;
+    const SECONDS: u16 = 2;
+    /// The part of the boost spent easing back to the usual speed.
+    const RAMP_SECONDS: u16 = 1;
+
+    /// Places a pad on the ground, starting at `x`.
+    pub(crate) fn new(x: f32) -> Self {
+        Self::restore(Point {
+            x,
+            y: HEIGHT - Self::HEIGHT,
+        })
+    }
+
+    pub(super) fn restore(position: Point) -> Self {
+        Self {
+            bounding_box: Rect::new(position, Self::WIDTH, Self::HEIGHT),
+        }
+    }
+
+    fn modifier() -> SpeedModifier {
+        let updates_per_second = engine::updates_per_second();
+        SpeedModifier::new(
+            Self::FACTOR,
+            Self::SECONDS * updates_per_second,
+            Self::RAMP_SECONDS * updates_per_second,
+        )
+    }
+}
+
+impl Body for BoostPad {
+    fn bounding_box(&self) -> Rect {
+        self.bounding_box
+    }
+
+    fn move_by(&mut self, x: f32, y: f32) {
+        self.bounding_box.move_by(x, y);
+    }
+}
+
+impl Obstacle for BoostPad {
+    fn kind(&self) -> ObstacleKind {
+        ObstacleKind::BoostPad
+    }
+
+    fn left(&self) -> f32 {
+        self.bounding_box.left()
+    }
+
+    fn right(&self) -> f32 {
+        self.bounding_box.right()
+    }
+
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        if boy.bounding_box().intersects(&self.bounding_box) {
+            boy.boost(Self::modifier());
+        }
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        const CHEVRON_COUNT: u8 = 3;
+
+        renderer.fill_rect(&self.bounding_box, "darkorange");
+        let chevron_width = Self::WIDTH / f32::from(CHEVRON_COUNT);
+        for chevron in 0..CHEVRON_COUNT {
+            let center = Point {
+                x: self.bounding_box.left() + chevron_width * (f32::from(chevron) + 0.5),
+                y: self.bounding_box.center().y,
+            };
+            renderer.fill_circle(&center, Self::HEIGHT / 4.0, "yellow");
+        }
+        renderer.draw_bounding_box(&self.bounding_box, DebugCategory::Other);
+    }
+
+    fn snapshot(&self) -> ObstacleSnapshot {
+        ObstacleSnapshot::BoostPad {
+            position: self.bounding_box.position,
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! A boss fight that interrupts the generated segments now and then.
+//!
+//! The boss waits at the end of an arena, a stretch of open ground, and
+//! keeps to the right edge of the screen once the arena is reached, throwing
+//! projectiles that roll along the ground, to be jumped, or fly at head
+//! height, to be slid under. The boss gives up when the boy reaches the end
+//! of the arena, and segments are generated again after it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{self, Body, DebugCategory, Point, Rect, Renderer, Velocity};
+
+use super::{Obstacle, ObstacleKind, ObstacleSnapshot, RedHatBoy, HEIGHT, WIDTH};
+
+/// A boss fight starts every time the boy has run this far.
+pub(super) const INTERVAL: f32 = 20000.0;
+pub(super) const ARENA_LENGTH: f32 = 3000.0;
+/// The points the boy scores for outlasting the boss.
+pub(super) const REWARD: u32 = 10;
+const SIZE: f32 = 120.0;
+/// How far from the right edge of the screen the boss stands.
+const SCREEN_OFFSET: f32 = 30.0;
+/// How many seconds go by between throws.
+const THROW_SECONDS: f32 = 1.2;
+/// The heights of the throws, in turn, so that every fight is the same.
+const THROWS: [Throw; 6] = [
+    Throw::Low,
+    Throw::High,
+    Throw::Low,
+    Throw::Low,
+    Throw::High,
+    Throw::High,
+];
+
+#[derive(Debug, Clone, Copy)]
+enum Throw {
+    /// Rolls along the ground, to be jumped over.
+    Low,
+    /// Flies above a sliding boy's head but not a running one's.
+    High,
+}
+
+impl Throw {
+    const fn top(self) -> f32 {
+        match self {
+            Self::Low => HEIGHT - Projectile::SIZE,
+            Self::High => HEIGHT - 95.0,
+        }
+    }
+}
+
+pub(super) enum BossAction {
+    Throw(Projectile),
+    Defeated,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct Boss {
+    arena_left: f32,
+    arena_right: f32,
+    frame: u16,
+    throws: usize,
+}
+
+impl Boss {
+    pub(super) fn new(arena_left: f32) -> Self {
+        Self {
+            arena_left,
+            arena_right: arena_left + ARENA_LENGTH,
+            frame: 0,
+            throws: 0,
+        }
+    }
+
+    pub(super) fn move_horizontally(&mut self, x: f32) {
+        self.arena_left += x;
+        self.arena_right += x;
+    }
+
+    /// Where the boss stands while the screen starts at `left_edge`: where
+    /// the right edge of the screen will be when the arena fills it, and at
+    /// that edge from then on.
+    fn bounding_box(&self, left_edge: f32) -> Rect {
+        let right =
+            (left_edge + WIDTH - SCREEN_OFFSET).max(self.arena_left + WIDTH - SCREEN_OFFSET);
+        Rect::from_xy(right - SIZE, HEIGHT - SIZE, SIZE, SIZE)
+    }
+
+    /// Advances the fight for the boy at `boy_x` on the screen starting at
+    /// `left_edge`.
+    pub(super) fn update(&mut self, boy_x: f32, left_edge: f32) -> Option<BossAction> {
+        if boy_x >= self.arena_right {
+            return Some(BossAction::Defeated);
+        }
+        if left_edge < self.arena_left {
+            return None;
+        }
+        self.frame = self.frame.wrapping_add(1);
+        if !self.frame.is_multiple_of(engine::updates_in(THROW_SECONDS)) {
+            return None;
+        }
+        let throw = THROWS[self.throws % THROWS.len()];
+        self.throws += 1;
+        let position = Point {
+            x: self.bounding_box(left_edge).left() - Projectile::SIZE,
+            y: throw.top(),
+        };
+        Some(BossAction::Throw(Projectile::new(position)))
+    }
+
+    pub(super) fn draw(&self, renderer: &Renderer, left_edge: f32) {
+        let bounding_box = self.bounding_box(left_edge);
+        let center = bounding_box.center();
+        let eye = |x: f32| Point {
+            x: center.x + x,
+            y: bounding_box.top() + SIZE / 3.0,
+        };
+        renderer.fill_circle(&center, SIZE / 2.0, "darkslategray");
+        renderer.fill_circle(&eye(-30.0), 10.0, "gold");
+        renderer.fill_circle(&eye(10.0), 10.0, "gold");
+        renderer.draw_bounding_box(&bounding_box, DebugCategory::Barrier);
+    }
+}
+
+/// Something thrown by the boss, flying left until it leaves the screen.
+#[derive(Debug, Clone)]
+pub(super) struct Projectile {
+    bounding_box: Rect,
+}
+
+impl Projectile {
+    const SIZE: f32 = 30.0;
+    /// How fast the projectile flies, in pixels per second.
+    const SPEED: f32 = -360.0;
+
+    pub(super) fn new(position: Point) -> Self {
+        Self {
+            bounding_box: Rect::new(position, Self::SIZE, Self::SIZE),
+        }
+    }
+}
+
+impl Body for Projectile {
+    fn bounding_box(&self) -> Rect {
+        self.bounding_box
+    }
+
+    fn move_by(&mut self, x: f32, y: f32) {
+        self.bounding_box.move_by(x, y);
+    }
+}
+
+impl Obstacle for Projectile {
+    fn kind(&self) -> ObstacleKind {
+        ObstacleKind::Enemy
+    }
+
+    fn left(&self) -> f32 {
+        self.bounding_box.left()
+    }
+
+    fn right(&self) -> f32 {
+        self.bounding_box.right()
+    }
+
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        if boy.bounding_box().intersects(&self.bounding_box) {
+            boy.knock_out();
+        }
+    }
+
+    fn collision_boxes(&self) -> Vec<Rect> {
+        vec![self.bounding_box]
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        renderer.fill_circle(&self.bounding_box.center(), Self::SIZE / 2.0, "dimgray");
+        renderer.draw_bounding_box(&self.bounding_box, DebugCategory::Barrier);
+    }
+
+    fn snapshot(&self) -> ObstacleSnapshot {
+        ObstacleSnapshot::Projectile {
+            position: self.bounding_box.position,
+        }
+    }
+
+    fn velocity(&self) -> Velocity {
+        Velocity {
+            x: Self::SPEED / f32::from(engine::DEFAULT_UPDATES_PER_SECOND),
+            y: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_boss_throws_from_the_screen_edge_until_the_arena_ends() {
+        let mut boss = Boss::new(1000.0);
+        assert!(boss.update(0.0, 0.0).is_none());
+
+        let left_edge = 1500.0;
+        let throws: Vec<_> = (0..engine::updates_in(THROW_SECONDS) * 2)
+            .filter_map(|_| match boss.update(left_edge + 100.0, left_edge) {
+                Some(BossAction::Throw(projectile)) => Some(projectile.bounding_box),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(throws.len(), 2);
+        assert!(throws
+            .iter()
+            .all(|throw| throw.right() <= left_edge + WIDTH));
+        assert_eq!(throws[0].top(), Throw::Low.top());
+        assert_eq!(throws[1].top(), Throw::High.top());
+
+        assert!(matches!(
+            boss.update(1000.0 + ARENA_LENGTH, left_edge),
+            Some(BossAction::Defeated)
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+//! Cheat codes: sequences of keys typed in any scene, each of which toggles
+//! a tweak for the rest of the session.
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::engine::KeyState;
+
+const KONAMI_CODE: [&str; 10] = [
+    "ArrowUp",
+    "ArrowUp",
+    "ArrowDown",
+    "ArrowDown",
+    "ArrowLeft",
+    "ArrowRight",
+    "ArrowLeft",
+    "ArrowRight",
+    "KeyB",
+    "KeyA",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum Cheat {
+    /// Hits never cost the boy a life.
+    InfiniteLives,
+    BigHead,
+    /// Every skin can be picked, whatever the best score.
+    AllSkins,
+}
+
+impl Cheat {
+    const ALL: [Cheat; 3] = [Cheat::InfiniteLives, Cheat::BigHead, Cheat::AllSkins];
+
+    /// The keys typed one after the other to toggle the cheat.
+    const fn code(self) -> &'static [&'static str] {
+        match self {
+            Cheat::InfiniteLives => &KONAMI_CODE,
+            Cheat::BigHead => &["KeyN", "KeyO", "KeyG", "KeyG", "KeyI", "KeyN"],
+            Cheat::AllSkins => &["KeyC", "KeyL", "KeyO", "KeyS", "KeyE", "KeyT"],
+        }
+    }
+
+    pub(super) const fn label_key(self) -> &'static str {
+        match self {
+            Cheat::InfiniteLives => "cheat.infinite_lives",
+            Cheat::BigHead => "cheat.big_head",
+            Cheat::AllSkins => "cheat.all_skins",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Cheats {
+    /// The keys down at the last update, so that holding one types it once.
+    held: HashSet<String>,
+    /// The last keys typed, oldest first, as many as the longest code has.
+    typed: VecDeque<String>,
+    enabled: HashSet<Cheat>,
+}
+
+impl Cheats {
+    /// Reads the keys pressed since the last update and toggles the cheats
+    /// whose codes they complete, returning those.
+    pub(super) fn update(&mut self, keystate: &KeyState) -> Vec<Cheat> {
+        self.held.retain(|code| keystate.is_pressed(code));
+        let pressed: Vec<_> = keystate
+            .pressed_keys()
+            .filter(|code| !self.held.contains(*code))
+            .map(str::to_string)
+            .collect();
+        let mut toggled = vec![];
+        for code in pressed {
+            self.held.insert(code.clone());
+            toggled.extend(self.type_key(code));
+        }
+        toggled
+    }
+
+    fn type_key(&mut self, code: String) -> Option<Cheat> {
+        let longest = Cheat::ALL.map(|cheat| cheat.code().len()).into_iter().max();
+        self.typed.push_back(code);
+        if self.typed.len() > longest.unwrap_or(0) {
+            self.typed.pop_front();
+        }
+        let cheat = Cheat::ALL.into_iter().find(|cheat| {
+            let code = cheat.code();
+            self.typed.len() >= code.len()
+                && self
+                    .typed
+                    .iter()
+                    .skip(self.typed.len() - code.len())
+                    .map(String::as_str)
+                    .eq(code.iter().copied())
+        })?;
+        self.typed.clear();
+        if !self.enabled.remove(&cheat) {
+            self.enabled.insert(cheat);
+        }
+        Some(cheat)
+    }
+
+    pub(super) fn is_enabled(&self, cheat: Cheat) -> bool {
+        self.enabled.contains(&cheat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typing_a_code_toggles_its_cheat() {
+        let mut cheats = Cheats::default();
+        let mut toggled = vec![];
+        for code in ["KeyX"].iter().chain(&KONAMI_CODE) {
+            toggled.extend(cheats.update(&KeyState::pressing(&[code])));
+            cheats.update(&KeyState::new());
+        }
+        assert_eq!(toggled, [Cheat::InfiniteLives]);
+        assert!(cheats.is_enabled(Cheat::InfiniteLives));
+
+        // Holding a key types it only once.
+        for _ in 0..3 {
+            cheats.update(&KeyState::pressing(&["KeyB"]));
+        }
+        assert_eq!(cheats.typed.len(), 1);
+    }
+}
@@ -0,0 +1,78 @@
+//! Bonus points for tricks done one after another.
+//!
+//! Every trick raises the combo, and scores its points times the combo, up
+//! to [`MAX_MULTIPLIER`]. The combo is lost when no trick follows for a
+//! while, or when the boy is hit.
+
+use crate::engine;
+
+/// How many seconds the combo lasts without another trick.
+const TIMEOUT_SECONDS: f32 = 2.0;
+const MAX_MULTIPLIER: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Trick {
+    JumpOverStone,
+    LandOnPlatform,
+    SlideUnderCeiling,
+}
+
+impl Trick {
+    const fn points(self) -> u32 {
+        match self {
+            Self::JumpOverStone | Self::LandOnPlatform => 1,
+            Self::SlideUnderCeiling => 2,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Combo {
+    tricks: u32,
+    /// Updates left before the combo is lost.
+    timer: u16,
+}
+
+impl Combo {
+    /// Counts `trick` towards the combo and returns the points it scores.
+    pub(super) fn trick(&mut self, trick: Trick) -> u32 {
+        self.tricks += 1;
+        self.timer = engine::updates_in(TIMEOUT_SECONDS);
+        trick.points() * self.multiplier()
+    }
+
+    pub(super) fn multiplier(&self) -> u32 {
+        self.tricks.clamp(1, MAX_MULTIPLIER)
+    }
+
+    pub(super) fn update(&mut self) {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.tricks = 0;
+        }
+    }
+
+    pub(super) fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chained_tricks_multiply_until_the_combo_times_out() {
+        let mut combo = Combo::default();
+        assert_eq!(combo.trick(Trick::JumpOverStone), 1);
+        assert_eq!(combo.trick(Trick::LandOnPlatform), 2);
+        assert_eq!(combo.trick(Trick::SlideUnderCeiling), 6);
+        assert_eq!(combo.multiplier(), 3);
+
+        for _ in 0..engine::updates_in(TIMEOUT_SECONDS) {
+            combo.update();
+        }
+        assert_eq!(combo.multiplier(), 1);
+        assert_eq!(combo.trick(Trick::JumpOverStone), 1);
+    }
+}
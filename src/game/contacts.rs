@@ -0,0 +1,161 @@
+//! The collisions that landed the boy or knocked him out lately, drawn in
+//! debug mode so that a mysterious death can be understood at a glance: the
+//! pair of boxes that touched, where they overlapped and what came of it.
+
+use crate::engine::{self, Point, Rect, Renderer, TextAlign, TextStyle};
+
+/// How many seconds a contact stays on the screen.
+const CONTACT_SECONDS: f32 = 1.5;
+const MAX_CONTACTS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ContactOutcome {
+    Land,
+    KnockOut,
+}
+
+impl ContactOutcome {
+    /// Tells what a collision did to the boy from how he was before it,
+    /// `(lives, hurt, airborne)`, and how he is after.
+    pub(super) fn between(before: (u8, bool, bool), after: (u8, bool, bool)) -> Option<Self> {
+        let ((lives, hurt, airborne), (lives_after, hurt_after, airborne_after)) = (before, after);
+        if lives_after < lives || (hurt_after && !hurt) {
+            Some(Self::KnockOut)
+        } else if airborne && !airborne_after {
+            Some(Self::Land)
+        } else {
+            None
+        }
+    }
+
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Land => "land",
+            Self::KnockOut => "knock out",
+        }
+    }
+
+    const fn overlap_color(self) -> &'static str {
+        match self {
+            Self::Land => "rgba(0, 255, 0, 0.5)",
+            Self::KnockOut => "rgba(255, 0, 0, 0.5)",
+        }
+    }
+}
+
+/// A collision, in world coordinates.
+#[derive(Debug, Clone)]
+struct Contact {
+    boy: Rect,
+    /// The box of the obstacle that the boy touched, if it has any.
+    other: Option<Rect>,
+    outcome: ContactOutcome,
+    remaining_frames: u16,
+}
+
+impl Contact {
+    fn overlap(&self) -> Option<Rect> {
+        self.other.and_then(|other| self.boy.intersection(&other))
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ContactLog {
+    contacts: Vec<Contact>,
+}
+
+impl ContactLog {
+    /// Records that the boy at `boy` met an obstacle made of `boxes`, with
+    /// `outcome`. The box that overlaps him the most is the one he touched.
+    pub(super) fn record(&mut self, boy: Rect, boxes: &[Rect], outcome: ContactOutcome) {
+        let area = |rect: &Rect| {
+            boy.intersection(rect)
+                .map_or(0.0, |overlap| overlap.width * overlap.height)
+        };
+        let other = boxes
+            .iter()
+            .max_by(|a, b| area(a).total_cmp(&area(b)))
+            .copied();
+        if self.contacts.len() == MAX_CONTACTS {
+            self.contacts.remove(0);
+        }
+        self.contacts.push(Contact {
+            boy,
+            other,
+            outcome,
+            remaining_frames: engine::updates_in(CONTACT_SECONDS),
+        });
+    }
+
+    pub(super) fn update(&mut self) {
+        self.contacts.retain_mut(|contact| {
+            contact.remaining_frames = contact.remaining_frames.saturating_sub(1);
+            contact.remaining_frames > 0
+        });
+    }
+
+    pub(super) fn move_horizontally(&mut self, distance: f32) {
+        for contact in &mut self.contacts {
+            contact.boy.set_x(contact.boy.x() + distance);
+            if let Some(other) = &mut contact.other {
+                other.set_x(other.x() + distance);
+            }
+        }
+    }
+
+    pub(super) fn draw(&self, renderer: &Renderer) {
+        const LABEL_STYLE: TextStyle = TextStyle::new("monospace", 12)
+            .with_color("white")
+            .with_stroke("black", 3)
+            .aligned(TextAlign::Center);
+
+        for contact in &self.contacts {
+            renderer.fill_rect(&contact.boy, "rgba(0, 255, 255, 0.15)");
+            if let Some(other) = &contact.other {
+                renderer.fill_rect(other, "rgba(255, 165, 0, 0.15)");
+            }
+            if let Some(overlap) = contact.overlap() {
+                renderer.fill_rect(&overlap, contact.outcome.overlap_color());
+            }
+            let label_at = Point {
+                x: contact.boy.center().x,
+                y: contact.boy.top() - 6.0,
+            };
+            renderer.draw_text(contact.outcome.label(), &label_at, &LABEL_STYLE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contacts_keep_the_box_that_overlaps_the_boy_most() {
+        assert_eq!(
+            ContactOutcome::between((3, false, false), (2, true, false)),
+            Some(ContactOutcome::KnockOut)
+        );
+        assert_eq!(
+            ContactOutcome::between((3, false, true), (3, false, false)),
+            Some(ContactOutcome::Land)
+        );
+        assert_eq!(
+            ContactOutcome::between((3, false, false), (3, false, false)),
+            None
+        );
+
+        let mut log = ContactLog::default();
+        let boy = Rect::from_xy(0.0, 0.0, 50.0, 100.0);
+        let (grazed, hit) = (
+            Rect::from_xy(45.0, 90.0, 50.0, 50.0),
+            Rect::from_xy(30.0, 50.0, 50.0, 50.0),
+        );
+        log.record(boy, &[grazed, hit], ContactOutcome::KnockOut);
+        let overlap = log.contacts[0].overlap().unwrap();
+        assert_eq!(
+            (overlap.x(), overlap.y(), overlap.width, overlap.height),
+            (30.0, 50.0, 20.0, 50.0)
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! The day and night cycle. The further the boy runs, the further the day
+//! turns: the background fades into a night one, and a lighting pass darkens
+//! and cools everything drawn in the world.
+
+use std::f32::consts::TAU;
+
+use crate::engine::{BlendMode, Rect, Renderer};
+
+use super::{Walk, HEIGHT, WIDTH};
+
+/// How far the boy runs from one noon to the next.
+const CYCLE_DISTANCE: f32 = 40000.0;
+/// The color the world is multiplied by at midnight.
+const MIDNIGHT_TINT: (f32, f32, f32) = (150.0, 160.0, 220.0);
+
+/// Returns how dark it is after running `distance`, from 0 at noon, when the
+/// run starts, to 1 at midnight.
+fn darkness(distance: f32) -> f32 {
+    let phase = (distance / CYCLE_DISTANCE).fract();
+    (1.0 - (TAU * phase).cos()) / 2.0
+}
+
+fn tint(darkness: f32) -> String {
+    let (red, green, blue) = MIDNIGHT_TINT;
+    let channel = |midnight: f32| (255.0 + (midnight - 255.0) * darkness).round();
+    format!(
+        "rgb({}, {}, {})",
+        channel(red),
+        channel(green),
+        channel(blue)
+    )
+}
+
+/// Draws the night background over the day one, as opaque as it is dark.
+/// Called with the camera applied, after the day backgrounds.
+pub(super) fn draw_sky(walk: &Walk, renderer: &Renderer) {
+    let darkness = darkness(walk.distance());
+    if darkness <= 0.0 {
+        return;
+    }
+    renderer.with_alpha(darkness.into(), |renderer| {
+        for background in &walk.backgrounds {
+            let position = background.bounding_box().position;
+            renderer.draw_entire_image(&walk.night_background, position);
+        }
+    });
+}
+
+/// Darkens everything drawn in the world so far to the light of the time of
+/// day.
+pub(super) fn light(walk: &Walk, renderer: &Renderer) {
+    let darkness = darkness(walk.distance());
+    if darkness <= 0.0 {
+        return;
+    }
+    renderer.tint(
+        &Rect::from_xy(0.0, 0.0, WIDTH, HEIGHT),
+        &tint(darkness),
+        BlendMode::Multiply,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_day_turns_to_night_and_back() {
+        assert_eq!(darkness(0.0), 0.0);
+        assert!((darkness(CYCLE_DISTANCE / 2.0) - 1.0).abs() < 1e-6);
+        assert!(darkness(CYCLE_DISTANCE).abs() < 1e-6);
+        assert_eq!(tint(0.0), "rgb(255, 255, 255)");
+        assert_eq!(tint(1.0), "rgb(150, 160, 220)");
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::Result;
+
+use crate::{
+    browser,
+    engine::{KeyState, Point, Rect, Renderer, Scene, SceneChange, TextStyle, Transition},
+    tiled::TiledMap,
+};
+
+use super::{Walk, COIN_SIZE, HEIGHT, WIDTH};
+
+const GRID: f32 = 32.0;
+const TILE_SIZE: f32 = 128.0;
+const EXPORT_KEY: &str = "walk_the_dog.editor_segment";
+const HELP_STYLE: TextStyle = TextStyle::new("serif", 16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Brush {
+    Tile(&'static str),
+    Stone,
+    Coin,
+    CollisionBox,
+}
+
+const BRUSHES: [Brush; 9] = [
+    Brush::Tile("13.png"),
+    Brush::Tile("14.png"),
+    Brush::Tile("15.png"),
+    Brush::Tile("1.png"),
+    Brush::Tile("2.png"),
+    Brush::Tile("3.png"),
+    Brush::Stone,
+    Brush::Coin,
+    Brush::CollisionBox,
+];
+
+impl Brush {
+    const fn label(self) -> &'static str {
+        match self {
+            Brush::Tile(name) => name,
+            Brush::Stone => "stone",
+            Brush::Coin => "coin",
+            Brush::CollisionBox => "collision box",
+        }
+    }
+
+    /// Returns the area the brush covers when used at `cursor`. Tiles snap to
+    /// the tile grid horizontally.
+    fn area(self, walk: &Walk, cursor: Point) -> Rect {
+        match self {
+            Brush::Tile(name) => {
+                let height = walk
+                    .obstacle_sheet
+                    .cell(name)
+                    .map_or(TILE_SIZE, |cell| cell.frame.h);
+                let x = (cursor.x / TILE_SIZE).floor() * TILE_SIZE;
+                Rect::from_xy(x, cursor.y, TILE_SIZE, height)
+            }
+            Brush::Stone => Rect::new(
+                cursor,
+                walk.stone.width() as f32,
+                walk.stone.height() as f32,
+            ),
+            Brush::Coin => Rect::new(cursor, COIN_SIZE, COIN_SIZE),
+            Brush::CollisionBox => Rect::new(cursor, GRID, GRID),
+        }
+    }
+
+    fn draw(self, walk: &Walk, renderer: &Renderer, area: &Rect) {
+        match self {
+            Brush::Tile(name) => {
+                if let Some(cell) = walk.obstacle_sheet.cell(name) {
+                    let frame = &cell.frame;
+                    walk.obstacle_sheet.draw(
+                        renderer,
+                        &Rect::from_xy(frame.x, frame.y, frame.w, frame.h),
+                        area,
+                    );
+                }
+            }
+            Brush::Stone => renderer.draw_entire_image(&walk.stone, area.position),
+            Brush::Coin => renderer.fill_circle(&area.center(), COIN_SIZE / 2.0, "gold"),
+            Brush::CollisionBox => renderer.fill_rect(area, "rgba(255, 0, 0, 0.4)"),
+        }
+    }
+}
+
+/// Lets a layout be put together on top of the paused world and exported as
+/// a Tiled map that can be added to the game's segments.
+///
+/// The arrow keys move the cursor, B changes the brush, Space places it,
+/// Backspace removes what is under the cursor and X exports the layout to
+/// the console and local storage.
+#[derive(Debug)]
+pub(super) struct Editor {
+    cursor: Point,
+    brush: usize,
+    placed: Vec<(Brush, Rect)>,
+}
+
+impl Editor {
+    pub(super) fn new(walk: &Walk) -> Self {
+        let snap = |value: f32| (value / GRID).floor() * GRID;
+        Self {
+            cursor: Point {
+                x: snap(walk.camera.x() + WIDTH / 2.0),
+                y: snap(HEIGHT / 2.0),
+            },
+            brush: 0,
+            placed: vec![],
+        }
+    }
+
+    fn move_cursor(&mut self, walk: &mut Walk, keystate: &KeyState) {
+        if walk.just_pressed(keystate, "ArrowLeft") {
+            self.cursor.x -= GRID;
+        }
+        if walk.just_pressed(keystate, "ArrowRight") {
+            self.cursor.x += GRID;
+        }
+        if walk.just_pressed(keystate, "ArrowUp") {
+            self.cursor.y = (self.cursor.y - GRID).max(0.0);
+        }
+        if walk.just_pressed(keystate, "ArrowDown") {
+            self.cursor.y = (self.cursor.y + GRID).min(HEIGHT - GRID);
+        }
+        walk.camera.set_x(self.cursor.x - WIDTH / 2.0);
+    }
+
+    fn remove_under_cursor(&mut self) {
+        let cursor = Rect::new(self.cursor, 1.0, 1.0);
+        if let Some(index) = self
+            .placed
+            .iter()
+            .rposition(|(_, area)| area.intersects(&cursor))
+        {
+            self.placed.remove(index);
+        }
+    }
+
+    fn export(&self) -> Result<()> {
+        let mut tiles = vec![];
+        let mut objects = vec![];
+        for &(brush, area) in &self.placed {
+            match brush {
+                Brush::Tile(name) => tiles.push((name, area.position)),
+                Brush::Stone => objects.push(("stone", area)),
+                Brush::Coin => objects.push(("coin", area)),
+                Brush::CollisionBox => objects.push(("", area)),
+            }
+        }
+        let map = TiledMap::from_world(TILE_SIZE, TILE_SIZE, &tiles, &objects);
+        let json = browser::to_json_string(&map)?;
+        info!("{json}");
+        browser::storage::save_item(EXPORT_KEY, &json)
+    }
+}
+
+impl Scene<Walk> for Editor {
+    fn update(&mut self, walk: &mut Walk, keystate: &KeyState) -> SceneChange<Walk> {
+        if walk.just_pressed(keystate, "F2") {
+            walk.follow_boy();
+            return SceneChange::Pop(Transition::Cut);
+        }
+
+        self.move_cursor(walk, keystate);
+        if walk.just_pressed(keystate, "KeyB") {
+            self.brush = (self.brush + 1) % BRUSHES.len();
+        }
+        if walk.just_pressed(keystate, "Space") {
+            let brush = BRUSHES[self.brush];
+            self.placed.push((brush, brush.area(walk, self.cursor)));
+        }
+        if walk.just_pressed(keystate, "Backspace") {
+            self.remove_under_cursor();
+        }
+        if walk.just_pressed(keystate, "KeyX") {
+            if let Err(err) = self.export() {
+                error!("error exporting the layout: {err:#?}");
+            }
+        }
+        SceneChange::None
+    }
+
+    fn draw(&self, walk: &Walk, renderer: &Renderer) {
+        walk.draw(renderer);
+
+        let brush = BRUSHES[self.brush];
+        renderer.with_camera(&walk.camera, |renderer| {
+            for (brush, area) in &self.placed {
+                brush.draw(walk, renderer, area);
+            }
+            let area = brush.area(walk, self.cursor);
+            renderer.with_alpha(0.5, |renderer| brush.draw(walk, renderer, &area));
+            renderer.draw_rect(&area);
+        });
+
+        let lines = [
+            format!("Editor - brush: {}", brush.label()),
+            "Arrows move, B brush, Space place, Backspace remove".to_string(),
+            "X export, F2 leave".to_string(),
+        ];
+        for (line, y) in lines
+            .iter()
+            .zip([HEIGHT - 60.0, HEIGHT - 40.0, HEIGHT - 20.0])
+        {
+            renderer.draw_text(line, &Point { x: 20.0, y }, &HELP_STYLE);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Renders a few fixed scenes and compares hashes of their pixels with the
+//! ones stored in `static/golden.json`, to catch rendering regressions.
+//!
+//! Open the game with `?golden=update` to take the hashes, and save the ones
+//! it logs to `static/golden.json`. Checking them with `?golden=1` is only
+//! offered once the game is built with some stored. A scene without a stored
+//! hash fails the check. Fonts and image decoding differ between browsers,
+//! so the stored hashes only hold for the browser they were taken in.
+
+use std::{collections::HashMap, str::FromStr};
+
+use anyhow::{anyhow, Error, Result};
+
+use crate::engine::{self, AssetLoader, KeyState, Rect, Renderer, SceneStack};
+
+use super::{
+    red_hat_boy::RUNNING_SPEED, Ready, Settings, Walk, WalkAssets, Walking, HEIGHT, WIDTH,
+};
+
+const GOLDENS: &str = "golden.json";
+/// The stored hashes as they were when the game was built.
+const BUILT_IN_GOLDENS: &str = include_str!("../../static/golden.json");
+const SEED: u64 = 1;
+/// How long the boy runs before the segment scene is taken.
+const SEGMENT_UPDATES: usize = 180;
+const MAX_KNOCKOUT_UPDATES: usize = 2000;
+
+/// What [`run_golden_tests`] does with the hashes it takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GoldenMode {
+    /// Compares them with the stored ones.
+    Check,
+    /// Logs them to be stored, without comparing them.
+    Update,
+}
+
+impl FromStr for GoldenMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" | "true" | "check" if !goldens_stored() => Err(anyhow!(
+                "no golden hashes are stored to check against, take them with `?golden=update`"
+            )),
+            "1" | "true" | "check" => Ok(Self::Check),
+            "update" => Ok(Self::Update),
+            _ => Err(anyhow!("unknown golden mode {s:?}")),
+        }
+    }
+}
+
+/// Whether any hashes were stored when the game was built. An empty JSON
+/// object is the only one without a colon.
+fn goldens_stored() -> bool {
+    BUILT_IN_GOLDENS.contains(':')
+}
+
+pub(crate) async fn run_golden_tests(mode: GoldenMode) -> Result<()> {
+    let assets = AssetLoader::shared();
+    let goldens = match assets.json::<HashMap<String, String>>(GOLDENS).await {
+        Ok(goldens) => goldens,
+        Err(err) => {
+            info!("no golden hashes loaded: {err:#}");
+            HashMap::new()
+        }
+    };
+
+    let mut hashes = HashMap::new();
+    let ready = SceneStack::new(new_walk(&assets).await?, Box::new(Ready));
+    hashes.insert("ready", capture(|renderer| ready.draw(renderer))?);
+
+    let mut walk = new_walk(&assets).await?;
+    walk.boy.run_right();
+    let mut segment = SceneStack::new(walk, Box::new(Walking));
+    let keystate = KeyState::new();
+    for _ in 0..SEGMENT_UPDATES {
+        segment.update(&keystate);
+    }
+    hashes.insert("segment", capture(|renderer| segment.draw(renderer))?);
+
+    let mut walk = new_walk(&assets).await?;
+    walk.boy.run_right();
+    for _ in 0..MAX_KNOCKOUT_UPDATES {
+        if walk.boy.knocked_out() {
+            break;
+        }
+        walk.boy.knock_out();
+        walk.boy.update();
+    }
+    walk.follow_boy();
+    hashes.insert("knockout", capture(|renderer| walk.draw(renderer))?);
+
+    let json = crate::browser::to_json_string(&hashes)?;
+    if mode == GoldenMode::Update {
+        warn!("golden hashes taken, save them to static/golden.json: {json}");
+        return Ok(());
+    }
+    info!("golden hashes: {json}");
+    let failures = check_hashes(&hashes, &goldens);
+    if failures.is_empty() {
+        info!("golden tests passed");
+        Ok(())
+    } else {
+        Err(anyhow!("golden tests failed: {}", failures.join(", ")))
+    }
+}
+
+/// Describes every hash in `hashes` that differs from its golden one, or
+/// that has no golden one to be compared with.
+fn check_hashes(hashes: &HashMap<&str, String>, goldens: &HashMap<String, String>) -> Vec<String> {
+    let mut failures: Vec<_> = hashes
+        .iter()
+        .filter_map(|(name, hash)| match goldens.get(*name) {
+            Some(golden) if golden == hash => None,
+            Some(_) => Some(format!("{name} differs")),
+            None => Some(format!("{name} has no golden hash, see `?golden=update`")),
+        })
+        .collect();
+    failures.sort();
+    failures
+}
+
+/// Returns a run that looks the same every time, with no tutorial or debug
+/// drawing and nothing saved by earlier sessions.
+async fn new_walk(assets: &AssetLoader) -> Result<Walk> {
+    let settings = Settings {
+        debug_mode: false,
+        ..Settings::default()
+    };
+    let assets = WalkAssets::load(assets).await?;
+    let walk = Walk::from_assets(assets, SEED, settings, RUNNING_SPEED, None)?;
+    walk.audio.close()?;
+    Ok(walk)
+}
+
+fn capture(draw: impl FnOnce(&Renderer)) -> Result<String> {
+    let pixels = engine::capture_frame(|renderer| {
+        renderer.clear(&Rect::from_xy(0.0, 0.0, WIDTH, HEIGHT));
+        draw(renderer);
+    })?;
+    Ok(engine::hash_pixels(&pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_golden_hashes_fail_the_check() {
+        let hashes = HashMap::from([("ready", "1".to_string()), ("segment", "2".to_string())]);
+        let goldens = HashMap::from([("ready".to_string(), "1".to_string())]);
+        assert_eq!(
+            check_hashes(&hashes, &goldens),
+            ["segment has no golden hash, see `?golden=update`"]
+        );
+        assert_eq!(check_hashes(&hashes, &HashMap::new()).len(), 2);
+    }
+
+    #[test]
+    fn checking_is_only_offered_once_golden_hashes_are_stored() {
+        let goldens: HashMap<String, String> = serde_json::from_str(BUILT_IN_GOLDENS).unwrap();
+        assert_eq!(goldens_stored(), !goldens.is_empty());
+        assert_eq!("1".parse::<GoldenMode>().is_ok(), goldens_stored());
+        assert_eq!("update".parse::<GoldenMode>().unwrap(), GoldenMode::Update);
+    }
+}
@@ -0,0 +1,78 @@
+//! The heads-up display, drawn over the world on every frame.
+//!
+//! Elements are placed in logical coordinates like the world, which the
+//! renderer scales to whatever size the canvas is, so they keep their place
+//! when it is resized.
+
+use crate::{
+    engine::{Point, Renderer, TextAlign, TextStyle},
+    i18n::{t, t_with},
+};
+
+use super::{Walk, WIDTH};
+
+const STYLE: TextStyle = TextStyle::new("serif", 21);
+const RIGHT_STYLE: TextStyle = STYLE.aligned(TextAlign::Right);
+const MARGIN: f32 = 20.0;
+const HEART_SIZE: f32 = 28.0;
+const LINE_HEIGHT: f32 = 30.0;
+
+/// Draws the score, the best score and the power-ups in the top left corner,
+/// and the lives, the speed and the combo in the top right corner.
+pub(super) fn draw(walk: &Walk, renderer: &Renderer) {
+    let line = |index: u8| 40.0 + LINE_HEIGHT * f32::from(index);
+
+    let score = walk.score.to_string();
+    let best_score = walk.best_score.max(walk.score).to_string();
+    let mut left_lines = vec![
+        t_with("hud.score", &[("score", &score)]),
+        t_with("hud.best", &[("score", &best_score)]),
+    ];
+    left_lines.extend(walk.boy.power_ups().iter().map(|power_up| {
+        let label = t(power_up.kind().label_key());
+        match power_up.remaining_seconds() {
+            Some(seconds) => format!("{label} {seconds:.1}s"),
+            None => label,
+        }
+    }));
+    for (index, text) in (0..).zip(&left_lines) {
+        let location = Point {
+            x: MARGIN,
+            y: line(index),
+        };
+        renderer.draw_text(text, &location, &STYLE);
+    }
+
+    for life in 0..walk.boy.max_lives() {
+        let color = if life < walk.boy.lives() {
+            "crimson"
+        } else {
+            "lightgray"
+        };
+        let location = Point {
+            x: WIDTH - HEART_SIZE * (f32::from(life) + 1.0),
+            y: HEART_SIZE,
+        };
+        renderer.fill_heart(&location, HEART_SIZE, color);
+    }
+    let speed = format!("{:.0}", walk.boy.velocity().x);
+    renderer.draw_text(
+        &t_with("hud.speed", &[("speed", &speed)]),
+        &Point {
+            x: WIDTH - MARGIN,
+            y: line(2),
+        },
+        &RIGHT_STYLE,
+    );
+    let multiplier = walk.combo.multiplier();
+    if multiplier > 1 {
+        renderer.draw_text(
+            &t_with("hud.combo", &[("multiplier", &multiplier.to_string())]),
+            &Point {
+                x: WIDTH - MARGIN,
+                y: line(3),
+            },
+            &RIGHT_STYLE,
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use crate::engine::{Point, Rect, Renderer, TextStyle};
+
+use super::{Walk, WIDTH};
+
+const PANEL_WIDTH: f32 = 220.0;
+const LINE_HEIGHT: f32 = 16.0;
+const PADDING: f32 = 8.0;
+/// Only the obstacles nearest the boy are listed, so that the panel stays on
+/// the screen.
+const MAX_OBSTACLES: usize = 12;
+
+/// Draws a panel with the state of the boy and the obstacles, which is kept
+/// up to date on every frame while debug mode is on.
+pub(super) fn draw(walk: &Walk, renderer: &Renderer) {
+    const STYLE: TextStyle = TextStyle::new("monospace", 12).with_color("white");
+
+    let boy = &walk.boy;
+    let (position, velocity) = (boy.position(), boy.velocity());
+    let mut lines = vec![
+        format!("{} frame {}", boy.state_name(), boy.frame()),
+        format!("pos {:7.1} {:7.1}", position.x, position.y),
+        format!("vel {:7.1} {:7.1}", velocity.x, velocity.y),
+    ];
+    if walk.frame_step {
+        lines.insert(0, "frame step: . steps, F6 resumes".to_string());
+    }
+    let boy_left = boy.bounding_box().left();
+    let mut ahead: Vec<_> = walk
+        .obstacles
+        .values()
+        .filter(|obstacle| obstacle.right() >= boy_left)
+        .collect();
+    ahead.sort_by(|a, b| a.left().total_cmp(&b.left()));
+    lines.extend(
+        ahead
+            .iter()
+            .take(MAX_OBSTACLES)
+            .map(|obstacle| format!("{:?} right {:.1}", obstacle.kind(), obstacle.right())),
+    );
+
+    let height = lines.len() as f32 * LINE_HEIGHT + PADDING * 2.0;
+    let left = WIDTH - PANEL_WIDTH - PADDING;
+    let top = 60.0;
+    renderer.fill_rect(
+        &Rect::from_xy(left, top, PANEL_WIDTH, height),
+        "rgba(0, 0, 0, 0.6)",
+    );
+    for (index, line) in lines.iter().enumerate() {
+        let location = Point {
+            x: left + PADDING,
+            y: top + PADDING + (index as f32 + 0.75) * LINE_HEIGHT,
+        };
+        renderer.draw_text(line, &location, &STYLE);
+    }
+}
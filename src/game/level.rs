@@ -0,0 +1,350 @@
+//! Levels: a fixed run of segments that ends at a goal flag, as opposed to
+//! the endless run made up as the boy goes.
+
+use std::{cell, rc::Rc};
+
+use anyhow::{anyhow, Result};
+use futures::channel::mpsc::UnboundedReceiver;
+use rand::rngs::StdRng;
+use serde::Deserialize;
+
+use crate::{
+    browser,
+    engine::{
+        self, BlendMode, Body, Button, DebugCategory, ImageSource, KeyState, Label, Panel, Point,
+        Rect, Redraw, Renderer, Scene, SceneChange, SpriteSheet, TextAlign, TextStyle, Transition,
+        Widget,
+    },
+    i18n::{t, t_with},
+    segments::{self, Segment, SegmentGeneratorFn},
+    sound::Looping,
+};
+
+use super::{
+    red_hat_boy::RedHatBoy, Obstacle, ObstacleKind, ObstacleSnapshot, Ready, Walk, HEIGHT,
+    KEN_FUTURE, NEW_GAME_FADE_SECONDS, WIDTH,
+};
+
+/// A level as written in `static/levels`, e.g.
+///
+/// ```json
+/// { "name": "Meadow", "segments": ["floating_and_stone", "boost_pad", "spring"] }
+/// ```
+///
+/// where the segments are named after their generators, and are played in
+/// that order.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct LevelManifest {
+    name: String,
+    segments: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Level {
+    name: String,
+    generators: Vec<SegmentGeneratorFn>,
+    /// The index of the segment to place next, which is the goal once it is
+    /// past the last one.
+    next_segment: usize,
+    goal_placed: bool,
+    /// Updates played since the start, to time the run.
+    frames: u32,
+    coins: u32,
+}
+
+impl Level {
+    /// Looks up the segments named in `manifest`, which must all exist.
+    pub(crate) fn from_manifest(manifest: LevelManifest) -> Result<Self> {
+        let generators = manifest
+            .segments
+            .iter()
+            .map(|name| {
+                segments::generator(name)
+                    .map(|generator| generator.generate)
+                    .ok_or_else(|| anyhow!("unknown segment {name:?} in level {:?}", manifest.name))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            name: manifest.name,
+            generators,
+            next_segment: 0,
+            goal_placed: false,
+            frames: 0,
+            coins: 0,
+        })
+    }
+
+    /// Starts the level over from its first segment.
+    pub(super) fn restart(&mut self) {
+        self.next_segment = 0;
+        self.goal_placed = false;
+        self.frames = 0;
+        self.coins = 0;
+    }
+
+    /// Returns the next segment of the level, placed at `offset_x`, then the
+    /// goal, and then nothing.
+    pub(super) fn next_segment(
+        &mut self,
+        rng: &mut StdRng,
+        stone: &ImageSource,
+        sprite_sheet: &Rc<SpriteSheet>,
+        offset_x: f32,
+    ) -> Option<Segment> {
+        if let Some(generate) = self.generators.get(self.next_segment) {
+            self.next_segment += 1;
+            return Some(generate(
+                rng,
+                stone.clone(),
+                Rc::clone(sprite_sheet),
+                offset_x,
+            ));
+        }
+        if self.goal_placed {
+            return None;
+        }
+        self.goal_placed = true;
+        Some(Segment {
+            obstacles: vec![Box::new(GoalFlag::new(offset_x))],
+            ..Segment::default()
+        })
+    }
+
+    pub(super) fn tick(&mut self) {
+        self.frames = self.frames.saturating_add(1);
+    }
+
+    pub(super) fn collect_coins(&mut self, coins: u32) {
+        self.coins += coins;
+    }
+
+    fn seconds(&self) -> f32 {
+        self.frames as f32 / f32::from(engine::updates_per_second())
+    }
+}
+
+/// The flag at the end of a level. The boy runs through it, and the level
+/// is complete once he has.
+#[derive(Debug, Clone)]
+pub(crate) struct GoalFlag {
+    position: Point,
+}
+
+impl GoalFlag {
+    const POLE_WIDTH: f32 = 8.0;
+    const POLE_HEIGHT: f32 = 220.0;
+    const FLAG_WIDTH: f32 = 70.0;
+    const FLAG_HEIGHT: f32 = 45.0;
+
+    /// Plants the flag's pole on the floor at `x`.
+    pub(super) fn new(x: f32) -> Self {
+        Self::restore(Point {
+            x,
+            y: HEIGHT - Self::POLE_HEIGHT,
+        })
+    }
+
+    pub(super) fn restore(position: Point) -> Self {
+        Self { position }
+    }
+}
+
+impl Body for GoalFlag {
+    fn bounding_box(&self) -> Rect {
+        Rect::new(
+            self.position,
+            Self::POLE_WIDTH + Self::FLAG_WIDTH,
+            Self::POLE_HEIGHT,
+        )
+    }
+
+    fn move_by(&mut self, x: f32, y: f32) {
+        self.position.x += x;
+        self.position.y += y;
+    }
+}
+
+impl Obstacle for GoalFlag {
+    fn kind(&self) -> ObstacleKind {
+        ObstacleKind::Goal
+    }
+
+    fn left(&self) -> f32 {
+        self.position.x
+    }
+
+    fn right(&self) -> f32 {
+        self.bounding_box().right()
+    }
+
+    fn check_intersection(&self, _boy: &mut RedHatBoy) {}
+
+    fn draw(&self, renderer: &Renderer) {
+        let Point { x, y } = self.position;
+        renderer.fill_rect(
+            &Rect::from_xy(x, y, Self::POLE_WIDTH, Self::POLE_HEIGHT),
+            "white",
+        );
+        let pennant = [
+            Point {
+                x: x + Self::POLE_WIDTH,
+                y,
+            },
+            Point {
+                x: x + Self::POLE_WIDTH + Self::FLAG_WIDTH,
+                y: y + Self::FLAG_HEIGHT / 2.0,
+            },
+            Point {
+                x: x + Self::POLE_WIDTH,
+                y: y + Self::FLAG_HEIGHT,
+            },
+        ];
+        renderer.fill_polygon(&pennant, "crimson");
+        renderer.draw_bounding_box(&self.bounding_box(), DebugCategory::Other);
+    }
+
+    fn snapshot(&self) -> ObstacleSnapshot {
+        ObstacleSnapshot::Goal {
+            position: self.position,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LevelCompleteButton {
+    PlayAgain,
+}
+
+/// The results of a level the boy has run to the end of.
+#[derive(Debug)]
+pub(super) struct LevelComplete {
+    clicks: UnboundedReceiver<Point>,
+    _click_listener: browser::Listening,
+    screen: Widget<LevelCompleteButton>,
+    overlay_drawn: cell::Cell<bool>,
+}
+
+impl LevelComplete {
+    pub(super) fn new(walk: &mut Walk) -> Self {
+        walk.record_run(false);
+        walk.stop_slow_motion();
+        let viewport = Rect::from_xy(0.0, 0.0, WIDTH, HEIGHT);
+        let (clicks, listener) = engine::add_pointer_handler(&*walk.page, viewport).unwrap();
+
+        LevelComplete {
+            clicks,
+            _click_listener: listener,
+            screen: Self::screen(walk),
+            overlay_drawn: cell::Cell::new(false),
+        }
+    }
+
+    fn screen(walk: &Walk) -> Widget<LevelCompleteButton> {
+        const TITLE_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 40)
+            .bold()
+            .with_color("white")
+            .with_stroke("black", 4)
+            .aligned(TextAlign::Center);
+        const TEXT_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 24)
+            .with_color("white")
+            .aligned(TextAlign::Center);
+
+        let center_x = WIDTH / 2.0;
+        let (name, seconds, coins) = walk.level.as_ref().map_or_else(
+            || (String::new(), String::new(), String::new()),
+            |level| {
+                (
+                    level.name.clone(),
+                    format!("{:.1}", level.seconds()),
+                    level.coins.to_string(),
+                )
+            },
+        );
+        let label =
+            |text, style, y| Widget::Label(Label::new(text, Point { x: center_x, y }, style));
+        Widget::Panel(Panel::new(
+            Rect::from_xy(100.0, 140.0, WIDTH - 200.0, 320.0),
+            vec![
+                label(t("level_complete.title"), TITLE_STYLE, 195.0),
+                label(name, TEXT_STYLE, 245.0),
+                label(
+                    t_with("level_complete.time", &[("seconds", &seconds)]),
+                    TEXT_STYLE,
+                    290.0,
+                ),
+                label(
+                    t_with("level_complete.coins", &[("coins", &coins)]),
+                    TEXT_STYLE,
+                    325.0,
+                ),
+                Widget::Button(Button::new(
+                    t("game_over.play_again"),
+                    Rect::from_xy(center_x - 110.0, 370.0, 220.0, 56.0),
+                    TEXT_STYLE,
+                    LevelCompleteButton::PlayAgain,
+                )),
+            ],
+        ))
+    }
+
+    fn play_again_pressed(&mut self) -> bool {
+        let mut pressed = false;
+        while let Ok(Some(point)) = self.clicks.try_next() {
+            pressed |= matches!(self.screen.hit(point), Some(LevelCompleteButton::PlayAgain));
+        }
+        pressed
+    }
+}
+
+impl Scene<Walk> for LevelComplete {
+    fn update(&mut self, walk: &mut Walk, keystate: &KeyState) -> SceneChange<Walk> {
+        if self.play_again_pressed() || keystate.is_pressed("Enter") {
+            walk.reset();
+            let background_music = walk.background_music.clone();
+            walk.fade_music_to(&background_music, Looping::Yes);
+            let fade = engine::updates_in(NEW_GAME_FADE_SECONDS);
+            SceneChange::Replace(Box::new(Ready), Transition::Fade(fade))
+        } else {
+            SceneChange::None
+        }
+    }
+
+    fn draw(&self, walk: &Walk, renderer: &Renderer) {
+        walk.draw(renderer);
+        renderer.tint(
+            &Rect::from_xy(0.0, 0.0, WIDTH, HEIGHT),
+            "rgb(100, 100, 100)",
+            BlendMode::Multiply,
+        );
+        self.screen.draw(renderer);
+        self.overlay_drawn.set(true);
+    }
+
+    /// The results never change once they have been drawn.
+    fn redraw(&self) -> Redraw {
+        if self.overlay_drawn.get() {
+            Redraw::Nothing
+        } else {
+            Redraw::Everything
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_name_existing_segments() {
+        let manifest = |segments: &[&str]| LevelManifest {
+            name: "Test".to_string(),
+            segments: segments.iter().map(ToString::to_string).collect(),
+        };
+        let level = Level::from_manifest(manifest(&["boost_pad", "spring"])).unwrap();
+        assert_eq!(level.generators.len(), 2);
+        assert!(Level::from_manifest(manifest(&["boost_pad", "lava"])).is_err());
+
+        let meadow = serde_json::from_str(include_str!("../../static/levels/meadow.json")).unwrap();
+        assert!(Level::from_manifest(meadow).is_ok());
+    }
+}
@@ -0,0 +1,79 @@
+//! The magnet power-up: coins near the boy speed up towards him until he
+//! picks them up.
+
+use crate::engine::{self, Point, Velocity, World};
+
+use super::Reward;
+
+/// How close, from center to center, a coin must be to be pulled.
+const MAGNET_RADIUS: f32 = 250.0;
+/// How much faster a pulled coin moves towards the boy on every update.
+const MAGNET_ACCELERATION: f32 = 1.5;
+const MAX_COIN_SPEED: f32 = 20.0;
+
+/// Accelerates every coin within reach of `target` towards it. Coins keep
+/// the speed they gained, so the pull builds up over several updates.
+pub(super) fn attract_coins(items: &mut World<Reward>, target: Point) {
+    let pulled: Vec<_> = items
+        .entities_with_data()
+        .filter(|(_, _, reward)| matches!(reward, Reward::Points(_)))
+        .filter_map(|(entity, bounding_box, _)| {
+            let center = bounding_box.center();
+            let dx = target.x - center.x;
+            let dy = target.y - center.y;
+            let distance = dx.hypot(dy);
+            (distance > 0.0 && distance < MAGNET_RADIUS).then_some((
+                entity,
+                dx / distance,
+                dy / distance,
+            ))
+        })
+        .collect();
+
+    let acceleration = MAGNET_ACCELERATION * engine::update_scale();
+    for (entity, direction_x, direction_y) in pulled {
+        let velocity = items.velocity(entity);
+        let x = velocity.x + direction_x * acceleration;
+        let y = velocity.y + direction_y * acceleration;
+        let speed = x.hypot(y);
+        let scale = if speed > MAX_COIN_SPEED {
+            MAX_COIN_SPEED / speed
+        } else {
+            1.0
+        };
+        items.set_velocity(
+            entity,
+            Velocity {
+                x: x * scale,
+                y: y * scale,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_coins_within_reach_are_pulled() {
+        let mut items = World::new();
+        let near = items
+            .spawn(Point { x: 100.0, y: 0.0 })
+            .with_data(Reward::Points(1))
+            .build();
+        let far = items
+            .spawn(Point {
+                x: MAGNET_RADIUS * 2.0,
+                y: 0.0,
+            })
+            .with_data(Reward::Points(1))
+            .build();
+
+        for _ in 0..100 {
+            attract_coins(&mut items, Point { x: 0.0, y: 0.0 });
+        }
+        assert_eq!(items.velocity(near).x, -MAX_COIN_SPEED);
+        assert_eq!(items.velocity(far).x, 0.0);
+    }
+}
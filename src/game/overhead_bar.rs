@@ -0,0 +1,98 @@
+//! Bars hanging at head height, which the boy has to slide under.
+
+use crate::engine::{Body, DebugCategory, Point, Rect, Renderer};
+
+use super::{red_hat_boy::RedHatBoy, Obstacle, ObstacleKind, ObstacleSnapshot, HEIGHT};
+
+/// A beam hanging on chains from the top of the screen. Its collider runs
+/// all the way up, so unlike a platform it is a ceiling: there is no top to
+/// land on and no jumping over it, and touching it anywhere knocks the boy
+/// out.
+#[derive(Debug, Clone)]
+pub(crate) struct OverheadBar {
+    bounding_box: Rect,
+}
+
+impl OverheadBar {
+    pub(crate) const WIDTH: f32 = 160.0;
+    /// How far above the bottom of the screen the underside of the beam is,
+    /// between the top of a sliding boy and that of a running one.
+    const CLEARANCE: f32 = 70.0;
+    const BEAM_HEIGHT: f32 = 30.0;
+    const CHAIN_WIDTH: f32 = 4.0;
+    const CHAIN_INSET: f32 = 20.0;
+
+    /// Hangs a bar over the floor, starting at `x`.
+    pub(crate) fn new(x: f32) -> Self {
+        Self::restore(Point { x, y: 0.0 })
+    }
+
+    pub(super) fn restore(position: Point) -> Self {
+        Self {
+            bounding_box: Rect::new(position, Self::WIDTH, HEIGHT - Self::CLEARANCE),
+        }
+    }
+}
+
+impl Body for OverheadBar {
+    fn bounding_box(&self) -> Rect {
+        self.bounding_box
+    }
+
+    fn move_by(&mut self, x: f32, y: f32) {
+        self.bounding_box.move_by(x, y);
+    }
+}
+
+impl Obstacle for OverheadBar {
+    fn kind(&self) -> ObstacleKind {
+        ObstacleKind::Ceiling
+    }
+
+    fn left(&self) -> f32 {
+        self.bounding_box.left()
+    }
+
+    fn right(&self) -> f32 {
+        self.bounding_box.right()
+    }
+
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        if boy.bounding_box().intersects(&self.bounding_box) {
+            boy.knock_out();
+        }
+    }
+
+    fn collision_boxes(&self) -> Vec<Rect> {
+        vec![self.bounding_box]
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        let beam_top = self.bounding_box.bottom() - Self::BEAM_HEIGHT;
+        for chain_x in [
+            self.bounding_box.x() + Self::CHAIN_INSET,
+            self.bounding_box.right() - Self::CHAIN_INSET - Self::CHAIN_WIDTH,
+        ] {
+            renderer.fill_rect(
+                &Rect::from_xy(chain_x, self.bounding_box.y(), Self::CHAIN_WIDTH, beam_top),
+                "dimgray",
+            );
+        }
+        renderer.fill_rect(
+            &Rect::from_xy(
+                self.bounding_box.x(),
+                beam_top,
+                Self::WIDTH,
+                Self::BEAM_HEIGHT,
+            ),
+            "saddlebrown",
+        );
+        renderer.draw_bounding_box(&self.bounding_box, DebugCategory::Barrier);
+    }
+
+    fn snapshot(&self) -> ObstacleSnapshot {
+        ObstacleSnapshot::OverheadBar {
+            position: self.bounding_box.position,
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Pools of water set into the floor, which the boy has to jump over.
+
+use crate::engine::{Body, DebugCategory, Point, Rect, Renderer};
+
+use super::{red_hat_boy::RedHatBoy, Obstacle, ObstacleKind, ObstacleSnapshot, HEIGHT};
+
+#[derive(Debug, Clone)]
+pub(crate) struct Pool {
+    bounding_box: Rect,
+}
+
+impl Pool {
+    /// How far above the bottom of the screen the water shows. It is drawn
+    /// over the boy, hiding him as he sinks.
+    const DEPTH: f32 = 30.0;
+    /// How far over the edge the middle of the boy can be before he falls in,
+    /// so that clipping a corner does not end the run.
+    const EDGE: f32 = 10.0;
+    const WAVE_LENGTH: f32 = 20.0;
+
+    /// Places a pool `width` pixels wide in the floor, starting at `x`.
+    pub(crate) fn new(x: f32, width: f32) -> Self {
+        Self::restore(
+            Point {
+                x,
+                y: HEIGHT - Self::DEPTH,
+            },
+            width,
+        )
+    }
+
+    pub(super) fn restore(position: Point, width: f32) -> Self {
+        Self {
+            bounding_box: Rect::new(position, width, Self::DEPTH),
+        }
+    }
+}
+
+impl Body for Pool {
+    fn bounding_box(&self) -> Rect {
+        self.bounding_box
+    }
+
+    fn move_by(&mut self, x: f32, y: f32) {
+        self.bounding_box.move_by(x, y);
+    }
+}
+
+impl Obstacle for Pool {
+    fn kind(&self) -> ObstacleKind {
+        ObstacleKind::Pool
+    }
+
+    fn left(&self) -> f32 {
+        self.bounding_box.left()
+    }
+
+    fn right(&self) -> f32 {
+        self.bounding_box.right()
+    }
+
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        let center = boy.bounding_box().center().x;
+        let over_water = center > self.left() + Self::EDGE && center < self.right() - Self::EDGE;
+        if over_water && boy.on_floor() {
+            boy.drown();
+        }
+    }
+
+    fn collision_boxes(&self) -> Vec<Rect> {
+        vec![self.bounding_box]
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        renderer.fill_rect(&self.bounding_box, "rgba(30, 100, 200, 0.85)");
+        let waves = (self.bounding_box.width / Self::WAVE_LENGTH) as u16;
+        for wave in 0..waves {
+            renderer.fill_rect(
+                &Rect::from_xy(
+                    self.bounding_box.x() + Self::WAVE_LENGTH * f32::from(wave),
+                    self.bounding_box.y(),
+                    Self::WAVE_LENGTH / 2.0,
+                    3.0,
+                ),
+                "lightblue",
+            );
+        }
+        renderer.draw_bounding_box(&self.bounding_box, DebugCategory::Barrier);
+    }
+
+    fn snapshot(&self) -> ObstacleSnapshot {
+        ObstacleSnapshot::Pool {
+            position: self.bounding_box.position,
+            width: self.bounding_box.width,
+        }
+    }
+}
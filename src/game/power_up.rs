@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{self, Entity, Point, Sprite, World};
+
+use super::{Collectible, Reward};
+
+const POWER_UP_SIZE: f32 = 32.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum PowerUpKind {
+    Invincibility,
+    SpeedBoost,
+    Shield,
+    Magnet,
+}
+
+impl PowerUpKind {
+    pub(crate) const ALL: [PowerUpKind; 4] = [
+        Self::Invincibility,
+        Self::SpeedBoost,
+        Self::Shield,
+        Self::Magnet,
+    ];
+
+    /// Returns how many updates the power-up lasts, or `None` if it lasts
+    /// until it is used up, like the shield absorbing a hit.
+    fn duration(self) -> Option<u16> {
+        let updates_per_second = engine::updates_per_second();
+        match self {
+            Self::Invincibility => Some(5 * updates_per_second),
+            Self::SpeedBoost => Some(3 * updates_per_second),
+            Self::Shield => None,
+            Self::Magnet => Some(6 * updates_per_second),
+        }
+    }
+
+    /// Returns the key of the kind's name in [`crate::i18n`].
+    pub(crate) const fn label_key(self) -> &'static str {
+        match self {
+            Self::Invincibility => "power_up.invincibility",
+            Self::SpeedBoost => "power_up.speed_boost",
+            Self::Shield => "power_up.shield",
+            Self::Magnet => "power_up.magnet",
+        }
+    }
+
+    pub(crate) const fn color(self) -> &'static str {
+        match self {
+            Self::Invincibility => "mediumpurple",
+            Self::SpeedBoost => "orangered",
+            Self::Shield => "deepskyblue",
+            Self::Magnet => "firebrick",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct ActivePowerUp {
+    kind: PowerUpKind,
+    remaining_frames: Option<u16>,
+}
+
+impl ActivePowerUp {
+    pub(crate) fn new(kind: PowerUpKind) -> Self {
+        Self {
+            kind,
+            remaining_frames: kind.duration(),
+        }
+    }
+
+    pub(crate) const fn kind(&self) -> PowerUpKind {
+        self.kind
+    }
+
+    pub(crate) fn remaining_seconds(&self) -> Option<f32> {
+        self.remaining_frames
+            .map(|frames| f32::from(frames) / f32::from(engine::updates_per_second()))
+    }
+
+    pub(crate) const fn is_expired(&self) -> bool {
+        matches!(self.remaining_frames, Some(0))
+    }
+
+    pub(crate) fn refresh(&mut self) {
+        self.remaining_frames = self.kind.duration();
+    }
+
+    pub(crate) fn update(&mut self) {
+        if let Some(frames) = &mut self.remaining_frames {
+            *frames = frames.saturating_sub(1);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PowerUp {
+    kind: PowerUpKind,
+    position: Point,
+}
+
+impl PowerUp {
+    pub(crate) fn new(kind: PowerUpKind, position: Point) -> Self {
+        Self { kind, position }
+    }
+}
+
+impl Collectible for PowerUp {
+    fn spawn(&self, world: &mut World<Reward>) -> Entity {
+        let kind = self.kind;
+        world
+            .spawn(self.position)
+            .with_collider(POWER_UP_SIZE, POWER_UP_SIZE)
+            .with_sprite(Sprite::new(move |renderer, bounding_box| {
+                let radius = POWER_UP_SIZE / 2.0;
+                let center = bounding_box.center();
+                renderer.fill_circle(&center, radius, kind.color());
+                renderer.fill_circle(&center, radius - 6.0, "white");
+                renderer.fill_circle(&center, radius - 10.0, kind.color());
+            }))
+            .with_data(Reward::PowerUp(kind))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_up_expires_after_its_duration() {
+        let mut power_up = ActivePowerUp::new(PowerUpKind::SpeedBoost);
+        for _ in 0..PowerUpKind::SpeedBoost.duration().unwrap() - 1 {
+            power_up.update();
+        }
+        assert!(!power_up.is_expired());
+        power_up.update();
+        assert!(power_up.is_expired());
+    }
+
+    #[test]
+    fn refreshing_a_power_up_restores_its_duration() {
+        let mut power_up = ActivePowerUp::new(PowerUpKind::Invincibility);
+        power_up.update();
+        power_up.refresh();
+        assert_eq!(power_up.remaining_seconds(), Some(5.0));
+    }
+
+    #[test]
+    fn shields_last_until_they_are_used() {
+        let mut power_up = ActivePowerUp::new(PowerUpKind::Shield);
+        for _ in 0..10 * engine::updates_per_second() {
+            power_up.update();
+        }
+        assert!(!power_up.is_expired());
+        assert_eq!(power_up.remaining_seconds(), None);
+    }
+}
@@ -0,0 +1,78 @@
+//! A strip along the top of the screen that shows the obstacles coming up
+//! beyond its right edge, so that there is time to react at high speed.
+//!
+//! The strip stands for the stretch of the world just past the screen: its
+//! left end is the right edge of the screen and its right end is
+//! [`LOOKAHEAD`] further, so markers slide left as obstacles approach.
+
+use crate::engine::{Rect, Renderer};
+
+use super::{ObstacleKind, Walk, WIDTH};
+
+const LOOKAHEAD: f32 = WIDTH;
+const MAX_MARKERS: usize = 5;
+const STRIP: Rect = Rect::from_xy(0.0, 0.0, WIDTH, 6.0);
+const MARKER_WIDTH: f32 = 8.0;
+const STRIP_COLOR: &str = "rgba(0, 0, 0, 0.3)";
+
+const fn marker_color(kind: ObstacleKind) -> &'static str {
+    match kind {
+        ObstacleKind::Platform => "steelblue",
+        ObstacleKind::Stone => "gray",
+        ObstacleKind::Enemy => "crimson",
+        ObstacleKind::BoostPad => "darkorange",
+        ObstacleKind::Spring => "limegreen",
+        ObstacleKind::Pool => "royalblue",
+        ObstacleKind::Saw => "silver",
+        ObstacleKind::Ceiling => "saddlebrown",
+        ObstacleKind::Goal => "crimson",
+    }
+}
+
+/// Returns where on the strip an obstacle starting at `left` is marked, or
+/// `None` if it is on the screen that ends at `screen_right` or too far away.
+fn marker_x(left: f32, screen_right: f32) -> Option<f32> {
+    let ahead = left - screen_right;
+    (0.0..LOOKAHEAD)
+        .contains(&ahead)
+        .then(|| ahead / LOOKAHEAD * (STRIP.width - MARKER_WIDTH))
+}
+
+pub(super) fn draw(walk: &Walk, renderer: &Renderer) {
+    let screen_right = walk.camera.x() + WIDTH;
+    let mut markers: Vec<_> = walk
+        .obstacles
+        .values()
+        .filter_map(|obstacle| {
+            marker_x(obstacle.left(), screen_right).map(|x| (x, obstacle.kind()))
+        })
+        .collect();
+    markers.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    markers.truncate(MAX_MARKERS);
+    if markers.is_empty() {
+        return;
+    }
+
+    renderer.fill_rect(&STRIP, STRIP_COLOR);
+    for (x, kind) in markers {
+        let marker = Rect::from_xy(STRIP.x() + x, STRIP.y(), MARKER_WIDTH, STRIP.height);
+        renderer.fill_rect(&marker, marker_color(kind));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_obstacles_just_past_the_screen_are_marked() {
+        let screen_right = 1000.0;
+        assert_eq!(marker_x(900.0, screen_right), None);
+        assert_eq!(marker_x(1000.0, screen_right), Some(0.0));
+        assert_eq!(
+            marker_x(1000.0 + LOOKAHEAD / 2.0, screen_right),
+            Some((STRIP.width - MARKER_WIDTH) / 2.0)
+        );
+        assert_eq!(marker_x(1000.0 + LOOKAHEAD, screen_right), None);
+    }
+}
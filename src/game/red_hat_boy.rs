@@ -1,69 +1,372 @@
-use web_sys::HtmlImageElement;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{
+    self, Audio, Cell, CollisionMask, DebugCategory, ImageSource, Point, Rect, Renderer, Shape,
+    Sound, Transform,
+};
+
+use self::states::{
+    Attacking, Drowning, Falling, Hurt, Idle, Jumping, KnockedOut, Running, Sliding, State,
+    WallSliding,
+};
+
+use super::{
+    power_up::{ActivePowerUp, PowerUpKind},
+    Sheet,
+};
+
+const SPEED_BOOST_FACTOR: f32 = 2.0;
+pub(super) const STARTING_POINT: f32 = -20.0;
+/// How many pixels the boy runs per update, unless told otherwise.
+pub(super) const RUNNING_SPEED: f32 = 4.0;
+
+/// A temporary change to how fast the boy runs, such as the push of a boost
+/// pad. The boy's running speed is multiplied by the factors of every
+/// modifier in effect, each of which eases back to no change over its last
+/// `ramp_frames` updates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(super) struct SpeedModifier {
+    factor: f32,
+    remaining_frames: u16,
+    ramp_frames: u16,
+}
+
+impl SpeedModifier {
+    pub(super) const fn new(factor: f32, frames: u16, ramp_frames: u16) -> Self {
+        Self {
+            factor,
+            remaining_frames: frames,
+            ramp_frames,
+        }
+    }
+
+    fn factor(&self) -> f32 {
+        if self.remaining_frames >= self.ramp_frames {
+            self.factor
+        } else {
+            let ramp = f32::from(self.remaining_frames) / f32::from(self.ramp_frames);
+            1.0 + (self.factor - 1.0) * ramp
+        }
+    }
+
+    /// Whether `other` comes from the same source, so that it restarts this
+    /// modifier rather than stacking on top of it.
+    fn same_source(&self, other: &SpeedModifier) -> bool {
+        self.factor == other.factor && self.ramp_frames == other.ramp_frames
+    }
+}
+
+/// The part of the boy that changes during a run, leaving out his sprites
+/// and sounds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct BoySnapshot {
+    state: BoyState,
+    context: states::ContextSnapshot,
+    power_ups: Vec<ActivePowerUp>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+enum BoyState {
+    Idle,
+    Running,
+    Sliding,
+    Attacking,
+    Jumping,
+    WallSliding,
+    Falling,
+    Drowning,
+    Hurt,
+    KnockedOut,
+}
 
-use crate::engine::{Audio, Cell, Point, Rect, Renderer, Sound};
+/// A frame of one of the boy's animations, looked up in the sprite sheet once
+/// when he is loaded.
+#[derive(Debug)]
+struct AnimationFrame {
+    name: String,
+    cell: Cell,
+    mask: CollisionMask,
+}
 
-use self::states::{Falling, Idle, Jumping, KnockedOut, Running, Sliding, State};
+/// The frames of every animation, indexed by the animation and then by the
+/// frame's position in it.
+#[derive(Debug)]
+pub(super) struct Animations(Vec<Vec<AnimationFrame>>);
+
+impl Animations {
+    pub(super) fn load(sheet: &Sheet, image: &ImageSource) -> Result<Self> {
+        states::animations()
+            .map(|(animation, frames)| {
+                (1..=frames / 3 + 1)
+                    .map(|index| {
+                        let name = format!("{animation} ({index}).png");
+                        let cell = *sheet
+                            .frames
+                            .get(&name)
+                            .ok_or_else(|| anyhow!("the sprite sheet has no frame {name}"))?;
+                        let frame = &cell.frame;
+                        let area = Rect::from_xy(frame.x, frame.y, frame.w, frame.h);
+                        let mask = CollisionMask::from_image(image, &area)?;
+                        Ok(AnimationFrame { name, cell, mask })
+                    })
+                    .collect()
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+}
 
-use super::Sheet;
+/// The sounds the boy makes himself.
+#[derive(Debug, Clone)]
+pub(super) struct BoySounds {
+    pub(super) jump: Sound,
+    pub(super) bounce: Sound,
+    pub(super) splash: Sound,
+}
 
 #[derive(Debug)]
 pub(crate) struct RedHatBoy {
-    state_machine: StateMachine,
+    /// Only `None` while [`RedHatBoy::transition`] has moved the state out.
+    state_machine: Option<StateMachine>,
     sprite_sheet: Sheet,
-    image: HtmlImageElement,
+    image: ImageSource,
+    animations: Rc<Animations>,
+    pixel_perfect_collisions: bool,
+    /// Hits only make the boy stumble, like with a shield.
+    infinite_lives: bool,
+    big_head: bool,
+    power_ups: Vec<ActivePowerUp>,
+    max_lives: u8,
+    running_speed: f32,
+    audio: Audio,
+    sounds: BoySounds,
 }
 
 impl RedHatBoy {
     pub(super) fn new(
         sheet: Sheet,
-        image: HtmlImageElement,
+        image: ImageSource,
+        animations: Rc<Animations>,
         audio: Audio,
-        jump_sound: Sound,
+        sounds: BoySounds,
+        lives: u8,
+        running_speed: f32,
     ) -> Self {
         Self {
-            state_machine: State::new(audio, jump_sound).into(),
+            state_machine: Some(State::new(lives, running_speed).into()),
             sprite_sheet: sheet,
             image,
+            animations,
+            pixel_perfect_collisions: false,
+            infinite_lives: false,
+            big_head: false,
+            power_ups: vec![],
+            max_lives: lives,
+            running_speed,
+            audio,
+            sounds,
         }
     }
 
-    pub(super) fn reset(boy: Self) -> Self {
-        let frame = boy.state_machine.as_frame();
-        let audio = frame.audio().clone();
-        let jump_sound = frame.jump_sound().clone();
-        Self::new(boy.sprite_sheet, boy.image, audio, jump_sound)
+    pub(super) fn reset(&self) -> Self {
+        let mut boy = Self::new(
+            self.sprite_sheet.clone(),
+            self.image.clone(),
+            Rc::clone(&self.animations),
+            self.audio.clone(),
+            self.sounds.clone(),
+            self.max_lives,
+            self.running_speed,
+        );
+        boy.pixel_perfect_collisions = self.pixel_perfect_collisions;
+        boy.infinite_lives = self.infinite_lives;
+        boy.big_head = self.big_head;
+        boy
     }
 
-    pub(super) fn walking_speed(&self) -> i16 {
-        self.state_machine.as_frame().walking_speed()
+    /// Draws the boy from `sheet` and `image` from now on, e.g. after they
+    /// were edited.
+    pub(super) fn set_sprites(&mut self, sheet: Sheet, image: ImageSource) -> Result<()> {
+        self.animations = Rc::new(Animations::load(&sheet, &image)?);
+        self.sprite_sheet = sheet;
+        self.image = image;
+        Ok(())
     }
 
-    pub(super) fn velocity_y(&self) -> i16 {
-        self.state_machine.as_frame().velocity_y()
+    /// Draws the boy from `image` from now on. It must have the same layout
+    /// and transparency as the current image, e.g. be a recolored copy.
+    pub(super) fn set_image(&mut self, image: ImageSource) {
+        self.image = image;
+    }
+
+    pub(super) fn snapshot(&self) -> BoySnapshot {
+        let (state, context) = match self.state_machine() {
+            StateMachine::Idle(state) => (BoyState::Idle, state.snapshot()),
+            StateMachine::Running(state) => (BoyState::Running, state.snapshot()),
+            StateMachine::Sliding(state) => (BoyState::Sliding, state.snapshot()),
+            StateMachine::Attacking(state) => (BoyState::Attacking, state.snapshot()),
+            StateMachine::Jumping(state) => (BoyState::Jumping, state.snapshot()),
+            StateMachine::WallSliding(state) => (BoyState::WallSliding, state.snapshot()),
+            StateMachine::Falling(state) => (BoyState::Falling, state.snapshot()),
+            StateMachine::Hurt(state) => (BoyState::Hurt, state.snapshot()),
+            StateMachine::Drowning(state) => (BoyState::Drowning, state.snapshot()),
+            StateMachine::KnockedOut(state) => (BoyState::KnockedOut, state.snapshot()),
+        };
+        BoySnapshot {
+            state,
+            context,
+            power_ups: self.power_ups.clone(),
+        }
+    }
+
+    /// Returns a boy in the state captured by `snapshot`, drawn and heard
+    /// like this one.
+    pub(super) fn restore(&self, snapshot: &BoySnapshot) -> Result<Self> {
+        let state_machine = states::restore(snapshot.state, &snapshot.context, self.running_speed)?;
+        Ok(Self {
+            state_machine: Some(state_machine),
+            sprite_sheet: self.sprite_sheet.clone(),
+            image: self.image.clone(),
+            animations: Rc::clone(&self.animations),
+            pixel_perfect_collisions: self.pixel_perfect_collisions,
+            infinite_lives: self.infinite_lives,
+            big_head: self.big_head,
+            power_ups: snapshot.power_ups.clone(),
+            max_lives: self.max_lives,
+            running_speed: self.running_speed,
+            audio: self.audio.clone(),
+            sounds: self.sounds.clone(),
+        })
+    }
+
+    pub(super) fn x(&self) -> f32 {
+        self.state_machine().as_frame().position().x
+    }
+
+    pub(super) fn state_name(&self) -> &'static str {
+        self.state_machine().name()
+    }
+
+    pub(super) fn frame(&self) -> u8 {
+        self.state_machine().as_frame().frame()
+    }
+
+    pub(super) fn position(&self) -> Point {
+        self.state_machine().as_frame().position()
+    }
+
+    pub(super) fn velocity(&self) -> Point {
+        self.state_machine().as_frame().velocity()
+    }
+
+    pub(super) fn velocity_y(&self) -> f32 {
+        self.state_machine().as_frame().velocity_y()
     }
 
     pub(super) fn knocked_out(&self) -> bool {
-        self.state_machine.knocked_out()
+        self.state_machine().knocked_out()
+    }
+
+    pub(super) fn jumping(&self) -> bool {
+        matches!(self.state_machine(), StateMachine::Jumping(_))
+    }
+
+    /// Whether the boy is off the ground of his own accord, so that he can
+    /// grab a wall he runs into.
+    pub(super) fn airborne(&self) -> bool {
+        matches!(
+            self.state_machine(),
+            StateMachine::Jumping(_) | StateMachine::WallSliding(_)
+        )
+    }
+
+    pub(super) fn sliding(&self) -> bool {
+        matches!(self.state_machine(), StateMachine::Sliding(_))
+    }
+
+    pub(super) fn attacking(&self) -> bool {
+        matches!(self.state_machine(), StateMachine::Attacking(_))
+    }
+
+    pub(super) fn lives(&self) -> u8 {
+        self.state_machine().as_frame().lives()
+    }
+
+    pub(super) fn max_lives(&self) -> u8 {
+        self.max_lives
     }
 
     pub(super) fn update(&mut self) {
-        self.state_machine = self.state_machine.clone().update();
+        self.transition(Event::Update);
+        if self.has_power_up(PowerUpKind::SpeedBoost) {
+            let boost = self.state_machine().as_frame().walking_speed()
+                * (SPEED_BOOST_FACTOR - 1.0)
+                * engine::update_scale();
+            self.carry(boost);
+        }
+    }
+
+    pub(super) fn power_ups(&self) -> &[ActivePowerUp] {
+        &self.power_ups
+    }
+
+    /// Whether a shield will absorb the next knockout.
+    pub(super) fn shielded(&self) -> bool {
+        self.has_power_up(PowerUpKind::Shield)
+    }
+
+    /// Whether nearby coins are pulled towards the boy.
+    pub(super) fn magnetic(&self) -> bool {
+        self.has_power_up(PowerUpKind::Magnet)
     }
 
-    fn frame_name(&self) -> String {
-        let frame = self.state_machine.as_frame();
-        format!("{} ({}).png", frame.frame_name(), (frame.frame() / 3) + 1)
+    fn has_power_up(&self, kind: PowerUpKind) -> bool {
+        self.power_ups
+            .iter()
+            .any(|power_up| power_up.kind() == kind)
     }
 
-    fn current_sprite(&self) -> Option<&Cell> {
-        self.sprite_sheet.frames.get(&self.frame_name())
+    pub(super) fn activate_power_up(&mut self, kind: PowerUpKind) {
+        match self
+            .power_ups
+            .iter_mut()
+            .find(|power_up| power_up.kind() == kind)
+        {
+            Some(power_up) => power_up.refresh(),
+            None => self.power_ups.push(ActivePowerUp::new(kind)),
+        }
+    }
+
+    pub(super) fn update_power_ups(&mut self) {
+        for power_up in &mut self.power_ups {
+            power_up.update();
+        }
+        self.power_ups.retain(|power_up| !power_up.is_expired());
+    }
+
+    fn current_frame(&self) -> &AnimationFrame {
+        let frame = self.state_machine().as_frame();
+        &self.animations.0[frame.animation()][usize::from(frame.frame() / 3)]
     }
 
+    /// Returns the current frame's hitbox, or a fixed inset of the frame if
+    /// it has none.
     pub(super) fn bounding_box(&self) -> Rect {
-        const X_OFFSET: i16 = 18;
-        const Y_OFFSET: i16 = 14;
-        const WIDTH_OFFSET: i16 = 28;
+        const X_OFFSET: f32 = 18.0;
+        const Y_OFFSET: f32 = 14.0;
+        const WIDTH_OFFSET: f32 = 28.0;
         let mut bounding_box = self.destination_box();
+        if let Some(hitbox) = self.current_frame().cell.hitbox {
+            return Rect::from_xy(
+                bounding_box.x() + hitbox.x,
+                bounding_box.y() + hitbox.y,
+                hitbox.w,
+                hitbox.h,
+            );
+        }
         bounding_box.set_x(bounding_box.x() + X_OFFSET);
         bounding_box.width -= WIDTH_OFFSET;
         bounding_box.set_y(bounding_box.y() + Y_OFFSET);
@@ -71,9 +374,45 @@ impl RedHatBoy {
         bounding_box
     }
 
+    /// Makes [`RedHatBoy::collides_with`] test the solid pixels of the boy
+    /// against those of obstacles that have a collision mask.
+    pub(super) fn set_pixel_perfect_collisions(&mut self, enabled: bool) {
+        self.pixel_perfect_collisions = enabled;
+    }
+
+    pub(super) fn set_infinite_lives(&mut self, enabled: bool) {
+        self.infinite_lives = enabled;
+    }
+
+    pub(super) fn set_big_head(&mut self, enabled: bool) {
+        self.big_head = enabled;
+    }
+
+    /// Returns whether the boy touches `shape`. When pixel perfect
+    /// collisions are on and there is a `mask` drawn into an area, only the
+    /// solid pixels of the mask count instead.
+    pub(super) fn collides_with(
+        &self,
+        shape: &Shape,
+        mask: Option<(&CollisionMask, &Rect)>,
+    ) -> bool {
+        match (self.pixel_perfect_collisions, mask) {
+            (true, Some((mask, area))) => {
+                let destination_box = self.destination_box();
+                destination_box.intersects(area)
+                    && self.current_frame().mask.overlaps(
+                        destination_box.position,
+                        mask,
+                        area.position,
+                    )
+            }
+            _ => shape.intersects(&self.bounding_box()),
+        }
+    }
+
     fn destination_box(&self) -> Rect {
-        let frame = self.state_machine.as_frame();
-        let sprite = self.current_sprite().expect("cell not found");
+        let frame = self.state_machine().as_frame();
+        let sprite = &self.current_frame().cell;
 
         Rect::from_xy(
             frame.position().x + sprite.sprite_source_size.x,
@@ -84,71 +423,323 @@ impl RedHatBoy {
     }
 
     pub(super) fn draw(&self, renderer: &Renderer) {
-        let sprite = self.current_sprite().expect("cell not found");
+        const BLINK_SECONDS: f32 = 1.0 / 15.0;
+        const BLINK_ALPHA: f64 = 0.3;
+        const INVINCIBLE_ALPHA: f64 = 0.7;
+        const SHIELD_ALPHA: f64 = 0.3;
+        const SHIELD_MARGIN: f32 = 8.0;
+
+        let invulnerable_frames = self.state_machine().as_frame().invulnerable_frames();
+        let alpha = if (invulnerable_frames / engine::updates_in(BLINK_SECONDS)) % 2 == 1 {
+            BLINK_ALPHA
+        } else if self.has_power_up(PowerUpKind::Invincibility) {
+            INVINCIBLE_ALPHA
+        } else {
+            1.0
+        };
+
+        let speed_factor = self.state_machine().as_frame().speed_factor();
+        if speed_factor > 1.0 {
+            self.draw_wind_streaks(renderer, speed_factor);
+        }
+
+        let sprite = &self.current_frame().cell;
+        renderer.with_alpha(alpha, |renderer| {
+            renderer.draw_image(
+                &self.image,
+                &Rect::from_xy(
+                    sprite.frame.x,
+                    sprite.frame.y,
+                    sprite.frame.w,
+                    sprite.frame.h,
+                ),
+                &self.destination_box(),
+                &Transform::default(),
+            );
+            if self.big_head {
+                self.draw_big_head(renderer);
+            }
+        });
+        if self.attacking() {
+            self.draw_swoosh(renderer);
+        }
+        if self.shielded() {
+            let bounding_box = self.bounding_box();
+            let radius = bounding_box.width.max(bounding_box.height) / 2.0 + SHIELD_MARGIN;
+            renderer.with_alpha(SHIELD_ALPHA, |renderer| {
+                renderer.fill_circle(&bounding_box.center(), radius, PowerUpKind::Shield.color());
+            });
+        }
+        renderer.draw_bounding_box(&self.bounding_box(), DebugCategory::Player);
+    }
+
+    /// Draws the top of the sprite, where the head is, again and bigger over
+    /// the neck.
+    fn draw_big_head(&self, renderer: &Renderer) {
+        const HEAD_FRACTION: f32 = 0.45;
+        const SCALE: f32 = 1.6;
+
+        let sprite = &self.current_frame().cell;
+        let destination = self.destination_box();
+        let head_height = sprite.frame.h * HEAD_FRACTION;
+        let (width, height) = (sprite.frame.w * SCALE, head_height * SCALE);
         renderer.draw_image(
             &self.image,
+            &Rect::from_xy(sprite.frame.x, sprite.frame.y, sprite.frame.w, head_height),
             &Rect::from_xy(
-                sprite.frame.x,
-                sprite.frame.y,
-                sprite.frame.w,
-                sprite.frame.h,
+                destination.center().x - width / 2.0,
+                destination.y() + head_height - height,
+                width,
+                height,
             ),
-            &self.destination_box(),
+            &Transform::default(),
         );
-        renderer.draw_bounding_box(&self.bounding_box());
+    }
+
+    /// Draws an arc in front of the boy's feet while he attacks.
+    fn draw_swoosh(&self, renderer: &Renderer) {
+        const SWOOSH_SEGMENTS: u8 = 8;
+        const SWOOSH_RADIUS: f32 = 30.0;
+        const SWOOSH_WIDTH: f32 = 8.0;
+
+        let bounding_box = self.bounding_box();
+        let center = Point {
+            x: bounding_box.right(),
+            y: bounding_box.bottom() - SWOOSH_RADIUS,
+        };
+        let arc = |radius: f32, segment: u8| {
+            let angle =
+                std::f32::consts::PI * (f32::from(segment) / f32::from(SWOOSH_SEGMENTS) - 0.5);
+            Point {
+                x: center.x + radius * angle.cos(),
+                y: center.y + radius * angle.sin(),
+            }
+        };
+        let points: Vec<_> = (0..=SWOOSH_SEGMENTS)
+            .map(|segment| arc(SWOOSH_RADIUS, segment))
+            .chain(
+                (0..=SWOOSH_SEGMENTS)
+                    .rev()
+                    .map(|segment| arc(SWOOSH_RADIUS - SWOOSH_WIDTH, segment)),
+            )
+            .collect();
+        renderer.with_alpha(0.6, |renderer| renderer.fill_polygon(&points, "white"));
+    }
+
+    /// Draws lines trailing behind the boy, fading as a speed boost wears
+    /// off.
+    fn draw_wind_streaks(&self, renderer: &Renderer, speed_factor: f32) {
+        const STREAK_COUNT: u8 = 4;
+        const STREAK_LENGTH: f32 = 40.0;
+        const STREAK_SPACING: f32 = 24.0;
+        const STREAK_FLICKER: f32 = 4.0;
+
+        let bounding_box = self.bounding_box();
+        let frame = self.state_machine().as_frame().frame();
+        let alpha = f64::from((speed_factor - 1.0).min(1.0));
+        renderer.with_alpha(alpha, |renderer| {
+            for streak in 0..STREAK_COUNT {
+                let shift = f32::from((frame / 2 + streak * 3) % 5) * STREAK_FLICKER;
+                renderer.fill_rect(
+                    &Rect::from_xy(
+                        bounding_box.left() - STREAK_LENGTH - shift,
+                        bounding_box.top() + STREAK_SPACING * f32::from(streak + 1),
+                        STREAK_LENGTH,
+                        2.0,
+                    ),
+                    "white",
+                );
+            }
+        });
+    }
+
+    /// Returns the name of the sprite frame being shown and where it is
+    /// drawn, for other players to draw a ghost of the boy.
+    pub(super) fn ghost_frame(&self) -> (String, Point) {
+        (
+            self.current_frame().name.clone(),
+            self.destination_box().position,
+        )
+    }
+
+    /// Draws a translucent copy of the boy showing `frame` at `position`.
+    pub(super) fn draw_ghost(&self, renderer: &Renderer, frame: &str, position: Point) {
+        const GHOST_ALPHA: f64 = 0.4;
+
+        let Some(sprite) = self.sprite_sheet.frames.get(frame) else {
+            return;
+        };
+        renderer.with_alpha(GHOST_ALPHA, |renderer| {
+            renderer.draw_image(
+                &self.image,
+                &Rect::from_xy(
+                    sprite.frame.x,
+                    sprite.frame.y,
+                    sprite.frame.w,
+                    sprite.frame.h,
+                ),
+                &Rect::new(position, sprite.frame.w, sprite.frame.h),
+                &Transform::default(),
+            );
+        });
+    }
+
+    pub(super) fn hurt(&self) -> bool {
+        matches!(self.state_machine(), StateMachine::Hurt(_))
+    }
+
+    fn state_machine(&self) -> &StateMachine {
+        self.state_machine
+            .as_ref()
+            .expect("the boy is always in a state outside transitions")
+    }
+
+    /// Moves the state out to run the transition for `event`, so that it does
+    /// not need to be cloned.
+    fn transition(&mut self, event: Event) {
+        if let Some(state_machine) = self.state_machine.take() {
+            self.state_machine = Some(state_machine.transition(event));
+        }
     }
 
     pub(super) fn run_right(&mut self) {
-        self.state_machine = self.state_machine.clone().transition(Event::Run);
+        self.transition(Event::Run);
     }
 
     pub(super) fn slide(&mut self) {
-        self.state_machine = self.state_machine.clone().transition(Event::Slide);
+        self.transition(Event::Slide);
+    }
+
+    pub(super) fn attack(&mut self) {
+        self.transition(Event::Attack);
     }
 
     pub(super) fn jump(&mut self) {
-        self.state_machine = self.state_machine.clone().transition(Event::Jump);
+        let was_jumping = self.jumping();
+        self.transition(Event::Jump);
+        if !was_jumping && self.jumping() {
+            if let Err(err) = self.audio.play_sound(&self.sounds.jump) {
+                warn!("Error playing jump sound: {err:#?}");
+            }
+        }
     }
 
-    pub(super) fn land_on(&mut self, position: i16) {
-        self.state_machine = self
-            .state_machine
-            .clone()
-            .transition(Event::Land { position });
+    pub(super) fn release_jump(&mut self) {
+        self.transition(Event::ReleaseJump);
+    }
+
+    /// Whether the boy stands on the floor, rather than on a platform or in
+    /// the air.
+    pub(super) fn on_floor(&self) -> bool {
+        self.state_machine().as_frame().position().y >= states::FLOOR
+    }
+
+    pub(super) fn drown(&mut self) {
+        let was_drowning = self.drowning();
+        self.transition(Event::Drown);
+        if !was_drowning && self.drowning() {
+            if let Err(err) = self.audio.play_sound(&self.sounds.splash) {
+                warn!("Error playing splash sound: {err:#?}");
+            }
+        }
+    }
+
+    fn drowning(&self) -> bool {
+        matches!(self.state_machine(), StateMachine::Drowning(_))
+    }
+
+    /// Launches the boy upwards at `velocity`, which is negative like every
+    /// upward velocity.
+    pub(super) fn bounce(&mut self, velocity: f32) {
+        self.transition(Event::Bounce { velocity });
+        if let Err(err) = self.audio.play_sound(&self.sounds.bounce) {
+            warn!("Error playing bounce sound: {err:#?}");
+        }
+    }
+
+    pub(super) fn land_on(&mut self, position: f32) {
+        self.transition(Event::Land { position });
+    }
+
+    pub(super) fn carry(&mut self, delta_x: f32) {
+        self.transition(Event::Carry { delta_x });
+    }
+
+    /// Stops the boy against a wall that he is `depth` pixels into, so that he
+    /// slides down it until he kicks off with a jump.
+    pub(super) fn grab_wall(&mut self, depth: f32) {
+        self.transition(Event::GrabWall);
+        self.carry(-depth);
+    }
+
+    pub(super) fn boost(&mut self, modifier: SpeedModifier) {
+        self.transition(Event::Boost(modifier));
+    }
+
+    /// Whether hits are ignored for a moment after the boy was hurt.
+    pub(super) fn invulnerable(&self) -> bool {
+        self.state_machine().as_frame().invulnerable_frames() > 0
     }
 
     pub(super) fn knock_out(&mut self) {
-        self.state_machine = self.state_machine.clone().transition(Event::KnockOut);
+        if self.has_power_up(PowerUpKind::Invincibility) || self.invulnerable() {
+            return;
+        }
+        if self.shielded() {
+            self.power_ups
+                .retain(|power_up| power_up.kind() != PowerUpKind::Shield);
+            self.transition(Event::Stumble);
+        } else if self.infinite_lives {
+            self.transition(Event::Stumble);
+        } else {
+            self.transition(Event::KnockOut);
+        }
     }
 }
 
 trait Frame {
-    fn frame_name(&self) -> &'static str;
+    /// The index of the animation in [`Animations`].
+    fn animation(&self) -> usize;
     fn frame(&self) -> u8;
     fn position(&self) -> Point;
-    fn velocity_y(&self) -> i16;
-    fn walking_speed(&self) -> i16;
-    fn audio(&self) -> &Audio;
-    fn jump_sound(&self) -> &Sound;
+    fn velocity(&self) -> Point;
+    fn velocity_y(&self) -> f32;
+    fn walking_speed(&self) -> f32;
+    /// How much faster than usual the boy runs because of speed modifiers.
+    fn speed_factor(&self) -> f32;
+    fn lives(&self) -> u8;
+    fn invulnerable_frames(&self) -> u16;
 }
 
 #[derive(Debug, Clone, Copy)]
 enum Event {
     Run,
     Slide,
+    Attack,
     Jump,
-    Land { position: i16 },
+    ReleaseJump,
+    Bounce { velocity: f32 },
+    Land { position: f32 },
+    Carry { delta_x: f32 },
+    GrabWall,
+    Boost(SpeedModifier),
     KnockOut,
+    Stumble,
+    Drown,
     Update,
 }
 
-#[derive(Debug, Clone, derive_more::From)]
+#[derive(Debug, derive_more::From)]
 enum StateMachine {
     Idle(State<Idle>),
     Running(State<Running>),
     Sliding(State<Sliding>),
+    Attacking(State<Attacking>),
     Jumping(State<Jumping>),
+    WallSliding(State<WallSliding>),
     Falling(State<Falling>),
+    Hurt(State<Hurt>),
+    Drowning(State<Drowning>),
     KnockedOut(State<KnockedOut>),
 }
 
@@ -158,12 +749,31 @@ impl StateMachine {
             Self::Idle(state) => state,
             Self::Running(state) => state,
             Self::Sliding(state) => state,
+            Self::Attacking(state) => state,
             Self::Jumping(state) => state,
+            Self::WallSliding(state) => state,
             Self::Falling(state) => state,
+            Self::Hurt(state) => state,
+            Self::Drowning(state) => state,
             Self::KnockedOut(state) => state,
         }
     }
 
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Idle(_) => "Idle",
+            Self::Running(_) => "Running",
+            Self::Sliding(_) => "Sliding",
+            Self::Attacking(_) => "Attacking",
+            Self::Jumping(_) => "Jumping",
+            Self::WallSliding(_) => "WallSliding",
+            Self::Falling(_) => "Falling",
+            Self::Hurt(_) => "Hurt",
+            Self::Drowning(_) => "Drowning",
+            Self::KnockedOut(_) => "KnockedOut",
+        }
+    }
+
     fn knocked_out(&self) -> bool {
         matches!(self, Self::KnockedOut(_))
     }
@@ -175,46 +785,99 @@ impl StateMachine {
             (Self::Running(state), Event::Slide) => state.slide(),
             (Self::Sliding(state), Event::Slide) => state.slide(),
 
+            (Self::Running(state), Event::Attack) => state.attack(),
+
             (Self::Running(state), Event::Jump) => state.jump(),
+            (Self::Jumping(state), Event::ReleaseJump) => state.release_jump(),
+            (Self::WallSliding(state), Event::Jump) => state.kick_off(),
+
+            (Self::Running(state), Event::Bounce { velocity }) => state.bounce(velocity),
+            (Self::Sliding(state), Event::Bounce { velocity }) => state.bounce(velocity),
+            (Self::Attacking(state), Event::Bounce { velocity }) => state.bounce(velocity),
+            (Self::Jumping(state), Event::Bounce { velocity }) => state.bounce(velocity),
+            (Self::WallSliding(state), Event::Bounce { velocity }) => state.bounce(velocity),
+
+            (Self::Jumping(state), Event::GrabWall) => state.grab_wall(),
 
             (Self::Running(state), Event::Land { position }) => state.land_on(position),
             (Self::Sliding(state), Event::Land { position }) => state.land_on(position),
+            (Self::Attacking(state), Event::Land { position }) => state.land_on(position),
             (Self::Jumping(state), Event::Land { position }) => state.land_on(position),
+            (Self::WallSliding(state), Event::Land { position }) => state.land_on(position),
             (Self::Falling(state), Event::Land { position }) => state.land_on(position),
-
-            (Self::Running(state), Event::KnockOut) => state.knock_out(),
-            (Self::Sliding(state), Event::KnockOut) => state.knock_out(),
-            (Self::Jumping(state), Event::KnockOut) => state.knock_out(),
+            (Self::Hurt(state), Event::Land { position }) => state.land_on(position),
+
+            (Self::Running(state), Event::Carry { delta_x }) => state.carry(delta_x),
+            (Self::Sliding(state), Event::Carry { delta_x }) => state.carry(delta_x),
+            (Self::Attacking(state), Event::Carry { delta_x }) => state.carry(delta_x),
+            (Self::Jumping(state), Event::Carry { delta_x }) => state.carry(delta_x),
+            (Self::WallSliding(state), Event::Carry { delta_x }) => state.carry(delta_x),
+            (Self::Falling(state), Event::Carry { delta_x }) => state.carry(delta_x),
+            (Self::Hurt(state), Event::Carry { delta_x }) => state.carry(delta_x),
+
+            (Self::Running(state), Event::Boost(modifier)) => state.boost(modifier),
+            (Self::Sliding(state), Event::Boost(modifier)) => state.boost(modifier),
+            (Self::Attacking(state), Event::Boost(modifier)) => state.boost(modifier),
+            (Self::Jumping(state), Event::Boost(modifier)) => state.boost(modifier),
+
+            (Self::Running(state), Event::KnockOut) => state.hit(),
+            (Self::Sliding(state), Event::KnockOut) => state.hit(),
+            (Self::Attacking(state), Event::KnockOut) => state.hit(),
+            (Self::Jumping(state), Event::KnockOut) => state.hit(),
+            (Self::WallSliding(state), Event::KnockOut) => state.hit(),
+
+            (Self::Running(state), Event::Stumble) => state.stumble(),
+            (Self::Sliding(state), Event::Stumble) => state.stumble(),
+            (Self::Attacking(state), Event::Stumble) => state.stumble(),
+            (Self::Jumping(state), Event::Stumble) => state.stumble(),
+            (Self::WallSliding(state), Event::Stumble) => state.stumble(),
+
+            (Self::Running(state), Event::Drown) => state.drown(),
+            (Self::Sliding(state), Event::Drown) => state.drown(),
+            (Self::Attacking(state), Event::Drown) => state.drown(),
+            (Self::Hurt(state), Event::Drown) => state.drown(),
 
             (Self::Idle(state), Event::Update) => state.update(),
             (Self::Running(state), Event::Update) => state.update(),
             (Self::Sliding(state), Event::Update) => state.update(),
+            (Self::Attacking(state), Event::Update) => state.update(),
             (Self::Jumping(state), Event::Update) => state.update(),
+            (Self::WallSliding(state), Event::Update) => state.update(),
             (Self::Falling(state), Event::Update) => state.update(),
+            (Self::Hurt(state), Event::Update) => state.update(),
+            (Self::Drowning(state), Event::Update) => state.update(),
             (this, _) => this,
         }
     }
-
-    fn update(self) -> Self {
-        self.transition(Event::Update)
-    }
 }
 
 mod states {
+    use anyhow::{anyhow, Result};
+    use serde::{Deserialize, Serialize};
+
     use crate::{
-        engine::{Audio, Point, Sound},
+        engine::{self, Point},
         game::HEIGHT,
     };
 
-    use super::{Frame, StateMachine};
-
-    const FLOOR: i16 = 479;
-    const PLAYER_HEIGHT: i16 = HEIGHT - FLOOR;
-    const STARTING_POINT: i16 = -20;
-    const TERMINAL_VELOCITY: i16 = 20;
-    const GRAVITY: i16 = 1;
-    const RUNNING_SPEED: i16 = 4;
-    const JUMP_SPEED: i16 = -25;
+    use super::{BoyState, Frame, SpeedModifier, StateMachine, STARTING_POINT};
+
+    pub(super) const FLOOR: f32 = 479.0;
+    const PLAYER_HEIGHT: f32 = HEIGHT - FLOOR;
+    const TERMINAL_VELOCITY: f32 = 20.0;
+    const GRAVITY: f32 = 1.0;
+    const JUMP_SPEED: f32 = -25.0;
+    const JUMP_CUT_SPEED: f32 = -10.0;
+    /// How far a drowning boy sinks in one default-rate update, enough to
+    /// disappear by the end of the animation.
+    const SINK_SPEED: f32 = PLAYER_HEIGHT / DROWN.frames as f32;
+    /// The fastest the boy slides down a wall he holds on to.
+    const WALL_SLIDE_SPEED: f32 = 2.0;
+    const KNOCK_BACK_SPEED: f32 = -3.0;
+    const KNOCK_BACK_JUMP_SPEED: f32 = -8.0;
+    /// How long hits are ignored after the boy is hurt, and at least until
+    /// he has got up again.
+    const INVULNERABLE_SECONDS: f32 = 1.5;
 
     trait FrameName {
         const FRAME_NAME: &'static str;
@@ -227,8 +890,8 @@ mod states {
     }
 
     impl<S> Frame for State<S> {
-        fn frame_name(&self) -> &'static str {
-            self.context.frame_config.frame_name
+        fn animation(&self) -> usize {
+            self.context.frame_config.animation
         }
 
         fn frame(&self) -> u8 {
@@ -239,57 +902,260 @@ mod states {
             self.context.position
         }
 
-        fn velocity_y(&self) -> i16 {
+        fn velocity(&self) -> Point {
+            self.context.velocity
+        }
+
+        fn velocity_y(&self) -> f32 {
             self.context.velocity.y
         }
 
-        fn walking_speed(&self) -> i16 {
-            self.context.velocity.x
+        fn walking_speed(&self) -> f32 {
+            self.context.velocity.x * self.context.speed_factor()
+        }
+
+        fn speed_factor(&self) -> f32 {
+            self.context.speed_factor()
+        }
+
+        fn lives(&self) -> u8 {
+            self.context.lives
+        }
+
+        fn invulnerable_frames(&self) -> u16 {
+            self.context.invulnerable_frames
+        }
+    }
+
+    impl<S> State<S>
+    where
+        State<S>: Into<StateMachine>,
+    {
+        pub(super) fn carry(mut self, delta_x: f32) -> StateMachine {
+            self.context = self.context.move_horizontally(delta_x);
+            self.into()
+        }
+
+        pub(super) fn bounce(self, velocity: f32) -> StateMachine {
+            let running_speed = self.context.running_speed;
+            State {
+                context: self
+                    .context
+                    .reset_frame(&JUMP)
+                    .set_vertical_velocity(velocity)
+                    .set_horizontal_velocity(running_speed),
+                _state: Jumping,
+            }
+            .into()
+        }
+
+        pub(super) fn boost(mut self, modifier: SpeedModifier) -> StateMachine {
+            self.context = self.context.add_speed_modifier(modifier);
+            self.into()
+        }
+
+        pub(super) fn hit(self) -> StateMachine {
+            if self.context.invulnerable_frames > 0 {
+                self.into()
+            } else if self.context.lives > 1 {
+                State {
+                    context: self.context.reset_frame(&HURT).lose_life().knock_back(),
+                    _state: Hurt,
+                }
+                .into()
+            } else {
+                State {
+                    context: self.context.reset_frame(&DEAD).lose_life().stop(),
+                    _state: Falling,
+                }
+                .into()
+            }
         }
 
-        fn audio(&self) -> &Audio {
-            &self.context.audio
+        pub(super) fn drown(self) -> StateMachine {
+            State {
+                context: self.context.reset_frame(&DROWN).drain_lives().stop(),
+                _state: Drowning,
+            }
+            .into()
         }
 
-        fn jump_sound(&self) -> &Sound {
-            &self.context.jump_sound
+        /// Plays the hurt animation and knocks the boy back like [`Self::hit`],
+        /// but keeps all his lives.
+        pub(super) fn stumble(self) -> StateMachine {
+            State {
+                context: self.context.reset_frame(&HURT).knock_back(),
+                _state: Hurt,
+            }
+            .into()
         }
     }
 
     #[derive(Debug, Clone, Copy)]
     struct FrameConfig {
+        /// The index of the config in [`FRAME_CONFIGS`].
+        animation: usize,
         frame_name: &'static str,
         frames: u8,
     }
     impl FrameConfig {
-        const fn new(frame_name: &'static str, frames: u8) -> Self {
-            Self { frame_name, frames }
+        const fn new(animation: usize, frame_name: &'static str, frames: u8) -> Self {
+            Self {
+                animation,
+                frame_name,
+                frames,
+            }
         }
     }
 
-    const IDLE: FrameConfig = FrameConfig::new("Idle", 29);
-    const RUN: FrameConfig = FrameConfig::new("Run", 23);
-    const SLIDE: FrameConfig = FrameConfig::new("Slide", 14);
-    const JUMP: FrameConfig = FrameConfig::new("Jump", 35);
-    const DEAD: FrameConfig = FrameConfig::new("Dead", 29);
+    const IDLE: FrameConfig = FrameConfig::new(0, "Idle", 29);
+    const RUN: FrameConfig = FrameConfig::new(1, "Run", 23);
+    const SLIDE: FrameConfig = FrameConfig::new(2, "Slide", 14);
+    const JUMP: FrameConfig = FrameConfig::new(3, "Jump", 35);
+    const DEAD: FrameConfig = FrameConfig::new(4, "Dead", 29);
+    const HURT: FrameConfig = FrameConfig::new(5, "Hurt", 23);
+    /// The boy flails his arms as he sinks.
+    const DROWN: FrameConfig = FrameConfig::new(6, "Drown", 35);
+    /// The boy kicks out feet first.
+    const ATTACK: FrameConfig = FrameConfig::new(7, "Attack", 14);
+    const FRAME_CONFIGS: [&FrameConfig; 8] =
+        [&IDLE, &RUN, &SLIDE, &JUMP, &DEAD, &HURT, &DROWN, &ATTACK];
+
+    /// Returns the name and the last frame of every animation, in the order
+    /// of their indices.
+    pub(super) fn animations() -> impl Iterator<Item = (&'static str, u8)> {
+        FRAME_CONFIGS
+            .into_iter()
+            .map(|frame_config| (frame_config.frame_name, frame_config.frames))
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub(super) struct ContextSnapshot {
+        animation: String,
+        frame: u8,
+        #[serde(default)]
+        frame_time: f32,
+        position: Point,
+        velocity: Point,
+        hold_state: bool,
+        lives: u8,
+        invulnerable_frames: u16,
+        #[serde(default)]
+        speed_modifiers: Vec<SpeedModifier>,
+    }
+
+    impl<S> State<S> {
+        pub(super) fn snapshot(&self) -> ContextSnapshot {
+            let context = &self.context;
+            ContextSnapshot {
+                animation: context.frame_config.frame_name.to_string(),
+                frame: context.frame,
+                frame_time: context.frame_time,
+                position: context.position,
+                velocity: context.velocity,
+                hold_state: context.hold_state,
+                lives: context.lives,
+                invulnerable_frames: context.invulnerable_frames,
+                speed_modifiers: context.speed_modifiers.clone(),
+            }
+        }
+    }
+
+    pub(super) fn restore(
+        state: BoyState,
+        snapshot: &ContextSnapshot,
+        running_speed: f32,
+    ) -> Result<StateMachine> {
+        let frame_config = FRAME_CONFIGS
+            .into_iter()
+            .find(|frame_config| frame_config.frame_name == snapshot.animation)
+            .ok_or_else(|| anyhow!("unknown animation {}", snapshot.animation))?;
+        let context = Context {
+            frame_config,
+            frame: snapshot.frame,
+            frame_time: snapshot.frame_time,
+            position: snapshot.position,
+            velocity: snapshot.velocity,
+            hold_state: snapshot.hold_state,
+            lives: snapshot.lives,
+            invulnerable_frames: snapshot.invulnerable_frames,
+            speed_modifiers: snapshot.speed_modifiers.clone(),
+            running_speed,
+        };
+        Ok(match state {
+            BoyState::Idle => State {
+                context,
+                _state: Idle,
+            }
+            .into(),
+            BoyState::Running => State {
+                context,
+                _state: Running,
+            }
+            .into(),
+            BoyState::Sliding => State {
+                context,
+                _state: Sliding,
+            }
+            .into(),
+            BoyState::Attacking => State {
+                context,
+                _state: Attacking,
+            }
+            .into(),
+            BoyState::Jumping => State {
+                context,
+                _state: Jumping,
+            }
+            .into(),
+            BoyState::WallSliding => State {
+                context,
+                _state: WallSliding,
+            }
+            .into(),
+            BoyState::Falling => State {
+                context,
+                _state: Falling,
+            }
+            .into(),
+            BoyState::Hurt => State {
+                context,
+                _state: Hurt,
+            }
+            .into(),
+            BoyState::Drowning => State {
+                context,
+                _state: Drowning,
+            }
+            .into(),
+            BoyState::KnockedOut => State {
+                context,
+                _state: KnockedOut,
+            }
+            .into(),
+        })
+    }
 
     #[derive(Debug, Clone, Copy)]
     pub(super) struct Idle;
 
     impl State<Idle> {
-        pub(super) fn new(audio: Audio, jump_sound: Sound) -> Self {
+        pub(super) fn new(lives: u8, running_speed: f32) -> Self {
             Self {
                 context: Context {
                     frame_config: &IDLE,
                     frame: 0,
+                    frame_time: 0.0,
                     position: Point {
                         x: STARTING_POINT,
                         y: FLOOR,
                     },
-                    velocity: Point { x: 0, y: 0 },
+                    velocity: Point { x: 0.0, y: 0.0 },
                     hold_state: false,
-                    audio,
-                    jump_sound,
+                    lives,
+                    invulnerable_frames: 0,
+                    speed_modifiers: vec![],
+                    running_speed,
                 },
                 _state: Idle,
             }
@@ -323,8 +1189,7 @@ mod states {
                 context: self
                     .context
                     .set_vertical_velocity(JUMP_SPEED)
-                    .reset_frame(&JUMP)
-                    .play_jump_sound(),
+                    .reset_frame(&JUMP),
                 _state: Jumping,
             }
             .into()
@@ -338,18 +1203,18 @@ mod states {
             .into()
         }
 
-        pub(super) fn land_on(mut self, position: i16) -> StateMachine {
-            self.context = self.context.set_on(position).set_vertical_velocity(0);
-            self.into()
-        }
-
-        pub(super) fn knock_out(self) -> StateMachine {
+        pub(super) fn attack(self) -> StateMachine {
             State {
-                context: self.context.reset_frame(&DEAD).stop(),
-                _state: Falling,
+                context: self.context.reset_frame(&ATTACK),
+                _state: Attacking,
             }
             .into()
         }
+
+        pub(super) fn land_on(mut self, position: f32) -> StateMachine {
+            self.context = self.context.set_on(position).set_vertical_velocity(0.0);
+            self.into()
+        }
     }
 
     #[derive(Debug, Clone, Copy)]
@@ -380,17 +1245,34 @@ mod states {
             .into()
         }
 
-        pub(super) fn land_on(mut self, position: i16) -> StateMachine {
-            self.context = self.context.set_on(position).set_vertical_velocity(0);
+        pub(super) fn land_on(mut self, position: f32) -> StateMachine {
+            self.context = self.context.set_on(position).set_vertical_velocity(0.0);
             self.into()
         }
+    }
 
-        pub(super) fn knock_out(self) -> StateMachine {
-            State {
-                context: self.context.reset_frame(&DEAD).stop(),
-                _state: Falling,
+    /// Kicking out, which breaks breakable obstacles instead of being hurt
+    /// by them.
+    #[derive(Debug, Clone, Copy)]
+    pub(super) struct Attacking;
+
+    impl State<Attacking> {
+        pub(super) fn update(mut self) -> StateMachine {
+            self.context = self.context.update();
+            if self.context.is_frames_end() {
+                State {
+                    context: self.context.reset_frame(&RUN),
+                    _state: Running,
+                }
+                .into()
+            } else {
+                self.into()
             }
-            .into()
+        }
+
+        pub(super) fn land_on(mut self, position: f32) -> StateMachine {
+            self.context = self.context.set_on(position).set_vertical_velocity(0.0);
+            self.into()
         }
     }
 
@@ -407,22 +1289,81 @@ mod states {
             }
         }
 
-        pub(super) fn land_on(self, position: i16) -> StateMachine {
+        pub(super) fn release_jump(mut self) -> StateMachine {
+            // Releasing the jump key while still rising fast ends the ascent early,
+            // so the jump height depends on how long the key was held.
+            if self.context.velocity.y < JUMP_CUT_SPEED {
+                self.context = self.context.set_vertical_velocity(JUMP_CUT_SPEED);
+            }
+            self.into()
+        }
+
+        pub(super) fn land_on(self, position: f32) -> StateMachine {
             State {
                 context: self
                     .context
                     .reset_frame(&RUN)
                     .set_on(position)
-                    .set_vertical_velocity(0),
+                    .set_vertical_velocity(0.0),
                 _state: Running,
             }
             .into()
         }
 
-        pub(super) fn knock_out(self) -> StateMachine {
+        pub(super) fn grab_wall(self) -> StateMachine {
             State {
-                context: self.context.reset_frame(&DEAD).stop(),
-                _state: Falling,
+                context: self.context.set_horizontal_velocity(0.0),
+                _state: WallSliding,
+            }
+            .into()
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub(super) struct WallSliding;
+
+    impl State<WallSliding> {
+        pub(super) fn update(mut self) -> StateMachine {
+            self.context = self.context.update();
+            if self.context.velocity.y > WALL_SLIDE_SPEED {
+                self.context = self.context.set_vertical_velocity(WALL_SLIDE_SPEED);
+            }
+            if self.context.position.y >= FLOOR {
+                self.land_on(HEIGHT)
+            } else {
+                self.into()
+            }
+        }
+
+        /// Jumps off the wall, running on towards it so that the boy either
+        /// clears it or grabs it again higher up. He can only kick off once
+        /// he has started sliding down.
+        pub(super) fn kick_off(self) -> StateMachine {
+            if self.context.velocity.y < 0.0 {
+                return self.into();
+            }
+            let running_speed = self.context.running_speed;
+            State {
+                context: self
+                    .context
+                    .reset_frame(&JUMP)
+                    .set_vertical_velocity(JUMP_SPEED)
+                    .set_horizontal_velocity(running_speed),
+                _state: Jumping,
+            }
+            .into()
+        }
+
+        pub(super) fn land_on(self, position: f32) -> StateMachine {
+            let running_speed = self.context.running_speed;
+            State {
+                context: self
+                    .context
+                    .reset_frame(&RUN)
+                    .set_on(position)
+                    .set_vertical_velocity(0.0)
+                    .set_horizontal_velocity(running_speed),
+                _state: Running,
             }
             .into()
         }
@@ -441,8 +1382,8 @@ mod states {
             }
         }
 
-        pub(super) fn land_on(mut self, position: i16) -> StateMachine {
-            self.context = self.context.set_on(position).set_vertical_velocity(0);
+        pub(super) fn land_on(mut self, position: f32) -> StateMachine {
+            self.context = self.context.set_on(position).set_vertical_velocity(0.0);
             self.into()
         }
 
@@ -455,6 +1396,56 @@ mod states {
         }
     }
 
+    #[derive(Debug, Clone, Copy)]
+    pub(super) struct Hurt;
+
+    impl State<Hurt> {
+        pub(super) fn update(mut self) -> StateMachine {
+            self.context = self.context.update();
+            if self.context.is_frames_end() {
+                self.recover()
+            } else {
+                self.into()
+            }
+        }
+
+        pub(super) fn land_on(mut self, position: f32) -> StateMachine {
+            self.context = self.context.set_on(position).set_vertical_velocity(0.0);
+            self.into()
+        }
+
+        fn recover(self) -> StateMachine {
+            let running_speed = self.context.running_speed;
+            State {
+                context: self
+                    .context
+                    .reset_frame(&RUN)
+                    .set_horizontal_velocity(running_speed),
+                _state: Running,
+            }
+            .into()
+        }
+    }
+
+    /// Sinking into water, which ends the run whatever lives are left.
+    #[derive(Debug, Clone, Copy)]
+    pub(super) struct Drowning;
+
+    impl State<Drowning> {
+        pub(super) fn update(mut self) -> StateMachine {
+            self.context = self.context.sink();
+            if self.context.is_frames_end() {
+                State {
+                    context: self.context,
+                    _state: KnockedOut,
+                }
+                .into()
+            } else {
+                self.into()
+            }
+        }
+    }
+
     #[derive(Debug, Clone, Copy)]
     pub(super) struct KnockedOut;
 
@@ -462,11 +1453,16 @@ mod states {
     struct Context {
         frame_config: &'static FrameConfig,
         frame: u8,
+        /// How many default-rate updates have gone by since the frame last
+        /// moved on, so that animations play as fast at any rate.
+        frame_time: f32,
         position: Point,
         velocity: Point,
         hold_state: bool,
-        audio: Audio,
-        jump_sound: Sound,
+        lives: u8,
+        invulnerable_frames: u16,
+        speed_modifiers: Vec<SpeedModifier>,
+        running_speed: f32,
     }
 
     impl Context {
@@ -476,17 +1472,34 @@ mod states {
 
         fn update(mut self) -> Self {
             self.hold_state = false;
-            if self.frame < self.frame_config.frames {
-                self.frame += 1;
-            } else {
-                self.frame = 0;
+            self.invulnerable_frames = self.invulnerable_frames.saturating_sub(1);
+            let scale = engine::update_scale();
+            self.frame_time += scale;
+            while self.frame_time >= 1.0 {
+                self.frame_time -= 1.0;
+                if self.is_frames_end() {
+                    self.frame = 0;
+                } else {
+                    self.frame += 1;
+                }
+                // The states look for the last frame to move on, so it is
+                // never skipped over.
+                if self.is_frames_end() {
+                    break;
+                }
             }
 
             if self.velocity.y < TERMINAL_VELOCITY {
-                self.velocity.y += GRAVITY;
+                self.velocity.y += GRAVITY * scale;
             }
 
-            self.position.y += self.velocity.y;
+            self.position.x += self.velocity.x * self.speed_factor() * scale;
+            for modifier in &mut self.speed_modifiers {
+                modifier.remaining_frames = modifier.remaining_frames.saturating_sub(1);
+            }
+            self.speed_modifiers
+                .retain(|modifier| modifier.remaining_frames > 0);
+            self.position.y += self.velocity.y * scale;
             if self.position.y > FLOOR {
                 self.position.y = FLOOR;
             }
@@ -496,38 +1509,267 @@ mod states {
         fn reset_frame(mut self, frame_config: &'static FrameConfig) -> Self {
             self.frame_config = frame_config;
             self.frame = 0;
+            self.frame_time = 0.0;
+            self
+        }
+
+        fn speed_factor(&self) -> f32 {
+            self.speed_modifiers
+                .iter()
+                .map(SpeedModifier::factor)
+                .product()
+        }
+
+        fn add_speed_modifier(mut self, modifier: SpeedModifier) -> Self {
+            match self
+                .speed_modifiers
+                .iter_mut()
+                .find(|existing| existing.same_source(&modifier))
+            {
+                Some(existing) => *existing = modifier,
+                None => self.speed_modifiers.push(modifier),
+            }
             self
         }
 
         fn run_right(mut self) -> Self {
-            self.velocity.x += RUNNING_SPEED;
+            self.velocity.x += self.running_speed;
+            self
+        }
+
+        fn move_horizontally(mut self, x: f32) -> Self {
+            self.position.x += x;
             self
         }
 
-        fn set_vertical_velocity(mut self, y: i16) -> Self {
+        fn set_horizontal_velocity(mut self, x: f32) -> Self {
+            self.velocity.x = x;
+            self
+        }
+
+        fn set_vertical_velocity(mut self, y: f32) -> Self {
             self.velocity.y = y;
             self
         }
 
-        fn set_on(mut self, position: i16) -> Self {
+        fn set_on(mut self, position: f32) -> Self {
             let position = position - PLAYER_HEIGHT;
             self.position.y = position;
             self
         }
 
-        fn stop(mut self) -> Self {
-            self.velocity.x = 0;
-            if self.velocity.y < 0 {
-                self.velocity.y = 0;
+        /// Moves the boy down through the floor, slowly enough to see him go.
+        fn sink(mut self) -> Self {
+            let scale = engine::update_scale();
+            self.frame_time += scale;
+            while self.frame_time >= 1.0 && !self.is_frames_end() {
+                self.frame_time -= 1.0;
+                self.frame += 1;
             }
+            self.position.y += SINK_SPEED * scale;
+            self
+        }
+
+        fn drain_lives(mut self) -> Self {
+            self.lives = 0;
             self
         }
 
-        fn play_jump_sound(self) -> Self {
-            if let Err(err) = self.audio.play_sound(&self.jump_sound) {
-                log!("Error playing jump sound: {err:#?}");
+        fn lose_life(mut self) -> Self {
+            self.lives = self.lives.saturating_sub(1);
+            self
+        }
+
+        fn knock_back(mut self) -> Self {
+            self.velocity.x = KNOCK_BACK_SPEED;
+            self.velocity.y = KNOCK_BACK_JUMP_SPEED;
+            // The hurt animation is counted in default-rate updates.
+            let animation = f32::from(self.frame_config.frames) / engine::update_scale();
+            let updates = INVULNERABLE_SECONDS * f32::from(engine::updates_per_second());
+            self.invulnerable_frames = animation.max(updates) as u16;
+            self
+        }
+
+        fn stop(mut self) -> Self {
+            self.velocity.x = 0.0;
+            if self.velocity.y < 0.0 {
+                self.velocity.y = 0.0;
             }
             self
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(mut state_machine: StateMachine, updates: usize) -> StateMachine {
+        for _ in 0..updates {
+            state_machine = state_machine.transition(Event::Update);
+        }
+        state_machine
+    }
+
+    fn running(lives: u8) -> StateMachine {
+        StateMachine::from(State::new(lives, RUNNING_SPEED)).transition(Event::Run)
+    }
+
+    #[test]
+    fn jumps_land_back_on_the_floor() {
+        let state_machine = running(3);
+        let floor = state_machine.as_frame().position().y;
+        let state_machine = state_machine.transition(Event::Jump);
+        assert_eq!(state_machine.name(), "Jumping");
+
+        let state_machine = update(state_machine, 60);
+        assert_eq!(state_machine.name(), "Running");
+        assert_eq!(state_machine.as_frame().position().y, floor);
+    }
+
+    #[test]
+    fn jumps_take_as_long_at_any_update_rate() {
+        fn seconds_in_the_air(updates_per_second: u16) -> f32 {
+            engine::set_updates_per_second(updates_per_second);
+            let mut state_machine = running(3).transition(Event::Jump);
+            let mut updates = 0;
+            while state_machine.name() == "Jumping" {
+                state_machine = state_machine.transition(Event::Update);
+                updates += 1;
+            }
+            engine::set_updates_per_second(engine::DEFAULT_UPDATES_PER_SECOND);
+            updates as f32 / f32::from(updates_per_second)
+        }
+
+        let (default_rate, double_rate) = (seconds_in_the_air(60), seconds_in_the_air(120));
+        assert!((default_rate - double_rate).abs() < 0.05);
+    }
+
+    #[test]
+    fn animations_play_as_fast_at_any_update_rate() {
+        fn after_a_second(updates_per_second: u16) -> (u8, f32) {
+            engine::set_updates_per_second(updates_per_second);
+            let state_machine = update(running(3), usize::from(updates_per_second));
+            engine::set_updates_per_second(engine::DEFAULT_UPDATES_PER_SECOND);
+            let frame = state_machine.as_frame();
+            (frame.frame(), frame.position().x)
+        }
+
+        let (default_rate, half_rate, quadruple_rate) =
+            (after_a_second(60), after_a_second(30), after_a_second(240));
+        assert_eq!(half_rate.0, default_rate.0);
+        assert_eq!(quadruple_rate.0, default_rate.0);
+        assert!((half_rate.1 - default_rate.1).abs() < 0.01);
+    }
+
+    #[test]
+    fn boys_kick_off_walls_once_they_slide_down() {
+        let state_machine = update(running(3).transition(Event::Jump), 5);
+        let state_machine = state_machine.transition(Event::GrabWall);
+        assert_eq!(state_machine.name(), "WallSliding");
+        assert_eq!(state_machine.as_frame().walking_speed(), 0.0);
+
+        // The boy has to start sliding down before he can kick off.
+        let state_machine = state_machine.transition(Event::Jump);
+        assert_eq!(state_machine.name(), "WallSliding");
+        let state_machine = update(state_machine, 30);
+        assert!(state_machine.as_frame().velocity_y() <= 2.0);
+        let kicked = state_machine.transition(Event::Jump);
+        assert_eq!(kicked.name(), "Jumping");
+        assert!(kicked.as_frame().velocity_y() < 0.0);
+    }
+
+    #[test]
+    fn attacks_return_to_running() {
+        let state_machine = running(3).transition(Event::Attack);
+        assert_eq!(state_machine.name(), "Attacking");
+
+        let state_machine = update(state_machine, 60);
+        assert_eq!(state_machine.name(), "Running");
+    }
+
+    #[test]
+    fn bounces_launch_higher_than_jumps() {
+        let jumping = running(3).transition(Event::Jump);
+        let bouncing = running(3).transition(Event::Bounce { velocity: -35.0 });
+        assert_eq!(bouncing.name(), "Jumping");
+        assert!(bouncing.as_frame().velocity_y() < jumping.as_frame().velocity_y());
+    }
+
+    #[test]
+    fn every_animation_has_frames_of_its_own() {
+        let sheet: Sheet =
+            serde_json::from_str(include_str!("../../static/sprites_sheets/rhb.json")).unwrap();
+        let mut animations = std::collections::HashSet::new();
+        for (animation, frames) in states::animations() {
+            assert!(animations.insert(animation), "{animation} is played twice");
+            for index in 1..=frames / 3 + 1 {
+                let name = format!("{animation} ({index}).png");
+                assert!(sheet.frames.contains_key(&name), "no frame {name}");
+            }
+        }
+    }
+
+    #[test]
+    fn drowning_ends_the_run_whatever_lives_are_left() {
+        let mut state_machine = running(3).transition(Event::Drown);
+        assert_eq!(state_machine.name(), "Drowning");
+        assert_eq!(state_machine.as_frame().lives(), 0);
+
+        let floor = state_machine.as_frame().position().y;
+        state_machine = update(state_machine, 10);
+        assert!(state_machine.as_frame().position().y > floor);
+        state_machine = update(state_machine, 30);
+        assert_eq!(state_machine.name(), "KnockedOut");
+    }
+
+    #[test]
+    fn speed_boosts_ramp_back_down() {
+        let boost = SpeedModifier::new(2.0, 20, 10);
+        let state_machine = running(3).transition(Event::Boost(boost));
+        assert_eq!(state_machine.as_frame().speed_factor(), 2.0);
+
+        // Running over the same pad again restarts the boost.
+        let state_machine = update(state_machine, 5).transition(Event::Boost(boost));
+        assert_eq!(state_machine.as_frame().speed_factor(), 2.0);
+
+        let state_machine = update(state_machine, 15);
+        assert_eq!(state_machine.as_frame().speed_factor(), 1.5);
+        let state_machine = update(state_machine, 5);
+        assert_eq!(state_machine.as_frame().speed_factor(), 1.0);
+    }
+
+    #[test]
+    fn stumbling_keeps_every_life() {
+        let state_machine = running(1).transition(Event::Stumble);
+        assert_eq!(state_machine.name(), "Hurt");
+        assert_eq!(state_machine.as_frame().lives(), 1);
+        assert!(state_machine.as_frame().invulnerable_frames() > 0);
+    }
+
+    #[test]
+    fn hits_are_ignored_for_as_long_at_any_update_rate() {
+        engine::set_updates_per_second(240);
+        let state_machine = running(3).transition(Event::KnockOut);
+        let invulnerable_frames = state_machine.as_frame().invulnerable_frames();
+        engine::set_updates_per_second(engine::DEFAULT_UPDATES_PER_SECOND);
+        // One and a half seconds, more than a `u8` of updates at this rate.
+        assert_eq!(invulnerable_frames, 360);
+    }
+
+    #[test]
+    fn losing_the_last_life_knocks_the_boy_out() {
+        let mut state_machine = running(2).transition(Event::KnockOut);
+        assert_eq!(state_machine.name(), "Hurt");
+        assert_eq!(state_machine.as_frame().lives(), 1);
+
+        // Hits are ignored while the boy is invulnerable after being hurt.
+        state_machine = update(state_machine, 30).transition(Event::KnockOut);
+        assert_eq!(state_machine.as_frame().lives(), 1);
+
+        state_machine = update(state_machine, 200).transition(Event::KnockOut);
+        assert_eq!(state_machine.name(), "Falling");
+        state_machine = update(state_machine, 60);
+        assert!(state_machine.knocked_out());
+    }
+}
@@ -0,0 +1,199 @@
+//! Spinning saw blades that travel back and forth along a straight path.
+
+use std::f32::consts::TAU;
+
+use crate::engine::{Body, DebugCategory, Point, Rect, Renderer, Shape, Transform};
+
+use super::{red_hat_boy::RedHatBoy, Obstacle, ObstacleKind, ObstacleSnapshot};
+
+#[derive(Debug, Clone)]
+pub(crate) struct Saw {
+    /// Where the center of the blade is at either end of its path.
+    start: Point,
+    end: Point,
+    /// How far along its trip there and back the blade is, in radians.
+    phase: f32,
+    /// How far the blade has turned, in radians. It is kept apart from the
+    /// phase, which wraps around after every trip.
+    rotation: f32,
+    /// How far the whole path has been moved since the saw was placed,
+    /// kept apart so that the path stays where it is relative to the world.
+    offset: Point,
+}
+
+impl Saw {
+    pub(crate) const RADIUS: f32 = 28.0;
+    /// The blade only hurts inside its teeth, a little short of its edge.
+    const HIT_RADIUS: f32 = 22.0;
+    const TEETH: u8 = 10;
+    /// How many seconds a trip from one end of the path to the other and back
+    /// takes.
+    const PERIOD: f32 = 3.0;
+    /// How fast the blade turns, in radians per second.
+    const SPIN_SPEED: f32 = 15.0;
+
+    pub(crate) fn new(start: Point, end: Point) -> Self {
+        Self::restore(start, end, 0.0, 0.0)
+    }
+
+    pub(super) fn restore(start: Point, end: Point, phase: f32, rotation: f32) -> Self {
+        Self {
+            start,
+            end,
+            phase,
+            rotation,
+            offset: Point::default(),
+        }
+    }
+
+    /// Returns where the blade is now, easing in and out at the ends of its
+    /// path.
+    fn center(&self) -> Point {
+        let progress = (1.0 - self.phase.cos()) / 2.0;
+        Point {
+            x: self.start.x + (self.end.x - self.start.x) * progress + self.offset.x,
+            y: self.start.y + (self.end.y - self.start.y) * progress + self.offset.y,
+        }
+    }
+
+    fn blade_box(&self) -> Rect {
+        let center = self.center();
+        Rect::from_xy(
+            center.x - Self::RADIUS,
+            center.y - Self::RADIUS,
+            Self::RADIUS * 2.0,
+            Self::RADIUS * 2.0,
+        )
+    }
+}
+
+impl Body for Saw {
+    /// Returns the area of the whole path, which the blade never leaves.
+    fn bounding_box(&self) -> Rect {
+        let top = self.start.y.min(self.end.y) + self.offset.y - Self::RADIUS;
+        let bottom = self.start.y.max(self.end.y) + self.offset.y + Self::RADIUS;
+        Rect::from_xy(self.left(), top, self.right() - self.left(), bottom - top)
+    }
+
+    fn move_by(&mut self, x: f32, y: f32) {
+        self.offset.x += x;
+        self.offset.y += y;
+    }
+}
+
+impl Obstacle for Saw {
+    fn kind(&self) -> ObstacleKind {
+        ObstacleKind::Saw
+    }
+
+    fn left(&self) -> f32 {
+        self.start.x.min(self.end.x) + self.offset.x - Self::RADIUS
+    }
+
+    fn right(&self) -> f32 {
+        self.start.x.max(self.end.x) + self.offset.x + Self::RADIUS
+    }
+
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        let blade = Shape::Circle {
+            center: self.center(),
+            radius: Self::HIT_RADIUS,
+        };
+        if boy.collides_with(&blade, None) {
+            boy.knock_out();
+        }
+    }
+
+    fn collision_boxes(&self) -> Vec<Rect> {
+        vec![self.blade_box()]
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        let center = self.center();
+        let transform = Transform {
+            rotation: f64::from(self.rotation),
+            ..Transform::default()
+        };
+        renderer.with_transform(&center, &transform, |renderer| {
+            let teeth: Vec<_> = (0..Self::TEETH * 2)
+                .map(|corner| {
+                    let angle = TAU * f32::from(corner) / f32::from(Self::TEETH * 2);
+                    let radius = if corner % 2 == 0 {
+                        Self::RADIUS
+                    } else {
+                        Self::HIT_RADIUS
+                    };
+                    Point {
+                        x: center.x + radius * angle.cos(),
+                        y: center.y + radius * angle.sin(),
+                    }
+                })
+                .collect();
+            renderer.fill_polygon(&teeth, "silver");
+            renderer.fill_circle(&center, Self::HIT_RADIUS / 3.0, "dimgray");
+            // A notch off the center, so that the spin shows.
+            let notch = Point {
+                x: center.x + Self::HIT_RADIUS / 2.0,
+                y: center.y,
+            };
+            renderer.fill_circle(&notch, Self::HIT_RADIUS / 6.0, "dimgray");
+        });
+        renderer.draw_bounding_box(&self.blade_box(), DebugCategory::Barrier);
+    }
+
+    fn snapshot(&self) -> ObstacleSnapshot {
+        let shift = |point: Point| Point {
+            x: point.x + self.offset.x,
+            y: point.y + self.offset.y,
+        };
+        ObstacleSnapshot::Saw {
+            start: shift(self.start),
+            end: shift(self.end),
+            phase: self.phase,
+            rotation: self.rotation,
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.phase = (self.phase + TAU * dt / Self::PERIOD) % TAU;
+        self.rotation = (self.rotation + Self::SPIN_SPEED * dt) % TAU;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saws_travel_to_the_end_of_their_path_and_back() {
+        let start = Point { x: 0.0, y: 100.0 };
+        let end = Point { x: 200.0, y: 100.0 };
+        let mut saw = Saw::new(start, end);
+        assert_eq!(saw.center(), start);
+
+        for _ in 0..90 {
+            saw.update(Saw::PERIOD / 180.0);
+        }
+        assert!((saw.center().x - end.x).abs() < 0.01);
+
+        saw.move_by(-50.0, 0.0);
+        for _ in 0..90 {
+            saw.update(Saw::PERIOD / 180.0);
+        }
+        assert!((saw.center().x - (start.x - 50.0)).abs() < 0.01);
+        assert_eq!(saw.left(), -50.0 - Saw::RADIUS);
+    }
+
+    #[test]
+    fn blades_keep_spinning_smoothly_when_a_trip_starts_over() {
+        let dt = Saw::PERIOD / 180.0;
+        let mut saw = Saw::new(Point { x: 0.0, y: 0.0 }, Point { x: 100.0, y: 0.0 });
+        for _ in 0..179 {
+            saw.update(dt);
+        }
+        let before = saw.rotation;
+        saw.update(dt);
+        let turned = (saw.rotation - before).rem_euclid(TAU);
+        assert!((turned - Saw::SPIN_SPEED * dt).abs() < 0.001);
+    }
+}
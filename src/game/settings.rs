@@ -0,0 +1,367 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    browser::storage,
+    engine::{
+        BlendMode, KeyState, Point, Rect, Renderer, Scene, SceneChange, TextAlign, TextStyle,
+        Transition,
+    },
+    i18n::t,
+};
+
+use super::{skins::Skin, Walk, HEIGHT, KEN_FUTURE, MUSIC_VOLUME, SFX_VOLUME, WIDTH};
+
+const SETTINGS_KEY: &str = "walk_the_dog.settings";
+/// How much the volumes and the rumble change by in a step.
+const LEVEL_STEP: f32 = 0.1;
+
+/// Something the player does with a key that can be rebound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Action {
+    Run,
+    Jump,
+    Slide,
+    Attack,
+    Pause,
+    Mute,
+    Restart,
+}
+
+impl Action {
+    const fn label_key(self) -> &'static str {
+        match self {
+            Action::Run => "action.run",
+            Action::Jump => "action.jump",
+            Action::Slide => "action.slide",
+            Action::Attack => "action.attack",
+            Action::Pause => "action.pause",
+            Action::Mute => "action.mute",
+            Action::Restart => "action.restart",
+        }
+    }
+}
+
+/// The key codes bound to each [`Action`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct Bindings {
+    run: String,
+    jump: String,
+    slide: String,
+    attack: String,
+    pause: String,
+    mute: String,
+    restart: String,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            run: "ArrowRight".to_string(),
+            jump: "Space".to_string(),
+            slide: "ArrowDown".to_string(),
+            attack: "KeyX".to_string(),
+            pause: "Escape".to_string(),
+            mute: "KeyM".to_string(),
+            restart: "KeyR".to_string(),
+        }
+    }
+}
+
+impl Bindings {
+    pub(super) fn key(&self, action: Action) -> &str {
+        match action {
+            Action::Run => &self.run,
+            Action::Jump => &self.jump,
+            Action::Slide => &self.slide,
+            Action::Attack => &self.attack,
+            Action::Pause => &self.pause,
+            Action::Mute => &self.mute,
+            Action::Restart => &self.restart,
+        }
+    }
+
+    fn bind(&mut self, action: Action, code: String) {
+        let key = match action {
+            Action::Run => &mut self.run,
+            Action::Jump => &mut self.jump,
+            Action::Slide => &mut self.slide,
+            Action::Attack => &mut self.attack,
+            Action::Pause => &mut self.pause,
+            Action::Mute => &mut self.mute,
+            Action::Restart => &mut self.restart,
+        };
+        *key = code;
+    }
+}
+
+/// The player's preferences, kept in local storage between sessions.
+///
+/// Missing fields take their default values, so settings saved by older
+/// versions of the game still load.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    pub(super) music_volume: f32,
+    pub(super) sfx_volume: f32,
+    pub(super) muted: bool,
+    pub(super) bindings: Bindings,
+    /// Draws bounding boxes and other debugging aids, and enables the debug
+    /// keys.
+    pub(super) debug_mode: bool,
+    /// Logs the keyboard state on every update.
+    pub(super) log_input: bool,
+    /// Turns off screen shake.
+    pub(super) reduced_motion: bool,
+    /// Plays with a single input, any key or pressing the canvas, which
+    /// slides under overhead obstacles and jumps otherwise.
+    pub(super) one_button: bool,
+    /// How hard connected gamepads rumble on hits and hard landings, from
+    /// `0.0` (not at all) to `1.0`.
+    pub(super) rumble: f32,
+    /// Only knocks the boy out when solid pixels of his sprite touch those
+    /// of a stone, rather than when their bounding boxes intersect.
+    pub(super) pixel_perfect_collisions: bool,
+    /// The boy's colors, used once they are unlocked.
+    pub(super) skin: Skin,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            music_volume: MUSIC_VOLUME,
+            sfx_volume: SFX_VOLUME,
+            muted: false,
+            bindings: Bindings::default(),
+            debug_mode: cfg!(debug_assertions),
+            log_input: false,
+            reduced_motion: false,
+            one_button: false,
+            rumble: 1.0,
+            pixel_perfect_collisions: false,
+            skin: Skin::Classic,
+        }
+    }
+}
+
+impl Settings {
+    /// Returns the saved settings, or the defaults if there are none or they
+    /// cannot be read.
+    pub(super) fn load() -> Self {
+        match storage::load_json(SETTINGS_KEY) {
+            Ok(settings) => settings.unwrap_or_default(),
+            Err(err) => {
+                error!("error loading settings: {err:#?}");
+                Self::default()
+            }
+        }
+    }
+
+    pub(super) fn save(&self) -> Result<()> {
+        storage::save_json(SETTINGS_KEY, self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    MusicVolume,
+    SfxVolume,
+    Muted,
+    ReducedMotion,
+    OneButton,
+    Rumble,
+    PixelPerfectCollisions,
+    DebugMode,
+    LogInput,
+    Skin,
+    Binding(Action),
+}
+
+const ROWS: [Row; 17] = [
+    Row::MusicVolume,
+    Row::SfxVolume,
+    Row::Muted,
+    Row::ReducedMotion,
+    Row::OneButton,
+    Row::Rumble,
+    Row::PixelPerfectCollisions,
+    Row::DebugMode,
+    Row::LogInput,
+    Row::Skin,
+    Row::Binding(Action::Run),
+    Row::Binding(Action::Jump),
+    Row::Binding(Action::Slide),
+    Row::Binding(Action::Attack),
+    Row::Binding(Action::Pause),
+    Row::Binding(Action::Mute),
+    Row::Binding(Action::Restart),
+];
+
+impl Row {
+    const fn label_key(self) -> &'static str {
+        match self {
+            Row::MusicVolume => "settings.music_volume",
+            Row::SfxVolume => "settings.sfx_volume",
+            Row::Muted => "settings.muted",
+            Row::ReducedMotion => "settings.reduced_motion",
+            Row::OneButton => "settings.one_button",
+            Row::Rumble => "settings.rumble",
+            Row::PixelPerfectCollisions => "settings.pixel_perfect_collisions",
+            Row::DebugMode => "settings.debug_mode",
+            Row::LogInput => "settings.log_input",
+            Row::Skin => "settings.skin",
+            Row::Binding(action) => action.label_key(),
+        }
+    }
+
+    fn value(self, settings: &Settings) -> String {
+        let on_off = |value: bool| t(if value { "settings.on" } else { "settings.off" });
+        let percent = |volume: f32| format!("{:.0}%", volume * 100.0);
+        match self {
+            Row::MusicVolume => percent(settings.music_volume),
+            Row::SfxVolume => percent(settings.sfx_volume),
+            Row::Muted => on_off(settings.muted),
+            Row::ReducedMotion => on_off(settings.reduced_motion),
+            Row::OneButton => on_off(settings.one_button),
+            Row::Rumble => percent(settings.rumble),
+            Row::PixelPerfectCollisions => on_off(settings.pixel_perfect_collisions),
+            Row::DebugMode => on_off(settings.debug_mode),
+            Row::LogInput => on_off(settings.log_input),
+            Row::Skin => t(settings.skin.label_key()),
+            Row::Binding(action) => settings.bindings.key(action).to_string(),
+        }
+    }
+
+    /// Changes the setting by one `step`, which is either `1.0` or `-1.0`.
+    /// Only the skins unlocked by `best_score` can be picked. Returns `true`
+    /// if the row is a key binding, which cannot be stepped.
+    fn change(self, settings: &mut Settings, step: f32, best_score: u32) -> bool {
+        let level = |level: &mut f32| *level = (*level + step * LEVEL_STEP).clamp(0.0, 1.0);
+        match self {
+            Row::MusicVolume => level(&mut settings.music_volume),
+            Row::SfxVolume => level(&mut settings.sfx_volume),
+            Row::Muted => settings.muted = !settings.muted,
+            Row::ReducedMotion => settings.reduced_motion = !settings.reduced_motion,
+            Row::OneButton => settings.one_button = !settings.one_button,
+            Row::Rumble => level(&mut settings.rumble),
+            Row::PixelPerfectCollisions => {
+                settings.pixel_perfect_collisions = !settings.pixel_perfect_collisions;
+            }
+            Row::DebugMode => settings.debug_mode = !settings.debug_mode,
+            Row::LogInput => settings.log_input = !settings.log_input,
+            Row::Skin => settings.skin = settings.skin.next_unlocked(step as isize, best_score),
+            Row::Binding(_) => return true,
+        }
+        false
+    }
+}
+
+/// Lets the player edit the [`Settings`], which are saved when the screen is
+/// closed.
+///
+/// The up and down arrow keys select a setting, the left and right arrow keys
+/// change it and Enter toggles it or waits for the next key to bind.
+#[derive(Debug)]
+pub(super) struct SettingsMenu {
+    selected: usize,
+    rebinding: bool,
+}
+
+impl SettingsMenu {
+    pub(super) fn new() -> Self {
+        Self {
+            selected: 0,
+            rebinding: false,
+        }
+    }
+}
+
+impl Scene<Walk> for SettingsMenu {
+    fn update(&mut self, walk: &mut Walk, keystate: &KeyState) -> SceneChange<Walk> {
+        let row = ROWS[self.selected];
+        if self.rebinding {
+            if let (Row::Binding(action), Some(code)) = (row, walk.newly_pressed(keystate)) {
+                walk.settings.bindings.bind(action, code);
+                self.rebinding = false;
+            }
+            return SceneChange::None;
+        }
+
+        if walk.just_pressed(keystate, "Escape") {
+            if let Err(err) = walk.settings.save() {
+                error!("error saving settings: {err:#?}");
+            }
+            return SceneChange::Pop(Transition::Cut);
+        }
+        let unlock_score = walk.skin_unlock_score();
+        if walk.just_pressed(keystate, "ArrowUp") {
+            self.selected = (self.selected + ROWS.len() - 1) % ROWS.len();
+        }
+        if walk.just_pressed(keystate, "ArrowDown") {
+            self.selected = (self.selected + 1) % ROWS.len();
+        }
+        if walk.just_pressed(keystate, "ArrowLeft") {
+            row.change(&mut walk.settings, -1.0, unlock_score);
+        }
+        if walk.just_pressed(keystate, "ArrowRight") {
+            row.change(&mut walk.settings, 1.0, unlock_score);
+        }
+        if walk.just_pressed(keystate, "Enter") {
+            self.rebinding = row.change(&mut walk.settings, 1.0, unlock_score);
+        }
+        walk.apply_settings();
+        SceneChange::None
+    }
+
+    fn draw(&self, walk: &Walk, renderer: &Renderer) {
+        const TITLE_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 36)
+            .bold()
+            .with_color("white")
+            .with_stroke("black", 4)
+            .aligned(TextAlign::Center);
+        const ROW_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 20).with_color("white");
+        const SELECTED_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 20).with_color("gold");
+        const HELP_STYLE: TextStyle = TextStyle::new("serif", 16)
+            .with_color("white")
+            .aligned(TextAlign::Center);
+
+        walk.draw(renderer);
+        renderer.tint(
+            &Rect::from_xy(0.0, 0.0, WIDTH, HEIGHT),
+            "rgb(80, 80, 80)",
+            BlendMode::Multiply,
+        );
+        renderer.draw_text(
+            &t("settings.title"),
+            &Point {
+                x: WIDTH / 2.0,
+                y: 90.0,
+            },
+            &TITLE_STYLE,
+        );
+        for (index, (row, y)) in ROWS.iter().zip((125_u16..).step_by(27)).enumerate() {
+            let y = f32::from(y);
+            let style = if index == self.selected {
+                &SELECTED_STYLE
+            } else {
+                &ROW_STYLE
+            };
+            let value = if index == self.selected && self.rebinding {
+                t("settings.press_a_key")
+            } else {
+                row.value(&walk.settings)
+            };
+            renderer.draw_text(&t(row.label_key()), &Point { x: 120.0, y }, style);
+            renderer.draw_text(&value, &Point { x: 340.0, y }, style);
+        }
+        renderer.draw_text(
+            &t("settings.help"),
+            &Point {
+                x: WIDTH / 2.0,
+                y: HEIGHT - 30.0,
+            },
+            &HELP_STYLE,
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! The pieces things break into: a shield when it absorbs a knockout, or a
+//! stone when the boy kicks it apart.
+
+use std::f32::consts::TAU;
+
+use crate::engine::{self, Point, Rect, Renderer};
+
+const SHARD_COUNT: u8 = 12;
+const SHARD_SIZE: f32 = 6.0;
+const SHARD_SPEED: f32 = 6.0;
+const SHARD_GRAVITY: f32 = 0.5;
+/// How many seconds the shards fly before they are gone, fading out on the
+/// way.
+const SHARD_SECONDS: f32 = 0.5;
+
+/// A piece of a shield, in world coordinates.
+#[derive(Debug, Clone)]
+struct Shard {
+    position: Point,
+    velocity: Point,
+    remaining_frames: u16,
+    color: &'static str,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Shatter {
+    shards: Vec<Shard>,
+}
+
+impl Shatter {
+    /// Breaks something at `center`, sending shards of `color` out in every
+    /// direction.
+    pub(super) fn burst(&mut self, center: Point, color: &'static str) {
+        self.shards.extend((0..SHARD_COUNT).map(|index| {
+            let angle = TAU * f32::from(index) / f32::from(SHARD_COUNT);
+            Shard {
+                position: center,
+                velocity: Point {
+                    x: angle.cos() * SHARD_SPEED,
+                    y: angle.sin() * SHARD_SPEED,
+                },
+                remaining_frames: engine::updates_in(SHARD_SECONDS),
+                color,
+            }
+        }));
+    }
+
+    pub(super) fn update(&mut self) {
+        let scale = engine::update_scale();
+        self.shards.retain_mut(|shard| {
+            shard.velocity.y += SHARD_GRAVITY * scale;
+            shard.position.x += shard.velocity.x * scale;
+            shard.position.y += shard.velocity.y * scale;
+            shard.remaining_frames = shard.remaining_frames.saturating_sub(1);
+            shard.remaining_frames > 0
+        });
+    }
+
+    pub(super) fn move_horizontally(&mut self, distance: f32) {
+        for shard in &mut self.shards {
+            shard.position.x += distance;
+        }
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.shards.clear();
+    }
+
+    pub(super) fn draw(&self, renderer: &Renderer) {
+        for shard in &self.shards {
+            let alpha =
+                f64::from(shard.remaining_frames) / f64::from(engine::updates_in(SHARD_SECONDS));
+            renderer.with_alpha(alpha, |renderer| {
+                renderer.fill_rect(
+                    &Rect::new(shard.position, SHARD_SIZE, SHARD_SIZE),
+                    shard.color,
+                );
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shards_are_gone_after_their_lifetime() {
+        let mut shatter = Shatter::default();
+        shatter.burst(Point { x: 100.0, y: 100.0 }, "white");
+        for _ in 1..engine::updates_in(SHARD_SECONDS) {
+            shatter.update();
+        }
+        assert_eq!(shatter.shards.len(), usize::from(SHARD_COUNT));
+        shatter.update();
+        assert!(shatter.shards.is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+//! Alternative looks for the boy, made by swapping the colors of his red
+//! hood in the sprite sheet. Skins are unlocked by reaching their score, and
+//! the best score is kept between sessions for that.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{browser::storage, engine::ImageSource};
+
+const BEST_SCORE_KEY: &str = "walk_the_dog.best_score";
+/// How much more red than green and blue a pixel must be to belong to the
+/// hood.
+const RED_MARGIN: u8 = 40;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) enum Skin {
+    #[default]
+    Classic,
+    Forest,
+    Ocean,
+    Gold,
+}
+
+impl Skin {
+    pub(super) const ALL: [Skin; 4] = [Self::Classic, Self::Forest, Self::Ocean, Self::Gold];
+
+    /// The best score that unlocks the skin.
+    const fn unlock_score(self) -> u32 {
+        match self {
+            Self::Classic => 0,
+            Self::Forest => 25,
+            Self::Ocean => 50,
+            Self::Gold => 100,
+        }
+    }
+
+    pub(super) const fn is_unlocked(self, best_score: u32) -> bool {
+        best_score >= self.unlock_score()
+    }
+
+    /// Returns the key of the skin's name in [`crate::i18n`].
+    pub(super) const fn label_key(self) -> &'static str {
+        match self {
+            Self::Classic => "skin.classic",
+            Self::Forest => "skin.forest",
+            Self::Ocean => "skin.ocean",
+            Self::Gold => "skin.gold",
+        }
+    }
+
+    /// Returns the unlocked skin `step` places after this one, wrapping
+    /// around.
+    pub(super) fn next_unlocked(self, step: isize, best_score: u32) -> Skin {
+        let unlocked: Vec<_> = Self::ALL
+            .into_iter()
+            .filter(|skin| skin.is_unlocked(best_score))
+            .collect();
+        let index = unlocked.iter().position(|skin| *skin == self).unwrap_or(0) as isize;
+        unlocked[(index + step).rem_euclid(unlocked.len() as isize) as usize]
+    }
+
+    /// Returns the color a pixel of the hood takes in this skin. Channels are
+    /// only swapped or scaled, so that the shading of the sprites is kept.
+    fn recolor(self, [red, green, blue]: [u8; 3]) -> [u8; 3] {
+        match self {
+            Self::Classic => [red, green, blue],
+            Self::Forest => [green, red, blue],
+            Self::Ocean => [blue, green, red],
+            Self::Gold => [red, (u16::from(red) * 5 / 6) as u8, blue],
+        }
+    }
+
+    /// Returns the boy's sprite sheet `image` in the colors of this skin.
+    pub(super) fn apply(self, image: &ImageSource) -> Result<ImageSource> {
+        if self == Self::Classic {
+            return Ok(image.clone());
+        }
+        image.edited(&format!("{}#{self:?}", image.url()), |pixels| {
+            swap_palette(pixels, self)
+        })
+    }
+}
+
+fn swap_palette(pixels: &mut [u8], skin: Skin) {
+    let (pixels, _) = pixels.as_chunks_mut::<4>();
+    for pixel in pixels {
+        let [red, green, blue, _alpha] = *pixel;
+        if red.saturating_sub(RED_MARGIN) > green && red.saturating_sub(RED_MARGIN) > blue {
+            pixel[..3].copy_from_slice(&skin.recolor([red, green, blue]));
+        }
+    }
+}
+
+/// Returns the best score reached in any session so far.
+pub(super) fn load_best_score() -> u32 {
+    match storage::load_json(BEST_SCORE_KEY) {
+        Ok(best_score) => best_score.unwrap_or_default(),
+        Err(err) => {
+            error!("error loading the best score: {err:#?}");
+            0
+        }
+    }
+}
+
+pub(super) fn save_best_score(best_score: u32) -> Result<()> {
+    storage::save_json(BEST_SCORE_KEY, &best_score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skins_recolor_only_the_hood_and_unlock_with_the_score() {
+        let mut pixels = [200, 30, 40, 255, 120, 110, 100, 255];
+        swap_palette(&mut pixels, Skin::Ocean);
+        assert_eq!(pixels, [40, 30, 200, 255, 120, 110, 100, 255]);
+
+        assert_eq!(Skin::Classic.next_unlocked(1, 30), Skin::Forest);
+        assert_eq!(Skin::Forest.next_unlocked(1, 30), Skin::Classic);
+        assert_eq!(Skin::Classic.next_unlocked(-1, 100), Skin::Gold);
+    }
+}
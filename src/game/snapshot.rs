@@ -0,0 +1,273 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    browser,
+    engine::{Cell, Image, ImageSource, Point, Rect, Shape, SpriteSheet},
+    segments::SegmentPicker,
+};
+
+use super::{
+    boost_pad::BoostPad,
+    boss::{Boss, Projectile},
+    level::GoalFlag,
+    overhead_bar::OverheadBar,
+    pool::Pool,
+    red_hat_boy::BoySnapshot,
+    saw::Saw,
+    spawn_obstacle,
+    spring::Spring,
+    Axis, Barrier, Coin, Collectible, Enemy, MovingPlatform, Obstacle, Platform, PowerUp, Reward,
+    Walk,
+};
+
+const SNAPSHOT_KEY: &str = "walk_the_dog.snapshot";
+
+/// Everything about a run that can change while it is played, so that it can
+/// be saved as JSON and resumed later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct WalkSnapshot {
+    boy: BoySnapshot,
+    obstacles: Vec<ObstacleSnapshot>,
+    items: Vec<(Point, Reward)>,
+    backgrounds: [f32; 2],
+    timeline: f32,
+    rebased_distance: f32,
+    score: u32,
+    best_score: u32,
+    #[serde(default)]
+    boss: Option<Boss>,
+    #[serde(default)]
+    bosses: u32,
+    /// The random number generator is reseeded from this when the snapshot
+    /// is taken, so that the segments after it are the same when resumed.
+    seed: u64,
+    /// Which segments the picker avoids next, for the same reason.
+    #[serde(default)]
+    segment_picker: SegmentPicker,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PlatformSnapshot {
+    position: Point,
+    sprites: Vec<Cell>,
+    colliders: Vec<Shape>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) enum ObstacleSnapshot {
+    Platform(PlatformSnapshot),
+    MovingPlatform {
+        platform: PlatformSnapshot,
+        axis: Axis,
+        amplitude: f32,
+        phase: f32,
+        offset: f32,
+        delta: f32,
+    },
+    Barrier {
+        position: Point,
+        #[serde(default)]
+        breakable: bool,
+    },
+    Enemy {
+        bounding_box: Rect,
+        base_y: f32,
+        velocity_x: f32,
+        amplitude: f32,
+        phase: f32,
+        call_timer: f32,
+    },
+    Projectile {
+        position: Point,
+    },
+    BoostPad {
+        position: Point,
+    },
+    Spring {
+        position: Point,
+    },
+    Pool {
+        position: Point,
+        width: f32,
+    },
+    Saw {
+        start: Point,
+        end: Point,
+        phase: f32,
+        #[serde(default)]
+        rotation: f32,
+    },
+    OverheadBar {
+        position: Point,
+    },
+    Goal {
+        position: Point,
+    },
+}
+
+impl Platform {
+    pub(super) fn platform_snapshot(&self) -> PlatformSnapshot {
+        PlatformSnapshot {
+            position: self.position,
+            sprites: self.sprites.clone(),
+            colliders: self.colliders.clone(),
+        }
+    }
+
+    fn restore(snapshot: PlatformSnapshot, sheet: &Rc<SpriteSheet>) -> Self {
+        Self {
+            sheet: Rc::clone(sheet),
+            colliders: snapshot.colliders,
+            sprites: snapshot.sprites,
+            position: snapshot.position,
+        }
+    }
+}
+
+impl ObstacleSnapshot {
+    fn restore(self, stone: &ImageSource, sheet: &Rc<SpriteSheet>) -> Box<dyn Obstacle> {
+        match self {
+            ObstacleSnapshot::Platform(platform) => Box::new(Platform::restore(platform, sheet)),
+            ObstacleSnapshot::MovingPlatform {
+                platform,
+                axis,
+                amplitude,
+                phase,
+                offset,
+                delta,
+            } => Box::new(MovingPlatform {
+                platform: Platform::restore(platform, sheet),
+                axis,
+                amplitude,
+                phase,
+                offset,
+                delta,
+            }),
+            ObstacleSnapshot::Barrier {
+                position,
+                breakable,
+            } => {
+                let image = Image::new(stone.clone(), position);
+                Box::new(if breakable {
+                    Barrier::breakable(image)
+                } else {
+                    Barrier::new(image)
+                })
+            }
+            ObstacleSnapshot::Enemy {
+                bounding_box,
+                base_y,
+                velocity_x,
+                amplitude,
+                phase,
+                call_timer,
+            } => Box::new(Enemy {
+                bounding_box,
+                base_y,
+                velocity_x,
+                amplitude,
+                phase,
+                call_timer,
+                calling: false,
+            }),
+            ObstacleSnapshot::Projectile { position } => Box::new(Projectile::new(position)),
+            ObstacleSnapshot::BoostPad { position } => Box::new(BoostPad::restore(position)),
+            ObstacleSnapshot::Spring { position } => Box::new(Spring::restore(position)),
+            ObstacleSnapshot::Pool { position, width } => Box::new(Pool::restore(position, width)),
+            ObstacleSnapshot::Saw {
+                start,
+                end,
+                phase,
+                rotation,
+            } => Box::new(Saw::restore(start, end, phase, rotation)),
+            ObstacleSnapshot::OverheadBar { position } => Box::new(OverheadBar::restore(position)),
+            ObstacleSnapshot::Goal { position } => Box::new(GoalFlag::restore(position)),
+        }
+    }
+}
+
+impl Walk {
+    /// Captures the run so far. This reseeds the random number generator, so
+    /// that the run continues in the same way as one resumed from the
+    /// snapshot.
+    pub(super) fn snapshot(&mut self) -> WalkSnapshot {
+        let seed = self.rng.gen();
+        self.rng = StdRng::seed_from_u64(seed);
+        let [first_background, second_background] = &self.backgrounds;
+        WalkSnapshot {
+            boy: self.boy.snapshot(),
+            obstacles: self
+                .obstacles
+                .values()
+                .map(|obstacle| obstacle.snapshot())
+                .collect(),
+            items: self
+                .items
+                .data()
+                .map(|(position, reward)| (position, *reward))
+                .collect(),
+            backgrounds: [
+                first_background.bounding_box().x(),
+                second_background.bounding_box().x(),
+            ],
+            timeline: self.timeline,
+            rebased_distance: self.rebased_distance,
+            score: self.score,
+            best_score: self.best_score,
+            boss: self.boss.clone(),
+            bosses: self.bosses,
+            seed,
+            segment_picker: self.segment_picker.clone(),
+        }
+    }
+
+    pub(super) fn restore(&mut self, snapshot: WalkSnapshot) -> Result<()> {
+        self.boy = self.boy.restore(&snapshot.boy)?;
+        self.obstacles.clear();
+        for obstacle in snapshot.obstacles {
+            let obstacle = obstacle.restore(&self.stone, &self.obstacle_sheet);
+            spawn_obstacle(&mut self.obstacles, obstacle);
+        }
+        self.items.clear();
+        for (position, reward) in snapshot.items {
+            match reward {
+                Reward::Points(_) => Coin::new(position).spawn(&mut self.items),
+                Reward::PowerUp(kind) => PowerUp::new(kind, position).spawn(&mut self.items),
+            };
+        }
+        for (background, x) in self.backgrounds.iter_mut().zip(snapshot.backgrounds) {
+            background.set_x(x);
+        }
+        self.timeline = snapshot.timeline;
+        self.rebased_distance = snapshot.rebased_distance;
+        self.score = snapshot.score;
+        self.best_score = snapshot.best_score;
+        self.boss = snapshot.boss;
+        self.bosses = snapshot.bosses;
+        self.rng = StdRng::seed_from_u64(snapshot.seed);
+        self.segment_picker = snapshot.segment_picker;
+        self.stop_slow_motion();
+        self.follow_boy();
+        Ok(())
+    }
+
+    /// Saves a snapshot of the run to local storage and logs it, so that it
+    /// can be attached to bug reports.
+    pub(super) fn save_snapshot(&mut self) -> Result<()> {
+        let json = browser::to_json_string(&self.snapshot())?;
+        info!("{json}");
+        browser::storage::save_item(SNAPSHOT_KEY, &json)
+    }
+
+    /// Resumes the run saved by [`Walk::save_snapshot`], if there is one.
+    pub(super) fn load_snapshot(&mut self) -> Result<()> {
+        match browser::storage::load_item(SNAPSHOT_KEY)? {
+            Some(json) => self.restore(browser::from_json_string(&json)?),
+            None => Ok(()),
+        }
+    }
+}
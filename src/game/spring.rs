@@ -0,0 +1,106 @@
+//! Springs on the ground that launch the boy higher than he can jump.
+
+use crate::engine::{Body, DebugCategory, Point, Rect, Renderer};
+
+use super::{red_hat_boy::RedHatBoy, Obstacle, ObstacleKind, ObstacleSnapshot, HEIGHT};
+
+#[derive(Debug, Clone)]
+pub(crate) struct Spring {
+    bounding_box: Rect,
+}
+
+impl Spring {
+    pub(crate) const WIDTH: f32 = 60.0;
+    const HEIGHT: f32 = 24.0;
+    /// The boy's vertical velocity right after a bounce, well above the
+    /// speed of a jump.
+    const LAUNCH_VELOCITY: f32 = -35.0;
+    const COILS: u8 = 3;
+
+    /// Places a spring on the ground, starting at `x`.
+    pub(crate) fn new(x: f32) -> Self {
+        Self::restore(Point {
+            x,
+            y: HEIGHT - Self::HEIGHT,
+        })
+    }
+
+    pub(super) fn restore(position: Point) -> Self {
+        Self {
+            bounding_box: Rect::new(position, Self::WIDTH, Self::HEIGHT),
+        }
+    }
+}
+
+impl Body for Spring {
+    fn bounding_box(&self) -> Rect {
+        self.bounding_box
+    }
+
+    fn move_by(&mut self, x: f32, y: f32) {
+        self.bounding_box.move_by(x, y);
+    }
+}
+
+impl Obstacle for Spring {
+    fn kind(&self) -> ObstacleKind {
+        ObstacleKind::Spring
+    }
+
+    fn left(&self) -> f32 {
+        self.bounding_box.left()
+    }
+
+    fn right(&self) -> f32 {
+        self.bounding_box.right()
+    }
+
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        // A boy on his way up has just been launched, or is jumping through.
+        if boy.velocity_y() >= 0.0 && boy.bounding_box().intersects(&self.bounding_box) {
+            boy.bounce(Self::LAUNCH_VELOCITY);
+        }
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        const PLATE_HEIGHT: f32 = 6.0;
+
+        let coil_height = (Self::HEIGHT - PLATE_HEIGHT * 2.0) / f32::from(Self::COILS);
+        for coil in 0..Self::COILS {
+            renderer.fill_rect(
+                &Rect::from_xy(
+                    self.bounding_box.x() + Self::WIDTH / 4.0,
+                    self.bounding_box.y() + PLATE_HEIGHT + coil_height * f32::from(coil),
+                    Self::WIDTH / 2.0,
+                    coil_height / 2.0,
+                ),
+                "silver",
+            );
+        }
+        renderer.fill_rect(
+            &Rect::from_xy(
+                self.bounding_box.x(),
+                self.bounding_box.y(),
+                Self::WIDTH,
+                PLATE_HEIGHT,
+            ),
+            "forestgreen",
+        );
+        renderer.fill_rect(
+            &Rect::from_xy(
+                self.bounding_box.x(),
+                self.bounding_box.bottom() - PLATE_HEIGHT,
+                Self::WIDTH,
+                PLATE_HEIGHT,
+            ),
+            "dimgray",
+        );
+        renderer.draw_bounding_box(&self.bounding_box, DebugCategory::Other);
+    }
+
+    fn snapshot(&self) -> ObstacleSnapshot {
+        ObstacleSnapshot::Spring {
+            position: self.bounding_box.position,
+        }
+    }
+}
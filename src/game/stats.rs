@@ -0,0 +1,171 @@
+//! Statistics over every run played, kept across sessions and shown on the
+//! stats screen.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    browser::storage,
+    engine::{
+        self, BlendMode, KeyState, Point, Rect, Renderer, Scene, SceneChange, TextAlign, TextStyle,
+        Transition,
+    },
+    i18n::{t, t_with},
+};
+
+use super::{Walk, HEIGHT, KEN_FUTURE, WIDTH};
+
+const STATS_KEY: &str = "walk_the_dog.stats";
+/// The boy is drawn a little over a meter tall.
+const PIXELS_PER_METER: f32 = 100.0;
+
+/// What happened during the run under way, counted from gameplay events.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct RunStats {
+    pub(super) jumps: u32,
+    pub(super) slides: u32,
+    /// Updates played, to tell the play time by.
+    pub(super) frames: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct Stats {
+    runs: u32,
+    /// In meters.
+    distance: f64,
+    jumps: u32,
+    slides: u32,
+    deaths: u32,
+    play_seconds: f64,
+}
+
+impl Stats {
+    /// Loads the saved statistics, or starts them over if there are none or
+    /// they cannot be read.
+    pub(super) fn load() -> Self {
+        match storage::load_json(STATS_KEY) {
+            Ok(stats) => stats.unwrap_or_default(),
+            Err(err) => {
+                error!("error loading the statistics: {err:#?}");
+                Self::default()
+            }
+        }
+    }
+
+    pub(super) fn save(&self) -> Result<()> {
+        storage::save_json(STATS_KEY, self)
+    }
+
+    /// Adds a run that ended after the boy ran `distance` pixels, counting
+    /// a death if he was knocked out.
+    pub(super) fn add_run(&mut self, run: &RunStats, distance: f32, died: bool) {
+        self.runs += 1;
+        self.distance += f64::from(distance / PIXELS_PER_METER);
+        self.jumps += run.jumps;
+        self.slides += run.slides;
+        self.deaths += u32::from(died);
+        self.play_seconds += f64::from(run.frames) / f64::from(engine::updates_per_second());
+    }
+
+    /// Returns the label key and value of every statistic, to list them.
+    fn rows(&self) -> [(&'static str, String); 6] {
+        let seconds = self.play_seconds as u64;
+        let play_time = format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        );
+        [
+            ("stats.runs", self.runs.to_string()),
+            (
+                "stats.distance",
+                t_with(
+                    "stats.meters",
+                    &[("meters", &format!("{:.0}", self.distance))],
+                ),
+            ),
+            ("stats.jumps", self.jumps.to_string()),
+            ("stats.slides", self.slides.to_string()),
+            ("stats.deaths", self.deaths.to_string()),
+            ("stats.play_time", play_time),
+        ]
+    }
+}
+
+/// Lists the statistics over the screen it was opened from.
+#[derive(Debug)]
+pub(super) struct StatsScreen;
+
+impl Scene<Walk> for StatsScreen {
+    fn update(&mut self, walk: &mut Walk, keystate: &KeyState) -> SceneChange<Walk> {
+        if walk.just_pressed(keystate, "Escape") || walk.just_pressed(keystate, "KeyT") {
+            SceneChange::Pop(Transition::Cut)
+        } else {
+            SceneChange::None
+        }
+    }
+
+    fn draw(&self, walk: &Walk, renderer: &Renderer) {
+        const TITLE_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 36)
+            .bold()
+            .with_color("white")
+            .with_stroke("black", 4)
+            .aligned(TextAlign::Center);
+        const ROW_STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 20).with_color("white");
+        const HELP_STYLE: TextStyle = TextStyle::new("serif", 16)
+            .with_color("white")
+            .aligned(TextAlign::Center);
+
+        walk.draw(renderer);
+        renderer.tint(
+            &Rect::from_xy(0.0, 0.0, WIDTH, HEIGHT),
+            "rgb(80, 80, 80)",
+            BlendMode::Multiply,
+        );
+        renderer.draw_text(
+            &t("stats.title"),
+            &Point {
+                x: WIDTH / 2.0,
+                y: 120.0,
+            },
+            &TITLE_STYLE,
+        );
+        for ((label, value), y) in walk.stats.rows().iter().zip((180_u16..).step_by(40)) {
+            let y = f32::from(y);
+            renderer.draw_text(&t(label), &Point { x: 140.0, y }, &ROW_STYLE);
+            renderer.draw_text(value, &Point { x: 360.0, y }, &ROW_STYLE);
+        }
+        renderer.draw_text(
+            &t("stats.help"),
+            &Point {
+                x: WIDTH / 2.0,
+                y: HEIGHT - 80.0,
+            },
+            &HELP_STYLE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_add_up() {
+        let mut stats = Stats::default();
+        let run = RunStats {
+            jumps: 3,
+            slides: 2,
+            frames: u32::from(engine::updates_per_second()) * 90,
+        };
+        stats.add_run(&run, 5000.0, true);
+        stats.add_run(&run, 1000.0, false);
+        assert_eq!(stats.runs, 2);
+        assert_eq!(stats.distance, 60.0);
+        assert_eq!(stats.jumps, 6);
+        assert_eq!(stats.deaths, 1);
+        assert_eq!(stats.rows()[5].1, "0:03:00");
+    }
+}
@@ -0,0 +1,205 @@
+//! Themed worlds. Every [`THEME_DISTANCE`] the run moves on to the next
+//! theme, with its own backgrounds, tiles and music. The next theme is
+//! fetched while the boy runs towards it, and swapped in once it has arrived:
+//! the old background fades out over the new one while the music crossfades.
+
+use std::rc::Rc;
+
+use anyhow::Result;
+use futures::channel::oneshot;
+
+use crate::{
+    browser,
+    engine::{self, AssetLoader, Audio, ImageSource, Point, Renderer, Sound, SpriteSheet},
+};
+
+/// How far the boy runs through each theme.
+const THEME_DISTANCE: f32 = 20000.0;
+/// How far ahead of the next theme its assets start being fetched.
+const PRELOAD_DISTANCE: f32 = 5000.0;
+/// How many seconds the old background takes to fade out.
+const FADE_SECONDS: f32 = 1.5;
+
+struct ThemeSpec {
+    background: &'static str,
+    night_background: &'static str,
+    /// The image of the tile sheet, laid out like `tiles.png`.
+    tiles: &'static str,
+    music: &'static str,
+}
+
+const THEMES: [ThemeSpec; 2] = [
+    ThemeSpec {
+        background: "images/BG.png",
+        night_background: "images/BG_night.png",
+        tiles: "sprites_sheets/tiles.png",
+        music: "sounds/background_song.mp3",
+    },
+    ThemeSpec {
+        background: "images/BG_autumn.png",
+        night_background: "images/BG_autumn_night.png",
+        tiles: "sprites_sheets/tiles_autumn.png",
+        music: "sounds/autumn_song.wav",
+    },
+];
+
+/// Returns the index of the theme of the world after running `distance`.
+fn theme_at(distance: f32) -> usize {
+    (distance / THEME_DISTANCE) as usize % THEMES.len()
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct ThemeAssets {
+    pub(super) background: ImageSource,
+    pub(super) night_background: ImageSource,
+    pub(super) obstacle_sheet: Rc<SpriteSheet>,
+    pub(super) music: Sound,
+}
+
+impl ThemeAssets {
+    async fn load(assets: AssetLoader, audio: Audio, index: usize) -> Result<Self> {
+        let theme = &THEMES[index];
+        let ([background, night_background], obstacle_sheet, music) = futures::try_join!(
+            assets.images([theme.background, theme.night_background]),
+            assets.sprite_sheet("sprites_sheets/tiles.json", theme.tiles),
+            assets.sound(&audio, theme.music),
+        )?;
+        Ok(Self {
+            background,
+            night_background,
+            obstacle_sheet,
+            music,
+        })
+    }
+}
+
+/// The background of the previous theme, fading out.
+#[derive(Debug)]
+struct Fade {
+    background: ImageSource,
+    remaining_frames: u16,
+}
+
+#[derive(Debug)]
+pub(super) struct Themes {
+    current: usize,
+    /// The assets of every theme loaded so far, by index.
+    loaded: Vec<Option<ThemeAssets>>,
+    /// Which themes have been fetched, so that one that failed to load is
+    /// not fetched over and over.
+    requested: Vec<bool>,
+    /// The theme being fetched and where its assets will arrive.
+    pending: Option<(usize, oneshot::Receiver<Result<ThemeAssets>>)>,
+    fade: Option<Fade>,
+}
+
+impl Themes {
+    /// Starts in the first theme, whose assets the run was built with.
+    pub(super) fn new(first: ThemeAssets) -> Self {
+        let mut loaded = vec![None; THEMES.len()];
+        loaded[0] = Some(first);
+        let mut requested = vec![false; THEMES.len()];
+        requested[0] = true;
+        Self {
+            current: 0,
+            loaded,
+            requested,
+            pending: None,
+            fade: None,
+        }
+    }
+
+    /// Goes back to the first theme, returning its assets if the world has
+    /// to be switched back to them.
+    pub(super) fn reset(&mut self) -> Option<ThemeAssets> {
+        self.fade = None;
+        if self.current == 0 {
+            return None;
+        }
+        self.current = 0;
+        self.loaded[0].clone()
+    }
+
+    /// Fetches the next theme once the boy is getting close to it, and
+    /// returns its assets once he has reached it and they have arrived. Until
+    /// they have, the world stays in the theme it is in.
+    pub(super) fn update(&mut self, distance: f32, audio: &Audio) -> Option<ThemeAssets> {
+        if let Some(fade) = &mut self.fade {
+            fade.remaining_frames = fade.remaining_frames.saturating_sub(1);
+            if fade.remaining_frames == 0 {
+                self.fade = None;
+            }
+        }
+        self.receive();
+
+        let upcoming = theme_at(distance + PRELOAD_DISTANCE);
+        if !self.requested[upcoming] && self.pending.is_none() {
+            self.requested[upcoming] = true;
+            let (sender, receiver) = oneshot::channel();
+            let audio = audio.clone();
+            browser::spawn_local(async move {
+                let theme = ThemeAssets::load(AssetLoader::shared(), audio, upcoming).await;
+                // The receiver is gone if the game was stopped meanwhile.
+                let _ = sender.send(theme);
+            });
+            self.pending = Some((upcoming, receiver));
+        }
+
+        let due = theme_at(distance);
+        if due == self.current {
+            return None;
+        }
+        let assets = self.loaded[due].clone()?;
+        self.fade = self.loaded[self.current].as_ref().map(|previous| Fade {
+            background: previous.background.clone(),
+            remaining_frames: engine::updates_in(FADE_SECONDS),
+        });
+        self.current = due;
+        Some(assets)
+    }
+
+    fn receive(&mut self) {
+        let Some((index, receiver)) = &mut self.pending else {
+            return;
+        };
+        let index = *index;
+        match receiver.try_recv() {
+            Ok(None) => return,
+            Ok(Some(Ok(assets))) => self.loaded[index] = Some(assets),
+            Ok(Some(Err(err))) => {
+                error!("error loading theme {index}: {err:#?}");
+            }
+            Err(oneshot::Canceled) => {
+                error!("theme {index} was never sent");
+            }
+        }
+        self.pending = None;
+    }
+
+    /// Draws the background of the previous theme over the new one at
+    /// `positions`, as opaque as there is fading left.
+    pub(super) fn draw_fade(&self, renderer: &Renderer, positions: impl Iterator<Item = Point>) {
+        let Some(fade) = &self.fade else {
+            return;
+        };
+        let alpha = f64::from(fade.remaining_frames) / f64::from(engine::updates_in(FADE_SECONDS));
+        renderer.with_alpha(alpha, |renderer| {
+            for position in positions {
+                renderer.draw_entire_image(&fade.background, position);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_change_every_theme_distance_and_cycle() {
+        assert_eq!(theme_at(0.0), 0);
+        assert_eq!(theme_at(THEME_DISTANCE - 1.0), 0);
+        assert_eq!(theme_at(THEME_DISTANCE), 1);
+        assert_eq!(theme_at(THEME_DISTANCE * THEMES.len() as f32), 0);
+    }
+}
@@ -0,0 +1,112 @@
+//! Short messages shown over the game for a moment, such as "New high
+//! score!". They are queued and shown one after another, fading in and out.
+
+use std::collections::VecDeque;
+
+use crate::engine::{self, Point, Renderer, TextAlign, TextStyle};
+
+use super::{KEN_FUTURE, WIDTH};
+
+const FADE_IN_SECONDS: f32 = 0.25;
+const SHOWN_SECONDS: f32 = 1.5;
+const FADE_OUT_SECONDS: f32 = 0.5;
+/// Messages beyond this many wait are dropped, so that a burst of events does
+/// not keep the screen busy long after it is over.
+const MAX_QUEUED: usize = 3;
+const TOP: f32 = 120.0;
+const STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 28)
+    .bold()
+    .with_color("gold")
+    .with_stroke("black", 3)
+    .aligned(TextAlign::Center);
+
+#[derive(Debug)]
+struct Toast {
+    message: String,
+    age: u16,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Toasts {
+    queue: VecDeque<Toast>,
+}
+
+impl Toasts {
+    pub(super) fn push(&mut self, message: String) {
+        if self.queue.len() < MAX_QUEUED {
+            self.queue.push_back(Toast { message, age: 0 });
+        }
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Ages the message on screen, replacing it with the next one once it has
+    /// faded out.
+    pub(super) fn update(&mut self) {
+        if let Some(toast) = self.queue.front_mut() {
+            toast.age += 1;
+            if toast.age >= Self::lifetime() {
+                self.queue.pop_front();
+            }
+        }
+    }
+
+    /// Returns how many updates a message is on the screen for.
+    fn lifetime() -> u16 {
+        [FADE_IN_SECONDS, SHOWN_SECONDS, FADE_OUT_SECONDS]
+            .into_iter()
+            .map(engine::updates_in)
+            .sum()
+    }
+
+    fn alpha(age: u16) -> f64 {
+        let fade_in = engine::updates_in(FADE_IN_SECONDS);
+        if age < fade_in {
+            f64::from(age) / f64::from(fade_in)
+        } else {
+            let fading = age.saturating_sub(fade_in + engine::updates_in(SHOWN_SECONDS));
+            1.0 - f64::from(fading) / f64::from(engine::updates_in(FADE_OUT_SECONDS))
+        }
+    }
+
+    pub(super) fn draw(&self, renderer: &Renderer) {
+        if let Some(toast) = self.queue.front() {
+            renderer.with_alpha(Self::alpha(toast.age), |renderer| {
+                let location = Point {
+                    x: WIDTH / 2.0,
+                    y: TOP,
+                };
+                renderer.draw_text(&toast.message, &location, &STYLE);
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toasts_fade_in_and_out_one_after_another() {
+        let mut toasts = Toasts::default();
+        toasts.push("first".to_string());
+        toasts.push("second".to_string());
+
+        let lifetime = Toasts::lifetime();
+        assert_eq!(Toasts::alpha(0), 0.0);
+        assert_eq!(Toasts::alpha(engine::updates_in(FADE_IN_SECONDS)), 1.0);
+        let half_faded = lifetime - engine::updates_in(FADE_OUT_SECONDS) / 2;
+        assert_eq!(Toasts::alpha(half_faded), 0.5);
+
+        for _ in 0..lifetime {
+            toasts.update();
+        }
+        assert_eq!(toasts.queue.front().unwrap().message, "second");
+        for _ in 0..lifetime {
+            toasts.update();
+        }
+        assert!(toasts.queue.is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{
+    browser,
+    engine::{KeyState, Point, Rect, Renderer, TextAlign, TextStyle, World},
+    i18n::t_with,
+};
+
+use super::{
+    settings::{Action, Bindings},
+    Obstacle, ObstacleKind, KEN_FUTURE, WIDTH,
+};
+
+const COMPLETED_KEY: &str = "walk_the_dog.tutorial_completed";
+const TRIGGER_DISTANCE: f32 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lesson {
+    JumpOverStone,
+    JumpOntoPlatform,
+}
+
+impl Lesson {
+    const fn obstacle(self) -> ObstacleKind {
+        match self {
+            Self::JumpOverStone => ObstacleKind::Stone,
+            Self::JumpOntoPlatform => ObstacleKind::Platform,
+        }
+    }
+
+    /// Returns the key of the lesson's prompt in [`crate::i18n`], which
+    /// names the key to press as `{key}`.
+    const fn prompt_key(self) -> &'static str {
+        match self {
+            Self::JumpOverStone => "tutorial.jump_over_stone",
+            Self::JumpOntoPlatform => "tutorial.jump_onto_platform",
+        }
+    }
+
+    const fn action(self) -> Action {
+        match self {
+            Self::JumpOverStone | Self::JumpOntoPlatform => Action::Jump,
+        }
+    }
+
+    /// Whether the lesson can be practised on `obstacle`, one of
+    /// `obstacles`.
+    fn applies_to(self, obstacle: &dyn Obstacle, obstacles: &World<Box<dyn Obstacle>>) -> bool {
+        obstacle.kind() == self.obstacle()
+            && match self {
+                Self::JumpOverStone => true,
+                Self::JumpOntoPlatform => has_room_on_top(obstacle, obstacles),
+            }
+    }
+}
+
+/// Whether the boy could stand on `platform`, which he cannot on the blocks a
+/// ceiling is built of: they hang from the top of the screen or from each
+/// other.
+fn has_room_on_top(platform: &dyn Obstacle, obstacles: &World<Box<dyn Obstacle>>) -> bool {
+    let Some(top) = platform
+        .collision_boxes()
+        .iter()
+        .map(Rect::top)
+        .reduce(f32::min)
+    else {
+        return false;
+    };
+    let above = Rect::from_xy(
+        platform.left(),
+        top - 1.0,
+        platform.right() - platform.left(),
+        1.0,
+    );
+    top > 0.0
+        && !obstacles
+            .values()
+            .flat_map(|obstacle| obstacle.collision_boxes())
+            .any(|area| area.intersects(&above))
+}
+
+/// Walks a first-time player through the basic moves by freezing the game in
+/// front of the first obstacle of each kind until the prompted key is pressed.
+#[derive(Debug)]
+pub(crate) struct Tutorial {
+    pending: Vec<Lesson>,
+    active: Option<Lesson>,
+}
+
+impl Tutorial {
+    /// Returns `None` if the player has already finished the tutorial.
+    pub(crate) fn load() -> Option<Self> {
+        match browser::storage::load_item(COMPLETED_KEY) {
+            Ok(Some(_)) => None,
+            Ok(None) => Some(Self {
+                pending: vec![Lesson::JumpOverStone, Lesson::JumpOntoPlatform],
+                active: None,
+            }),
+            Err(err) => {
+                error!("error loading tutorial progress: {err:#?}");
+                None
+            }
+        }
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.pending.is_empty() && self.active.is_none()
+    }
+
+    /// Advances the tutorial and returns `true` while the game should stay paused.
+    pub(crate) fn update(
+        &mut self,
+        keystate: &KeyState,
+        bindings: &Bindings,
+        obstacles: &World<Box<dyn Obstacle>>,
+        boy_right: f32,
+    ) -> bool {
+        if let Some(lesson) = self.active {
+            if !keystate.is_pressed(bindings.key(lesson.action())) {
+                return true;
+            }
+            self.active = None;
+            if self.is_finished() {
+                if let Err(err) = browser::storage::save_item(COMPLETED_KEY, "true") {
+                    error!("error saving tutorial progress: {err:#?}");
+                }
+            }
+            return false;
+        }
+
+        let approaching = |lesson: &Lesson| {
+            obstacles.values().any(|obstacle| {
+                lesson.applies_to(obstacle.as_ref(), obstacles)
+                    && (0.0..TRIGGER_DISTANCE).contains(&(obstacle.left() - boy_right))
+            })
+        };
+        if let Some(index) = self.pending.iter().position(approaching) {
+            self.active = Some(self.pending.remove(index));
+            return true;
+        }
+        false
+    }
+
+    pub(crate) fn draw(&self, renderer: &Renderer, bindings: &Bindings) {
+        const STYLE: TextStyle = TextStyle::new(KEN_FUTURE, 20)
+            .with_color("white")
+            .aligned(TextAlign::Center);
+        const PADDING: f32 = 20.0;
+
+        if let Some(lesson) = self.active {
+            let prompt = t_with(
+                lesson.prompt_key(),
+                &[("key", bindings.key(lesson.action()))],
+            );
+            let text_width = renderer
+                .measure_text(&prompt, &STYLE)
+                .map_or(WIDTH, |width| width.ceil() as f32 + PADDING * 2.0);
+            renderer.fill_rect(
+                &Rect::from_xy((WIDTH - text_width) / 2.0, 150.0, text_width, 60.0),
+                "rgba(0, 0, 0, 0.5)",
+            );
+            renderer.draw_text(
+                &prompt,
+                &Point {
+                    x: WIDTH / 2.0,
+                    y: 188.0,
+                },
+                &STYLE,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        engine::{ImageSource, SpriteSheet},
+        game::spawn_obstacle,
+        segments,
+    };
+
+    /// Returns the obstacles of the segment called `name`, generated from
+    /// `seed`.
+    fn obstacles(name: &str, seed: u64) -> World<Box<dyn Obstacle>> {
+        let blank = |url: &str, width, height| ImageSource::Blank {
+            url: url.into(),
+            width,
+            height,
+        };
+        let tiles = SpriteSheet::new(
+            serde_json::from_str(include_str!("../../static/sprites_sheets/tiles.json")).unwrap(),
+            blank("tiles.png", 650, 485),
+        );
+        let generate = segments::generator(name).unwrap().generate;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut obstacles = World::new();
+        for obstacle in
+            generate(&mut rng, blank("Stone.png", 90, 54), Rc::new(tiles), 0.0).obstacles
+        {
+            spawn_obstacle(&mut obstacles, obstacle);
+        }
+        obstacles
+    }
+
+    /// Whether the boy running through `obstacles` is taught to jump onto a
+    /// platform.
+    fn teaches_platforms(obstacles: &World<Box<dyn Obstacle>>) -> bool {
+        let mut tutorial = Tutorial {
+            pending: vec![Lesson::JumpOntoPlatform],
+            active: None,
+        };
+        let bindings = Bindings::default();
+        (-1000..1000).step_by(10).any(|boy_right| {
+            tutorial.update(&KeyState::new(), &bindings, obstacles, boy_right as f32)
+        })
+    }
+
+    #[test]
+    fn platforms_are_taught_on_floating_platforms_only() {
+        // Ceilings are built as deep as four blocks, down to where a
+        // floating platform could be.
+        for seed in 0..20 {
+            assert!(teaches_platforms(&obstacles("floating_and_stone", seed)));
+            assert!(!teaches_platforms(&obstacles("ceiling", seed)));
+        }
+    }
+}
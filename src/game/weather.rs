@@ -0,0 +1,206 @@
+//! Rain and snow: a layer of particles falling over the world and a slippery
+//! ground that keeps the boy sliding forward for a moment after he lands.
+//!
+//! The weather changes at distance milestones, can be set for the length of a
+//! segment by the segment itself, or can be fixed for the whole session with
+//! `?weather=` in the URL.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{self, Rect, Renderer};
+
+use super::{HEIGHT, WIDTH};
+
+/// The weather changes every time the boy has run this far.
+const MILESTONE_DISTANCE: f32 = 15000.0;
+/// The weather of each milestone, repeated once they run out.
+const MILESTONES: [Weather; 4] = [Weather::Clear, Weather::Rain, Weather::Clear, Weather::Snow];
+/// How much of the landing slip is left after each default-rate update.
+const SLIP_DECAY: f32 = 0.9;
+/// Slips shorter than this many pixels per default-rate update are stopped.
+const MIN_SLIP: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Weather {
+    #[default]
+    Clear,
+    Rain,
+    Snow,
+}
+
+impl Weather {
+    /// Returns the weather at `distance` from the start of the run, unless a
+    /// segment says otherwise.
+    pub(super) fn for_distance(distance: f32) -> Self {
+        let milestone = (distance / MILESTONE_DISTANCE) as usize;
+        MILESTONES[milestone % MILESTONES.len()]
+    }
+
+    /// How many pixels per update the boy slides on after landing.
+    const fn landing_slip(self) -> f32 {
+        match self {
+            Self::Clear => 0.0,
+            Self::Rain => 2.0,
+            Self::Snow => 4.0,
+        }
+    }
+
+    const fn particle_count(self) -> usize {
+        match self {
+            Self::Clear => 0,
+            Self::Rain => 120,
+            Self::Snow => 80,
+        }
+    }
+}
+
+impl FromStr for Weather {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clear" => Ok(Self::Clear),
+            "rain" => Ok(Self::Rain),
+            "snow" => Ok(Self::Snow),
+            _ => Err(anyhow!("unknown weather {s:?}")),
+        }
+    }
+}
+
+/// A raindrop or a snowflake, in screen coordinates.
+#[derive(Debug, Clone)]
+struct Particle {
+    x: f32,
+    y: f32,
+    speed: f32,
+    weather: Weather,
+}
+
+#[derive(Debug)]
+pub(super) struct WeatherEffects {
+    weather: Weather,
+    particles: Vec<Particle>,
+    /// Kept apart from the run's generator, so that the weather does not
+    /// change the levels.
+    rng: StdRng,
+    slip: f32,
+}
+
+impl WeatherEffects {
+    pub(super) fn new(seed: u64) -> Self {
+        Self {
+            weather: Weather::Clear,
+            particles: vec![],
+            rng: StdRng::seed_from_u64(seed),
+            slip: 0.0,
+        }
+    }
+
+    /// Changes the weather. Particles already falling finish their fall, and
+    /// the new ones start at the top of the screen.
+    pub(super) fn set_weather(&mut self, weather: Weather) {
+        self.weather = weather;
+    }
+
+    /// Starts the slide that follows a landing on wet or snowy ground.
+    pub(super) fn land(&mut self) {
+        self.slip = self.weather.landing_slip();
+    }
+
+    /// Moves the particles and returns how far the boy slides in this update.
+    pub(super) fn update(&mut self) -> f32 {
+        let scale = engine::update_scale();
+        self.particles.retain_mut(|particle| {
+            match particle.weather {
+                Weather::Snow => {
+                    particle.y += particle.speed * scale;
+                    particle.x -= (0.5 + (particle.y / 40.0).sin() * 0.5) * scale;
+                }
+                _ => {
+                    particle.y += particle.speed * scale;
+                    particle.x -= particle.speed / 4.0 * scale;
+                }
+            }
+            particle.y < HEIGHT && particle.x > 0.0
+        });
+        if self.particles.len() < self.weather.particle_count() {
+            let (x, speed) = match self.weather {
+                Weather::Snow => (
+                    self.rng.gen_range(0.0..WIDTH * 1.2),
+                    self.rng.gen_range(1.0..2.5),
+                ),
+                _ => (
+                    self.rng.gen_range(0.0..WIDTH * 1.5),
+                    self.rng.gen_range(10.0..14.0),
+                ),
+            };
+            self.particles.push(Particle {
+                x,
+                y: 0.0,
+                speed,
+                weather: self.weather,
+            });
+        }
+
+        let slip = self.slip;
+        let decayed = slip * SLIP_DECAY.powf(scale);
+        self.slip = if decayed < MIN_SLIP { 0.0 } else { decayed };
+        slip * scale
+    }
+
+    pub(super) fn draw(&self, renderer: &Renderer) {
+        for particle in &self.particles {
+            match particle.weather {
+                Weather::Snow => {
+                    renderer.fill_rect(&Rect::from_xy(particle.x, particle.y, 3.0, 3.0), "white");
+                }
+                _ => renderer.fill_rect(
+                    &Rect::from_xy(particle.x, particle.y, 1.0, 10.0),
+                    "rgba(170, 190, 255, 0.6)",
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_weather_changes_at_milestones() {
+        assert_eq!(Weather::for_distance(0.0), Weather::Clear);
+        assert_eq!(Weather::for_distance(MILESTONE_DISTANCE), Weather::Rain);
+        assert_eq!(
+            Weather::for_distance(MILESTONE_DISTANCE * 3.5),
+            Weather::Snow
+        );
+        assert_eq!(
+            Weather::for_distance(MILESTONE_DISTANCE * 4.0),
+            Weather::Clear
+        );
+        assert_eq!("snow".parse::<Weather>().unwrap(), Weather::Snow);
+        assert!("hail".parse::<Weather>().is_err());
+    }
+
+    #[test]
+    fn landings_on_snow_slide_until_the_slip_wears_off() {
+        let mut effects = WeatherEffects::new(1);
+        effects.land();
+        assert_eq!(effects.update(), 0.0);
+
+        effects.set_weather(Weather::Snow);
+        effects.land();
+        let slips: Vec<_> = std::iter::from_fn(|| Some(effects.update()))
+            .take_while(|slip| *slip > 0.0)
+            .collect();
+        assert_eq!(slips[0], Weather::Snow.landing_slip());
+        assert!(slips.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(slips.len() < 60);
+    }
+}
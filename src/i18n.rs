@@ -0,0 +1,196 @@
+//! Looks up the text shown to players in their language.
+//!
+//! Every message has a key and an English text built into the game.
+//! Translations live in `locales/<language>.json`, which maps keys to
+//! translated texts, for every language in [`LANGUAGES`]. Messages missing from a translation fall back to English.
+//! Texts can contain placeholders such as `{score}`, which [`t_with`] fills
+//! in.
+
+use std::{cell::RefCell, collections::HashMap};
+
+use anyhow::Result;
+
+use crate::{browser, engine::AssetLoader};
+
+/// The languages the game can be shown in. Any other is shown in English.
+const LANGUAGES: &[&str] = &["en", "de"];
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("hud.score", "Score {score}"),
+    ("hud.best", "Best {score}"),
+    ("hud.speed", "Speed {speed}"),
+    ("hud.combo", "Combo x{multiplier}"),
+    ("hud.enable_sound", "Click or press any key to enable sound"),
+    ("paused.title", "Paused"),
+    ("paused.settings_hint", "Press S for settings"),
+    ("paused.stats_hint", "Press T for statistics"),
+    ("game_over.title", "Game Over"),
+    ("game_over.score", "Score {score}"),
+    ("game_over.best", "Best {score}"),
+    ("game_over.play_again", "Play again"),
+    (
+        "game_over.stats_hint",
+        "Press {key} to restart, T for statistics",
+    ),
+    ("game_over.share", "Share"),
+    ("share.title", "Walk the Dog"),
+    (
+        "share.text",
+        "I scored {score} in Walk the Dog. Can you beat me on the same run?",
+    ),
+    ("share.copied", "Link copied to the clipboard"),
+    ("share.failed", "Could not share the score"),
+    ("level_complete.title", "Level Complete"),
+    ("level_complete.time", "Time {seconds} s"),
+    ("level_complete.coins", "Coins {coins}"),
+    ("stats.title", "Statistics"),
+    ("stats.runs", "Runs"),
+    ("stats.distance", "Distance"),
+    ("stats.meters", "{meters} m"),
+    ("stats.jumps", "Jumps"),
+    ("stats.slides", "Slides"),
+    ("stats.deaths", "Deaths"),
+    ("stats.play_time", "Play time"),
+    ("stats.help", "Escape or T goes back"),
+    ("settings.title", "Settings"),
+    ("settings.music_volume", "Music volume"),
+    ("settings.sfx_volume", "Sound volume"),
+    ("settings.muted", "Mute"),
+    ("settings.reduced_motion", "Reduced motion"),
+    ("settings.one_button", "One-button mode"),
+    ("settings.rumble", "Gamepad rumble"),
+    ("settings.pixel_perfect_collisions", "Pixel collisions"),
+    ("settings.debug_mode", "Debug mode"),
+    ("settings.log_input", "Log input"),
+    ("settings.skin", "Skin"),
+    ("settings.on", "On"),
+    ("settings.off", "Off"),
+    ("settings.press_a_key", "Press a key"),
+    (
+        "settings.help",
+        "Arrows select and change, Enter toggles or rebinds, Escape saves",
+    ),
+    ("skin.classic", "Classic"),
+    ("skin.forest", "Forest"),
+    ("skin.ocean", "Ocean"),
+    ("skin.gold", "Gold"),
+    ("action.run", "Run"),
+    ("action.jump", "Jump"),
+    ("action.slide", "Slide"),
+    ("action.attack", "Attack"),
+    ("action.pause", "Pause"),
+    ("action.mute", "Mute"),
+    ("action.restart", "Restart"),
+    (
+        "tutorial.jump_over_stone",
+        "Press {key} to jump over the stone",
+    ),
+    (
+        "tutorial.jump_onto_platform",
+        "Press {key} to jump onto the platform",
+    ),
+    ("toast.new_high_score", "New high score!"),
+    ("toast.checkpoint_reached", "Checkpoint reached"),
+    ("toast.sound_enabled", "Sound enabled"),
+    ("toast.boss_defeated", "Boss defeated!"),
+    ("toast.screenshot_saved", "Screenshot saved"),
+    ("toast.cheat_on", "Cheat on: {cheat}"),
+    ("toast.cheat_off", "Cheat off: {cheat}"),
+    ("cheat.infinite_lives", "Infinite lives"),
+    ("cheat.big_head", "Big head"),
+    ("cheat.all_skins", "All skins"),
+    ("power_up.invincibility", "Invincible"),
+    ("power_up.speed_boost", "Speed"),
+    ("power_up.shield", "Shield"),
+    ("power_up.magnet", "Magnet"),
+    ("frame_graph.summary", "{fps} fps {average}ms {slow} slow"),
+    ("splash.title", "Walk the Dog"),
+    ("crash.title", "Something went wrong"),
+    ("crash.reload", "Reload"),
+    ("loading_failed.title", "Some files could not be loaded"),
+    ("loading_failed.retry", "Retry"),
+];
+
+thread_local! {
+    static TRANSLATION: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+/// Returns the language to show the game in: `language` if it is given,
+/// otherwise the browser's, without its region, e.g. `de` for `de-AT`. Falls
+/// back to English if there is no translation into it.
+pub(crate) fn pick_language(language: Option<&str>) -> Result<String> {
+    let language = match language {
+        Some(language) => language.to_string(),
+        None => browser::language()?,
+    };
+    let language = language
+        .split('-')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if !LANGUAGES.contains(&language.as_str()) {
+        info!("no translation into {language:?}, showing English");
+        return Ok("en".to_string());
+    }
+    Ok(language)
+}
+
+/// Loads the translation into `language`, replacing the one loaded before.
+/// English needs no translation.
+pub(crate) async fn load(assets: &AssetLoader, language: &str) -> Result<()> {
+    let translation = if language == "en" {
+        HashMap::new()
+    } else {
+        assets
+            .json::<HashMap<String, String>>(&format!("locales/{language}.json"))
+            .await?
+    };
+    TRANSLATION.with(|current| current.replace(translation));
+    Ok(())
+}
+
+/// Returns the text of the message `key` in the current language. Unknown
+/// keys are returned as they are, so that they stand out.
+pub(crate) fn t(key: &str) -> String {
+    TRANSLATION.with(|translation| match translation.borrow().get(key) {
+        Some(text) => text.clone(),
+        None => ENGLISH
+            .iter()
+            .find(|(english_key, _)| *english_key == key)
+            .map_or(key, |(_, text)| text)
+            .to_string(),
+    })
+}
+
+/// Returns the text of the message `key` with every `{name}` placeholder
+/// replaced by the value of `name` in `args`.
+pub(crate) fn t_with(key: &str, args: &[(&str, &str)]) -> String {
+    args.iter().fold(t(key), |text, (name, value)| {
+        text.replace(&format!("{{{name}}}"), value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_translations_fall_back_to_english() {
+        TRANSLATION.with(|translation| {
+            translation.replace(HashMap::from([(
+                "paused.title".to_string(),
+                "Pause".to_string(),
+            )]))
+        });
+        assert_eq!(t("paused.title"), "Pause");
+        assert_eq!(t("game_over.title"), "Game Over");
+        assert_eq!(t("no.such.key"), "no.such.key");
+        assert_eq!(t_with("hud.score", &[("score", "12")]), "Score 12");
+    }
+
+    #[test]
+    fn languages_without_a_translation_fall_back_to_english() {
+        assert_eq!(pick_language(Some("de-AT")).unwrap(), "de");
+        assert_eq!(pick_language(Some("fr-FR")).unwrap(), "en");
+    }
+}
@@ -1,25 +1,104 @@
-use engine::GameLoop;
+use std::cell::RefCell;
+
+use config::Config;
+use engine::{GameLoop, GameLoopHandle, DEFAULT_UPDATES_PER_SECOND};
 use game::WalkTheDog;
 use wasm_bindgen::prelude::*;
 
+#[macro_use]
+mod logging;
+
 #[macro_use]
 mod browser;
+mod config;
 mod engine;
 mod game;
+mod i18n;
+mod net;
 mod segments;
 mod sound;
+mod tiled;
+
+thread_local! {
+    static GAME_LOOP: RefCell<Option<GameLoopHandle>> = const { RefCell::new(None) };
+}
 
 // This is like the `main` function, except for JavaScript.
 #[wasm_bindgen(start)]
 pub fn main_js() -> Result<(), JsValue> {
-    console_error_panic_hook::set_once();
+    std::panic::set_hook(Box::new(on_panic));
 
     browser::spawn_local(async move {
-        let game = WalkTheDog::new();
-        GameLoop::start(game)
+        let config = match Config::from_url() {
+            Ok(config) => config,
+            Err(err) => {
+                error!("error reading the options in the URL: {err:#}");
+                Config::default()
+            }
+        };
+        logging::set_filter(config.log.clone().unwrap_or_default());
+        if let Some(mode) = config.golden {
+            if let Err(err) = game::run_golden_tests(mode).await {
+                error!("{err:#}");
+            }
+            return;
+        }
+
+        let seed = config.seed.unwrap_or_else(rand::random);
+        info!("seed: {seed}");
+        let updates_per_second = config
+            .updates_per_second
+            .unwrap_or(DEFAULT_UPDATES_PER_SECOND);
+        let record = config.record;
+        let game = WalkTheDog::new(seed, config);
+        let recording = record.then(|| game.new_recording(updates_per_second));
+        let game_loop = GameLoop::start(game, updates_per_second, recording)
             .await
             .expect("could not start game loop");
+        GAME_LOOP.with(|handle| handle.replace(Some(game_loop)));
     });
 
     Ok(())
 }
+
+/// Logs a panic with its stack trace, then tells the player about it, since
+/// the game stops updating and would otherwise just freeze.
+fn on_panic(info: &std::panic::PanicHookInfo) {
+    console_error_panic_hook::hook(info);
+    if let Err(err) = browser::show_crash_overlay(
+        &i18n::t("crash.title"),
+        &info.to_string(),
+        &i18n::t("crash.reload"),
+    ) {
+        error!("{err:#}");
+    }
+}
+
+/// Stops the game, e.g. before the page removes the canvas it draws on.
+#[wasm_bindgen]
+pub fn stop_game() -> Result<(), JsValue> {
+    match GAME_LOOP.with(RefCell::take) {
+        Some(game_loop) => game_loop
+            .stop()
+            .map_err(|err| JsValue::from_str(&format!("{err:#}"))),
+        None => Ok(()),
+    }
+}
+
+/// Returns the keys recorded so far with `?record` as JSON, or `undefined`
+/// if they are not being recorded.
+#[wasm_bindgen]
+pub fn input_recording() -> Result<Option<String>, JsValue> {
+    GAME_LOOP.with(|game_loop| {
+        let Some(recording) = game_loop
+            .borrow()
+            .as_ref()
+            .and_then(GameLoopHandle::input_recording)
+        else {
+            return Ok(None);
+        };
+        browser::to_json_string(&recording)
+            .map(Some)
+            .map_err(|err| JsValue::from_str(&format!("{err:#}")))
+    })
+}
@@ -0,0 +1,243 @@
+//! Leveled logging to the browser console, e.g. `info!("seed: {seed}")`.
+//!
+//! What is logged can be narrowed down per module with the `log` option in
+//! the page URL, e.g. `?log=warn,engine=debug,game::net=trace`, and a line
+//! that logs every frame is cut down to a few messages a second.
+
+use std::{cell::RefCell, collections::HashMap, fmt, str::FromStr};
+
+use anyhow::{anyhow, Error};
+
+use crate::browser;
+
+/// How many messages a single line can log in [`RATE_LIMIT_WINDOW`]; the
+/// ones after are dropped, and counted in the next one that gets through.
+const RATE_LIMIT: u32 = 10;
+const RATE_LIMIT_WINDOW: f64 = 1000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    const fn name(self) -> &'static str {
+        match self {
+            Level::Trace => "TRACE",
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trace" => Ok(Level::Trace),
+            "debug" => Ok(Level::Debug),
+            "info" => Ok(Level::Info),
+            "warn" => Ok(Level::Warn),
+            "error" => Ok(Level::Error),
+            _ => Err(anyhow!("unknown log level {s:?}")),
+        }
+    }
+}
+
+/// The lowest level logged, for the whole game and for some of its modules.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Filter {
+    default: Level,
+    /// Module paths without the crate name, e.g. `game::boss`, each covering
+    /// its submodules too.
+    modules: Vec<(String, Level)>,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self {
+            default: Level::Info,
+            modules: vec![],
+        }
+    }
+}
+
+impl Filter {
+    fn level_for(&self, module_path: &str) -> Level {
+        let module = module_path
+            .split_once("::")
+            .map_or("", |(_crate, module)| module);
+        self.modules
+            .iter()
+            .filter(|(prefix, _)| {
+                module
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn enabled(&self, level: Level, module_path: &str) -> bool {
+        level >= self.level_for(module_path)
+    }
+}
+
+/// Reads a comma-separated list of a level for the whole game and
+/// `module=level` pairs, e.g. `warn,engine=debug`.
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = Filter::default();
+        for directive in s.split(',').filter(|directive| !directive.is_empty()) {
+            match directive.split_once('=') {
+                Some((module, level)) => filter.modules.push((module.to_string(), level.parse()?)),
+                None => filter.default = directive.parse()?,
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// How much a line logged in the current window.
+#[derive(Debug, Clone, Copy)]
+struct Site {
+    window_start: f64,
+    logged: u32,
+    dropped: u32,
+}
+
+#[derive(Debug, Default)]
+struct Logger {
+    filter: Filter,
+    sites: HashMap<(&'static str, u32), Site>,
+}
+
+impl Logger {
+    /// Returns whether the message logged by `line` of `module_path` at `now`
+    /// gets through the rate limit, with how many were dropped before it.
+    fn admit(&mut self, module_path: &'static str, line: u32, now: f64) -> Option<u32> {
+        let site = self.sites.entry((module_path, line)).or_insert(Site {
+            window_start: now,
+            logged: 0,
+            dropped: 0,
+        });
+        if now - site.window_start >= RATE_LIMIT_WINDOW {
+            site.window_start = now;
+            site.logged = 0;
+        }
+        if site.logged == RATE_LIMIT {
+            site.dropped += 1;
+            return None;
+        }
+        site.logged += 1;
+        Some(std::mem::take(&mut site.dropped))
+    }
+}
+
+thread_local! {
+    static LOGGER: RefCell<Logger> = RefCell::new(Logger::default());
+}
+
+/// Replaces what is logged, e.g. with the `log` option of the page URL.
+pub(crate) fn set_filter(filter: Filter) {
+    LOGGER.with(|logger| logger.borrow_mut().filter = filter);
+}
+
+pub(crate) fn enabled(level: Level, module_path: &str) -> bool {
+    LOGGER.with(|logger| logger.borrow().filter.enabled(level, module_path))
+}
+
+/// Writes a message to the console; see the macros instead.
+pub(crate) fn write(level: Level, module_path: &'static str, line: u32, args: fmt::Arguments) {
+    let now = if cfg!(target_arch = "wasm32") {
+        browser::now().unwrap_or_default()
+    } else {
+        0.0
+    };
+    let Some(dropped) = LOGGER.with(|logger| logger.borrow_mut().admit(module_path, line, now))
+    else {
+        return;
+    };
+    let module = module_path
+        .split_once("::")
+        .map_or(module_path, |(_crate, module)| module);
+    let mut message = format!("[{} {module}] {args}", level.name());
+    if dropped > 0 {
+        message.push_str(&format!(" ({dropped} more dropped)"));
+    }
+    if !cfg!(target_arch = "wasm32") {
+        eprintln!("{message}");
+        return;
+    }
+    let message = message.into();
+    match level {
+        Level::Trace | Level::Debug => web_sys::console::debug_1(&message),
+        Level::Info => web_sys::console::info_1(&message),
+        Level::Warn => web_sys::console::warn_1(&message),
+        Level::Error => web_sys::console::error_1(&message),
+    }
+}
+
+macro_rules! log_at {
+    ($level:expr, $($t:tt)*) => {
+        if $crate::logging::enabled($level, module_path!()) {
+            $crate::logging::write($level, module_path!(), line!(), format_args!($($t)*));
+        }
+    }
+}
+
+macro_rules! trace {
+    ($($t:tt)*) => { log_at!($crate::logging::Level::Trace, $($t)*) }
+}
+
+macro_rules! debug {
+    ($($t:tt)*) => { log_at!($crate::logging::Level::Debug, $($t)*) }
+}
+
+macro_rules! info {
+    ($($t:tt)*) => { log_at!($crate::logging::Level::Info, $($t)*) }
+}
+
+macro_rules! warn {
+    ($($t:tt)*) => { log_at!($crate::logging::Level::Warn, $($t)*) }
+}
+
+macro_rules! error {
+    ($($t:tt)*) => { log_at!($crate::logging::Level::Error, $($t)*) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modules_are_filtered_by_their_longest_prefix_and_lines_are_rate_limited() {
+        let filter: Filter = "warn,game=debug,game::boss=error".parse().unwrap();
+        assert!(filter.enabled(Level::Warn, "walk_the_dog::engine"));
+        assert!(!filter.enabled(Level::Info, "walk_the_dog::engine"));
+        assert!(filter.enabled(Level::Debug, "walk_the_dog::game::saw"));
+        assert!(!filter.enabled(Level::Warn, "walk_the_dog::game::boss"));
+        assert!(filter.enabled(Level::Debug, "walk_the_dog::game::bossy"));
+        assert!("loud".parse::<Filter>().is_err());
+
+        let mut logger = Logger::default();
+        let admitted: Vec<_> = (0..12)
+            .map(|i| logger.admit("walk_the_dog::game", 1, f64::from(i)))
+            .collect();
+        assert_eq!(admitted.iter().flatten().count(), RATE_LIMIT as usize);
+        assert_eq!(logger.admit("walk_the_dog::game", 2, 0.0), Some(0));
+        assert_eq!(
+            logger.admit("walk_the_dog::game", 1, RATE_LIMIT_WINDOW),
+            Some(2)
+        );
+    }
+}
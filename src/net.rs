@@ -0,0 +1,206 @@
+//! Races against other players by exchanging positions with a relay server
+//! over a WebSocket.
+//!
+//! The server is expected to forward every text message it receives from a
+//! client to all the other clients. Messages are JSON objects tagged with a
+//! `type`, e.g. `{"type":"position","player":7,"x":120.0,"y":478.0,
+//! "frame":"Run (3).png"}`.
+
+use anyhow::{anyhow, Result};
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use serde::{Deserialize, Serialize};
+use web_sys::{MessageEvent, WebSocket};
+
+use crate::browser::{self, EventListenerHandle};
+
+const FIRST_RETRY_DELAY: f64 = 1000.0;
+const MAX_RETRY_DELAY: f64 = 30000.0;
+
+pub(crate) type PlayerId = u32;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum Message {
+    /// Where a player's runner is in the world, measured from the start of
+    /// the run, and the sprite frame it shows.
+    Position {
+        player: PlayerId,
+        x: f32,
+        y: f32,
+        frame: String,
+    },
+    /// Sent when a player stops playing, so that others can remove them
+    /// without waiting for them to time out.
+    Leave { player: PlayerId },
+}
+
+/// How long to wait before reconnecting, doubling after every failed attempt.
+#[derive(Debug)]
+struct Backoff {
+    delay: f64,
+}
+
+impl Backoff {
+    fn new() -> Self {
+        Self {
+            delay: FIRST_RETRY_DELAY,
+        }
+    }
+
+    fn next_delay(&mut self) -> f64 {
+        let delay = self.delay;
+        self.delay = (self.delay * 2.0).min(MAX_RETRY_DELAY);
+        delay
+    }
+}
+
+#[derive(Debug)]
+struct Connection {
+    socket: WebSocket,
+    _message_listener: EventListenerHandle,
+}
+
+impl Connection {
+    fn open(url: &str, sender: UnboundedSender<Message>) -> Result<Self> {
+        let socket =
+            WebSocket::new(url).map_err(|err| anyhow!("could not connect to `{url}`: {err:#?}"))?;
+        let on_message = browser::closure_wrap(Box::new(move |event: MessageEvent| {
+            let Some(json) = event.data().as_string() else {
+                return;
+            };
+            match browser::from_json_string(&json) {
+                Ok(message) => {
+                    if let Err(err) = sender.unbounded_send(message) {
+                        error!("error receiving message: {err:#?}");
+                    }
+                }
+                Err(err) => {
+                    error!("ignoring unknown message {json}: {err:#?}");
+                }
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+        let message_listener = browser::add_event_listener(&socket, "message", on_message)?;
+        Ok(Self {
+            socket,
+            _message_listener: message_listener,
+        })
+    }
+
+    fn is_open(&self) -> bool {
+        self.socket.ready_state() == WebSocket::OPEN
+    }
+
+    fn is_closed(&self) -> bool {
+        self.socket.ready_state() == WebSocket::CLOSED
+    }
+
+    fn send(&self, message: &Message) -> Result<()> {
+        self.socket
+            .send_with_str(&browser::to_json_string(message)?)
+            .map_err(|err| anyhow!("error sending message: {err:#?}"))
+    }
+}
+
+/// A connection to the relay server that reconnects whenever it is lost.
+///
+/// Messages are only sent while the connection is open, so whatever happens
+/// in the meantime is dropped rather than queued up.
+#[derive(Debug)]
+pub(crate) struct NetClient {
+    url: String,
+    player: PlayerId,
+    connection: Connection,
+    sender: UnboundedSender<Message>,
+    incoming: UnboundedReceiver<Message>,
+    backoff: Backoff,
+    retry_at: Option<f64>,
+}
+
+impl NetClient {
+    pub(crate) fn connect(url: &str, player: PlayerId) -> Result<Self> {
+        let (sender, incoming) = unbounded();
+        let connection = Connection::open(url, sender.clone())?;
+        Ok(Self {
+            url: url.to_string(),
+            player,
+            connection,
+            sender,
+            incoming,
+            backoff: Backoff::new(),
+            retry_at: None,
+        })
+    }
+
+    pub(crate) fn player(&self) -> PlayerId {
+        self.player
+    }
+
+    /// Reconnects if the connection has been closed for long enough. Should
+    /// be called on every update.
+    pub(crate) fn update(&mut self) -> Result<()> {
+        if self.connection.is_open() {
+            self.backoff = Backoff::new();
+            self.retry_at = None;
+        }
+        if !self.connection.is_closed() {
+            return Ok(());
+        }
+
+        let now = browser::now()?;
+        match self.retry_at {
+            None => self.retry_at = Some(now + self.backoff.next_delay()),
+            Some(retry_at) if now >= retry_at => {
+                info!("reconnecting to {}", self.url);
+                self.retry_at = None;
+                self.connection = Connection::open(&self.url, self.sender.clone())?;
+            }
+            Some(_) => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn send(&self, message: &Message) -> Result<()> {
+        if self.connection.is_open() {
+            self.connection.send(message)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the messages received since the last call.
+    pub(crate) fn receive(&mut self) -> Vec<Message> {
+        let mut messages = vec![];
+        while let Ok(Some(message)) = self.incoming.try_next() {
+            messages.push(message);
+        }
+        messages
+    }
+}
+
+impl Drop for NetClient {
+    fn drop(&mut self) {
+        let leave = Message::Leave {
+            player: self.player,
+        };
+        if let Err(err) = self.send(&leave) {
+            error!("error leaving: {err:#?}");
+        }
+        if let Err(err) = self.connection.socket.close() {
+            error!("error closing connection: {err:#?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_back_off_up_to_a_limit() {
+        let mut backoff = Backoff::new();
+        let delays: Vec<_> = (0..7).map(|_| backoff.next_delay()).collect();
+        assert_eq!(
+            delays,
+            [1000.0, 2000.0, 4000.0, 8000.0, 16000.0, 30000.0, 30000.0]
+        );
+    }
+}
@@ -1,25 +1,52 @@
 use std::{iter, rc::Rc};
 
-use rand::{seq::SliceRandom, Rng};
-use web_sys::HtmlImageElement;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    rngs::StdRng,
+    seq::{IteratorRandom, SliceRandom},
+    Rng,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    engine::{Image, Point, Rect, SpriteSheet},
-    game::{Barrier, Obstacle, Platform, HEIGHT},
+    engine::{Image, ImageSource, Point, Rect, SpriteSheet},
+    game::{
+        Axis, Barrier, BoostPad, Coin, Collectible, Enemy, MovingPlatform, Obstacle, OverheadBar,
+        Platform, Pool, PowerUp, PowerUpKind, Saw, Spring, Weather, HEIGHT,
+    },
 };
 
-const LOW_PLATFORM: i16 = 420;
-const HIGH_PLATFORM: i16 = 375;
+const LOW_PLATFORM: f32 = 420.0;
+const HIGH_PLATFORM: f32 = 375.0;
+
+const TILE_WIDTH: f32 = 128.0;
+const TILE_HEIGHT: f32 = 128.0;
+
+const STONE_HEIGHT: f32 = 54.0;
+const STONE_ON_GROUND: f32 = HEIGHT - STONE_HEIGHT;
+
+const FLOATING_HEIGHT: f32 = 93.0;
+const FLOATING_EDGE_WIDTH: f32 = 60.0;
+const FLOATING_EDGE_HEIGHT: f32 = 54.0;
 
-const TILE_WIDTH: i16 = 128;
-const TILE_HEIGHT: i16 = 128;
+const FLYING_ENEMY_LOW: f32 = HEIGHT - 80.0;
+const FLYING_ENEMY_HIGH: f32 = HEIGHT - 170.0;
+const WALKING_ENEMY_ON_GROUND: f32 = HEIGHT - 30.0;
 
-const STONE_HEIGHT: i16 = 54;
-const STONE_ON_GROUND: i16 = HEIGHT - STONE_HEIGHT;
+const COIN_HEIGHT_ABOVE_PLATFORM: f32 = 50.0;
+const COIN_SPACING: f32 = TILE_WIDTH / 2.0;
 
-const FLOATING_HEIGHT: i16 = 93;
-const FLOATING_EDGE_WIDTH: i16 = 60;
-const FLOATING_EDGE_HEIGHT: i16 = 54;
+const POWER_UP_CHANCE: f64 = 0.2;
+const BREAKABLE_STONE_CHANCE: f64 = 0.3;
+const POWER_UP_HEIGHT_ABOVE_STONE: f32 = 120.0;
+
+#[derive(Debug, Default)]
+pub(crate) struct Segment {
+    pub(crate) obstacles: Vec<Box<dyn Obstacle>>,
+    pub(crate) collectibles: Vec<Box<dyn Collectible>>,
+    /// The weather while the boy runs through the segment, if it has its own.
+    pub(crate) weather: Option<Weather>,
+}
 
 fn create_floating_platform(
     sprite_sheet: Rc<SpriteSheet>,
@@ -30,19 +57,19 @@ fn create_floating_platform(
         .chain(iter::repeat("14.png").take(body_blocks))
         .chain(iter::once("15.png"));
 
-    let platform_width: i16 = iter::repeat(TILE_WIDTH).take(body_blocks + 2).sum();
+    let platform_width: f32 = iter::repeat(TILE_WIDTH).take(body_blocks + 2).sum();
 
     let bounding_boxes = [
-        Rect::from_xy(0, 0, FLOATING_EDGE_WIDTH, FLOATING_EDGE_HEIGHT),
+        Rect::from_xy(0.0, 0.0, FLOATING_EDGE_WIDTH, FLOATING_EDGE_HEIGHT),
         Rect::from_xy(
             FLOATING_EDGE_WIDTH,
-            0,
-            platform_width - (FLOATING_EDGE_WIDTH * 2),
+            0.0,
+            platform_width - (FLOATING_EDGE_WIDTH * 2.0),
             FLOATING_HEIGHT,
         ),
         Rect::from_xy(
             platform_width - FLOATING_EDGE_WIDTH,
-            0,
+            0.0,
             FLOATING_EDGE_WIDTH,
             FLOATING_EDGE_HEIGHT,
         ),
@@ -60,8 +87,8 @@ fn create_repeat_platform(
     let sprite_names = iter::once(tile_names[0])
         .chain(iter::repeat(tile_names[1]).take(mid_blocks))
         .chain(iter::once(tile_names[2]));
-    let platform_width: i16 = iter::repeat(TILE_WIDTH).take(mid_blocks + 2).sum();
-    let bounding_boxes = [Rect::from_xy(0, 0, platform_width, TILE_HEIGHT)];
+    let platform_width: f32 = iter::repeat(TILE_WIDTH).take(mid_blocks + 2).sum();
+    let bounding_boxes = [Rect::from_xy(0.0, 0.0, platform_width, TILE_HEIGHT)];
     Platform::new(sprite_sheet, position, sprite_names, bounding_boxes)
 }
 
@@ -104,50 +131,214 @@ fn create_filled_bottom(
     )
 }
 
-pub(crate) type SegmentGeneratorFn =
-    fn(HtmlImageElement, Rc<SpriteSheet>, i16) -> Vec<Box<dyn Obstacle>>;
+fn create_coin_row(platform_position: Point, tiles: usize) -> Vec<Box<dyn Collectible>> {
+    let coins = tiles * (TILE_WIDTH / COIN_SPACING) as usize;
+    (0..coins)
+        .map(|i| {
+            Box::new(Coin::new(Point {
+                x: platform_position.x + COIN_SPACING / 4.0 + COIN_SPACING * i as f32,
+                y: platform_position.y - COIN_HEIGHT_ABOVE_PLATFORM,
+            })) as Box<dyn Collectible>
+        })
+        .collect()
+}
+
+fn maybe_create_power_up(rng: &mut impl Rng, position: Point) -> Option<Box<dyn Collectible>> {
+    if !rng.gen_bool(POWER_UP_CHANCE) {
+        return None;
+    }
+    let kind = *PowerUpKind::ALL.choose(rng).unwrap();
+    Some(Box::new(PowerUp::new(kind, position)))
+}
 
-pub(crate) const SEGMENT_GENERATORS: &[SegmentGeneratorFn] = &[floating_and_stone, mount, ceiling];
+pub(crate) type SegmentGeneratorFn = fn(&mut StdRng, ImageSource, Rc<SpriteSheet>, f32) -> Segment;
+
+/// How hard a segment is to get through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    const MEDIUM_DISTANCE: f32 = 5000.0;
+    const HARD_DISTANCE: f32 = 15000.0;
+
+    /// Returns the difficulty suited to a run that has covered `distance`.
+    pub(crate) fn for_distance(distance: f32) -> Self {
+        if distance < Self::MEDIUM_DISTANCE {
+            Self::Easy
+        } else if distance < Self::HARD_DISTANCE {
+            Self::Medium
+        } else {
+            Self::Hard
+        }
+    }
+
+    /// Returns how much a segment of this difficulty is favoured when
+    /// `target` is wanted.
+    const fn affinity(self, target: Difficulty) -> u32 {
+        match (self as i8 - target as i8).abs() {
+            0 => 4,
+            1 => 1,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SegmentGenerator {
+    /// What level manifests call the segment.
+    pub(crate) name: &'static str,
+    pub(crate) generate: SegmentGeneratorFn,
+    pub(crate) weight: u32,
+    pub(crate) difficulty: Difficulty,
+}
+
+impl SegmentGenerator {
+    const fn new(
+        name: &'static str,
+        generate: SegmentGeneratorFn,
+        weight: u32,
+        difficulty: Difficulty,
+    ) -> Self {
+        Self {
+            name,
+            generate,
+            weight,
+            difficulty,
+        }
+    }
+}
+
+pub(crate) const SEGMENT_GENERATORS: &[SegmentGenerator] = &[
+    SegmentGenerator::new(
+        "floating_and_stone",
+        floating_and_stone,
+        3,
+        Difficulty::Easy,
+    ),
+    SegmentGenerator::new("mount", mount, 2, Difficulty::Medium),
+    SegmentGenerator::new("ceiling", ceiling, 2, Difficulty::Medium),
+    SegmentGenerator::new("flying_enemies", flying_enemies, 2, Difficulty::Hard),
+    SegmentGenerator::new("walking_enemy", walking_enemy, 2, Difficulty::Medium),
+    SegmentGenerator::new("moving_platforms", moving_platforms, 2, Difficulty::Hard),
+    SegmentGenerator::new("boost_pad", boost_pad, 2, Difficulty::Easy),
+    SegmentGenerator::new("spring", spring, 2, Difficulty::Medium),
+    SegmentGenerator::new("pools", pools, 2, Difficulty::Medium),
+    SegmentGenerator::new("saw_over_platform", saw_over_platform, 2, Difficulty::Hard),
+    SegmentGenerator::new("overhead_bars", overhead_bars, 2, Difficulty::Hard),
+];
+
+/// Returns the generator of the segment called `name`.
+pub(crate) fn generator(name: &str) -> Option<&'static SegmentGenerator> {
+    SEGMENT_GENERATORS
+        .iter()
+        .find(|generator| generator.name == name)
+}
+
+/// Picks segments at random by weight, favouring those close to the wanted
+/// difficulty and never picking the same one twice in a row.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct SegmentPicker {
+    last: Option<usize>,
+}
+
+impl SegmentPicker {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the index of the next segment out of `candidates`, given as
+    /// weight and difficulty.
+    pub(crate) fn pick(
+        &mut self,
+        rng: &mut impl Rng,
+        candidates: &[(u32, Difficulty)],
+        target: Difficulty,
+    ) -> usize {
+        let allowed = |index: usize| candidates.len() == 1 || self.last != Some(index);
+        let weights = candidates
+            .iter()
+            .enumerate()
+            .map(|(index, &(weight, difficulty))| {
+                if allowed(index) {
+                    weight * difficulty.affinity(target)
+                } else {
+                    0
+                }
+            });
+        let index = match WeightedIndex::new(weights) {
+            Ok(weights) => weights.sample(rng),
+            // Nothing of a suitable difficulty, so any other segment will do.
+            Err(_) => (0..candidates.len())
+                .filter(|&index| allowed(index))
+                .choose(rng)
+                .unwrap_or(0),
+        };
+        self.last = Some(index);
+        index
+    }
+}
 
 fn floating_and_stone(
-    stone: HtmlImageElement,
+    rng: &mut StdRng,
+    stone: ImageSource,
     sprite_sheet: Rc<SpriteSheet>,
-    offset_x: i16,
-) -> Vec<Box<dyn Obstacle>> {
-    let mut rng = rand::thread_rng();
-
-    let stone_offset = *[150, 400].choose(&mut rng).unwrap();
-    let platform_offset = *[370, 200].choose(&mut rng).unwrap();
-    let platform_y = *[HIGH_PLATFORM, LOW_PLATFORM].choose(&mut rng).unwrap();
+    offset_x: f32,
+) -> Segment {
+    let stone_offset = *[150.0, 400.0].choose(rng).unwrap();
+    let platform_offset = *[370.0, 200.0].choose(rng).unwrap();
+    let platform_y = *[HIGH_PLATFORM, LOW_PLATFORM].choose(rng).unwrap();
     let mid_blocks = rng.gen_range(0..4);
+    let platform_position = Point {
+        x: offset_x + platform_offset,
+        y: platform_y,
+    };
+    let stone_position = Point {
+        x: offset_x + stone_offset,
+        y: STONE_ON_GROUND,
+    };
 
-    vec![
-        Box::new(Barrier::new(Image::new(
-            stone,
-            Point {
-                x: offset_x + stone_offset,
-                y: STONE_ON_GROUND,
-            },
-        ))),
-        Box::new(create_floating_platform(
-            sprite_sheet,
-            Point {
-                x: offset_x + platform_offset,
-                y: platform_y,
-            },
-            mid_blocks,
-        )),
-    ]
+    let mut collectibles = create_coin_row(platform_position, mid_blocks + 2);
+    collectibles.extend(maybe_create_power_up(
+        rng,
+        Point {
+            x: stone_position.x,
+            y: stone_position.y - POWER_UP_HEIGHT_ABOVE_STONE,
+        },
+    ));
+
+    let stone = Image::new(stone, stone_position);
+    let stone = if rng.gen_bool(BREAKABLE_STONE_CHANCE) {
+        Barrier::breakable(stone)
+    } else {
+        Barrier::new(stone)
+    };
+
+    Segment {
+        obstacles: vec![
+            Box::new(stone),
+            Box::new(create_floating_platform(
+                sprite_sheet,
+                platform_position,
+                mid_blocks,
+            )),
+        ],
+        collectibles,
+        weather: None,
+    }
 }
 
 fn mount(
-    _stone: HtmlImageElement,
+    rng: &mut StdRng,
+    _stone: ImageSource,
     sprite_sheet: Rc<SpriteSheet>,
-    offset_x: i16,
-) -> Vec<Box<dyn Obstacle>> {
-    const INITIAL_MOUNT_OFFSET: i16 = 200;
+    offset_x: f32,
+) -> Segment {
+    const INITIAL_MOUNT_OFFSET: f32 = 200.0;
 
-    let mut rng = rand::thread_rng();
     let h_mid_blocks = rng.gen_range(0..4);
     let v_mid_blocks = rng.gen_range(0..2);
 
@@ -164,29 +355,34 @@ fn mount(
         )));
         y -= TILE_HEIGHT;
     }
+    let top_position = Point {
+        x: offset_x + INITIAL_MOUNT_OFFSET,
+        y,
+    };
     obstacles.push(Box::new(create_filled_top(
         sprite_sheet.clone(),
-        Point {
-            x: offset_x + INITIAL_MOUNT_OFFSET,
-            y,
-        },
+        top_position,
         h_mid_blocks,
     )));
-    obstacles
+    Segment {
+        obstacles,
+        collectibles: create_coin_row(top_position, h_mid_blocks + 2),
+        weather: None,
+    }
 }
 
 fn ceiling(
-    _stone: HtmlImageElement,
+    rng: &mut StdRng,
+    _stone: ImageSource,
     sprite_sheet: Rc<SpriteSheet>,
-    offset_x: i16,
-) -> Vec<Box<dyn Obstacle>> {
-    const INITIAL_MOUNT_OFFSET: i16 = 200;
+    offset_x: f32,
+) -> Segment {
+    const INITIAL_MOUNT_OFFSET: f32 = 200.0;
 
-    let mut rng = rand::thread_rng();
     let h_mid_blocks = rng.gen_range(0..4);
     let v_mid_blocks = rng.gen_range(0..4);
 
-    let mut y = 0;
+    let mut y = 0.0;
     let mut obstacles: Vec<Box<dyn Obstacle>> = vec![];
     for _ in 0..v_mid_blocks {
         obstacles.push(Box::new(create_filled_body(
@@ -207,5 +403,317 @@ fn ceiling(
         },
         h_mid_blocks,
     )));
-    obstacles
+    Segment {
+        obstacles,
+        collectibles: vec![],
+        weather: None,
+    }
+}
+
+fn flying_enemies(
+    rng: &mut StdRng,
+    _stone: ImageSource,
+    _sprite_sheet: Rc<SpriteSheet>,
+    offset_x: f32,
+) -> Segment {
+    const INITIAL_ENEMY_OFFSET: f32 = 300.0;
+    const ENEMY_SPACING: f32 = 250.0;
+    const FLYING_SPEED: f32 = -2.0;
+    const BOB_AMPLITUDE: f32 = 20.0;
+
+    let enemies: u8 = rng.gen_range(1..3);
+
+    let obstacles = (0..enemies)
+        .map(|i| {
+            let y = *[FLYING_ENEMY_HIGH, FLYING_ENEMY_LOW].choose(rng).unwrap();
+            Box::new(Enemy::new(
+                Point {
+                    x: offset_x + INITIAL_ENEMY_OFFSET + ENEMY_SPACING * f32::from(i),
+                    y,
+                },
+                FLYING_SPEED,
+                BOB_AMPLITUDE,
+            )) as Box<dyn Obstacle>
+        })
+        .collect();
+    Segment {
+        obstacles,
+        collectibles: vec![],
+        weather: None,
+    }
+}
+
+fn walking_enemy(
+    rng: &mut StdRng,
+    _stone: ImageSource,
+    sprite_sheet: Rc<SpriteSheet>,
+    offset_x: f32,
+) -> Segment {
+    const INITIAL_ENEMY_OFFSET: f32 = 300.0;
+    const WALKING_SPEED: f32 = -1.0;
+
+    let platform_position = Point {
+        x: offset_x + INITIAL_ENEMY_OFFSET,
+        y: LOW_PLATFORM,
+    };
+    let mid_blocks = rng.gen_range(0..2);
+
+    Segment {
+        obstacles: vec![
+            Box::new(create_floating_platform(
+                sprite_sheet,
+                platform_position,
+                mid_blocks,
+            )),
+            Box::new(Enemy::new(
+                Point {
+                    x: platform_position.x,
+                    y: WALKING_ENEMY_ON_GROUND,
+                },
+                WALKING_SPEED,
+                0.0,
+            )),
+        ],
+        collectibles: create_coin_row(platform_position, mid_blocks + 2),
+        weather: None,
+    }
+}
+
+fn moving_platforms(
+    rng: &mut StdRng,
+    stone: ImageSource,
+    sprite_sheet: Rc<SpriteSheet>,
+    offset_x: f32,
+) -> Segment {
+    const INITIAL_PLATFORM_OFFSET: f32 = 200.0;
+    const HORIZONTAL_AMPLITUDE: f32 = 60.0;
+    const VERTICAL_AMPLITUDE: f32 = 40.0;
+
+    let axis = *[Axis::Horizontal, Axis::Vertical].choose(rng).unwrap();
+    let amplitude = match axis {
+        Axis::Horizontal => HORIZONTAL_AMPLITUDE,
+        Axis::Vertical => VERTICAL_AMPLITUDE,
+    };
+    let platform_position = Point {
+        x: offset_x + INITIAL_PLATFORM_OFFSET,
+        y: LOW_PLATFORM,
+    };
+    let platform = create_floating_platform(sprite_sheet, platform_position, 1);
+    let stone_x = platform.right() - TILE_WIDTH;
+
+    Segment {
+        obstacles: vec![
+            Box::new(MovingPlatform::new(platform, axis, amplitude)),
+            Box::new(Barrier::new(Image::new(
+                stone,
+                Point {
+                    x: stone_x,
+                    y: STONE_ON_GROUND,
+                },
+            ))),
+        ],
+        collectibles: vec![],
+        weather: None,
+    }
+}
+
+/// A boost pad with a row of coins along the ground behind it, ending in a
+/// stone that comes up faster than usual.
+fn boost_pad(
+    rng: &mut StdRng,
+    stone: ImageSource,
+    _sprite_sheet: Rc<SpriteSheet>,
+    offset_x: f32,
+) -> Segment {
+    const PAD_OFFSET: f32 = 100.0;
+    const COIN_TILES: usize = 4;
+
+    let coin_start = Point {
+        x: offset_x + PAD_OFFSET + BoostPad::WIDTH,
+        y: HEIGHT,
+    };
+    let stone_offset = *[700.0, 900.0].choose(rng).unwrap();
+
+    Segment {
+        obstacles: vec![
+            Box::new(BoostPad::new(offset_x + PAD_OFFSET)),
+            Box::new(Barrier::new(Image::new(
+                stone,
+                Point {
+                    x: coin_start.x + stone_offset,
+                    y: STONE_ON_GROUND,
+                },
+            ))),
+        ],
+        collectibles: create_coin_row(coin_start, COIN_TILES),
+        weather: None,
+    }
+}
+
+/// A spring under a platform too high to jump onto, with coins on top.
+fn spring(
+    rng: &mut StdRng,
+    _stone: ImageSource,
+    sprite_sheet: Rc<SpriteSheet>,
+    offset_x: f32,
+) -> Segment {
+    const SPRING_OFFSET: f32 = 150.0;
+    const PLATFORM_OFFSET: f32 = 350.0;
+    const SKY_PLATFORM: f32 = 230.0;
+
+    let platform_position = Point {
+        x: offset_x + PLATFORM_OFFSET,
+        y: SKY_PLATFORM,
+    };
+    let mid_blocks = rng.gen_range(1..3);
+
+    Segment {
+        obstacles: vec![
+            Box::new(Spring::new(offset_x + SPRING_OFFSET)),
+            Box::new(create_floating_platform(
+                sprite_sheet,
+                platform_position,
+                mid_blocks,
+            )),
+        ],
+        collectibles: create_coin_row(platform_position, mid_blocks + 2),
+        weather: None,
+    }
+}
+
+/// Two pools of water in the floor, each narrow enough to jump over, with
+/// coins over the second one.
+fn pools(
+    rng: &mut StdRng,
+    _stone: ImageSource,
+    _sprite_sheet: Rc<SpriteSheet>,
+    offset_x: f32,
+) -> Segment {
+    const FIRST_POOL_OFFSET: f32 = 200.0;
+    const POOL_SPACING: f32 = 450.0;
+    const COIN_HEIGHT_ABOVE_POOL: f32 = 150.0;
+
+    let widths = [rng.gen_range(100.0..=160.0), rng.gen_range(100.0..=160.0)];
+    let first_x = offset_x + FIRST_POOL_OFFSET;
+    let second_x = first_x + widths[0] + POOL_SPACING;
+    let coins = create_coin_row(
+        Point {
+            x: second_x,
+            y: HEIGHT - COIN_HEIGHT_ABOVE_POOL + COIN_HEIGHT_ABOVE_PLATFORM,
+        },
+        1,
+    );
+
+    Segment {
+        obstacles: vec![
+            Box::new(Pool::new(first_x, widths[0])),
+            Box::new(Pool::new(second_x, widths[1])),
+        ],
+        collectibles: coins,
+        weather: None,
+    }
+}
+
+/// A platform with a saw blade running along its top and coins below it, so
+/// that the boy either jumps the blade on top or keeps to the ground.
+fn saw_over_platform(
+    rng: &mut StdRng,
+    _stone: ImageSource,
+    sprite_sheet: Rc<SpriteSheet>,
+    offset_x: f32,
+) -> Segment {
+    const PLATFORM_OFFSET: f32 = 200.0;
+
+    let mid_blocks = rng.gen_range(1..3);
+    let platform_position = Point {
+        x: offset_x + PLATFORM_OFFSET,
+        y: HIGH_PLATFORM,
+    };
+    let platform_width = TILE_WIDTH * (mid_blocks + 2) as f32;
+    let blade_y = platform_position.y - Saw::RADIUS;
+    let blade_start = Point {
+        x: platform_position.x + Saw::RADIUS,
+        y: blade_y,
+    };
+    let blade_end = Point {
+        x: platform_position.x + platform_width - Saw::RADIUS,
+        y: blade_y,
+    };
+
+    Segment {
+        obstacles: vec![
+            Box::new(create_floating_platform(
+                sprite_sheet,
+                platform_position,
+                mid_blocks,
+            )),
+            Box::new(Saw::new(blade_start, blade_end)),
+        ],
+        collectibles: create_coin_row(
+            Point {
+                x: platform_position.x,
+                y: HEIGHT,
+            },
+            mid_blocks + 2,
+        ),
+        weather: None,
+    }
+}
+
+/// One or two bars hanging at head height with coins under them, which the
+/// boy has to slide under.
+fn overhead_bars(
+    rng: &mut StdRng,
+    _stone: ImageSource,
+    _sprite_sheet: Rc<SpriteSheet>,
+    offset_x: f32,
+) -> Segment {
+    const FIRST_BAR_OFFSET: f32 = 250.0;
+    const BAR_SPACING: f32 = 500.0;
+    /// Low enough for a sliding boy to pick up.
+    const COIN_HEIGHT: f32 = 20.0;
+
+    let bars: u8 = rng.gen_range(1..=2);
+    let mut obstacles: Vec<Box<dyn Obstacle>> = vec![];
+    let mut collectibles = vec![];
+    for bar in 0..bars {
+        let x = offset_x + FIRST_BAR_OFFSET + BAR_SPACING * f32::from(bar);
+        obstacles.push(Box::new(OverheadBar::new(x)));
+        collectibles.push(Box::new(Coin::new(Point {
+            x: x + OverheadBar::WIDTH / 2.0,
+            y: HEIGHT - COIN_HEIGHT,
+        })) as Box<dyn Collectible>);
+    }
+    Segment {
+        obstacles,
+        collectibles,
+        weather: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn picker_favours_the_target_difficulty_without_repeating() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut picker = SegmentPicker::new();
+        let candidates = [
+            (1, Difficulty::Easy),
+            (1, Difficulty::Hard),
+            (1, Difficulty::Hard),
+        ];
+
+        let mut last = None;
+        for _ in 0..100 {
+            let index = picker.pick(&mut rng, &candidates, Difficulty::Hard);
+            assert_ne!(index, 0);
+            assert_ne!(Some(index), last);
+            last = Some(index);
+        }
+        assert_eq!(picker.pick(&mut rng, &candidates[..1], Difficulty::Hard), 0);
+    }
 }
@@ -2,30 +2,103 @@ use anyhow::{anyhow, Result};
 use js_sys::ArrayBuffer;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{AudioBuffer, AudioBufferSourceNode, AudioContext, AudioDestinationNode, AudioNode};
+use web_sys::{
+    AudioBuffer, AudioBufferSourceNode, AudioContext, AudioContextState, AudioNode, GainNode,
+    StereoPannerNode,
+};
 
 pub(crate) fn create_audio_context() -> Result<AudioContext> {
     AudioContext::new().map_err(|err| anyhow!("could not create audio context: {err:#?}"))
 }
 
+/// Returns whether `ctx` is producing sound, which browsers only allow after
+/// the user has interacted with the page.
+pub(crate) fn is_running(ctx: &AudioContext) -> bool {
+    ctx.state() == AudioContextState::Running
+}
+
+pub(crate) async fn resume_audio_context(ctx: &AudioContext) -> Result<()> {
+    JsFuture::from(
+        ctx.resume()
+            .map_err(|err| anyhow!("could not resume audio context: {err:#?}"))?,
+    )
+    .await
+    .map(|_| ())
+    .map_err(|err| anyhow!("error resuming audio context: {err:#?}"))
+}
+
+/// Stops every sound in `ctx` and releases its audio resources.
+pub(crate) fn close_audio_context(ctx: &AudioContext) -> Result<()> {
+    ctx.close()
+        .map(|_| ())
+        .map_err(|err| anyhow!("could not close audio context: {err:#?}"))
+}
+
 fn create_buffer_source(ctx: &AudioContext) -> Result<AudioBufferSourceNode> {
     ctx.create_buffer_source()
         .map_err(|err| anyhow!("could not create buffer source: {err:#?}"))
 }
 
-fn connect_with_audio_node(
-    buffer_source: &AudioBufferSourceNode,
-    destination: &AudioDestinationNode,
-) -> Result<AudioNode> {
-    buffer_source
+fn connect_with_audio_node(source: &AudioNode, destination: &AudioNode) -> Result<AudioNode> {
+    source
         .connect_with_audio_node(destination)
-        .map_err(|err| anyhow!("could not connect buffer source with destination: {err:#?}"))
+        .map_err(|err| anyhow!("could not connect audio node with destination: {err:#?}"))
+}
+
+/// Creates a gain node that feeds into `destination`.
+pub(crate) fn create_gain(ctx: &AudioContext, destination: &AudioNode) -> Result<GainNode> {
+    let gain = ctx
+        .create_gain()
+        .map_err(|err| anyhow!("could not create gain node: {err:#?}"))?;
+    connect_with_audio_node(&gain, destination)?;
+    Ok(gain)
+}
+
+pub(crate) fn set_gain(gain: &GainNode, value: f32) {
+    gain.gain().set_value(value);
+}
+
+/// Ramps `gain` linearly from `from` to `to` over `duration` seconds.
+pub(crate) fn fade_gain(
+    ctx: &AudioContext,
+    gain: &GainNode,
+    from: f32,
+    to: f32,
+    duration: f64,
+) -> Result<()> {
+    let now = ctx.current_time();
+    let param = gain.gain();
+    param
+        .cancel_scheduled_values(now)
+        .and_then(|_| param.set_value_at_time(from, now))
+        .and_then(|_| param.linear_ramp_to_value_at_time(to, now + duration))
+        .map(|_| ())
+        .map_err(|err| anyhow!("could not fade gain: {err:#?}"))
 }
 
-fn create_track_source(ctx: &AudioContext, buffer: &AudioBuffer) -> Result<AudioBufferSourceNode> {
+/// Creates a stereo panner that feeds into `destination`, with `pan` ranging
+/// from `-1.0` (fully left) to `1.0` (fully right).
+pub(crate) fn create_stereo_panner(
+    ctx: &AudioContext,
+    destination: &AudioNode,
+    pan: f32,
+) -> Result<StereoPannerNode> {
+    let panner = ctx
+        .create_stereo_panner()
+        .map_err(|err| anyhow!("could not create stereo panner: {err:#?}"))?;
+    panner.pan().set_value(pan.clamp(-1.0, 1.0));
+    connect_with_audio_node(&panner, destination)?;
+    Ok(panner)
+}
+
+fn create_track_source(
+    ctx: &AudioContext,
+    buffer: &AudioBuffer,
+    destination: &AudioNode,
+) -> Result<AudioBufferSourceNode> {
     let track_source = create_buffer_source(ctx)?;
     track_source.set_buffer(Some(buffer));
-    connect_with_audio_node(&track_source, &ctx.destination())?;
+    connect_with_audio_node(&track_source, destination)?;
     Ok(track_source)
 }
 
@@ -35,15 +108,35 @@ pub(crate) enum Looping {
     Yes,
 }
 
-pub(crate) fn play_sound(ctx: &AudioContext, buffer: &AudioBuffer, looping: Looping) -> Result<()> {
-    let track_source = create_track_source(ctx, buffer)?;
+/// Starts playing `buffer` from `offset` seconds into it.
+pub(crate) fn play_sound(
+    ctx: &AudioContext,
+    buffer: &AudioBuffer,
+    destination: &AudioNode,
+    looping: Looping,
+    offset: f64,
+) -> Result<AudioBufferSourceNode> {
+    let track_source = create_track_source(ctx, buffer, destination)?;
     if matches!(looping, Looping::Yes) {
         track_source.set_loop(true);
     }
 
     track_source
-        .start()
-        .map_err(|err| anyhow!("could not start track: {err:#?}"))
+        .start_with_when_and_grain_offset(0.0, offset)
+        .map_err(|err| anyhow!("could not start track: {err:#?}"))?;
+    Ok(track_source)
+}
+
+pub(crate) fn stop_sound(track_source: &AudioBufferSourceNode) -> Result<()> {
+    track_source
+        .stop()
+        .map_err(|err| anyhow!("could not stop track: {err:#?}"))
+}
+
+pub(crate) fn stop_sound_at(track_source: &AudioBufferSourceNode, when: f64) -> Result<()> {
+    track_source
+        .stop_with_when(when)
+        .map_err(|err| anyhow!("could not stop track: {err:#?}"))
 }
 
 pub(crate) async fn decode_audio_data(
@@ -0,0 +1,460 @@
+//! Imports segments authored in the [Tiled](https://www.mapeditor.org/) map
+//! editor from its JSON export.
+//!
+//! Every run of tiles in a row of a tile layer becomes a [`Platform`] drawn
+//! with the obstacle sprite sheet, whose cells are looked up by the image
+//! names of a collection-of-images tileset. Rectangles in object layers are
+//! the collision boxes of the platforms they overlap; platforms without any
+//! are solid over their whole area. Ellipses collide as circles and
+//! rectangles of class `ramp_up` or `ramp_down` as slopes rising to the right
+//! or to the left. Objects of class `stone` become [`Barrier`]s and objects of
+//! class `coin` become [`Coin`]s. A `weather` string property on the map,
+//! `rain` or `snow`, sets the weather while the boy runs through it.
+//!
+//! The bottom of the map is aligned with the bottom of the screen.
+//!
+//! Layouts made in the level editor are exported in the same format.
+
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engine::{Image, ImageSource, Point, Rect, Shape, SpriteSheet},
+    game::{Barrier, Coin, Collectible, Obstacle, Platform, Weather, HEIGHT},
+    segments::Segment,
+};
+
+/// Tiled stores flipping and rotation in the highest bits of a tile id.
+const GID_MASK: u32 = 0x1fff_ffff;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct TiledMap {
+    width: u32,
+    height: u32,
+    #[serde(rename = "tilewidth")]
+    tile_width: f32,
+    #[serde(rename = "tileheight")]
+    tile_height: f32,
+    layers: Vec<Layer>,
+    tilesets: Vec<Tileset>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    properties: Vec<Property>,
+}
+
+/// A custom property, of which only the string ones are read.
+#[derive(Debug, Deserialize, Serialize)]
+struct Property {
+    name: String,
+    value: PropertyValue,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum PropertyValue {
+    String(String),
+    Bool(bool),
+    Number(f64),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Layer {
+    #[serde(rename = "tilelayer")]
+    Tiles {
+        width: usize,
+        data: Vec<u32>,
+        #[serde(default, rename = "offsetx")]
+        offset_x: f32,
+        #[serde(default, rename = "offsety")]
+        offset_y: f32,
+    },
+    #[serde(rename = "objectgroup")]
+    Objects {
+        objects: Vec<Object>,
+        #[serde(default, rename = "offsetx")]
+        offset_x: f32,
+        #[serde(default, rename = "offsety")]
+        offset_y: f32,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Object {
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    /// Called `type` before Tiled 1.9.
+    #[serde(default, alias = "type")]
+    class: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    ellipse: bool,
+}
+
+impl Object {
+    /// Returns the collider of the object placed at `position`.
+    fn shape(&self, position: Point) -> Shape {
+        let area = Rect::new(position, self.width, self.height);
+        match self.class.as_str() {
+            _ if self.ellipse => Shape::Circle {
+                center: area.center(),
+                radius: self.width.min(self.height) / 2.0,
+            },
+            "ramp_up" => Shape::Slope { area, rising: true },
+            "ramp_down" => Shape::Slope {
+                area,
+                rising: false,
+            },
+            _ => Shape::Rect(area),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Tileset {
+    #[serde(rename = "firstgid")]
+    first_gid: u32,
+    #[serde(default)]
+    tiles: Vec<Tile>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Tile {
+    id: u32,
+    image: String,
+}
+
+/// A horizontal run of tiles, identified by their global ids.
+#[derive(Debug, PartialEq)]
+struct TileRun {
+    row: usize,
+    column: usize,
+    gids: Vec<u32>,
+}
+
+impl TiledMap {
+    /// Builds a map out of tiles, named after their sprite sheet cells, and
+    /// objects placed in the world. They keep their positions relative to
+    /// each other and to the bottom of the screen.
+    pub(crate) fn from_world(
+        tile_width: f32,
+        tile_height: f32,
+        tiles: &[(&str, Point)],
+        objects: &[(&str, Rect)],
+    ) -> Self {
+        let positions = tiles
+            .iter()
+            .map(|(_, position)| *position)
+            .chain(objects.iter().map(|(_, rect)| rect.position));
+        let left = positions
+            .clone()
+            .map(|position| position.x)
+            .reduce(f32::min)
+            .unwrap_or(0.0);
+        let top = positions
+            .map(|position| position.y)
+            .reduce(f32::min)
+            .unwrap_or(HEIGHT);
+        let right = tiles
+            .iter()
+            .map(|(_, position)| position.x + tile_width)
+            .chain(objects.iter().map(|(_, rect)| rect.right()))
+            .reduce(f32::max)
+            .unwrap_or(left);
+        let width = ((right - left) / tile_width).ceil().max(1.0) as u32;
+        let height = ((HEIGHT - top) / tile_height).ceil().max(1.0) as u32;
+        let origin = Point {
+            x: left,
+            y: HEIGHT - height as f32 * tile_height,
+        };
+
+        let mut names: Vec<&str> = vec![];
+        let mut layers: Vec<Layer> = vec![];
+        for &(name, position) in tiles {
+            let index = names.iter().position(|&other| other == name);
+            let index = index.unwrap_or_else(|| {
+                names.push(name);
+                names.len() - 1
+            });
+            let gid = index as u32 + 1;
+            let (x, y) = (position.x - origin.x, position.y - origin.y);
+            let (column, row) = ((x / tile_width).floor(), (y / tile_height).floor());
+            let (layer_x, layer_y) = (x - column * tile_width, y - row * tile_height);
+            let layer = layers.iter().position(|layer| {
+                matches!(layer, Layer::Tiles { offset_x, offset_y, .. }
+                    if *offset_x == layer_x && *offset_y == layer_y)
+            });
+            let layer = layer.unwrap_or_else(|| {
+                layers.push(Layer::Tiles {
+                    width: width as usize,
+                    data: vec![0; (width * height) as usize],
+                    offset_x: layer_x,
+                    offset_y: layer_y,
+                });
+                layers.len() - 1
+            });
+            if let Layer::Tiles { data, .. } = &mut layers[layer] {
+                let index = row as usize * width as usize + column as usize;
+                if let Some(tile) = data.get_mut(index) {
+                    *tile = gid;
+                }
+            }
+        }
+        layers.push(Layer::Objects {
+            objects: objects
+                .iter()
+                .map(|&(class, rect)| Object {
+                    x: rect.x() - origin.x,
+                    y: rect.y() - origin.y,
+                    width: rect.width,
+                    height: rect.height,
+                    class: class.to_string(),
+                    ellipse: false,
+                })
+                .collect(),
+            offset_x: 0.0,
+            offset_y: 0.0,
+        });
+
+        Self {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            layers,
+            tilesets: vec![Tileset {
+                first_gid: 1,
+                tiles: names
+                    .iter()
+                    .zip(0..)
+                    .map(|(name, id)| Tile {
+                        id,
+                        image: name.to_string(),
+                    })
+                    .collect(),
+            }],
+            properties: vec![],
+        }
+    }
+
+    /// Builds the segment described by the map with its left edge at
+    /// `offset_x`.
+    pub(crate) fn segment(
+        &self,
+        stone: &ImageSource,
+        sprite_sheet: &Rc<SpriteSheet>,
+        offset_x: f32,
+    ) -> Result<Segment> {
+        let origin = Point {
+            x: offset_x,
+            y: HEIGHT - self.height as f32 * self.tile_height,
+        };
+
+        let mut colliders = vec![];
+        let mut obstacles: Vec<Box<dyn Obstacle>> = vec![];
+        let mut collectibles: Vec<Box<dyn Collectible>> = vec![];
+        for layer in &self.layers {
+            let Layer::Objects {
+                objects,
+                offset_x,
+                offset_y,
+            } = layer
+            else {
+                continue;
+            };
+            for object in objects {
+                let position = Point {
+                    x: origin.x + offset_x + object.x,
+                    y: origin.y + offset_y + object.y,
+                };
+                match object.class.as_str() {
+                    "stone" => {
+                        obstacles.push(Box::new(Barrier::new(Image::new(stone.clone(), position))))
+                    }
+                    "coin" => collectibles.push(Box::new(Coin::new(position))),
+                    _ => colliders.push(object.shape(position)),
+                }
+            }
+        }
+
+        for layer in &self.layers {
+            let Layer::Tiles {
+                width,
+                data,
+                offset_x,
+                offset_y,
+            } = layer
+            else {
+                continue;
+            };
+            for run in tile_runs(data, *width) {
+                let position = Point {
+                    x: origin.x + offset_x + run.column as f32 * self.tile_width,
+                    y: origin.y + offset_y + run.row as f32 * self.tile_height,
+                };
+                let area = Rect::new(
+                    position,
+                    run.gids.len() as f32 * self.tile_width,
+                    self.tile_height,
+                );
+                let sprite_names = run
+                    .gids
+                    .iter()
+                    .map(|&gid| self.sprite_name(gid, sprite_sheet))
+                    .collect::<Result<Vec<_>>>()?;
+                let mut platform_colliders: Vec<_> = colliders
+                    .iter()
+                    .filter(|collider| area.intersects(&collider.bounding_box()))
+                    .map(|collider| {
+                        let mut collider = *collider;
+                        collider.move_by(-position.x, -position.y);
+                        collider
+                    })
+                    .collect();
+                if platform_colliders.is_empty() {
+                    platform_colliders.push(Shape::Rect(Rect::from_xy(
+                        0.0,
+                        0.0,
+                        area.width,
+                        area.height,
+                    )));
+                }
+                obstacles.push(Box::new(Platform::new(
+                    Rc::clone(sprite_sheet),
+                    position,
+                    sprite_names,
+                    platform_colliders,
+                )));
+            }
+        }
+
+        Ok(Segment {
+            obstacles,
+            collectibles,
+            weather: self.weather()?,
+        })
+    }
+
+    fn weather(&self) -> Result<Option<Weather>> {
+        self.properties
+            .iter()
+            .find(|property| property.name == "weather")
+            .map(|property| match &property.value {
+                PropertyValue::String(weather) => weather.parse(),
+                _ => Err(anyhow!("the `weather` property must be a string")),
+            })
+            .transpose()
+    }
+
+    /// Returns the name of the sprite sheet cell drawn for the tile `gid`.
+    fn sprite_name(&self, gid: u32, sprite_sheet: &SpriteSheet) -> Result<&str> {
+        let tileset = self
+            .tilesets
+            .iter()
+            .filter(|tileset| tileset.first_gid <= gid)
+            .max_by_key(|tileset| tileset.first_gid)
+            .ok_or_else(|| anyhow!("no tileset contains tile {gid}"))?;
+        let id = gid - tileset.first_gid;
+        let tile = tileset
+            .tiles
+            .iter()
+            .find(|tile| tile.id == id)
+            .ok_or_else(|| anyhow!("tile {gid} has no image"))?;
+        let name = tile.image.rsplit('/').next().unwrap_or(&tile.image);
+        match sprite_sheet.cell(name) {
+            Some(_) => Ok(name),
+            None => Err(anyhow!("the sprite sheet has no cell named {name}")),
+        }
+    }
+}
+
+/// Splits the rows of a tile layer `width` tiles wide into runs of
+/// consecutive tiles, skipping empty ones.
+fn tile_runs(data: &[u32], width: usize) -> Vec<TileRun> {
+    let mut runs = vec![];
+    for (row, tiles) in data.chunks(width.max(1)).enumerate() {
+        let mut run: Option<TileRun> = None;
+        for (column, &gid) in tiles.iter().enumerate() {
+            let gid = gid & GID_MASK;
+            match (&mut run, gid) {
+                (Some(_), 0) => runs.extend(run.take()),
+                (None, 0) => {}
+                (Some(run), gid) => run.gids.push(gid),
+                (None, gid) => {
+                    run = Some(TileRun {
+                        row,
+                        column,
+                        gids: vec![gid],
+                    })
+                }
+            }
+        }
+        runs.extend(run);
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_placed_in_the_world_keep_their_positions() {
+        let tiles = [
+            ("13.png", Point { x: 100.0, y: 375.0 }),
+            ("15.png", Point { x: 228.0, y: 375.0 }),
+            ("1.png", Point { x: 356.0, y: 472.0 }),
+        ];
+        let stone = Rect::from_xy(420.0, 546.0, 90.0, 54.0);
+        let map = TiledMap::from_world(128.0, 128.0, &tiles, &[("stone", stone)]);
+
+        assert_eq!((map.width, map.height), (4, 2));
+        let Layer::Tiles {
+            data,
+            offset_x,
+            offset_y,
+            ..
+        } = &map.layers[0]
+        else {
+            panic!("expected a tile layer");
+        };
+        assert_eq!((*offset_x, *offset_y), (0.0, 31.0));
+        assert_eq!(data, &[1, 2, 0, 0, 0, 0, 0, 0]);
+        let Layer::Objects { objects, .. } = &map.layers[2] else {
+            panic!("expected an object layer");
+        };
+        assert_eq!((objects[0].x, objects[0].y), (320.0, 202.0));
+    }
+
+    #[test]
+    fn tile_runs_are_split_by_empty_tiles_and_rows() {
+        let flipped = 0x8000_0000 | 3;
+        let data = [0, 1, 2, 0, 4, 5, 0, flipped];
+        assert_eq!(
+            tile_runs(&data, 4),
+            [
+                TileRun {
+                    row: 0,
+                    column: 1,
+                    gids: vec![1, 2],
+                },
+                TileRun {
+                    row: 1,
+                    column: 0,
+                    gids: vec![4, 5],
+                },
+                TileRun {
+                    row: 1,
+                    column: 3,
+                    gids: vec![3],
+                },
+            ]
+        );
+    }
+}
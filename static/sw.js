@@ -0,0 +1,30 @@
+// Keeps the page, the script and the WebAssembly module around for offline
+// play. They are fetched from the network first so that a new version is
+// picked up as soon as it is online; the game caches its own assets (see
+// `src/browser/cache.rs`), which are left alone here.
+const SHELL_CACHE = "walk-the-dog-shell";
+const GAME_ASSETS = /\/(images|levels|locales|maps|sounds|sprites_sheets)\//;
+
+self.addEventListener("fetch", (event) => {
+  const url = new URL(event.request.url);
+  if (
+    event.request.method !== "GET" ||
+    url.origin !== self.location.origin ||
+    GAME_ASSETS.test(url.pathname)
+  ) {
+    return;
+  }
+  event.respondWith(
+    fetch(event.request)
+      .then((response) => {
+        if (response.ok) {
+          const copy = response.clone();
+          caches.open(SHELL_CACHE).then((cache) => cache.put(event.request, copy));
+        }
+        return response;
+      })
+      .catch(() =>
+        caches.match(event.request).then((cached) => cached || Response.error())
+      )
+  );
+});